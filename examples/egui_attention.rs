@@ -0,0 +1,58 @@
+use egui::CentralPanel;
+use egui::Context;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::xdg::window::WindowDecorations;
+use std::time::Duration;
+use wayapp::EguiAppData;
+use wayapp::EguiWindow;
+use wayapp::RedrawHandle;
+use wayapp::get_init_app;
+use wayapp::take_startup_token;
+use wayland_client::Proxy;
+
+const APP_ID: &str = "io.github.ciantic.wayapp.AttentionExample";
+
+struct Reminder {
+    handle: RedrawHandle,
+}
+
+impl EguiAppData for Reminder {
+    fn ui(&mut self, ctx: &Context) {
+        CentralPanel::default().show(ctx, |ui| {
+            ui.label("Switch to another window after clicking, this one asks for focus");
+            if ui.button("Remind me in 5 seconds").clicked() {
+                // Stand-in for a background event, e.g. a finished download
+                let handle = self.handle.clone();
+                std::thread::spawn(move || {
+                    std::thread::sleep(Duration::from_secs(5));
+                    handle.request_attention();
+                });
+            }
+        });
+    }
+}
+
+fn main() {
+    // SAFETY: no other threads have been spawned yet
+    let startup_token = unsafe { take_startup_token() };
+    env_logger::init();
+    let app = get_init_app();
+    app.app_id = Some(APP_ID.to_string());
+    app.set_startup_token(startup_token);
+
+    // Launched with XDG_ACTIVATION_TOKEN the first window takes the focus
+    let surface = app.compositor_state.create_surface(&app.qh);
+    let window = app
+        .xdg_shell
+        .create_window(surface, WindowDecorations::ServerDefault, &app.qh);
+    window.set_title("Attention example");
+    window.set_app_id(APP_ID);
+    window.commit();
+
+    let reminder = Reminder {
+        handle: app.redraw_handle(&window.wl_surface().id()),
+    };
+    app.push_window(EguiWindow::new(window, reminder, 360, 120));
+
+    app.run_blocking();
+}
@@ -0,0 +1,94 @@
+//! Replays a scripted demo against a counter, run with
+//! `cargo run --example egui_automation --features automation`
+use egui::Button;
+use egui::CentralPanel;
+use egui::Context;
+use egui::Rect;
+use egui::ScrollArea;
+use egui::TextEdit;
+use egui::pos2;
+use egui::vec2;
+use log::info;
+use smithay_client_toolkit::reexports::calloop::timer::TimeoutAction;
+use smithay_client_toolkit::reexports::calloop::timer::Timer;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::xdg::window::WindowDecorations;
+use std::time::Duration;
+use wayapp::EguiAppData;
+use wayapp::EguiWindow;
+use wayapp::Script;
+use wayapp::get_init_app;
+
+#[derive(Default)]
+struct Counter {
+    counter: i32,
+    text: String,
+}
+
+impl EguiAppData for Counter {
+    fn ui(&mut self, ctx: &Context) {
+        CentralPanel::default().show(ctx, |ui| {
+            // Fixed positions so the script knows where to click
+            let increment = Rect::from_min_size(pos2(20.0, 20.0), vec2(120.0, 32.0));
+            if ui.put(increment, Button::new("Increment")).clicked() {
+                self.counter += 1;
+            }
+            let text = Rect::from_min_size(pos2(20.0, 70.0), vec2(200.0, 24.0));
+            ui.put(text, TextEdit::singleline(&mut self.text));
+            ui.add_space(100.0);
+            ui.label(format!("Counter: {}", self.counter));
+            ScrollArea::vertical().show(ui, |ui| {
+                for i in 0..50 {
+                    ui.label(format!("Row {}", i));
+                }
+            });
+        });
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+
+    let surface = app.compositor_state.create_surface(&app.qh);
+    let window =
+        app.xdg_shell
+            .create_window(surface.clone(), WindowDecorations::ServerDefault, &app.qh);
+    window.set_title("Automation");
+    window.set_app_id("io.github.ciantic.wayapp.Automation");
+    window.commit();
+    app.push_window(EguiWindow::new(window, Counter::default(), 320, 400));
+
+    let script = Script::new()
+        .click_at(80.0, 36.0)
+        .wait(Duration::from_millis(300))
+        .click_at(80.0, 36.0)
+        .wait(Duration::from_millis(300))
+        .click_at(120.0, 82.0)
+        .type_text("Hello from a script")
+        .wait(Duration::from_millis(300))
+        .scroll_by(0, 5)
+        .wait(Duration::from_millis(300))
+        .drag(
+            (100.0, 380.0),
+            (100.0, 200.0),
+            10,
+            Duration::from_millis(500),
+        );
+
+    // Give the compositor time to configure the window
+    let mut script = Some(script);
+    app.loop_handle
+        .insert_source(
+            Timer::from_duration(Duration::from_secs(1)),
+            move |_, _, app| {
+                if let Some(script) = script.take() {
+                    app.inject(&surface, script, 1.0, |_| info!("[MAIN] Script done"));
+                }
+                TimeoutAction::Drop
+            },
+        )
+        .expect("Failed to insert script timer");
+
+    app.run_blocking();
+}
@@ -0,0 +1,72 @@
+use egui::CentralPanel;
+use egui::Context;
+use smithay_client_toolkit::reexports::calloop::timer::TimeoutAction;
+use smithay_client_toolkit::reexports::calloop::timer::Timer;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::wlr_layer::Anchor;
+use smithay_client_toolkit::shell::wlr_layer::Layer;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+use wayapp::EguiAppData;
+use wayapp::EguiLayerSurface;
+use wayapp::EguiSurface;
+use wayapp::LayerSurfaceSettings;
+use wayapp::SurfaceOptions;
+use wayapp::get_init_app;
+use wayland_client::Proxy;
+
+struct ClockBar;
+
+impl EguiAppData for ClockBar {
+    fn ui(&mut self, ctx: &Context) {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let (h, m, s) = ((secs / 3600) % 24, (secs / 60) % 60, secs % 60);
+
+        CentralPanel::default().show(ctx, |ui| {
+            ui.label(format!("{:02}:{:02}:{:02} UTC", h, m, s));
+        });
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+
+    let layer_surface = app.layer_shell.create_layer_surface(
+        &app.qh,
+        app.compositor_state.create_surface(&app.qh),
+        Layer::Top,
+        Some("ClockBar"),
+        None,
+    );
+    LayerSurfaceSettings {
+        anchor: Anchor::TOP | Anchor::LEFT | Anchor::RIGHT,
+        width: 0,
+        height: 32,
+        exclusive_zone: 32,
+        ..Default::default()
+    }
+    .apply(&layer_surface, true)
+    .expect("Invalid bar settings");
+    layer_surface.commit();
+    let surface_id = layer_surface.wl_surface().id();
+
+    // Updates once a second, Fifo avoids spinning the GPU
+    let clock_bar = EguiLayerSurface::new(layer_surface, ClockBar, 256, 32)
+        .with_surface_options(SurfaceOptions::fifo());
+    app.push_layer_surface(clock_bar);
+
+    // Redraw the clock every second, without any input events
+    app.loop_handle
+        .insert_source(Timer::immediate(), move |_, _, app| {
+            app.request_redraw(&surface_id);
+            TimeoutAction::ToDuration(Duration::from_secs(1))
+        })
+        .expect("Failed to insert clock timer");
+
+    app.run_blocking();
+}
@@ -0,0 +1,71 @@
+use egui::Align2;
+use egui::CentralPanel;
+use egui::Context;
+use egui::Window;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::xdg::window::WindowDecorations;
+use wayapp::EguiAppData;
+use wayapp::EguiWindow;
+use wayapp::RedrawHandle;
+use wayapp::get_init_app;
+use wayland_client::Proxy;
+
+struct CloseConfirm {
+    handle: RedrawHandle,
+    prevent_closing: bool,
+    confirming: bool,
+}
+
+impl EguiAppData for CloseConfirm {
+    fn ui(&mut self, ctx: &Context) {
+        CentralPanel::default().show(ctx, |ui| {
+            ui.checkbox(&mut self.prevent_closing, "Prevent closing");
+            ui.label("Close the window with the titlebar button");
+        });
+
+        if self.confirming {
+            Window::new("Close the window?")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        if ui.button("Close anyway").clicked() {
+                            self.handle.close();
+                        }
+                        if ui.button("Cancel").clicked() {
+                            self.confirming = false;
+                        }
+                    });
+                });
+        }
+    }
+
+    fn close_requested(&mut self) -> bool {
+        // The window is drawn again with the dialog open
+        self.confirming = self.prevent_closing;
+        !self.prevent_closing
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+
+    let surface = app.compositor_state.create_surface(&app.qh);
+    let window = app
+        .xdg_shell
+        .create_window(surface, WindowDecorations::ServerDefault, &app.qh);
+    window.set_title("Close confirmation example");
+    window.set_app_id("io.github.ciantic.wayapp.CloseConfirmExample");
+    window.commit();
+
+    let app_data = CloseConfirm {
+        handle: app.redraw_handle(&window.wl_surface().id()),
+        prevent_closing: true,
+        confirming: false,
+    };
+    app.push_window(EguiWindow::new(window, app_data, 360, 160));
+
+    app.run_blocking();
+}
@@ -0,0 +1,95 @@
+use egui::CentralPanel;
+use egui::Context;
+use egui::PointerButton;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::xdg::popup::Popup;
+use smithay_client_toolkit::shell::xdg::window::Window;
+use smithay_client_toolkit::shell::xdg::window::WindowDecorations;
+use std::cell::RefCell;
+use std::rc::Rc;
+use wayapp::EguiAppData;
+use wayapp::EguiPopup;
+use wayapp::EguiWindow;
+use wayapp::PopupParent;
+use wayapp::PopupPlacement;
+use wayapp::get_app;
+use wayapp::get_init_app;
+
+struct Menu {
+    popup: Popup,
+    selected: Rc<RefCell<String>>,
+}
+
+impl EguiAppData for Menu {
+    fn ui(&mut self, ctx: &Context) {
+        CentralPanel::default().show(ctx, |ui| {
+            for item in ["Cut", "Copy", "Paste"] {
+                if ui.button(item).clicked() {
+                    *self.selected.borrow_mut() = item.to_string();
+                    let popup = self.popup.clone();
+                    // Containers can't be removed while one of them is rendering
+                    get_app().loop_handle.insert_idle(move |app| {
+                        app.close_popup(&popup);
+                    });
+                }
+            }
+        });
+    }
+}
+
+struct MainApp {
+    window: Window,
+    selected: Rc<RefCell<String>>,
+}
+
+impl EguiAppData for MainApp {
+    fn ui(&mut self, ctx: &Context) {
+        CentralPanel::default().show(ctx, |ui| {
+            ui.heading("Right-click anywhere");
+            ui.label(format!("Selected: {}", self.selected.borrow()));
+        });
+
+        let open_at = ctx.input(|i| {
+            i.pointer
+                .button_clicked(PointerButton::Secondary)
+                .then(|| i.pointer.interact_pos())
+                .flatten()
+        });
+        if let Some(pos) = open_at {
+            let window = self.window.clone();
+            let selected = self.selected.clone();
+            // Containers can't be added while one of them is rendering
+            get_app().loop_handle.insert_idle(move |app| {
+                let placement = PopupPlacement::at_point(pos.x as i32, pos.y as i32, 120, 100);
+                let popup = app.create_popup(PopupParent::Window(&window), &placement, true);
+                let menu = Menu {
+                    popup: popup.clone(),
+                    selected,
+                };
+                app.push_popup(EguiPopup::new(popup, menu, 120, 100));
+            });
+        }
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+
+    let surface = app.compositor_state.create_surface(&app.qh);
+    let window = app
+        .xdg_shell
+        .create_window(surface, WindowDecorations::ServerDefault, &app.qh);
+    window.set_title("Context menu example");
+    window.set_app_id("io.github.ciantic.wayapp.ContextMenuExample");
+    window.set_min_size(Some((320, 240)));
+    window.commit();
+
+    let main_app = MainApp {
+        window: window.clone(),
+        selected: Rc::new(RefCell::new(String::new())),
+    };
+    app.push_window(EguiWindow::new(window, main_app, 320, 240));
+
+    app.run_blocking();
+}
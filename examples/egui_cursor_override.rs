@@ -0,0 +1,78 @@
+use egui::CentralPanel;
+use egui::Context;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::xdg::window::WindowDecorations;
+use std::time::Duration;
+use wayapp::EguiAppData;
+use wayapp::EguiWindow;
+use wayapp::RedrawHandle;
+use wayapp::Tasks;
+use wayapp::get_init_app;
+use wayland_client::Proxy;
+use wayland_protocols::wp::cursor_shape::v1::client::wp_cursor_shape_device_v1::Shape;
+
+struct Picker {
+    redraw: RedrawHandle,
+    crosshair: bool,
+    note: String,
+    work: Tasks<()>,
+}
+
+impl Picker {
+    fn tool_cursor(&self) -> Option<Shape> {
+        self.crosshair.then_some(Shape::Crosshair)
+    }
+}
+
+impl EguiAppData for Picker {
+    fn ui(&mut self, ctx: &Context) {
+        if self.work.drain().count() > 0 {
+            self.redraw.set_cursor_override(self.tool_cursor());
+        }
+        let working = self.work.running() > 0;
+        CentralPanel::default().show(ctx, |ui| {
+            let crosshair = ui.add_enabled(
+                !working,
+                egui::Checkbox::new(&mut self.crosshair, "Crosshair over everything"),
+            );
+            if crosshair.changed() {
+                self.redraw.set_cursor_override(self.tool_cursor());
+            }
+            if ui
+                .add_enabled(!working, egui::Button::new("Work for 3 seconds"))
+                .clicked()
+            {
+                // Shown over the buttons too until the work is done
+                self.redraw.set_cursor_override(Some(Shape::Wait));
+                self.work
+                    .spawn_blocking(|| std::thread::sleep(Duration::from_secs(3)));
+            }
+            ui.label("Widgets keep their own cursors without an override");
+            ui.text_edit_singleline(&mut self.note);
+        });
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+
+    let surface = app.compositor_state.create_surface(&app.qh);
+    let window = app
+        .xdg_shell
+        .create_window(surface, WindowDecorations::ServerDefault, &app.qh);
+    window.set_title("Cursor override example");
+    window.set_app_id("io.github.ciantic.wayapp.CursorOverrideExample");
+    window.commit();
+
+    let redraw = app.redraw_handle(&window.wl_surface().id());
+    let picker = Picker {
+        redraw: redraw.clone(),
+        crosshair: false,
+        note: "Text cursor here".to_string(),
+        work: Tasks::new(redraw),
+    };
+    app.push_window(EguiWindow::new(window, picker, 320, 160));
+
+    app.run_blocking();
+}
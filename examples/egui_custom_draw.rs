@@ -0,0 +1,202 @@
+use egui::CentralPanel;
+use egui::Color32;
+use egui::Context;
+use egui::Frame;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::xdg::window::WindowDecorations;
+use std::time::Instant;
+use wayapp::CustomDrawFrame;
+use wayapp::EguiAppData;
+use wayapp::EguiSurface;
+use wayapp::EguiWindow;
+use wayapp::get_init_app;
+
+const SHADER: &str = r#"
+@group(0) @binding(0) var<uniform> time: f32;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    // Triangle covering the whole target
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let phase = position.x * 0.004 + position.y * 0.002 + time;
+    return vec4<f32>(
+        0.5 + 0.5 * sin(phase),
+        0.5 + 0.5 * sin(phase + 2.1),
+        0.5 + 0.5 * sin(phase + 4.2),
+        1.0,
+    );
+}
+"#;
+
+/// Animated gradient, the pipeline is created again if the format changes
+struct Gradient {
+    started: Instant,
+    uniform: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    layout: wgpu::PipelineLayout,
+    shader: wgpu::ShaderModule,
+    pipeline: Option<(wgpu::TextureFormat, wgpu::RenderPipeline)>,
+}
+
+impl Gradient {
+    fn new(device: &wgpu::Device) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("gradient"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let uniform = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("gradient time"),
+            // Uniform buffers are at least 16 bytes
+            size: 16,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("gradient"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("gradient"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform.as_entire_binding(),
+            }],
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("gradient"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        Self {
+            started: Instant::now(),
+            uniform,
+            bind_group,
+            layout,
+            shader,
+            pipeline: None,
+        }
+    }
+
+    fn pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+    ) -> &wgpu::RenderPipeline {
+        if self
+            .pipeline
+            .as_ref()
+            .is_none_or(|(created, _)| *created != format)
+        {
+            let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("gradient"),
+                layout: Some(&self.layout),
+                vertex: wgpu::VertexState {
+                    module: &self.shader,
+                    entry_point: Some("vs_main"),
+                    compilation_options: Default::default(),
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &self.shader,
+                    entry_point: Some("fs_main"),
+                    compilation_options: Default::default(),
+                    targets: &[Some(format.into())],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            });
+            self.pipeline = Some((format, pipeline));
+        }
+        &self.pipeline.as_ref().expect("Pipeline was just created").1
+    }
+
+    fn draw(&mut self, frame: &mut CustomDrawFrame<'_>) {
+        let time = self.started.elapsed().as_secs_f32();
+        let mut uniform = [0; 16];
+        uniform[..4].copy_from_slice(&time.to_ne_bytes());
+        frame.queue.write_buffer(&self.uniform, 0, &uniform);
+        let mut pass = frame
+            .encoder
+            .begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("gradient"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: frame.view,
+                    resolve_target: None,
+                    depth_slice: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+        pass.set_pipeline(self.pipeline(frame.device, frame.format));
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+struct Counter {
+    count: i32,
+}
+
+impl EguiAppData for Counter {
+    fn ui(&mut self, ctx: &Context) {
+        // Panel is translucent so the gradient shows through
+        let frame = Frame::central_panel(&ctx.style()).fill(Color32::from_black_alpha(96));
+        CentralPanel::default().frame(frame).show(ctx, |ui| {
+            ui.heading("Custom draw");
+            ui.horizontal(|ui| {
+                if ui.button("-").clicked() {
+                    self.count -= 1;
+                }
+                ui.label(self.count.to_string());
+                if ui.button("+").clicked() {
+                    self.count += 1;
+                }
+            });
+        });
+        ctx.request_repaint();
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+
+    let surface = app.compositor_state.create_surface(&app.qh);
+    let window = app
+        .xdg_shell
+        .create_window(surface, WindowDecorations::ServerDefault, &app.qh);
+    window.set_title("Custom draw example");
+    window.set_app_id("io.github.ciantic.wayapp.CustomDrawExample");
+    window.set_min_size(Some((300, 200)));
+    window.commit();
+
+    let window = EguiWindow::new(window, Counter { count: 0 }, 400, 300);
+    let mut gradient = Gradient::new(window.device());
+    let window = window.with_custom_draw(move |frame| gradient.draw(frame));
+    app.push_window(window);
+
+    app.run_blocking();
+}
@@ -0,0 +1,77 @@
+use egui::CentralPanel;
+use egui::Context;
+use egui::CursorIcon;
+use egui::PointerButton;
+use egui::Rect;
+use egui::Sense;
+use egui::TopBottomPanel;
+use egui::vec2;
+use smithay_client_toolkit::reexports::protocols::xdg::shell::client::xdg_toplevel::ResizeEdge;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::xdg::window::WindowDecorations;
+use wayapp::EguiAppData;
+use wayapp::EguiWindow;
+use wayapp::WindowHandle;
+use wayapp::get_init_app;
+
+const GRIP: f32 = 16.0;
+
+struct CustomTitlebar {
+    window: WindowHandle,
+}
+
+impl EguiAppData for CustomTitlebar {
+    fn ui(&mut self, ctx: &Context) {
+        TopBottomPanel::top("titlebar")
+            .exact_height(36.0)
+            .show(ctx, |ui| {
+                let sense = Sense::click_and_drag();
+                let response = ui.interact(ui.max_rect(), ui.id().with("drag"), sense);
+                if response.drag_started_by(PointerButton::Primary) {
+                    self.window.start_interactive_move();
+                } else if response.secondary_clicked() {
+                    let pos = response.interact_pointer_pos().unwrap_or_default();
+                    self.window.show_window_menu(pos.x as i32, pos.y as i32);
+                }
+                ui.centered_and_justified(|ui| {
+                    ui.label("Drag me, right click for the window menu");
+                });
+            });
+        CentralPanel::default().show(ctx, |ui| {
+            ui.label("Resize from the grip in the corner");
+            let size = vec2(GRIP, GRIP);
+            let rect = Rect::from_min_size(ui.max_rect().max - size, size);
+            let grip = ui
+                .interact(rect, ui.id().with("grip"), Sense::drag())
+                .on_hover_cursor(CursorIcon::ResizeSouthEast);
+            if grip.drag_started_by(PointerButton::Primary) {
+                self.window
+                    .start_interactive_resize(ResizeEdge::BottomRight);
+            }
+            ui.painter()
+                .rect_filled(rect, 2.0, ui.visuals().widgets.inactive.bg_fill);
+        });
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+
+    let surface = app.compositor_state.create_surface(&app.qh);
+    let window = app
+        .xdg_shell
+        .create_window(surface, WindowDecorations::RequestClient, &app.qh);
+    window.set_title("Custom titlebar example");
+    window.set_app_id("io.github.ciantic.wayapp.CustomTitlebarExample");
+    window.set_min_size(Some((240, 160)));
+    window.commit();
+
+    let app_data = CustomTitlebar {
+        window: app.window_handle(&window),
+    };
+    let window = EguiWindow::new(window, app_data, 400, 300).with_client_decorations(false);
+    app.push_window(window);
+
+    app.run_blocking();
+}
@@ -0,0 +1,88 @@
+use egui::Align2;
+use egui::Area;
+use egui::Color32;
+use egui::Context;
+use egui::Frame;
+use smithay_client_toolkit::shell::wlr_layer::Anchor;
+use smithay_client_toolkit::shell::wlr_layer::KeyboardInteractivity;
+use smithay_client_toolkit::shell::wlr_layer::Layer;
+use wayapp::DockHandle;
+use wayapp::DockMode;
+use wayapp::EguiAppData;
+use wayapp::EguiLayerSurface;
+use wayapp::EguiSurface;
+use wayapp::LayerSurfaceBuilder;
+use wayapp::get_init_app;
+
+const HEIGHT: u32 = 64;
+const STRIP: u32 = 4;
+
+struct Dock {
+    handle: DockHandle,
+}
+
+impl EguiAppData for Dock {
+    fn ui(&mut self, ctx: &Context) {
+        // Slides down out of the surface and fades out, the strip still
+        // takes the pointer
+        let hidden = (1.0 - self.handle.progress()) * (HEIGHT - STRIP) as f32;
+        Area::new("dock".into())
+            .anchor(Align2::CENTER_BOTTOM, [0.0, hidden])
+            .constrain(false)
+            .show(ctx, |ui| {
+                ui.multiply_opacity(self.handle.progress());
+                Frame::window(ui.style()).show(ui, |ui| {
+                    ui.set_height((HEIGHT - 16) as f32);
+                    ui.horizontal_centered(|ui| {
+                        for name in ["Files", "Terminal", "Browser"] {
+                            let _ = ui.button(name);
+                        }
+                        ui.separator();
+                        let mut pinned = self.handle.is_pinned();
+                        if ui.checkbox(&mut pinned, "Pin").changed() {
+                            // Windows are laid out again only now
+                            self.handle.set_pinned(pinned);
+                        }
+                    });
+                });
+            });
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+
+    // Always the expanded height, the zone of the dock mode replaces this
+    let builder = LayerSurfaceBuilder::new()
+        .layer(Layer::Top)
+        .anchor(Anchor::LEFT | Anchor::RIGHT | Anchor::BOTTOM)
+        .size(0, HEIGHT)
+        .keyboard_interactivity(KeyboardInteractivity::None)
+        .namespace("Dock");
+
+    let handle = DockHandle::new();
+    // Collapsed frames are invisible and not drawn at all
+    handle.set_faded_out(true);
+    let dock = EguiLayerSurface::from_builder_with(
+        app,
+        &builder,
+        Dock {
+            handle: handle.clone(),
+        },
+    )
+    .expect("Invalid dock settings")
+    .with_background(Color32::TRANSPARENT)
+    .with_dock(
+        app,
+        DockMode {
+            strip: STRIP,
+            exclusive_zone: STRIP as i32,
+            ..Default::default()
+        },
+        handle,
+    );
+    app.push_layer_surface(dock);
+
+    app.run_blocking();
+}
@@ -0,0 +1,114 @@
+use egui::CentralPanel;
+use egui::Context;
+use egui::Key;
+use egui::KeyboardShortcut;
+use egui::Modifiers;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::wlr_layer::KeyboardInteractivity;
+use smithay_client_toolkit::shell::wlr_layer::Layer;
+use smithay_client_toolkit::shell::xdg::window::WindowDecorations;
+use std::cell::Cell;
+use std::cell::RefCell;
+use std::rc::Rc;
+use wayapp::EguiAppData;
+use wayapp::EguiLayerSurface;
+use wayapp::EguiSurface;
+use wayapp::EguiWindow;
+use wayapp::RedrawHandle;
+use wayapp::get_init_app;
+use wayland_client::Proxy;
+
+const EMOJIS: [&str; 8] = ["😀", "👍", "🎉", "❤️", "🔥", "👩‍💻", "🇫🇮", "👋🏽"];
+
+/// Takes the keyboard focus while open, the emoji goes to the widget that
+/// was focused in the editor
+struct Picker {
+    editor: RedrawHandle,
+    picked: Rc<Cell<bool>>,
+}
+
+impl EguiAppData for Picker {
+    fn ui(&mut self, ctx: &Context) {
+        CentralPanel::default().show(ctx, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                for emoji in EMOJIS {
+                    if ui.button(emoji).clicked() {
+                        self.editor.insert_text(emoji);
+                        self.picked.set(true);
+                    }
+                }
+            });
+        });
+    }
+}
+
+struct Editor {
+    text: String,
+    picker: Rc<RefCell<EguiLayerSurface<Picker>>>,
+    picked: Rc<Cell<bool>>,
+}
+
+impl EguiAppData for Editor {
+    fn ui(&mut self, ctx: &Context) {
+        // Runs for the inserted text, the picker is no longer needed
+        if self.picked.take() {
+            self.picker.borrow_mut().set_visible(false);
+        }
+        CentralPanel::default().show(ctx, |ui| {
+            ui.label("Ctrl+. opens the emoji picker");
+            ui.text_edit_multiline(&mut self.text);
+        });
+    }
+
+    fn emoji_picker_requested(&mut self) {
+        self.picker.borrow_mut().set_visible(true);
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+    app.app_id = Some("io.github.ciantic.wayapp.EmojiPickerExample".to_string());
+
+    let surface = app.compositor_state.create_surface(&app.qh);
+    let window = app
+        .xdg_shell
+        .create_window(surface, WindowDecorations::ServerDefault, &app.qh);
+    window.set_title("Emoji picker example");
+    window.set_app_id("io.github.ciantic.wayapp.EmojiPickerExample");
+    window.commit();
+    let editor_handle = app.redraw_handle(&window.wl_surface().id());
+
+    let layer_surface = app.layer_shell.create_layer_surface(
+        &app.qh,
+        app.compositor_state.create_surface(&app.qh),
+        Layer::Overlay,
+        Some("EmojiPicker"),
+        None,
+    );
+    layer_surface.set_keyboard_interactivity(KeyboardInteractivity::OnDemand);
+    layer_surface.set_size(240, 80);
+    layer_surface.commit();
+    let picked = Rc::new(Cell::new(false));
+    let picker = Rc::new(RefCell::new(EguiLayerSurface::new(
+        layer_surface,
+        Picker {
+            editor: editor_handle,
+            picked: picked.clone(),
+        },
+        240,
+        80,
+    )));
+    picker.borrow_mut().set_visible(false);
+    app.push_layer_surface(picker.clone());
+
+    let editor = Editor {
+        text: String::new(),
+        picker,
+        picked,
+    };
+    let shortcut = KeyboardShortcut::new(Modifiers::CTRL, Key::Period);
+    app.push_window(EguiWindow::new(window, editor, 360, 200).with_emoji_picker_shortcut(shortcut));
+
+    app.run_blocking();
+}
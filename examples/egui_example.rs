@@ -55,6 +55,13 @@ impl EguiAppData for EguiApp {
     }
 }
 
+/// Screenshots for the docs, called by the `render-docs` bin
+#[cfg(feature = "docs-render")]
+#[allow(dead_code)]
+pub fn register_docs(gallery: &mut wayapp::Gallery) {
+    gallery.register("egui_example", (256, 256), EguiApp::default);
+}
+
 fn main() {
     env_logger::init();
     let app = get_init_app();
@@ -0,0 +1,93 @@
+use egui::CentralPanel;
+use egui::Color32;
+use egui::Context;
+use egui::Pos2;
+use egui::Sense;
+use egui::Stroke;
+use egui::TopBottomPanel;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::xdg::window::WindowDecorations;
+use wayapp::EguiAppData;
+use wayapp::EguiWindow;
+use wayapp::PickColorError;
+use wayapp::Tasks;
+use wayapp::get_init_app;
+use wayapp::pick_color;
+use wayland_client::Proxy;
+
+struct Drawing {
+    brush: Color32,
+    strokes: Vec<(Color32, Vec<Pos2>)>,
+    picks: Tasks<Result<Color32, PickColorError>>,
+    status: String,
+}
+
+impl EguiAppData for Drawing {
+    fn ui(&mut self, ctx: &Context) {
+        for result in self.picks.drain() {
+            match result {
+                Ok(color) => {
+                    self.brush = color;
+                    self.status.clear();
+                }
+                // Cancelling keeps the brush as is
+                Err(PickColorError::Cancelled) => self.status.clear(),
+                Err(err) => self.status = err.to_string(),
+            }
+        }
+        TopBottomPanel::top("toolbar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.color_edit_button_srgba(&mut self.brush);
+                let picking = self.picks.running() > 0;
+                if ui
+                    .add_enabled(!picking, egui::Button::new("Pick"))
+                    .clicked()
+                {
+                    self.picks.spawn_blocking(|| pick_color(""));
+                }
+                if ui.button("Clear").clicked() {
+                    self.strokes.clear();
+                }
+                ui.label(&self.status);
+            });
+        });
+        CentralPanel::default().show(ctx, |ui| {
+            let (response, painter) = ui.allocate_painter(ui.available_size(), Sense::drag());
+            if response.drag_started() {
+                self.strokes.push((self.brush, Vec::new()));
+            }
+            if response.dragged()
+                && let Some(pos) = response.interact_pointer_pos()
+                && let Some((_, points)) = self.strokes.last_mut()
+            {
+                points.push(pos);
+            }
+            for (color, points) in &self.strokes {
+                painter.line(points.clone(), Stroke::new(3.0, *color));
+            }
+        });
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+
+    let surface = app.compositor_state.create_surface(&app.qh);
+    let window = app
+        .xdg_shell
+        .create_window(surface, WindowDecorations::ServerDefault, &app.qh);
+    window.set_title("Eyedropper example");
+    window.set_app_id("io.github.ciantic.wayapp.EyedropperExample");
+    window.commit();
+
+    let drawing = Drawing {
+        brush: Color32::WHITE,
+        strokes: Vec::new(),
+        picks: Tasks::new(app.redraw_handle(&window.wl_surface().id())),
+        status: String::new(),
+    };
+    app.push_window(EguiWindow::new(window, drawing, 480, 360));
+
+    app.run_blocking();
+}
@@ -0,0 +1,59 @@
+use egui::CentralPanel;
+use egui::Context;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::xdg::window::WindowDecorations;
+use wayapp::EguiAppData;
+use wayapp::EguiWindow;
+use wayapp::get_init_app;
+
+/// Lists files dragged from a file manager, dropped text is shown as is
+#[derive(Default)]
+struct DropTarget {
+    dropped: Vec<String>,
+}
+
+impl EguiAppData for DropTarget {
+    fn ui(&mut self, ctx: &Context) {
+        let (hovered, dropped) = ctx.input(|input| {
+            (
+                input.raw.hovered_files.clone(),
+                input.raw.dropped_files.clone(),
+            )
+        });
+        for file in dropped {
+            match (file.path, file.bytes) {
+                (Some(path), _) => self.dropped.push(path.display().to_string()),
+                (None, Some(bytes)) => self.dropped.push(String::from_utf8_lossy(&bytes).into()),
+                (None, None) => {}
+            }
+        }
+        CentralPanel::default().show(ctx, |ui| {
+            if hovered.is_empty() {
+                ui.heading("Drop files or text here");
+            } else {
+                let mime_types: Vec<&str> = hovered.iter().map(|file| file.mime.as_str()).collect();
+                ui.heading(format!("Dragging {}", mime_types.join(", ")));
+            }
+            for item in &self.dropped {
+                ui.label(item);
+            }
+        });
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+
+    let surface = app.compositor_state.create_surface(&app.qh);
+    let window = app
+        .xdg_shell
+        .create_window(surface, WindowDecorations::ServerDefault, &app.qh);
+    window.set_title("File drop example");
+    window.set_app_id("io.github.ciantic.wayapp.FileDropExample");
+    window.commit();
+
+    app.push_window(EguiWindow::new(window, DropTarget::default(), 400, 300));
+
+    app.run_blocking();
+}
@@ -0,0 +1,49 @@
+use egui::CentralPanel;
+use egui::Context;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::xdg::window::WindowDecorations;
+use wayapp::ContentFit;
+use wayapp::EguiAppData;
+use wayapp::EguiWindow;
+use wayapp::get_init_app;
+
+/// Fixed layout that doesn't work below 400x300
+struct Dialog {
+    accepted: bool,
+}
+
+impl EguiAppData for Dialog {
+    fn ui(&mut self, ctx: &Context) {
+        CentralPanel::default().show(ctx, |ui| {
+            ui.heading("Fixed layout dialog");
+            ui.label("Tiling compositors may configure this smaller than it is,");
+            ui.label("it's scaled down instead of clipped.");
+            ui.add_space(120.0);
+            ui.horizontal(|ui| {
+                if ui.button("Accept").clicked() {
+                    self.accepted = true;
+                }
+                ui.label(if self.accepted { "Accepted" } else { "" });
+            });
+        });
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+
+    let surface = app.compositor_state.create_surface(&app.qh);
+    let window = app
+        .xdg_shell
+        .create_window(surface, WindowDecorations::ServerDefault, &app.qh);
+    window.set_title("Fixed dialog example");
+    window.set_app_id("io.github.ciantic.wayapp.FixedDialogExample");
+    window.commit();
+
+    let mut dialog = EguiWindow::new(window, Dialog { accepted: false }, 400, 300);
+    dialog.set_content_fit(ContentFit::ScaleDown);
+    app.push_window(dialog);
+
+    app.run_blocking();
+}
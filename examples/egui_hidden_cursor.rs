@@ -0,0 +1,40 @@
+use egui::CentralPanel;
+use egui::Context;
+use egui::CursorIcon;
+use egui::Sense;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::xdg::window::WindowDecorations;
+use wayapp::EguiAppData;
+use wayapp::EguiWindow;
+use wayapp::get_init_app;
+
+struct HiddenCursorApp;
+
+impl EguiAppData for HiddenCursorApp {
+    fn ui(&mut self, ctx: &Context) {
+        CentralPanel::default().show(ctx, |ui| {
+            ui.label("Cursor is hidden while hovering the dark area");
+            let (rect, response) = ui.allocate_exact_size(egui::vec2(200.0, 120.0), Sense::hover());
+            ui.painter()
+                .rect_filled(rect, 4.0, egui::Color32::from_gray(40));
+            response.on_hover_cursor(CursorIcon::None);
+        });
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+
+    let surface = app.compositor_state.create_surface(&app.qh);
+    let window = app
+        .xdg_shell
+        .create_window(surface, WindowDecorations::ServerDefault, &app.qh);
+    window.set_title("Hidden cursor example");
+    window.set_app_id("io.github.ciantic.wayapp.HiddenCursorExample");
+    window.set_min_size(Some((256, 256)));
+    window.commit();
+
+    app.push_window(EguiWindow::new(window, HiddenCursorApp, 256, 256));
+    app.run_blocking();
+}
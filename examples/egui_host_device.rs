@@ -0,0 +1,159 @@
+use egui::CentralPanel;
+use egui::Context;
+use pollster::block_on;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::xdg::window::WindowDecorations;
+use std::cell::Cell;
+use std::rc::Rc;
+use wayapp::EguiAppData;
+use wayapp::EguiSurface;
+use wayapp::EguiWindow;
+use wayapp::GpuContext;
+use wayapp::devices_created;
+use wayapp::get_init_app;
+
+const TEXTURE_SIZE: u32 = 256;
+
+const SHADER: &str = r#"
+@vertex
+fn vs_main(@builtin(vertex_index) i: u32) -> @builtin(position) vec4<f32> {
+    let x = f32(i32(i) - 1);
+    let y = f32(i32(i & 1u) * 2 - 1);
+    return vec4<f32>(x, y, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0, 0.5, 0.0, 1.0);
+}
+"#;
+
+struct ImageApp {
+    texture: Rc<Cell<Option<egui::TextureId>>>,
+}
+
+impl EguiAppData for ImageApp {
+    fn ui(&mut self, ctx: &Context) {
+        CentralPanel::default().show(ctx, |ui| {
+            ui.label(format!(
+                "WGPU devices created by wayapp: {}",
+                devices_created()
+            ));
+            if let Some(texture) = self.texture.get() {
+                ui.image((
+                    texture,
+                    egui::vec2(TEXTURE_SIZE as f32, TEXTURE_SIZE as f32),
+                ));
+            }
+        });
+    }
+}
+
+/// Host renders a triangle with its own pipeline into an offscreen texture
+fn render_host_texture(device: &wgpu::Device, queue: &wgpu::Queue) -> wgpu::Texture {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("host texture"),
+        size: wgpu::Extent3d {
+            width: TEXTURE_SIZE,
+            height: TEXTURE_SIZE,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("host shader"),
+        source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+    });
+    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("host pipeline"),
+        layout: None,
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vs_main"),
+            compilation_options: Default::default(),
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some("fs_main"),
+            compilation_options: Default::default(),
+            targets: &[Some(wgpu::TextureFormat::Rgba8UnormSrgb.into())],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("host encoder"),
+    });
+    {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("host pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        pass.set_pipeline(&pipeline);
+        pass.draw(0..3, 0..1);
+    }
+    queue.submit(Some(encoder.finish()));
+    texture
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+
+    // The host owns the device
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+    let adapter = block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))
+        .expect("Failed to find an adapter");
+    let (device, queue) = block_on(adapter.request_device(&wgpu::DeviceDescriptor::default()))
+        .expect("Failed to request device");
+    let host_texture = render_host_texture(&device, &queue);
+
+    let gpu = GpuContext::with_existing(instance, adapter, device, queue)
+        .expect("Host device doesn't satisfy the renderer");
+    app.set_gpu_context(gpu);
+
+    let surface = app.compositor_state.create_surface(&app.qh);
+    let window = app
+        .xdg_shell
+        .create_window(surface, WindowDecorations::ServerDefault, &app.qh);
+    window.set_title("Host device example");
+    window.set_app_id("io.github.ciantic.wayapp.HostDeviceExample");
+    window.set_min_size(Some((320, 320)));
+    window.commit();
+
+    let texture = Rc::new(Cell::new(None));
+    let image_app = ImageApp {
+        texture: texture.clone(),
+    };
+    let mut egui_window = EguiWindow::new(window, image_app, 320, 320);
+    let view = host_texture.create_view(&wgpu::TextureViewDescriptor::default());
+    texture.set(Some(
+        egui_window.register_native_texture(&view, wgpu::FilterMode::Linear),
+    ));
+
+    app.push_window(egui_window);
+    app.run_blocking();
+}
@@ -0,0 +1,82 @@
+use egui::CentralPanel;
+use egui::Context;
+use egui::vec2;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::wlr_layer::Anchor;
+use smithay_client_toolkit::shell::wlr_layer::Layer;
+use wayapp::EguiAppData;
+use wayapp::EguiLayerSurface;
+use wayapp::Icon;
+use wayapp::Icons;
+use wayapp::LayerSurfaceSettings;
+use wayapp::get_init_app;
+
+/// Shown when the theme has no such icon
+const FALLBACK_SVG: &[u8] = br#"<svg xmlns="http://www.w3.org/2000/svg" width="16" height="16">
+<circle cx="8" cy="8" r="6" fill="none" stroke="white" stroke-width="1.5"/>
+</svg>"#;
+
+struct IconBar {
+    icons: Icons,
+    network: Icon,
+    battery: Icon,
+}
+
+impl EguiAppData for IconBar {
+    fn ui(&mut self, ctx: &Context) {
+        CentralPanel::default().show(ctx, |ui| {
+            ui.horizontal_centered(|ui| {
+                // Rasterized again when the bar moves to an output of another
+                // scale, so the icons stay pixel-crisp
+                ui.add(self.icons.image(ctx, self.network, vec2(16.0, 16.0)));
+                ui.label("Wired");
+                ui.add(self.icons.image(ctx, self.battery, vec2(16.0, 16.0)));
+                ui.label("80 %");
+            });
+        });
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+
+    let icons = app.icons();
+    if let Ok(theme) = std::env::var("ICON_THEME") {
+        icons.set_theme(&theme);
+    }
+    let fallback = icons.load_svg(FALLBACK_SVG).expect("Invalid fallback icon");
+    let network = icons
+        .load_themed("network-wired-symbolic", 16)
+        .unwrap_or(fallback);
+    let battery = icons
+        .load_themed("battery-good-symbolic", 16)
+        .unwrap_or(fallback);
+
+    let layer_surface = app.layer_shell.create_layer_surface(
+        &app.qh,
+        app.compositor_state.create_surface(&app.qh),
+        Layer::Top,
+        Some("IconBar"),
+        None,
+    );
+    LayerSurfaceSettings {
+        anchor: Anchor::TOP | Anchor::LEFT | Anchor::RIGHT,
+        width: 0,
+        height: 28,
+        exclusive_zone: 28,
+        ..Default::default()
+    }
+    .apply(&layer_surface, true)
+    .expect("Invalid bar settings");
+    layer_surface.commit();
+
+    let bar = IconBar {
+        icons,
+        network,
+        battery,
+    };
+    app.push_layer_surface(EguiLayerSurface::new(layer_surface, bar, 256, 28));
+
+    app.run_blocking();
+}
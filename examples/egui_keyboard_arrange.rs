@@ -0,0 +1,44 @@
+use egui::CentralPanel;
+use egui::Context;
+use smithay_client_toolkit::shell::wlr_layer::Anchor;
+use smithay_client_toolkit::shell::wlr_layer::KeyboardInteractivity;
+use smithay_client_toolkit::shell::wlr_layer::Layer;
+use wayapp::EguiAppData;
+use wayapp::EguiLayerSurface;
+use wayapp::KeyboardArrange;
+use wayapp::LayerSurfaceBuilder;
+use wayapp::get_init_app;
+
+struct Panel;
+
+impl EguiAppData for Panel {
+    fn ui(&mut self, ctx: &Context) {
+        CentralPanel::default().show(ctx, |ui| {
+            ui.heading("Keyboard arrange");
+            ui.label("Alt+F8 to arrange");
+            ui.label("Arrows move 10 px, Shift+arrows resize");
+            ui.label("Enter or Escape to finish");
+        });
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+
+    // Anchored to the top left corner, so the margins move it on both axes
+    let builder = LayerSurfaceBuilder::new()
+        .layer(Layer::Top)
+        .anchor(Anchor::TOP | Anchor::LEFT)
+        .margins(40, 0, 0, 40)
+        .size(320, 160)
+        .keyboard_interactivity(KeyboardInteractivity::OnDemand)
+        .namespace("KeyboardArrange")
+        .strict(true);
+    let panel = EguiLayerSurface::from_builder(&builder, Panel)
+        .expect("Invalid panel settings")
+        .with_keyboard_arrange(KeyboardArrange::new().with_step(10));
+    app.push_layer_surface(panel);
+
+    app.run_blocking();
+}
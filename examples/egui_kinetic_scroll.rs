@@ -0,0 +1,58 @@
+use egui::CentralPanel;
+use egui::Context;
+use egui::ScrollArea;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::xdg::window::WindowDecorations;
+use wayapp::EguiAppData;
+use wayapp::EguiSurface;
+use wayapp::EguiWindow;
+use wayapp::KineticScrolling;
+use wayapp::get_init_app;
+
+struct List {
+    reduce_motion: bool,
+}
+
+impl EguiAppData for List {
+    fn ui(&mut self, ctx: &Context) {
+        CentralPanel::default().show(ctx, |ui| {
+            if ui
+                .checkbox(&mut self.reduce_motion, "Reduce motion")
+                .changed()
+            {
+                // No animation time turns the coasting off
+                let animation_time = if self.reduce_motion { 0.0 } else { 1.0 / 12.0 };
+                ctx.style_mut(|style| style.animation_time = animation_time);
+            }
+            ui.label("Flick the list with two fingers on a touchpad");
+            ui.separator();
+            ScrollArea::vertical().show(ui, |ui| {
+                for row in 1..=500 {
+                    ui.label(format!("Row {}", row));
+                }
+            });
+        });
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+
+    let surface = app.compositor_state.create_surface(&app.qh);
+    let window = app
+        .xdg_shell
+        .create_window(surface, WindowDecorations::ServerDefault, &app.qh);
+    window.set_title("Kinetic scrolling example");
+    window.set_app_id("io.github.ciantic.wayapp.KineticScrollExample");
+    window.commit();
+
+    let list = List {
+        reduce_motion: false,
+    };
+    let window =
+        EguiWindow::new(window, list, 300, 400).with_kinetic_scrolling(KineticScrolling::default());
+    app.push_window(window);
+
+    app.run_blocking();
+}
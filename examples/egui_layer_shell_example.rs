@@ -8,6 +8,7 @@ use smithay_client_toolkit::shell::wlr_layer::Layer;
 use smithay_client_toolkit::shell::wlr_layer::LayerSurface;
 use wayapp::EguiAppData;
 use wayapp::EguiLayerSurface;
+use wayapp::LayerSurfaceSettings;
 use wayapp::get_init_app;
 
 struct EguiApp {
@@ -40,6 +41,37 @@ impl EguiApp {
             anchor_right: false,
         }
     }
+
+    /// Invalid combinations are corrected with a warning
+    fn apply_settings(&self) {
+        let mut anchor = Anchor::empty();
+        if self.anchor_top {
+            anchor |= Anchor::TOP;
+        }
+        if self.anchor_bottom {
+            anchor |= Anchor::BOTTOM;
+        }
+        if self.anchor_left {
+            anchor |= Anchor::LEFT;
+        }
+        if self.anchor_right {
+            anchor |= Anchor::RIGHT;
+        }
+        let settings = LayerSurfaceSettings {
+            anchor,
+            width: self.width,
+            height: self.height,
+            exclusive_zone: 0,
+            margin: (
+                self.margin_top,
+                self.margin_right,
+                self.margin_bottom,
+                self.margin_left,
+            ),
+        };
+        debug!("Applying {:?}", settings);
+        let _ = settings.apply(&self.layer_surface, false);
+    }
 }
 
 impl EguiAppData for EguiApp {
@@ -62,8 +94,7 @@ impl EguiAppData for EguiApp {
                 ui.add(egui::Slider::new(&mut self.height, 100..=1024).text("Height"));
             });
             if ui.button("Apply Size").clicked() {
-                debug!("Setting size to {}x{}", self.width, self.height);
-                self.layer_surface.set_size(self.width, self.height);
+                self.apply_settings();
             }
 
             ui.separator();
@@ -77,21 +108,7 @@ impl EguiAppData for EguiApp {
                 ui.checkbox(&mut self.anchor_right, "Right");
             });
             if ui.button("Apply Anchor").clicked() {
-                let mut anchor = Anchor::empty();
-                if self.anchor_top {
-                    anchor |= Anchor::TOP;
-                }
-                if self.anchor_bottom {
-                    anchor |= Anchor::BOTTOM;
-                }
-                if self.anchor_left {
-                    anchor |= Anchor::LEFT;
-                }
-                if self.anchor_right {
-                    anchor |= Anchor::RIGHT;
-                }
-                debug!("Setting anchor to {:?}", anchor);
-                self.layer_surface.set_anchor(anchor);
+                self.apply_settings();
             }
 
             ui.separator();
@@ -115,16 +132,7 @@ impl EguiAppData for EguiApp {
                 ui.add(egui::Slider::new(&mut self.margin_left, 0..=100).text("Left"));
             });
             if ui.button("Apply Margin").clicked() {
-                debug!(
-                    "Setting margin to ({}, {}, {}, {})",
-                    self.margin_top, self.margin_right, self.margin_bottom, self.margin_left
-                );
-                self.layer_surface.set_margin(
-                    self.margin_top,
-                    self.margin_right,
-                    self.margin_bottom,
-                    self.margin_left,
-                );
+                self.apply_settings();
             }
         });
     }
@@ -143,9 +151,13 @@ fn main() {
         None,
     );
     layer_surface.set_keyboard_interactivity(KeyboardInteractivity::Exclusive);
-    // layer_surface.set_anchor(Anchor::BOTTOM | Anchor::LEFT);
-    layer_surface.set_margin(0, 0, 0, 0);
-    layer_surface.set_size(512, 512);
+    LayerSurfaceSettings {
+        width: 512,
+        height: 512,
+        ..Default::default()
+    }
+    .apply(&layer_surface, true)
+    .expect("Invalid layer surface settings");
     layer_surface.commit();
     let egui_app = EguiApp::new(layer_surface.clone());
     let egui_layer_surface = EguiLayerSurface::new(layer_surface, egui_app, 256, 256);
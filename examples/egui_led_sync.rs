@@ -0,0 +1,77 @@
+use egui::CentralPanel;
+use egui::Color32;
+use egui::Context;
+use log::info;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::xdg::window::WindowDecorations;
+use std::time::Instant;
+use wayapp::EguiAppData;
+use wayapp::EguiWindow;
+use wayapp::FrameStatus;
+use wayapp::get_app;
+use wayapp::get_init_app;
+use wayland_backend::client::ObjectId;
+use wayland_client::Proxy;
+
+/// Stand-in for an LED strip, a real one would write to a serial port
+fn update_led_strip(color: Color32) {
+    info!("LED strip set to {:?}", color);
+}
+
+struct LedPicker {
+    surface_id: ObjectId,
+    color: Color32,
+}
+
+impl EguiAppData for LedPicker {
+    fn ui(&mut self, ctx: &Context) {
+        let before = self.color;
+        CentralPanel::default().show(ctx, |ui| {
+            ui.heading("LED color");
+            ui.color_edit_button_srgba(&mut self.color);
+            let (rect, _) = ui.allocate_exact_size(ui.available_size(), egui::Sense::hover());
+            ui.painter().rect_filled(rect, 4.0, self.color);
+        });
+        if self.color == before {
+            return;
+        }
+        // The preview drawn now and the LEDs change together
+        let color = self.color;
+        let app = get_app();
+        let token = app.frame_token(&self.surface_id);
+        app.on_presented(token, move |status| match status {
+            FrameStatus::Presented(presented) => {
+                update_led_strip(color);
+                info!(
+                    "LED update {:.2} ms after the preview was presented",
+                    Instant::now()
+                        .saturating_duration_since(presented.presented_at)
+                        .as_secs_f64()
+                        * 1000.0
+                );
+            }
+            FrameStatus::Discarded => info!("Preview of {:?} was never shown", color),
+        });
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+
+    let surface = app.compositor_state.create_surface(&app.qh);
+    let window = app
+        .xdg_shell
+        .create_window(surface, WindowDecorations::ServerDefault, &app.qh);
+    window.set_title("LED sync example");
+    window.set_app_id("io.github.ciantic.wayapp.LedSyncExample");
+    window.commit();
+
+    let picker = LedPicker {
+        surface_id: window.wl_surface().id(),
+        color: Color32::from_rgb(255, 128, 0),
+    };
+    app.push_window(EguiWindow::new(window, picker, 320, 240));
+
+    app.run_blocking();
+}
@@ -0,0 +1,109 @@
+use egui::CentralPanel;
+use egui::Context;
+use egui::Slider;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::wlr_layer::Anchor;
+use smithay_client_toolkit::shell::wlr_layer::KeyboardInteractivity;
+use smithay_client_toolkit::shell::wlr_layer::Layer;
+use smithay_client_toolkit::shell::xdg::window::Window;
+use smithay_client_toolkit::shell::xdg::window::WindowDecorations;
+use wayapp::Application;
+use wayapp::EguiAppData;
+use wayapp::EguiLayerSurface;
+use wayapp::EguiWindow;
+use wayapp::LayerSurfaceBuilder;
+use wayapp::LayerSurfaceContainer;
+use wayapp::SingleColorRole;
+use wayapp::SingleColorSurface;
+use wayapp::WindowContainer;
+use wayapp::get_init_app;
+
+struct Counter {
+    count: i32,
+}
+
+impl EguiAppData for Counter {
+    fn ui(&mut self, ctx: &Context) {
+        CentralPanel::default().show(ctx, |ui| {
+            if ui.button("Increment").clicked() {
+                self.count += 1;
+            }
+            ui.label(format!("Count: {}", self.count));
+        });
+    }
+}
+
+struct Settings {
+    volume: f32,
+}
+
+impl EguiAppData for Settings {
+    fn ui(&mut self, ctx: &Context) {
+        CentralPanel::default().show(ctx, |ui| {
+            ui.add(Slider::new(&mut self.volume, 0.0..=1.0).text("Volume"));
+        });
+    }
+}
+
+struct Status;
+
+impl EguiAppData for Status {
+    fn ui(&mut self, ctx: &Context) {
+        CentralPanel::default().show(ctx, |ui| {
+            ui.label("Layer surface next to the windows");
+        });
+    }
+}
+
+fn create_window(app: &mut Application, title: &str) -> Window {
+    let surface = app.compositor_state.create_surface(&app.qh);
+    let window = app
+        .xdg_shell
+        .create_window(surface, WindowDecorations::ServerDefault, &app.qh);
+    window.set_title(title);
+    window.set_app_id("io.github.ciantic.wayapp.MixedContainersExample");
+    window.commit();
+    window
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+
+    // Containers of different types, boxed containers are containers too
+    let windows: Vec<Box<dyn WindowContainer>> = vec![
+        Box::new(EguiWindow::new(
+            create_window(app, "Counter"),
+            Counter { count: 0 },
+            300,
+            160,
+        )),
+        Box::new(EguiWindow::new(
+            create_window(app, "Settings"),
+            Settings { volume: 0.5 },
+            300,
+            120,
+        )),
+        Box::new(SingleColorSurface::new(
+            SingleColorRole::Window(create_window(app, "Backdrop")),
+            [32, 48, 96, 255],
+            300,
+            200,
+        )),
+    ];
+    for window in windows {
+        app.push_window(window);
+    }
+
+    let builder = LayerSurfaceBuilder::new()
+        .layer(Layer::Top)
+        .anchor(Anchor::RIGHT | Anchor::TOP)
+        .size(260, 40)
+        .keyboard_interactivity(KeyboardInteractivity::None)
+        .namespace("MixedContainersStatus");
+    let status: Box<dyn LayerSurfaceContainer> =
+        Box::new(EguiLayerSurface::from_builder(&builder, Status).expect("Invalid layer settings"));
+    app.push_layer_surface(status);
+
+    app.run_blocking();
+}
@@ -0,0 +1,92 @@
+use egui::CentralPanel;
+use egui::Context;
+use egui::Key;
+use log::info;
+use smithay_client_toolkit::reexports::csd_frame::WindowState;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::xdg::window::Window;
+use smithay_client_toolkit::shell::xdg::window::WindowDecorations;
+use wayapp::EguiAppData;
+use wayapp::EguiSurface;
+use wayapp::EguiWindow;
+use wayapp::FocusPolicy;
+use wayapp::OutputSelector;
+use wayapp::devices_created;
+use wayapp::get_app;
+use wayapp::get_init_app;
+
+struct CounterApp {
+    index: usize,
+    counter: i32,
+    text: String,
+    window: Window,
+    fullscreen: bool,
+}
+
+impl EguiAppData for CounterApp {
+    fn ui(&mut self, ctx: &Context) {
+        // F11 fullscreens on the output the window is on
+        if ctx.input(|i| i.key_pressed(Key::F11)) {
+            if self.fullscreen {
+                self.window.unset_fullscreen();
+            } else {
+                let window = self.window.clone();
+                get_app().loop_handle.insert_idle(move |app| {
+                    app.set_window_fullscreen(&window, OutputSelector::Current);
+                });
+            }
+        }
+        CentralPanel::default().show(ctx, |ui| {
+            ui.heading(format!("Window {}", self.index));
+            ui.label(format!("Counter: {}", self.counter));
+            if ui.button("Increment").clicked() {
+                self.counter += 1;
+            }
+            ui.text_edit_singleline(&mut self.text);
+            ui.label(format!("WGPU devices created: {}", devices_created()));
+            ui.label("F11 toggles fullscreen");
+        });
+    }
+
+    fn window_state_changed(&mut self, state: WindowState) {
+        self.fullscreen = state.contains(WindowState::FULLSCREEN);
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+    // Only one focused text field across the windows with this argument,
+    // otherwise each window remembers its own
+    let focus_policy = if std::env::args().any(|arg| arg == "--exclusive-focus") {
+        FocusPolicy::Exclusive
+    } else {
+        FocusPolicy::Remember
+    };
+
+    for index in 0..4 {
+        let surface = app.compositor_state.create_surface(&app.qh);
+        let window =
+            app.xdg_shell
+                .create_window(surface, WindowDecorations::ServerDefault, &app.qh);
+        window.set_title(format!("Multi window example {}", index));
+        window.set_app_id("io.github.ciantic.wayapp.MultiWindowExample");
+        window.set_min_size(Some((256, 256)));
+        window.commit();
+
+        let counter_app = CounterApp {
+            index,
+            counter: 0,
+            text: String::new(),
+            window: window.clone(),
+            fullscreen: false,
+        };
+        let window = EguiWindow::new(window, counter_app, 256, 256).with_focus_policy(focus_policy);
+        app.push_window(window);
+    }
+
+    // All windows share the same device and queue
+    info!("WGPU devices created: {}", devices_created());
+
+    app.run_blocking();
+}
@@ -0,0 +1,87 @@
+use egui::CentralPanel;
+use egui::Context;
+use egui::Key;
+use egui::Modifiers;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::wlr_layer::KeyboardInteractivity;
+use smithay_client_toolkit::shell::wlr_layer::Layer;
+use smithay_client_toolkit::shell::xdg::window::WindowDecorations;
+use std::cell::RefCell;
+use std::rc::Rc;
+use wayapp::EguiAppData;
+use wayapp::EguiLayerSurface;
+use wayapp::EguiWindow;
+use wayapp::get_init_app;
+
+struct Palette {
+    query: String,
+}
+
+impl EguiAppData for Palette {
+    fn ui(&mut self, ctx: &Context) {
+        CentralPanel::default().show(ctx, |ui| {
+            ui.heading("Palette");
+            ui.text_edit_singleline(&mut self.query).request_focus();
+        });
+    }
+}
+
+/// Toggles the palette with a button or Ctrl+P
+struct Control {
+    palette: Rc<RefCell<EguiLayerSurface<Palette>>>,
+}
+
+impl EguiAppData for Control {
+    fn ui(&mut self, ctx: &Context) {
+        let shortcut = ctx.input_mut(|i| i.consume_key(Modifiers::CTRL, Key::P));
+        CentralPanel::default().show(ctx, |ui| {
+            let visible = self.palette.borrow().is_visible();
+            let text = if visible {
+                "Hide palette"
+            } else {
+                "Show palette"
+            };
+            if ui.button(text).clicked() || shortcut {
+                // Time to map again is logged on debug level
+                self.palette.borrow_mut().set_visible(!visible);
+            }
+        });
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+
+    let layer_surface = app.layer_shell.create_layer_surface(
+        &app.qh,
+        app.compositor_state.create_surface(&app.qh),
+        Layer::Overlay,
+        Some("Palette"),
+        None,
+    );
+    layer_surface.set_keyboard_interactivity(KeyboardInteractivity::OnDemand);
+    layer_surface.set_size(400, 120);
+    layer_surface.commit();
+    let palette = Rc::new(RefCell::new(EguiLayerSurface::new(
+        layer_surface,
+        Palette {
+            query: String::new(),
+        },
+        400,
+        120,
+    )));
+    app.push_layer_surface(palette.clone());
+
+    let surface = app.compositor_state.create_surface(&app.qh);
+    let window = app
+        .xdg_shell
+        .create_window(surface, WindowDecorations::ServerDefault, &app.qh);
+    window.set_title("Palette example");
+    window.set_app_id("io.github.ciantic.wayapp.PaletteExample");
+    window.set_min_size(Some((200, 100)));
+    window.commit();
+    app.push_window(EguiWindow::new(window, Control { palette }, 200, 100));
+
+    app.run_blocking();
+}
@@ -0,0 +1,55 @@
+use egui::CentralPanel;
+use egui::Context;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::xdg::window::WindowDecorations;
+use wayapp::EguiAppData;
+use wayapp::EguiSubsurface;
+use wayapp::EguiWindow;
+use wayapp::get_init_app;
+
+struct MainApp;
+
+impl EguiAppData for MainApp {
+    fn ui(&mut self, ctx: &Context) {
+        CentralPanel::default().show(ctx, |ui| {
+            ui.heading("Main window");
+            ui.label("The counter in the corner is a subsurface");
+        });
+    }
+}
+
+struct CounterApp {
+    counter: i32,
+}
+
+impl EguiAppData for CounterApp {
+    fn ui(&mut self, ctx: &Context) {
+        CentralPanel::default().show(ctx, |ui| {
+            ui.label(format!("Counter: {}", self.counter));
+            if ui.button("Increment").clicked() {
+                self.counter += 1;
+            }
+        });
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+
+    let surface = app.compositor_state.create_surface(&app.qh);
+    let window = app
+        .xdg_shell
+        .create_window(surface, WindowDecorations::ServerDefault, &app.qh);
+    window.set_title("Picture in picture example");
+    window.set_app_id("io.github.ciantic.wayapp.PictureInPictureExample");
+    window.set_min_size(Some((480, 360)));
+    window.commit();
+    let parent = window.wl_surface().clone();
+    app.push_window(EguiWindow::new(window, MainApp, 480, 360));
+
+    let counter = EguiSubsurface::create(&parent, 300, 240, CounterApp { counter: 0 }, 160, 100);
+    app.push_subsurface(counter);
+
+    app.run_blocking();
+}
@@ -0,0 +1,64 @@
+use egui::CentralPanel;
+use egui::Context;
+use log::info;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::xdg::window::WindowDecorations;
+use std::time::Instant;
+use wayapp::EguiAppData;
+use wayapp::EguiWindow;
+use wayapp::Presented;
+use wayapp::get_init_app;
+
+struct PresentationLog {
+    last_presented: Option<Instant>,
+    last_delta: Option<f32>,
+}
+
+impl EguiAppData for PresentationLog {
+    fn ui(&mut self, ctx: &Context) {
+        CentralPanel::default().show(ctx, |ui| {
+            // Time of the vblank this frame is predicted to be shown at
+            ui.label(format!("Frame time {:.4} s", ctx.input(|input| input.time)));
+            match self.last_delta {
+                Some(delta) => ui.label(format!("Presented {:.2} ms apart", delta)),
+                None => ui.label("No presentation feedback yet"),
+            };
+            ui.spinner();
+        });
+        ctx.request_repaint();
+    }
+
+    fn presented(&mut self, presented: &Presented) {
+        if let Some(last) = self.last_presented {
+            let delta = presented.presented_at.saturating_duration_since(last);
+            let delta = delta.as_secs_f32() * 1000.0;
+            info!(
+                "[MAIN] Presented {:.2} ms after the previous frame, refresh {:?}, vsync {}",
+                delta, presented.refresh, presented.vsync
+            );
+            self.last_delta = Some(delta);
+        }
+        self.last_presented = Some(presented.presented_at);
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+
+    let surface = app.compositor_state.create_surface(&app.qh);
+    let window = app
+        .xdg_shell
+        .create_window(surface, WindowDecorations::ServerDefault, &app.qh);
+    window.set_title("Presentation log example");
+    window.set_app_id("io.github.ciantic.wayapp.PresentationLogExample");
+    window.commit();
+
+    let log = PresentationLog {
+        last_presented: None,
+        last_delta: None,
+    };
+    app.push_window(EguiWindow::new(window, log, 320, 120));
+
+    app.run_blocking();
+}
@@ -0,0 +1,100 @@
+use egui::CentralPanel;
+use egui::Context;
+use egui::ProgressBar;
+use log::info;
+use smithay_client_toolkit::reexports::calloop::timer::TimeoutAction;
+use smithay_client_toolkit::reexports::calloop::timer::Timer;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::wlr_layer::Anchor;
+use smithay_client_toolkit::shell::wlr_layer::Layer;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+use std::time::Instant;
+use wayapp::EguiAppData;
+use wayapp::EguiLayerSurface;
+use wayapp::EguiSurface;
+use wayapp::LayerSurfaceSettings;
+use wayapp::get_init_app;
+use wayland_client::Proxy;
+
+struct Dashboard {
+    started: Instant,
+}
+
+impl EguiAppData for Dashboard {
+    fn ui(&mut self, ctx: &Context) {
+        let t = self.started.elapsed().as_secs_f32();
+        CentralPanel::default().show(ctx, |ui| {
+            ui.heading("Render stats");
+            ui.add(ProgressBar::new((t * 0.5).fract()).animate(true));
+            ui.spinner();
+        });
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+
+    let layer_surface = app.layer_shell.create_layer_surface(
+        &app.qh,
+        app.compositor_state.create_surface(&app.qh),
+        Layer::Top,
+        Some("RenderStats"),
+        None,
+    );
+    LayerSurfaceSettings {
+        anchor: Anchor::TOP | Anchor::RIGHT,
+        width: 256,
+        height: 96,
+        ..Default::default()
+    }
+    .apply(&layer_surface, true)
+    .expect("Invalid dashboard settings");
+    layer_surface.commit();
+    let surface_id = layer_surface.wl_surface().id();
+
+    let dashboard = Rc::new(RefCell::new(
+        EguiLayerSurface::new(
+            layer_surface,
+            Dashboard {
+                started: Instant::now(),
+            },
+            256,
+            96,
+        )
+        .with_render_stats(true),
+    ));
+    app.push_layer_surface(dashboard.clone());
+
+    // Animate by redrawing on every frame callback
+    app.loop_handle
+        .insert_source(Timer::immediate(), move |_, _, app| {
+            app.request_redraw(&surface_id);
+            TimeoutAction::ToDuration(Duration::from_millis(16))
+        })
+        .expect("Failed to insert animation timer");
+
+    app.loop_handle
+        .insert_source(
+            Timer::from_duration(Duration::from_secs(1)),
+            move |_, _, _| {
+                if let Some(stats) = dashboard.borrow().render_stats() {
+                    info!(
+                        "[MAIN] frames {} skipped {} last {:?} avg {:?} p95 {:?} error {:?}",
+                        stats.frames,
+                        stats.frames_skipped,
+                        stats.last_frame,
+                        stats.avg_frame_time,
+                        stats.p95_frame_time,
+                        stats.last_present_error
+                    );
+                }
+                TimeoutAction::ToDuration(Duration::from_secs(1))
+            },
+        )
+        .expect("Failed to insert stats timer");
+
+    app.run_blocking();
+}
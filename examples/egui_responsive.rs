@@ -0,0 +1,89 @@
+use egui::CentralPanel;
+use egui::Context;
+use egui::SidePanel;
+use egui::TopBottomPanel;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::xdg::window::WindowDecorations;
+use wayapp::EguiAppData;
+use wayapp::EguiWindow;
+use wayapp::get_init_app;
+
+/// Narrower windows get a menu button instead of the sidebar
+const COLLAPSE_WIDTH: u32 = 600;
+
+const PAGES: [&str; 3] = ["Inbox", "Sent", "Archive"];
+
+struct Responsive {
+    width: u32,
+    pixels_per_point: f32,
+    page: usize,
+}
+
+impl Responsive {
+    fn page_list(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut picked = false;
+        for (index, name) in PAGES.iter().enumerate() {
+            if ui.selectable_label(self.page == index, *name).clicked() {
+                self.page = index;
+                picked = true;
+            }
+        }
+        picked
+    }
+}
+
+impl EguiAppData for Responsive {
+    fn resized(&mut self, width: u32, _height: u32) {
+        self.width = width;
+    }
+
+    fn scale_factor_changed(&mut self, pixels_per_point: f32) {
+        self.pixels_per_point = pixels_per_point;
+    }
+
+    fn ui(&mut self, ctx: &Context) {
+        if self.width < COLLAPSE_WIDTH {
+            TopBottomPanel::top("menu").show(ctx, |ui| {
+                ui.menu_button("☰ Menu", |ui| {
+                    if self.page_list(ui) {
+                        ui.close();
+                    }
+                });
+            });
+        } else {
+            SidePanel::left("sidebar").show(ctx, |ui| {
+                self.page_list(ui);
+            });
+        }
+        CentralPanel::default().show(ctx, |ui| {
+            ui.heading(PAGES[self.page]);
+            ui.label(format!(
+                "{} points wide at {} pixels per point, the sidebar collapses below {}",
+                self.width, self.pixels_per_point, COLLAPSE_WIDTH
+            ));
+        });
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+
+    let surface = app.compositor_state.create_surface(&app.qh);
+    let window = app
+        .xdg_shell
+        .create_window(surface, WindowDecorations::ServerDefault, &app.qh);
+    window.set_title("Responsive layout example");
+    window.set_app_id("io.github.ciantic.wayapp.ResponsiveExample");
+    window.set_min_size(Some((300, 200)));
+    window.commit();
+
+    let responsive = Responsive {
+        width: 0,
+        pixels_per_point: 1.0,
+        page: 0,
+    };
+    app.push_window(EguiWindow::new(window, responsive, 720, 400));
+
+    app.run_blocking();
+}
@@ -0,0 +1,65 @@
+use egui::CentralPanel;
+use egui::Context;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::wlr_layer::Anchor;
+use smithay_client_toolkit::shell::wlr_layer::KeyboardInteractivity;
+use smithay_client_toolkit::shell::wlr_layer::Layer;
+use smithay_client_toolkit::shell::xdg::window::WindowDecorations;
+use wayapp::EguiAppData;
+use wayapp::EguiLayerSurface;
+use wayapp::EguiWindow;
+use wayapp::LayerSurfaceBuilder;
+use wayapp::get_init_app;
+use wayland_client::Proxy;
+
+/// Surfaces of the group are mapped only once all of them are configured
+const GROUP: &str = "startup";
+
+struct Label(&'static str);
+
+impl EguiAppData for Label {
+    fn ui(&mut self, ctx: &Context) {
+        CentralPanel::default().show(ctx, |ui| {
+            ui.label(self.0);
+        });
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+
+    let bar = LayerSurfaceBuilder::new()
+        .layer(Layer::Top)
+        .anchor(Anchor::LEFT | Anchor::RIGHT | Anchor::TOP)
+        .size(0, 32)
+        .exclusive_zone(32)
+        .keyboard_interactivity(KeyboardInteractivity::None)
+        .namespace("RevealBar");
+    let bar = EguiLayerSurface::from_builder(&bar, Label("Bar")).expect("Invalid bar settings");
+    app.reveal_together(GROUP, &bar.layer_surface.wl_surface().id());
+    app.push_layer_surface(bar);
+
+    let dock = LayerSurfaceBuilder::new()
+        .layer(Layer::Top)
+        .anchor(Anchor::LEFT | Anchor::RIGHT | Anchor::BOTTOM)
+        .size(0, 48)
+        .exclusive_zone(48)
+        .keyboard_interactivity(KeyboardInteractivity::None)
+        .namespace("RevealDock");
+    let dock = EguiLayerSurface::from_builder(&dock, Label("Dock")).expect("Invalid dock settings");
+    app.reveal_together(GROUP, &dock.layer_surface.wl_surface().id());
+    app.push_layer_surface(dock);
+
+    let surface = app.compositor_state.create_surface(&app.qh);
+    let window = app
+        .xdg_shell
+        .create_window(surface, WindowDecorations::ServerDefault, &app.qh);
+    window.set_title("Reveal example");
+    window.set_app_id("io.github.ciantic.wayapp.RevealExample");
+    window.commit();
+    app.reveal_together(GROUP, &window.wl_surface().id());
+    app.push_window(EguiWindow::new(window, Label("Main window"), 400, 300));
+
+    app.run_blocking();
+}
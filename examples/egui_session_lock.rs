@@ -0,0 +1,65 @@
+use egui::CentralPanel;
+use egui::Context;
+use egui::Key;
+use egui::TextEdit;
+use log::info;
+use wayapp::EguiAppData;
+use wayapp::EguiLockSurface;
+use wayapp::SessionLockHandle;
+use wayapp::get_app;
+use wayapp::get_init_app;
+
+/// Password prompt shown on every output
+///
+/// There is no PAM here, any non-empty password unlocks. A real locker
+/// verifies it before calling `unlock`.
+struct LockScreen {
+    lock: SessionLockHandle,
+    password: String,
+}
+
+impl EguiAppData for LockScreen {
+    fn ui(&mut self, ctx: &Context) {
+        CentralPanel::default().show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.add_space(ui.available_height() / 3.0);
+                ui.heading("Locked");
+                if !self.lock.is_locked() {
+                    ui.label("Waiting for the compositor...");
+                }
+                let response = ui.add(
+                    TextEdit::singleline(&mut self.password)
+                        .password(true)
+                        .hint_text("Password"),
+                );
+                response.request_focus();
+                if response.lost_focus()
+                    && ui.input(|input| input.key_pressed(Key::Enter))
+                    && !self.password.is_empty()
+                {
+                    info!("Unlocking");
+                    self.lock.unlock();
+                    // Idle callbacks run in order, the loop exits after the
+                    // unlock and destroys the rest cleanly
+                    get_app().loop_handle.insert_idle(|app| app.exit());
+                }
+            });
+        });
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+
+    app.lock_session(|app, lock, output| {
+        let lock_screen = LockScreen {
+            lock: lock.clone(),
+            password: String::new(),
+        };
+        EguiLockSurface::new_with(app, lock, output, lock_screen)
+    })
+    .expect("Compositor doesn't support ext-session-lock-v1");
+
+    app.run_blocking();
+}
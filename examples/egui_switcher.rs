@@ -0,0 +1,152 @@
+use egui::Button;
+use egui::CentralPanel;
+use egui::Context;
+use egui::TextureId;
+use log::info;
+use smithay_client_toolkit::reexports::calloop::timer::TimeoutAction;
+use smithay_client_toolkit::reexports::calloop::timer::Timer;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::wlr_layer::Anchor;
+use smithay_client_toolkit::shell::wlr_layer::Layer;
+use smithay_client_toolkit::shell::xdg::window::WindowDecorations;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+use wayapp::EguiAppData;
+use wayapp::EguiLayerSurface;
+use wayapp::EguiSurface;
+use wayapp::EguiWindow;
+use wayapp::LayerSurfaceSettings;
+use wayapp::get_init_app;
+use wayland_backend::client::ObjectId;
+use wayland_client::Proxy;
+
+const THUMBNAIL_SIZE: u32 = 160;
+
+struct CounterApp {
+    index: usize,
+    counter: u32,
+}
+
+impl EguiAppData for CounterApp {
+    fn ui(&mut self, ctx: &Context) {
+        // Changes every frame so the thumbnails have something to show
+        self.counter += 1;
+        CentralPanel::default().show(ctx, |ui| {
+            ui.heading(format!("Window {}", self.index));
+            ui.label(format!("Frame {}", self.counter));
+            ui.spinner();
+        });
+    }
+}
+
+/// Registered thumbnail texture and its generation per window
+type Registered = Rc<RefCell<Vec<Option<(u64, TextureId, egui::Vec2)>>>>;
+
+struct Switcher {
+    registered: Registered,
+}
+
+impl EguiAppData for Switcher {
+    fn ui(&mut self, ctx: &Context) {
+        CentralPanel::default().show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                for (index, thumbnail) in self.registered.borrow().iter().enumerate() {
+                    let Some((_, texture, size)) = thumbnail else {
+                        ui.label(format!("Window {}", index));
+                        continue;
+                    };
+                    if ui.add(Button::image((*texture, *size))).clicked() {
+                        // Activation needs xdg-activation, which isn't bound
+                        info!("[MAIN] Switch to window {}", index);
+                    }
+                }
+            });
+        });
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+    let thumbnails = app.thumbnails(THUMBNAIL_SIZE, Duration::from_millis(500));
+
+    let mut windows = Vec::new();
+    for index in 0..3 {
+        let surface = app.compositor_state.create_surface(&app.qh);
+        let window =
+            app.xdg_shell
+                .create_window(surface, WindowDecorations::ServerDefault, &app.qh);
+        window.set_title(format!("Switcher example {}", index));
+        window.set_app_id("io.github.ciantic.wayapp.SwitcherExample");
+        window.commit();
+        let surface_id: ObjectId = window.wl_surface().id();
+        let window = Rc::new(RefCell::new(
+            EguiWindow::new(window, CounterApp { index, counter: 0 }, 320, 240)
+                .with_render_stats(true),
+        ));
+        app.push_window(window.clone());
+        windows.push((surface_id, window));
+    }
+
+    let layer_surface = app.layer_shell.create_layer_surface(
+        &app.qh,
+        app.compositor_state.create_surface(&app.qh),
+        Layer::Overlay,
+        Some("Switcher"),
+        None,
+    );
+    LayerSurfaceSettings {
+        anchor: Anchor::BOTTOM,
+        width: 3 * (THUMBNAIL_SIZE + 16),
+        height: THUMBNAIL_SIZE + 32,
+        ..Default::default()
+    }
+    .apply(&layer_surface, true)
+    .expect("Invalid switcher settings");
+    layer_surface.commit();
+    let switcher_id = layer_surface.wl_surface().id();
+
+    let registered: Registered = Rc::new(RefCell::new(vec![None; windows.len()]));
+    let switcher = Rc::new(RefCell::new(EguiLayerSurface::new(
+        layer_surface,
+        Switcher {
+            registered: registered.clone(),
+        },
+        3 * (THUMBNAIL_SIZE + 16),
+        THUMBNAIL_SIZE + 32,
+    )));
+    app.push_layer_surface(switcher.clone());
+
+    // Register new thumbnail textures with the switcher, they update in place
+    // so only resizes need a new registration. Render stats of the captured
+    // windows show the cost of capturing.
+    app.loop_handle
+        .insert_source(Timer::immediate(), move |_, _, app| {
+            for (index, (surface_id, window)) in windows.iter().enumerate() {
+                app.request_redraw(surface_id);
+                let Some(thumbnail) = thumbnails.get(surface_id) else {
+                    continue;
+                };
+                let current = registered.borrow()[index].map(|(generation, ..)| generation);
+                if current != Some(thumbnail.generation) {
+                    let texture = switcher
+                        .borrow_mut()
+                        .register_native_texture(&thumbnail.view, wgpu::FilterMode::Linear);
+                    let size = egui::vec2(thumbnail.width as f32, thumbnail.height as f32);
+                    registered.borrow_mut()[index] = Some((thumbnail.generation, texture, size));
+                }
+                if let Some(stats) = window.borrow().render_stats() {
+                    info!(
+                        "[MAIN] Window {} p95 {:?} avg {:?}",
+                        index, stats.p95_frame_time, stats.avg_frame_time
+                    );
+                }
+            }
+            app.request_redraw(&switcher_id);
+            TimeoutAction::ToDuration(Duration::from_millis(500))
+        })
+        .expect("Failed to insert switcher timer");
+
+    app.run_blocking();
+}
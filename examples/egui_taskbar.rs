@@ -0,0 +1,73 @@
+use egui::CentralPanel;
+use egui::Context;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::wlr_layer::Anchor;
+use smithay_client_toolkit::shell::wlr_layer::KeyboardInteractivity;
+use smithay_client_toolkit::shell::wlr_layer::Layer;
+use smithay_client_toolkit::shell::wlr_layer::LayerSurface;
+use wayapp::EguiAppData;
+use wayapp::EguiLayerSurface;
+use wayapp::LayerSurfaceBuilder;
+use wayapp::get_init_app;
+
+const HEIGHT: u32 = 32;
+const EXPANDED_HEIGHT: u32 = 96;
+
+struct Taskbar {
+    layer_surface: LayerSurface,
+    expanded: bool,
+}
+
+impl Taskbar {
+    /// Reserve exactly the height of the bar so windows are laid out below it
+    fn set_height(&self, height: u32) {
+        self.layer_surface.set_size(0, height);
+        self.layer_surface.set_exclusive_zone(height as i32);
+        self.layer_surface.commit();
+    }
+}
+
+impl EguiAppData for Taskbar {
+    fn ui(&mut self, ctx: &Context) {
+        CentralPanel::default().show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Taskbar");
+                let text = if self.expanded { "Collapse" } else { "Expand" };
+                if ui.button(text).clicked() {
+                    self.expanded = !self.expanded;
+                    self.set_height(if self.expanded {
+                        EXPANDED_HEIGHT
+                    } else {
+                        HEIGHT
+                    });
+                }
+            });
+        });
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+
+    // Stretched between the left and right edges, the exclusive zone equals
+    // the height
+    let layer_surface = LayerSurfaceBuilder::new()
+        .layer(Layer::Top)
+        .anchor(Anchor::LEFT | Anchor::RIGHT | Anchor::TOP)
+        .size(0, HEIGHT)
+        .exclusive_zone(HEIGHT as i32)
+        .keyboard_interactivity(KeyboardInteractivity::None)
+        .namespace("Taskbar")
+        .strict(true)
+        .build(app)
+        .expect("Invalid taskbar settings");
+
+    let taskbar = Taskbar {
+        layer_surface: layer_surface.clone(),
+        expanded: false,
+    };
+    app.push_layer_surface(EguiLayerSurface::new(layer_surface, taskbar, 256, HEIGHT));
+
+    app.run_blocking();
+}
@@ -0,0 +1,54 @@
+use egui::CentralPanel;
+use egui::Context;
+use egui::Theme;
+use egui::emath::easing;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::xdg::window::WindowDecorations;
+use std::time::Duration;
+use wayapp::EguiAppData;
+use wayapp::EguiSurface;
+use wayapp::EguiWindow;
+use wayapp::get_init_app;
+use wayapp::set_theme_animated;
+
+/// Fades between the light and dark theme
+struct ThemeFade {
+    dark: bool,
+    text: String,
+}
+
+impl EguiAppData for ThemeFade {
+    fn ui(&mut self, ctx: &Context) {
+        CentralPanel::default().show(ctx, |ui| {
+            ui.heading("Theme fade");
+            if ui.checkbox(&mut self.dark, "Dark mode").changed() {
+                let theme = if self.dark { Theme::Dark } else { Theme::Light };
+                set_theme_animated(ctx, theme, Duration::from_millis(200), easing::cubic_in_out);
+            }
+            ui.text_edit_singleline(&mut self.text);
+            let _ = ui.button("Button");
+            ui.hyperlink("https://github.com/Ciantic/wayapp");
+        });
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+
+    let surface = app.compositor_state.create_surface(&app.qh);
+    let window = app
+        .xdg_shell
+        .create_window(surface, WindowDecorations::ServerDefault, &app.qh);
+    window.set_title("Theme fade example");
+    window.set_app_id("io.github.ciantic.wayapp.ThemeFadeExample");
+    window.set_min_size(Some((300, 200)));
+    window.commit();
+    let fade = ThemeFade {
+        dark: true,
+        text: String::from("Some text"),
+    };
+    app.push_window(EguiWindow::new(window, fade, 300, 200).with_theme(Theme::Dark));
+
+    app.run_blocking();
+}
@@ -0,0 +1,51 @@
+use egui::CentralPanel;
+use egui::Color32;
+use egui::Context;
+use egui::Frame;
+use egui::Theme;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::wlr_layer::Anchor;
+use smithay_client_toolkit::shell::wlr_layer::Layer;
+use wayapp::EguiAppData;
+use wayapp::EguiLayerSurface;
+use wayapp::EguiSurface;
+use wayapp::get_init_app;
+
+struct PanelApp;
+
+impl EguiAppData for PanelApp {
+    fn ui(&mut self, ctx: &Context) {
+        let frame = Frame::NONE
+            .fill(Color32::from_black_alpha(160))
+            .corner_radius(8.0)
+            .inner_margin(12.0);
+        CentralPanel::default().frame(frame).show(ctx, |ui| {
+            ui.heading("Semi-transparent panel");
+            ui.label("The desktop is visible through this panel");
+        });
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+
+    let layer_surface = app.layer_shell.create_layer_surface(
+        &app.qh,
+        app.compositor_state.create_surface(&app.qh),
+        Layer::Top,
+        Some("TransparentPanel"),
+        None,
+    );
+    layer_surface.set_anchor(Anchor::TOP | Anchor::RIGHT);
+    layer_surface.set_margin(16, 16, 0, 0);
+    layer_surface.set_size(320, 120);
+    layer_surface.commit();
+
+    let panel = EguiLayerSurface::new(layer_surface, PanelApp, 320, 120)
+        .with_theme(Theme::Dark)
+        .with_background(Color32::TRANSPARENT);
+    app.push_layer_surface(panel);
+
+    app.run_blocking();
+}
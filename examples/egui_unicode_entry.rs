@@ -0,0 +1,62 @@
+use egui::CentralPanel;
+use egui::Context;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::xdg::window::WindowDecorations;
+use wayapp::EguiAppData;
+use wayapp::EguiSurface;
+use wayapp::EguiWindow;
+use wayapp::get_init_app;
+
+struct MainApp {
+    text: String,
+    rejected: u32,
+}
+
+impl EguiAppData for MainApp {
+    fn ui(&mut self, ctx: &Context) {
+        CentralPanel::default().show(ctx, |ui| {
+            ui.label("Type Ctrl+Shift+U, 1f600 and Enter to insert 😀");
+            ui.label("Or hold Alt and type 128512 on the numpad");
+            ui.text_edit_singleline(&mut self.text).request_focus();
+            if self.rejected > 0 {
+                ui.label(format!("Rejected codepoints: {}", self.rejected));
+            }
+        });
+    }
+
+    fn bell(&mut self) {
+        self.rejected += 1;
+    }
+}
+
+/// Screenshots for the docs, called by the `render-docs` bin
+#[cfg(feature = "docs-render")]
+#[allow(dead_code)]
+pub fn register_docs(gallery: &mut wayapp::Gallery) {
+    gallery.register("egui_unicode_entry", (360, 160), || MainApp {
+        text: "😀".to_string(),
+        rejected: 0,
+    });
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+
+    let surface = app.compositor_state.create_surface(&app.qh);
+    let window = app
+        .xdg_shell
+        .create_window(surface, WindowDecorations::ServerDefault, &app.qh);
+    window.set_title("Unicode entry example");
+    window.set_app_id("io.github.ciantic.wayapp.UnicodeEntryExample");
+    window.set_min_size(Some((360, 160)));
+    window.commit();
+
+    let main_app = MainApp {
+        text: String::new(),
+        rejected: 0,
+    };
+    app.push_window(EguiWindow::new(window, main_app, 360, 160).with_unicode_entry(true));
+
+    app.run_blocking();
+}
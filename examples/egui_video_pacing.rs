@@ -0,0 +1,95 @@
+use egui::CentralPanel;
+use egui::Color32;
+use egui::Context;
+use egui::RichText;
+use log::info;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::xdg::window::WindowDecorations;
+use std::time::Duration;
+use std::time::Instant;
+use wayapp::EguiAppData;
+use wayapp::EguiWindow;
+use wayapp::RedrawHandle;
+use wayapp::get_app;
+use wayapp::get_init_app;
+use wayland_backend::client::ObjectId;
+use wayland_client::Proxy;
+
+const FPS: f64 = 24.0;
+
+/// Synthetic 24 fps video, each frame is a highlighted cell of a counter
+/// strip. On a 60 Hz output the presentation deltas alternate between 3 and
+/// 2 refreshes.
+struct VideoPlayer {
+    surface_id: ObjectId,
+    redraw: RedrawHandle,
+    start: Instant,
+    last_presented: Option<(u64, Instant)>,
+}
+
+impl VideoPlayer {
+    /// Log the vblanks between the last two presented frames
+    fn measure(&mut self) {
+        let Some(presented) = get_app().last_presented(&self.surface_id) else {
+            return;
+        };
+        if let Some((sequence, presented_at)) = self.last_presented {
+            if sequence == presented.sequence {
+                return;
+            }
+            info!(
+                "Presented after {} refreshes, {:.1} ms",
+                presented.sequence - sequence,
+                (presented.presented_at - presented_at).as_secs_f64() * 1000.0
+            );
+        }
+        self.last_presented = Some((presented.sequence, presented.presented_at));
+    }
+}
+
+impl EguiAppData for VideoPlayer {
+    fn ui(&mut self, ctx: &Context) {
+        self.measure();
+        let frame = (self.start.elapsed().as_secs_f64() * FPS) as u64;
+        CentralPanel::default().show(ctx, |ui| {
+            ui.heading(format!("Frame {}", frame));
+            ui.horizontal(|ui| {
+                for cell in 0..24 {
+                    let text = RichText::new(format!("{:02}", cell)).monospace();
+                    if cell == frame % 24 {
+                        ui.label(text.background_color(Color32::YELLOW).color(Color32::BLACK));
+                    } else {
+                        ui.label(text);
+                    }
+                }
+            });
+        });
+        // Shown at the vblank nearest to the time of the next video frame
+        let next = self.start + Duration::from_secs_f64((frame + 1) as f64 / FPS);
+        self.redraw.present_at(next);
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+
+    let surface = app.compositor_state.create_surface(&app.qh);
+    let window = app
+        .xdg_shell
+        .create_window(surface, WindowDecorations::ServerDefault, &app.qh);
+    window.set_title("Video pacing example");
+    window.set_app_id("io.github.ciantic.wayapp.VideoPacingExample");
+    window.commit();
+
+    let surface_id = window.wl_surface().id();
+    let player = VideoPlayer {
+        redraw: app.redraw_handle(&surface_id),
+        surface_id,
+        start: Instant::now(),
+        last_presented: None,
+    };
+    app.push_window(EguiWindow::new(window, player, 640, 120));
+
+    app.run_blocking();
+}
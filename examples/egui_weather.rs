@@ -0,0 +1,97 @@
+use egui::CentralPanel;
+use egui::Context;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::xdg::window::WindowDecorations;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+use wayapp::EguiAppData;
+use wayapp::EguiWindow;
+use wayapp::Tasks;
+use wayapp::get_init_app;
+use wayland_client::Proxy;
+
+struct Forecast {
+    city: String,
+    temperature: i32,
+}
+
+/// Stand-in for an HTTP request, takes a while and yields a forecast
+async fn fetch_forecast(city: String) -> Result<Forecast, String> {
+    std::thread::sleep(Duration::from_millis(800));
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|err| err.to_string())?
+        .subsec_millis();
+    Ok(Forecast {
+        city,
+        temperature: (seed % 35) as i32 - 5,
+    })
+}
+
+struct WeatherWidget {
+    city: String,
+    tasks: Tasks<Result<Forecast, String>>,
+    forecast: Option<Result<Forecast, String>>,
+}
+
+impl EguiAppData for WeatherWidget {
+    fn ui(&mut self, ctx: &Context) {
+        // Results first, before this frame's input reaches the widgets
+        for result in self.tasks.drain() {
+            self.forecast = Some(result);
+        }
+        CentralPanel::default().show(ctx, |ui| {
+            ui.heading("Weather");
+            ui.text_edit_singleline(&mut self.city);
+            ui.horizontal(|ui| {
+                if ui.button("Fetch").clicked() {
+                    self.tasks.spawn(fetch_forecast(self.city.clone()));
+                }
+                if self.tasks.running() > 0 {
+                    ui.spinner();
+                }
+            });
+            match &self.forecast {
+                Some(Ok(forecast)) => {
+                    ui.label(format!("{}: {} °C", forecast.city, forecast.temperature));
+                }
+                Some(Err(err)) => {
+                    ui.label(format!("Fetch failed: {}", err));
+                }
+                None => {
+                    ui.label("No forecast yet");
+                }
+            }
+        });
+    }
+
+    /// The forecast is stale after a suspend, fetch it again
+    fn system_resumed(&mut self, _gap: Duration) {
+        if self.forecast.is_some() {
+            self.tasks.spawn(fetch_forecast(self.city.clone()));
+        }
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+
+    let surface = app.compositor_state.create_surface(&app.qh);
+    let window = app
+        .xdg_shell
+        .create_window(surface, WindowDecorations::ServerDefault, &app.qh);
+    window.set_title("Weather example");
+    window.set_app_id("io.github.ciantic.wayapp.WeatherExample");
+    window.commit();
+
+    let widget = WeatherWidget {
+        city: "Helsinki".to_string(),
+        tasks: Tasks::new(app.redraw_handle(&window.wl_surface().id())),
+        forecast: None,
+    };
+    app.push_window(EguiWindow::new(window, widget, 320, 200));
+
+    app.run_blocking();
+}
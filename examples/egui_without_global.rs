@@ -0,0 +1,43 @@
+use egui::CentralPanel;
+use egui::Context;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::xdg::window::WindowDecorations;
+use wayapp::Application;
+use wayapp::EguiAppData;
+use wayapp::EguiWindow;
+
+struct MainApp {
+    counter: i32,
+}
+
+impl EguiAppData for MainApp {
+    fn ui(&mut self, ctx: &Context) {
+        CentralPanel::default().show(ctx, |ui| {
+            ui.heading("Application owned by main");
+            ui.label(format!("Counter: {}", self.counter));
+            if ui.button("Increment").clicked() {
+                self.counter += 1;
+            }
+        });
+    }
+}
+
+fn main() {
+    env_logger::init();
+    // Not the global from `get_init_app`, containers get it explicitly
+    let mut app = Application::new();
+
+    let surface = app.compositor_state.create_surface(&app.qh);
+    let window = app
+        .xdg_shell
+        .create_window(surface, WindowDecorations::ServerDefault, &app.qh);
+    window.set_title("Without global example");
+    window.set_app_id("io.github.ciantic.wayapp.WithoutGlobalExample");
+    window.set_min_size(Some((256, 128)));
+    window.commit();
+
+    let egui_window = EguiWindow::new_with(&mut app, window, MainApp { counter: 0 }, 256, 128);
+    app.push_window(egui_window);
+
+    app.run_blocking();
+}
@@ -0,0 +1,45 @@
+use egui::CentralPanel;
+use egui::Context;
+use egui::Frame;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::xdg::window::WindowDecorations;
+use wayapp::EguiAppData;
+use wayapp::EguiSubsurface;
+use wayapp::SingleColorRole;
+use wayapp::SingleColorSurface;
+use wayapp::get_init_app;
+
+struct Card;
+
+impl EguiAppData for Card {
+    fn ui(&mut self, ctx: &Context) {
+        CentralPanel::default()
+            .frame(Frame::default().inner_margin(12.0))
+            .show(ctx, |ui| {
+                ui.heading("Backdrop");
+                ui.label("The window itself is a shm buffer, only this card uses WGPU");
+            });
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let app = get_init_app();
+
+    let surface = app.compositor_state.create_surface(&app.qh);
+    let window = app
+        .xdg_shell
+        .create_window(surface, WindowDecorations::ServerDefault, &app.qh);
+    window.set_title("Single color backdrop example");
+    window.set_app_id("io.github.ciantic.wayapp.SingleColorBackdropExample");
+    window.set_min_size(Some((360, 200)));
+    window.commit();
+
+    let card = EguiSubsurface::create(window.wl_surface(), 20, 20, Card, 320, 160);
+    let backdrop =
+        SingleColorSurface::new(SingleColorRole::Window(window), [32, 48, 96, 255], 480, 320);
+    app.push_window(backdrop);
+    app.push_subsurface(card);
+
+    app.run_blocking();
+}
@@ -31,6 +31,7 @@ fn main() {
         layer_surface: example_layer_surface,
         color: (255, 0, 0),
         pool: None,
+        qh: None,
     });
 
     let surface2 = app.compositor_state.create_surface(&app.qh);
@@ -51,6 +52,7 @@ fn main() {
         layer_surface: example_layer_surface2,
         color: (0, 255, 0),
         pool: None,
+        qh: None,
     });
 
     // Example window --------------------------
@@ -69,6 +71,7 @@ fn main() {
         window: example_window.clone(),
         color: (0, 0, 255),
         pool: None,
+        qh: None,
     });
 
     // Example child window --------------------------
@@ -89,6 +92,7 @@ fn main() {
         window: child_window,
         color: (255, 0, 255),
         pool: None,
+        qh: None,
     });
 
     // Example subsurface --------------------------
@@ -105,6 +109,7 @@ fn main() {
         wl_surface: sub_wlsurface,
         color: (128, 128, 0),
         pool: None,
+        qh: None,
     };
 
     // Configure initial size for subsurface
@@ -131,6 +136,7 @@ fn main() {
         popup,
         color: (255, 255, 0),
         pool: None,
+        qh: None,
     });
 
     trace!("Starting event loop for common example");
@@ -0,0 +1,156 @@
+//! Advisory warnings for common application mistakes.
+//!
+//! On by default in debug builds, `WAYAPP_ADVICE=0` turns them off and
+//! `WAYAPP_ADVICE=1` turns them on in release builds. Each advice is logged
+//! once per surface, naming the API that avoids the problem.
+use crate::Application;
+use log::warn;
+use smithay_client_toolkit::reexports::calloop::timer::TimeoutAction;
+use smithay_client_toolkit::reexports::calloop::timer::Timer;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::OnceLock;
+use std::time::Duration;
+use wayland_backend::client::ObjectId;
+
+/// `ui` calls slower than this count as blocking
+const SLOW_UI: Duration = Duration::from_millis(100);
+const SLOW_UI_COUNT: u32 = 5;
+
+/// Consecutive frames allocating new textures
+const TEXTURE_CHURN_FRAMES: u32 = 10;
+
+const CLOSE_REFUSALS: u32 = 3;
+
+/// Xdg and layer surfaces get their first configure well before this once
+/// committed
+const CONFIGURE_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub(crate) fn enabled() -> bool {
+    static ENABLED: OnceLock<bool> = OnceLock::new();
+    *ENABLED.get_or_init(|| match std::env::var("WAYAPP_ADVICE").as_deref() {
+        Ok("0") => false,
+        Ok(_) => true,
+        Err(_) => cfg!(debug_assertions),
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Advice {
+    SlowUi,
+    TextureChurn,
+    CloseRefused,
+    NeverConfigured,
+}
+
+/// Heuristics measured by the surface while rendering
+#[derive(Default)]
+pub(crate) struct SurfaceAdvice {
+    slow_ui: u32,
+    texture_frames: u32,
+    given: HashSet<Advice>,
+}
+
+impl SurfaceAdvice {
+    pub fn ui_time(&mut self, surface: &ObjectId, elapsed: Duration) {
+        if elapsed < SLOW_UI || !enabled() {
+            return;
+        }
+        self.slow_ui += 1;
+        if self.slow_ui >= SLOW_UI_COUNT && self.given.insert(Advice::SlowUi) {
+            warn!(
+                "[ADVICE] Surface {}: ui() took {} ms, {} times over {} ms; move blocking work to \
+                 a thread and redraw with a RedrawHandle when it's done",
+                surface,
+                elapsed.as_millis(),
+                self.slow_ui,
+                SLOW_UI.as_millis()
+            );
+        }
+    }
+
+    /// Whether the frame allocated textures other than the font atlas
+    pub fn textures_allocated(&mut self, surface: &ObjectId, allocated: bool) {
+        if !enabled() {
+            return;
+        }
+        if !allocated {
+            self.texture_frames = 0;
+            return;
+        }
+        self.texture_frames += 1;
+        if self.texture_frames >= TEXTURE_CHURN_FRAMES && self.given.insert(Advice::TextureChurn) {
+            warn!(
+                "[ADVICE] Surface {}: new textures allocated on {} frames in a row; keep the \
+                 TextureHandle from Context::load_texture instead of loading it in ui()",
+                surface, self.texture_frames
+            );
+        }
+    }
+}
+
+/// Heuristics tracked by the application
+#[derive(Default)]
+pub(crate) struct AppAdvice {
+    unconfigured: HashSet<ObjectId>,
+    close_refusals: HashMap<ObjectId, u32>,
+    given: HashSet<(ObjectId, Advice)>,
+}
+
+impl AppAdvice {
+    /// Container was pushed for a surface that needs a configure
+    pub fn expect_configure(app: &mut Application, surface_id: &ObjectId) {
+        if !enabled() || !app.advice.unconfigured.insert(surface_id.clone()) {
+            return;
+        }
+        let surface_id = surface_id.clone();
+        app.loop_handle
+            .insert_source(Timer::from_duration(CONFIGURE_TIMEOUT), move |_, _, app| {
+                let exists = app.has_surface(&surface_id);
+                let advice = &mut app.advice;
+                if advice.unconfigured.remove(&surface_id)
+                    && exists
+                    && advice
+                        .given
+                        .insert((surface_id.clone(), Advice::NeverConfigured))
+                {
+                    warn!(
+                        "[ADVICE] Surface {}: no configure within {} s of pushing its container; \
+                         commit the surface once after creating it so the compositor configures it",
+                        surface_id,
+                        CONFIGURE_TIMEOUT.as_secs()
+                    );
+                }
+                TimeoutAction::Drop
+            })
+            .expect("Failed to insert advice timer");
+    }
+
+    pub fn configured(&mut self, surface_id: &ObjectId) {
+        self.unconfigured.remove(surface_id);
+    }
+
+    pub fn close_refused(&mut self, surface_id: &ObjectId) {
+        if !enabled() {
+            return;
+        }
+        let refusals = self.close_refusals.entry(surface_id.clone()).or_default();
+        *refusals += 1;
+        if *refusals >= CLOSE_REFUSALS
+            && self
+                .given
+                .insert((surface_id.clone(), Advice::CloseRefused))
+        {
+            warn!(
+                "[ADVICE] Window {}: refused to close {} times; allowed_to_close returns false, \
+                 handle request_close and remove the window when the app is done",
+                surface_id, refusals
+            );
+        }
+    }
+
+    pub fn removed(&mut self, surface_id: &ObjectId) {
+        self.unconfigured.remove(surface_id);
+        self.close_refusals.remove(surface_id);
+    }
+}
@@ -1,24 +1,95 @@
+use crate::BaseTrait;
+use crate::DndHandlerContainer;
+use crate::FrameStatus;
+use crate::FrameToken;
+use crate::GpuContext;
+use crate::KeyboardHandlerContainer;
 use crate::LayerSurfaceContainer;
+use crate::LockSurfaceContainer;
+use crate::OutputSelector;
 use crate::PopupContainer;
+use crate::Presented;
+use crate::ProtocolVersions;
+use crate::RedrawHandle;
+use crate::SessionLockHandle;
 use crate::SubsurfaceContainer;
+use crate::Thumbnails;
 use crate::WindowContainer;
+use crate::WindowHandle;
+use crate::advice::AppAdvice;
+use crate::dnd;
+use crate::dnd::DragState;
+use crate::dnd::DropResult;
+use crate::dnd::TransferLimits;
+use crate::graveyard::Graveyard;
+use crate::input_batch::InputBatch;
+use crate::keyboard::Compose;
+use crate::keyboard::KeyboardLayouts;
+use crate::keyboard::repeat_schedule;
+use crate::outputs::SurfaceOutputs;
+use crate::presentation::FrameTransactions;
+use crate::presentation::PresentationTime;
+use crate::protocol_versions::Global;
+use crate::raw_handles::clipboard_for_connection;
+use crate::readiness;
+use crate::readiness::HeldConfigure;
+use crate::readiness::Readiness;
+use crate::redraw::SurfaceRequest;
+use crate::redraw::SurfaceRequestKind;
+use crate::resume;
+use crate::resume::ResumeWatch;
+use crate::seats::KeyRepeat;
+use crate::seats::Seats;
+use crate::session_lock::ActiveLock;
+use crate::startup_timing;
+use crate::startup_timing::Phase;
+use crate::stuck_grab;
+use crate::stuck_grab::STUCK_GRAB_TIMEOUT;
+use crate::text_input::SeatTextInput;
+use crate::text_input::TextInputState;
+use crate::viewporter;
+use log::debug;
 use log::trace;
+use log::warn;
+use smithay_client_toolkit::activation::ActivationHandler;
+use smithay_client_toolkit::activation::ActivationState;
+use smithay_client_toolkit::activation::RequestData;
 use smithay_client_toolkit::compositor::CompositorHandler;
 use smithay_client_toolkit::compositor::CompositorState;
+use smithay_client_toolkit::data_device_manager::DataDeviceManagerState;
+use smithay_client_toolkit::data_device_manager::WritePipe;
+use smithay_client_toolkit::data_device_manager::data_device::DataDevice;
+use smithay_client_toolkit::data_device_manager::data_device::DataDeviceHandler;
+use smithay_client_toolkit::data_device_manager::data_offer::DataOfferHandler;
+use smithay_client_toolkit::data_device_manager::data_offer::DragOffer;
+use smithay_client_toolkit::data_device_manager::data_source::DataSourceHandler;
+use smithay_client_toolkit::delegate_activation;
 use smithay_client_toolkit::delegate_compositor;
+use smithay_client_toolkit::delegate_data_device;
 use smithay_client_toolkit::delegate_keyboard;
 use smithay_client_toolkit::delegate_layer;
 use smithay_client_toolkit::delegate_output;
 use smithay_client_toolkit::delegate_pointer;
 use smithay_client_toolkit::delegate_registry;
 use smithay_client_toolkit::delegate_seat;
+use smithay_client_toolkit::delegate_session_lock;
 use smithay_client_toolkit::delegate_shm;
 use smithay_client_toolkit::delegate_subcompositor;
 use smithay_client_toolkit::delegate_xdg_popup;
 use smithay_client_toolkit::delegate_xdg_shell;
 use smithay_client_toolkit::delegate_xdg_window;
+use smithay_client_toolkit::error::GlobalError;
 use smithay_client_toolkit::output::OutputHandler;
 use smithay_client_toolkit::output::OutputState;
+use smithay_client_toolkit::reexports::calloop::EventLoop;
+use smithay_client_toolkit::reexports::calloop::LoopHandle;
+use smithay_client_toolkit::reexports::calloop::RegistrationToken;
+use smithay_client_toolkit::reexports::calloop::channel;
+use smithay_client_toolkit::reexports::calloop::channel::Sender;
+use smithay_client_toolkit::reexports::calloop::timer::TimeoutAction;
+use smithay_client_toolkit::reexports::calloop::timer::Timer;
+use smithay_client_toolkit::reexports::calloop_wayland_source::WaylandSource;
+use smithay_client_toolkit::reexports::protocols::xdg::shell::client::xdg_toplevel::XdgToplevel;
 use smithay_client_toolkit::registry::ProvidesRegistryState;
 use smithay_client_toolkit::registry::RegistryState;
 use smithay_client_toolkit::registry_handlers;
@@ -27,11 +98,20 @@ use smithay_client_toolkit::seat::SeatHandler;
 use smithay_client_toolkit::seat::SeatState;
 use smithay_client_toolkit::seat::keyboard::KeyEvent;
 use smithay_client_toolkit::seat::keyboard::KeyboardHandler;
+use smithay_client_toolkit::seat::keyboard::Keymap;
 use smithay_client_toolkit::seat::keyboard::Keysym;
+use smithay_client_toolkit::seat::keyboard::RepeatInfo;
+use smithay_client_toolkit::seat::pointer::CursorIcon;
 use smithay_client_toolkit::seat::pointer::PointerEvent;
 use smithay_client_toolkit::seat::pointer::PointerEventKind;
 use smithay_client_toolkit::seat::pointer::PointerHandler;
+use smithay_client_toolkit::seat::pointer::ThemeSpec;
 use smithay_client_toolkit::seat::pointer::cursor_shape::CursorShapeManager;
+use smithay_client_toolkit::session_lock::SessionLock;
+use smithay_client_toolkit::session_lock::SessionLockHandler;
+use smithay_client_toolkit::session_lock::SessionLockState;
+use smithay_client_toolkit::session_lock::SessionLockSurface;
+use smithay_client_toolkit::session_lock::SessionLockSurfaceConfigure;
 use smithay_client_toolkit::shell::WaylandSurface;
 use smithay_client_toolkit::shell::wlr_layer::LayerShell;
 use smithay_client_toolkit::shell::wlr_layer::LayerShellHandler;
@@ -49,20 +129,36 @@ use smithay_client_toolkit::shm::ShmHandler;
 use smithay_client_toolkit::subcompositor::SubcompositorState;
 use smithay_clipboard::Clipboard;
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fs::File;
+use std::hash::Hash;
 use std::mem::MaybeUninit;
+use std::os::fd::OwnedFd;
+use std::sync::LazyLock;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
 use wayland_backend::client::ObjectId;
 use wayland_client::Connection;
 use wayland_client::EventQueue;
 use wayland_client::Proxy;
 use wayland_client::QueueHandle;
 use wayland_client::globals::registry_queue_init;
+use wayland_client::protocol::wl_data_device::WlDataDevice;
+use wayland_client::protocol::wl_data_device_manager::DndAction;
+use wayland_client::protocol::wl_data_source::WlDataSource;
 use wayland_client::protocol::wl_keyboard::WlKeyboard;
 use wayland_client::protocol::wl_output;
 use wayland_client::protocol::wl_pointer::WlPointer;
 use wayland_client::protocol::wl_seat;
+use wayland_client::protocol::wl_subsurface::WlSubsurface;
 use wayland_client::protocol::wl_surface::WlSurface;
 use wayland_protocols::wp::cursor_shape::v1::client::wp_cursor_shape_device_v1::Shape;
 use wayland_protocols::wp::cursor_shape::v1::client::wp_cursor_shape_device_v1::WpCursorShapeDeviceV1;
+use wayland_protocols::wp::viewporter::client::wp_viewporter::WpViewporter;
 
 /// Enum representing the kind of surface container stored in the application
 enum Kind {
@@ -70,23 +166,121 @@ enum Kind {
     LayerSurface(Box<dyn LayerSurfaceContainer>),
     Popup(Box<dyn PopupContainer>),
     Subsurface(Box<dyn SubsurfaceContainer>),
+    LockSurface(Box<dyn LockSurfaceContainer>),
 }
 
+impl Kind {
+    fn container(&mut self) -> &mut dyn BaseTrait {
+        match self {
+            Kind::Window(window) => window.as_mut(),
+            Kind::LayerSurface(layer_surface) => layer_surface.as_mut(),
+            Kind::Popup(popup) => popup.as_mut(),
+            Kind::Subsurface(subsurface) => subsurface.as_mut(),
+            Kind::LockSurface(lock_surface) => lock_surface.as_mut(),
+        }
+    }
+}
+
+/// Configure of a surface role, a container of another role ignores it
+trait RoleConfigure {
+    fn apply(&self, kind: &mut Kind);
+}
+
+impl RoleConfigure for HeldConfigure {
+    fn apply(&self, kind: &mut Kind) {
+        match (kind, self) {
+            (Kind::Window(window), HeldConfigure::Window(configure)) => {
+                window.configure(configure);
+            }
+            (Kind::LayerSurface(layer), HeldConfigure::LayerSurface(configure)) => {
+                layer.configure(configure);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl RoleConfigure for PopupConfigure {
+    fn apply(&self, kind: &mut Kind) {
+        if let Kind::Popup(popup) = kind {
+            popup.configure(self);
+        }
+    }
+}
+
+impl RoleConfigure for SessionLockSurfaceConfigure {
+    fn apply(&self, kind: &mut Kind) {
+        if let Kind::LockSurface(lock_surface) = kind {
+            lock_surface.configure(self);
+        }
+    }
+}
+
+/// Configure the container of the surface, configures of removed surfaces
+/// are dropped
+fn deliver_configure<K: Eq + Hash>(
+    surfaces: &mut HashMap<K, Kind>,
+    surface_id: &K,
+    configure: &impl RoleConfigure,
+) {
+    if let Some(kind) = surfaces.get_mut(surface_id) {
+        configure.apply(kind);
+    }
+}
+
+/// Container the events of the surface go to, e.g. frame callbacks and
+/// scale changes
+fn surface_target<'a, K: Eq + Hash>(
+    surfaces: &'a mut HashMap<K, Kind>,
+    surface_id: &K,
+) -> Option<&'a mut dyn BaseTrait> {
+    surfaces.get_mut(surface_id).map(Kind::container)
+}
+
+/// Container input of the surface goes to, input of surfaces without one is
+/// dropped
+fn input_target<'a, K: Eq + Hash>(
+    surfaces: &'a mut HashMap<K, Kind>,
+    surface_id: Option<&K>,
+) -> Option<&'a mut dyn BaseTrait> {
+    surface_target(surfaces, surface_id?)
+}
+
+/// Weston default, used until the compositor sends repeat_info
+const DEFAULT_REPEAT_INFO: RepeatInfo = RepeatInfo::Repeat {
+    rate: std::num::NonZeroU32::new(40).unwrap(),
+    delay: 400,
+};
+
 pub static mut WAYAPP: MaybeUninit<Application> = MaybeUninit::uninit();
 
+/// `WAYAPP` is written, `get_app` panics before that
+static WAYAPP_INIT: AtomicBool = AtomicBool::new(false);
+
+/// Create the global application, the EGUI containers' `new` use it.
+/// Applications created with `Application::new` are given to `new_with`.
 pub fn get_init_app() -> &'static mut Application {
     // Look behind you! A three-headed monkey!
     #[allow(static_mut_refs)]
     unsafe {
         WAYAPP.write(Application::new())
     };
+    WAYAPP_INIT.store(true, Ordering::Release);
     #[allow(static_mut_refs)]
     unsafe {
         WAYAPP.assume_init_mut()
     }
 }
 
+/// The global application created by `get_init_app`
+///
+/// Panics if it was never created, e.g. the application came from
+/// `Application::new` and a constructor without `_with` was used.
 pub fn get_app<'a>() -> &'a mut Application {
+    assert!(
+        WAYAPP_INIT.load(Ordering::Acquire),
+        "get_app() before get_init_app(), give an Application::new to the new_with constructors"
+    );
     // Look behind you! A three-headed monkey!
     #[allow(static_mut_refs)]
     unsafe {
@@ -98,6 +292,15 @@ pub struct Application {
     pub conn: Connection,
     pub event_queue: Option<EventQueue<Self>>,
     pub qh: QueueHandle<Self>,
+    event_loop: Option<EventLoop<'static, Self>>,
+    /// Handle for inserting timers, channels and other event sources, their
+    /// callbacks get `&mut Application`
+    pub loop_handle: LoopHandle<'static, Self>,
+    redraw_sender: Sender<SurfaceRequest>,
+    /// Drops read on background threads are delivered through this
+    drop_sender: Sender<DropResult>,
+    /// GPU resources of closed surfaces, dropped once their frames are done
+    graveyard: Graveyard,
     pub registry_state: RegistryState,
     pub seat_state: SeatState,
     pub output_state: OutputState,
@@ -106,34 +309,166 @@ pub struct Application {
     pub subcompositor_state: SubcompositorState,
     pub xdg_shell: XdgShell,
     pub layer_shell: LayerShell,
+    pub session_lock_state: SessionLockState,
     windows: Vec<ObjectId>,
     layer_surfaces: Vec<ObjectId>,
     popups: Vec<ObjectId>,
     subsurfaces: Vec<ObjectId>,
+    lock_surfaces: Vec<ObjectId>,
+    /// Parent surface of each subsurface created with `create_subsurface`
+    subsurface_parents: HashMap<ObjectId, ObjectId>,
     /// HashMap storing surface kind by ObjectId for quick lookup
     surfaces_by_id: HashMap<ObjectId, Kind>,
+    /// Deadline and timer of the pending delayed redraw of each surface
+    redraw_timers: HashMap<ObjectId, (Instant, RegistrationToken)>,
     pub clipboard: Clipboard,
 
-    cursor_shape_manager: CursorShapeManager,
+    /// Without cursor shape protocol the cursor is drawn from the theme
+    cursor_shape_manager: Option<CursorShapeManager>,
+    /// Keyboards and pointers of each seat
+    pub(crate) seats: Seats,
 
     /// For cursor set_shape to work serial parameter must match the latest
     /// wl_pointer.enter or zwp_tablet_tool_v2.proximity_in serial number sent
     /// to the client.
     last_pointer_enter_serial: Option<u32>,
     last_pointer: Option<WlPointer>,
+    /// Current cursor, `None` is hidden, re-applied on pointer enter
+    cursor: Option<Shape>,
+    /// Last pointer position on a surface that is not a popup
+    last_parent_pointer: Option<(WlSurface, (f64, f64))>,
+    /// Amount of pointer frames received, used for detecting stuck grabs
+    pointer_frame_count: u64,
+    /// Surface the pointer entered last, `None` after it left
+    pointer_focus: Option<ObjectId>,
+    /// Stuck grab resets are logged as warnings at most once a minute
+    stuck_grab_warned: Option<Instant>,
+    /// Serial of the latest pointer button or key press, popup grabs need it
+    last_input_serial: Option<u32>,
+    /// Seat of the latest keyboard enter, key press or pointer press
+    input_seat: Option<wl_seat::WlSeat>,
+    /// Compositors, as named in `XDG_CURRENT_DESKTOP`, that may leave the
+    /// parent surface without pointer events after a popup is dismissed. On
+    /// these the pointer state of the parent is reset if the popup had the
+    /// pointer and no pointer events arrive shortly after it's destroyed.
+    pub stuck_grab_compositors: Vec<String>,
     // Cache cursor shape devices per pointer to avoid repeated protocol calls
     pointer_shape_devices: HashMap<ObjectId, WpCursorShapeDeviceV1>,
-    /// Currently focused keyboard surface
-    keyboard_focused_surface: Option<ObjectId>,
+    /// Created lazily when the first WGPU surface is created
+    wgpu_instance: Option<wgpu::Instance>,
+    /// Instance being created on a worker thread while the globals are bound
+    wgpu_instance_init: Option<JoinHandle<wgpu::Instance>>,
+    gpu: Option<GpuContext>,
+    resume_watch: ResumeWatch,
+    keyboard_layouts: KeyboardLayouts,
+    pub(crate) text_input: TextInputState,
+    /// Outputs entered by windows, for picking the fullscreen output
+    surface_outputs: SurfaceOutputs,
+    pub(crate) advice: AppAdvice,
+    /// Groups of surfaces mapped together, see `reveal_together`
+    readiness: Readiness,
+    protocol_versions: ProtocolVersions,
+    /// Shared with every EGUI surface, captures nothing until enabled
+    thumbnails: Thumbnails,
+    /// Shared so every surface reuses the same rasterizations
+    #[cfg(feature = "icons")]
+    icons: crate::Icons,
+    pub(crate) presentation: PresentationTime,
+    pub(crate) transactions: FrameTransactions,
+    /// Surfaces to render once the current dispatch is done
+    pub(crate) input_batch: InputBatch,
+    session_lock: Option<ActiveLock>,
+    /// `None` without wl_data_device_manager, nothing can be dropped then
+    data_device_manager: Option<DataDeviceManagerState>,
+    data_device: Option<DataDevice>,
+    drag: Option<DragState>,
+    /// Offer of the drop being read, finished once its data has arrived
+    dropping: Option<(u64, DragOffer)>,
+    drops: u64,
+    transfer_limits: TransferLimits,
+    /// `None` without xdg_activation_v1, windows can't ask for focus then
+    activation: Option<ActivationState>,
+    /// Token of the launcher from `XDG_ACTIVATION_TOKEN`, used for the first
+    /// configured window
+    startup_token: Option<String>,
+    /// App id sent with activation token requests, set it to the app id of
+    /// the windows so the compositor can match them
+    pub app_id: Option<String>,
+    /// Stretches single pixel buffers of `SingleColorSurface`
+    pub(crate) viewporter: Option<WpViewporter>,
+    /// `run_blocking` returns after the current dispatch
+    exit_requested: bool,
+    exit_when_no_windows: bool,
+    /// A window has been pushed, apps without windows never exit on their own
+    had_windows: bool,
 }
 
 impl Application {
     /// Create a new Application, initializing all Wayland globals and state.
+    ///
+    /// Panics without a Wayland compositor, X11 sessions are not supported.
+    ///
+    /// The launcher's `XDG_ACTIVATION_TOKEN` is read for the first window but
+    /// left in the environment, see `take_startup_token`.
     pub fn new() -> Self {
-        let conn = Connection::connect_to_env().expect("Failed to connect to Wayland");
+        startup_timing::start();
+        // Before the driver loading thread below, which may read the
+        // environment
+        let startup_token = startup_token();
+        let conn = Connection::connect_to_env()
+            .expect("Failed to connect to Wayland, X11 is not supported, is WAYLAND_DISPLAY set?");
+        startup_timing::mark(Phase::Connected);
+        // Loading the graphics drivers overlaps the registry roundtrips, the
+        // thread touches no Wayland objects
+        let wgpu_instance_init = std::thread::Builder::new()
+            .name("wayapp-wgpu-init".to_string())
+            .spawn(|| {
+                let instance = create_wgpu_instance();
+                startup_timing::mark(Phase::InstanceReady);
+                instance
+            })
+            .inspect_err(|err| warn!("[MAIN] No WGPU instance thread: {}", err))
+            .ok();
         let (globals, event_queue) =
             registry_queue_init::<Self>(&conn).expect("Failed to init registry");
+        startup_timing::mark(Phase::RegistryReady);
         let qh: QueueHandle<Self> = event_queue.handle();
+        let event_loop: EventLoop<'static, Self> =
+            EventLoop::try_new().expect("Failed to create event loop");
+        let (redraw_sender, redraw_channel) = channel::channel::<SurfaceRequest>();
+        event_loop
+            .handle()
+            .insert_source(redraw_channel, |event, _, app| {
+                if let channel::Event::Msg(request) = event {
+                    app.handle_surface_request(request);
+                }
+            })
+            .expect("Failed to insert redraw channel");
+        let (drop_sender, drop_channel) = channel::channel::<DropResult>();
+        event_loop
+            .handle()
+            .insert_source(drop_channel, |event, _, app| {
+                if let channel::Event::Msg(dropped) = event {
+                    app.finish_drop(dropped);
+                }
+            })
+            .expect("Failed to insert drop channel");
+        let (grave_sender, grave_channel) = channel::channel::<u64>();
+        event_loop
+            .handle()
+            .insert_source(grave_channel, |event, _, app| {
+                if let channel::Event::Msg(id) = event {
+                    app.graveyard.release(id);
+                }
+            })
+            .expect("Failed to insert graveyard channel");
+        event_loop
+            .handle()
+            .insert_source(Timer::from_duration(resume::WATCH_INTERVAL), |_, _, app| {
+                app.watch_resume();
+                TimeoutAction::ToDuration(resume::WATCH_INTERVAL)
+            })
+            .expect("Failed to insert resume watch timer");
 
         // Bind required globals
         let compositor_state =
@@ -144,14 +479,31 @@ impl Application {
         let xdg_shell = XdgShell::bind(&globals, &qh).expect("xdg shell not available");
         let shm_state = Shm::bind(&globals, &qh).expect("wl_shm not available");
         let layer_shell = LayerShell::bind(&globals, &qh).expect("layer shell not available");
-        let cursor_shape_manager =
-            CursorShapeManager::bind(&globals, &qh).expect("cursor shape manager not available");
-        let clipboard = unsafe { Clipboard::new(conn.display().id().as_ptr() as *mut _) };
+        let cursor_shape_manager = CursorShapeManager::bind(&globals, &qh)
+            .inspect_err(|_| warn!("[MAIN] Cursor shape manager not available, using cursor theme"))
+            .ok();
+        let clipboard = clipboard_for_connection(&conn);
+        let text_input = TextInputState::bind(&globals, &qh);
+        let presentation = PresentationTime::bind(&globals, &qh);
+        let data_device_manager = DataDeviceManagerState::bind(&globals, &qh)
+            .inspect_err(|_| warn!("[MAIN] Data device manager not available, no drag and drop"))
+            .ok();
+        let activation = ActivationState::bind(&globals, &qh)
+            .inspect_err(|_| warn!("[MAIN] XDG activation not available, windows can't take focus"))
+            .ok();
+        let protocol_versions = ProtocolVersions::from_globals(&globals);
+        startup_timing::mark(Phase::GlobalsBound);
+        let graveyard = Graveyard::new(grave_sender, event_loop.handle());
 
         Self {
             event_queue: Some(event_queue),
             conn,
             qh: qh.clone(),
+            loop_handle: event_loop.handle(),
+            event_loop: Some(event_loop),
+            redraw_sender,
+            drop_sender,
+            graveyard,
             subcompositor_state,
             registry_state: RegistryState::new(&globals),
             seat_state: SeatState::new(&globals, &qh),
@@ -160,36 +512,694 @@ impl Application {
             compositor_state,
             xdg_shell,
             layer_shell,
+            session_lock_state: SessionLockState::new(&globals, &qh),
             windows: Vec::new(),
             layer_surfaces: Vec::new(),
             popups: Vec::new(),
             subsurfaces: Vec::new(),
+            lock_surfaces: Vec::new(),
+            subsurface_parents: HashMap::new(),
             surfaces_by_id: HashMap::new(),
+            redraw_timers: HashMap::new(),
             // windows: Vec::new(),
             // layer_surfaces: Vec::new(),
             clipboard,
             cursor_shape_manager,
+            seats: Seats::default(),
             last_pointer_enter_serial: None,
             last_pointer: None,
+            cursor: Some(Shape::Default),
+            last_parent_pointer: None,
+            pointer_frame_count: 0,
+            pointer_focus: None,
+            stuck_grab_warned: None,
+            last_input_serial: None,
+            input_seat: None,
+            stuck_grab_compositors: vec!["sway".into(), "river".into(), "wlroots".into()],
             pointer_shape_devices: HashMap::new(),
-            keyboard_focused_surface: None,
+            wgpu_instance: None,
+            wgpu_instance_init,
+            gpu: None,
+            resume_watch: ResumeWatch::new(),
+            keyboard_layouts: KeyboardLayouts::default(),
+            text_input,
+            surface_outputs: SurfaceOutputs::default(),
+            advice: AppAdvice::default(),
+            readiness: Readiness::default(),
+            protocol_versions,
+            thumbnails: Thumbnails::default(),
+            #[cfg(feature = "icons")]
+            icons: crate::Icons::default(),
+            presentation,
+            transactions: FrameTransactions::default(),
+            input_batch: InputBatch::default(),
+            session_lock: None,
+            data_device_manager,
+            data_device: None,
+            drag: None,
+            dropping: None,
+            drops: 0,
+            transfer_limits: TransferLimits::default(),
+            activation,
+            startup_token,
+            app_id: None,
+            viewporter: viewporter::bind(&globals, &qh),
+            exit_requested: false,
+            exit_when_no_windows: true,
+            had_windows: false,
         }
     }
 
+    /// Run the event loop until `exit` is called or the last window is
+    /// closed, see `set_exit_when_no_windows`. Every surface is destroyed
+    /// before this returns.
     pub fn run_blocking(&mut self) {
-        // Run the Wayland event loop. This example will run until the process is killed
-        let mut event_queue = self.event_queue.take().unwrap();
-        loop {
-            event_queue
-                .blocking_dispatch(self)
-                .expect("Wayland dispatch failed");
+        let mut event_loop = self.event_loop.take().unwrap();
+        let event_queue = self.event_queue.take().unwrap();
+        WaylandSource::new(self.conn.clone(), event_queue)
+            .insert(self.loop_handle.clone())
+            .expect("Failed to insert Wayland source");
+        while !self.should_exit() {
+            event_loop
+                .dispatch(None, self)
+                .expect("Event loop dispatch failed");
+            self.flush_input();
         }
+        self.shutdown();
     }
 
-    pub fn set_cursor(&mut self, shape: Shape) {
-        if let Some(serial) = self.last_pointer_enter_serial
-            && let Some(pointer) = &self.last_pointer
+    /// Make `run_blocking` return after the current dispatch, safe to call
+    /// from `ui()`
+    pub fn exit(&mut self) {
+        self.exit_requested = true;
+    }
+
+    /// Whether `run_blocking` returns once the last window is closed, on by
+    /// default. Apps with only layer surfaces run until `exit`.
+    pub fn set_exit_when_no_windows(&mut self, exit: bool) {
+        self.exit_when_no_windows = exit;
+    }
+
+    /// Limits for dropped and pasted data from other clients, surfaces
+    /// created afterwards use them for pastes
+    pub fn set_transfer_limits(&mut self, limits: TransferLimits) {
+        self.transfer_limits = limits;
+    }
+
+    pub fn transfer_limits(&self) -> TransferLimits {
+        self.transfer_limits
+    }
+
+    fn should_exit(&self) -> bool {
+        self.exit_requested
+            || (self.exit_when_no_windows && self.had_windows && self.windows.is_empty())
+    }
+
+    /// Destroy the surfaces children first, then drop the GPU resources
+    /// still waiting in the graveyard
+    ///
+    /// Swapchains go before the role objects that own their wl_surface, and
+    /// the connection outlives both.
+    fn shutdown(&mut self) {
+        // A session lock is left locked, only `unlock` ends it
+        debug!("[MAIN] Shutting down");
+        let surfaces = [
+            std::mem::take(&mut self.subsurfaces),
+            std::mem::take(&mut self.popups),
+            std::mem::take(&mut self.lock_surfaces),
+            std::mem::take(&mut self.layer_surfaces),
+            std::mem::take(&mut self.windows),
+        ];
+        for surface_id in surfaces.iter().flatten() {
+            self.surfaces_by_id.remove(surface_id);
+        }
+        self.surfaces_by_id.clear();
+        self.subsurface_parents.clear();
+        self.graveyard.release_all();
+        if let Err(err) = self.conn.flush() {
+            warn!("[MAIN] Failed to flush the connection on exit: {}", err);
+        }
+    }
+
+    /// Versions the globals were bound at and the features they enable
+    pub fn protocol_versions(&self) -> &ProtocolVersions {
+        &self.protocol_versions
+    }
+
+    /// Capture thumbnails of the EGUI surfaces, each at most `max_size`
+    /// pixels wide or high and refreshed at most every `interval`
+    pub fn thumbnails(&mut self, max_size: u32, interval: Duration) -> Thumbnails {
+        self.thumbnails.enable(max_size, interval);
+        self.thumbnails.clone()
+    }
+
+    pub(crate) fn thumbnail_service(&self) -> Thumbnails {
+        self.thumbnails.clone()
+    }
+
+    /// Icon service shared by all surfaces of the application
+    #[cfg(feature = "icons")]
+    pub fn icons(&self) -> crate::Icons {
+        self.icons.clone()
+    }
+
+    pub(crate) fn graveyard(&self) -> Graveyard {
+        self.graveyard.clone()
+    }
+
+    /// Name of the active keyboard layout, e.g. "English (US)"
+    pub fn keyboard_layout(&self) -> Option<&str> {
+        self.keyboard_layouts.active_name()
+    }
+
+    fn watch_resume(&mut self) {
+        if let Some(gap) = self.resume_watch.sample(Instant::now(), SystemTime::now()) {
+            self.system_resumed(gap);
+        }
+    }
+
+    /// Render the surfaces that got input during the dispatch, once each
+    fn flush_input(&mut self) {
+        for surface_id in self.input_batch.take() {
+            // Removed surfaces are skipped
+            let Some(kind) = self.get_by_surface_id_mut(&surface_id) else {
+                continue;
+            };
+            kind.container().flush_input();
+        }
+    }
+
+    /// Time passed without the loop running, e.g. a suspend. Keys may have
+    /// been released meanwhile, the focused container gets leave and enter
+    /// so nothing stays held or repeats.
+    fn system_resumed(&mut self, gap: Duration) {
+        debug!("[MAIN] Resumed after a gap of {:?}", gap);
+        for seat in self.seats.all() {
+            let Some(resources) = self.seats.get_mut(&seat) else {
+                continue;
+            };
+            let (held, timer) = resources.forget_held_keys();
+            if let Some(token) = timer {
+                self.loop_handle.remove(token);
+            }
+            if !held {
+                continue;
+            }
+            if let Some(surface_id) = self.keyboard_target(&seat)
+                && let Some(kind) = self.get_by_surface_id_mut(&surface_id)
+            {
+                let container = kind.container();
+                container.leave();
+                container.enter();
+            }
+        }
+        for kind in self.surfaces_by_id.values_mut() {
+            kind.container().system_resumed(gap);
+        }
+    }
+
+    /// Tell every container the active layout, a bar shows it without the
+    /// keyboard focus
+    fn dispatch_layout_changed(&mut self) {
+        let Some(name) = self.keyboard_layouts.active_name().map(String::from) else {
+            return;
+        };
+        debug!("[INPUT] Keyboard layout: {}", name);
+        for kind in self.surfaces_by_id.values_mut() {
+            match kind {
+                Kind::Window(window) => {
+                    window.layout_changed(name.clone());
+                }
+                Kind::LayerSurface(layer_surface) => {
+                    layer_surface.layout_changed(name.clone());
+                }
+                Kind::Popup(popup) => {
+                    popup.layout_changed(name.clone());
+                }
+                Kind::Subsurface(subsurface) => {
+                    subsurface.layout_changed(name.clone());
+                }
+                Kind::LockSurface(lock_surface) => {
+                    lock_surface.layout_changed(name.clone());
+                }
+            }
+        }
+    }
+
+    /// Latest presentation of the surface, `None` until the first frame is
+    /// shown or without the presentation time protocol
+    pub fn last_presented(&self, surface_id: &ObjectId) -> Option<Presented> {
+        self.presentation.last_presented(surface_id)
+    }
+
+    /// Token for a state change shown by the next presented frame of the
+    /// surface, e.g. taken in `ui()` for the frame being drawn
+    pub fn frame_token(&self, surface_id: &ObjectId) -> FrameToken {
+        self.transactions.token(surface_id)
+    }
+
+    /// Called once the frame including the token is shown or discarded,
+    /// register it before that frame is presented
+    pub fn on_presented(&self, token: FrameToken, callback: impl FnOnce(FrameStatus) + 'static) {
+        self.transactions.on_presented(token, Box::new(callback));
+    }
+
+    pub(crate) fn dispatch_presented(&mut self, surface_id: &ObjectId, presented: &Presented) {
+        if let Some(kind) = self.get_by_surface_id_mut(surface_id) {
+            match kind {
+                Kind::Window(window) => {
+                    window.presented(presented);
+                }
+                Kind::LayerSurface(layer_surface) => {
+                    layer_surface.presented(presented);
+                }
+                Kind::Popup(popup) => {
+                    popup.presented(presented);
+                }
+                Kind::Subsurface(subsurface) => {
+                    subsurface.presented(presented);
+                }
+                Kind::LockSurface(lock_surface) => {
+                    lock_surface.presented(presented);
+                }
+            }
+        } else {
+            self.presentation.remove_surface(surface_id);
+        }
+    }
+
+    /// Raw keycodes currently held down on the keyboard of the seat of the
+    /// latest input
+    pub fn pressed_keys(&self) -> &HashSet<u32> {
+        static NO_KEYS: LazyLock<HashSet<u32>> = LazyLock::new(HashSet::new);
+        self.input_seat
+            .as_ref()
+            .and_then(|seat| self.seats.get(seat))
+            .map_or(&NO_KEYS, |resources| &resources.pressed_keys)
+    }
+
+    /// Redraw the surface on its next frame callback
+    pub fn request_redraw(&mut self, surface_id: &ObjectId) {
+        if let Some(kind) = self.get_by_surface_id_mut(surface_id) {
+            kind.container().request_redraw();
+        }
+    }
+
+    /// Activation token for the first configured window, e.g. the one
+    /// returned by `take_startup_token`
+    pub fn set_startup_token(&mut self, token: Option<String>) {
+        self.startup_token = token;
+    }
+
+    /// Thread-safe handle for requesting redraws of the surface
+    pub fn redraw_handle(&self, surface_id: &ObjectId) -> RedrawHandle {
+        RedrawHandle {
+            surface: surface_id.clone(),
+            sender: self.redraw_sender.clone(),
+        }
+    }
+
+    /// Handle for moving and resizing the window from its own titlebar
+    pub fn window_handle(&self, window: &Window) -> WindowHandle {
+        WindowHandle {
+            redraw: self.redraw_handle(&window.wl_surface().id()),
+            toplevel: window.xdg_toplevel().clone(),
+        }
+    }
+
+    fn handle_surface_request(&mut self, request: SurfaceRequest) {
+        // Handles of destroyed surfaces don't reach a surface reusing the id
+        if !self.surfaces_by_id.contains_key(&request.surface) {
+            return;
+        }
+        let surface_id = request.surface;
+        let delay = match request.kind {
+            SurfaceRequestKind::Redraw { delay } => delay,
+            SurfaceRequestKind::FrameFallback { delay } => {
+                return self.schedule_frame_fallback(surface_id, delay);
+            }
+            SurfaceRequestKind::DockTimer { delay } => {
+                return self.schedule_dock_timer(surface_id, delay);
+            }
+            SurfaceRequestKind::Cursor(Some(shape)) => return self.set_cursor(shape),
+            SurfaceRequestKind::Cursor(None) => return self.hide_cursor(),
+            SurfaceRequestKind::CursorOverride(shape) => {
+                if let Some(kind) = self.get_by_surface_id_mut(&surface_id) {
+                    kind.container().set_cursor_override(shape);
+                }
+                return;
+            }
+            SurfaceRequestKind::ImeCursorArea(x, y, width, height) => {
+                return self.set_ime_cursor_area(&surface_id, x, y, width, height);
+            }
+            SurfaceRequestKind::ImeDisabled => return self.disable_ime(&surface_id),
+            SurfaceRequestKind::Move(toplevel) => {
+                if let Some((seat, serial)) = self.grab_serial() {
+                    toplevel._move(&seat, serial);
+                }
+                return;
+            }
+            SurfaceRequestKind::Resize(toplevel, edge) => {
+                if let Some((seat, serial)) = self.grab_serial() {
+                    toplevel.resize(&seat, serial, edge);
+                }
+                return;
+            }
+            SurfaceRequestKind::WindowMenu(toplevel, x, y) => {
+                if let Some((seat, serial)) = self.grab_serial() {
+                    toplevel.show_window_menu(&seat, serial, x, y);
+                }
+                return;
+            }
+            SurfaceRequestKind::Close => return self.close_window(&surface_id),
+            SurfaceRequestKind::ForceClose => {
+                if self.windows.contains(&surface_id) {
+                    self.remove_window(&surface_id);
+                }
+                return;
+            }
+            SurfaceRequestKind::RequestAttention => return self.request_attention(&surface_id),
+            SurfaceRequestKind::InsertText(text) => return self.insert_text(&surface_id, &text),
+            SurfaceRequestKind::Fullscreen(toplevel, selector) => {
+                return self.set_fullscreen(surface_id, toplevel, selector);
+            }
+            SurfaceRequestKind::PresentAt(target) => {
+                if let Some(kind) = self.get_by_surface_id_mut(&surface_id) {
+                    kind.container().present_at(target);
+                }
+                return;
+            }
+        };
+        if delay.is_zero() {
+            self.request_redraw(&surface_id);
+            return;
+        }
+        self.schedule_redraw(surface_id, Instant::now() + delay);
+    }
+
+    /// Redraw at the deadline, a surface has one timer at a time so the
+    /// earliest pending deadline wins like with
+    /// `Context::request_repaint_after`
+    fn schedule_redraw(&mut self, surface_id: ObjectId, deadline: Instant) {
+        if let Some((pending, token)) = self.redraw_timers.get(&surface_id) {
+            if *pending <= deadline {
+                return;
+            }
+            self.loop_handle.remove(*token);
+        }
+        let id = surface_id.clone();
+        let token = self
+            .loop_handle
+            .insert_source(Timer::from_deadline(deadline), move |_, _, app| {
+                app.redraw_timers.remove(&id);
+                app.request_redraw(&id);
+                TimeoutAction::Drop
+            })
+            .expect("Failed to insert redraw timer");
+        self.redraw_timers.insert(surface_id, (deadline, token));
+    }
+
+    /// Fallback for a withheld frame callback, a timer armed before a
+    /// callback that did arrive does nothing
+    fn schedule_frame_fallback(&mut self, surface_id: ObjectId, delay: Duration) {
+        self.loop_handle
+            .insert_source(Timer::from_duration(delay), move |_, _, app| {
+                if let Some(kind) = app.get_by_surface_id_mut(&surface_id) {
+                    kind.container().frame_fallback();
+                }
+                TimeoutAction::Drop
+            })
+            .expect("Failed to insert frame fallback timer");
+    }
+
+    /// The dock keeps one timer at a time, it doesn't wait for a frame
+    /// callback
+    fn schedule_dock_timer(&mut self, surface_id: ObjectId, delay: Duration) {
+        self.loop_handle
+            .insert_source(Timer::from_duration(delay), move |_, _, app| {
+                if let Some(Kind::LayerSurface(layer_surface)) =
+                    app.get_by_surface_id_mut(&surface_id)
+                {
+                    layer_surface.dock_timer();
+                }
+                TimeoutAction::Drop
+            })
+            .expect("Failed to insert dock timer");
+    }
+
+    /// Ask the compositor to focus the window, or mark it urgent if focus
+    /// can't be taken, e.g. when a background event needs the user
+    ///
+    /// A fresh token is requested with the serial of the latest input, the
+    /// window is activated once the token arrives.
+    pub fn request_attention(&mut self, surface_id: &ObjectId) {
+        let Some(activation) = &self.activation else {
+            debug!("[MAIN] No XDG activation, can't request attention");
+            return;
+        };
+        let Ok(surface) = WlSurface::from_id(&self.conn, surface_id.clone()) else {
+            return;
+        };
+        activation.request_token::<Self>(
+            &self.qh,
+            RequestData {
+                app_id: self.app_id.clone(),
+                seat_and_serial: self.grab_serial(),
+                surface: Some(surface),
+            },
+        );
+    }
+
+    /// Insert text into the widget focused on the surface as if typed, e.g.
+    /// an emoji from a picker that took the keyboard focus
+    ///
+    /// The surface keeps its focused widget while it doesn't have the
+    /// keyboard focus, unless `FocusPolicy::Exclusive`. Windows are asked to
+    /// take the keyboard focus back.
+    pub fn insert_text(&mut self, surface_id: &ObjectId, text: &str) {
+        if self.windows.contains(surface_id) {
+            self.request_attention(surface_id);
+        }
+        self.with_keyboard_container(surface_id, |container| container.insert_text(text));
+    }
+
+    /// WGPU instance shared by all surfaces
+    pub fn wgpu_instance(&mut self) -> wgpu::Instance {
+        if let Some(instance) = &self.wgpu_instance {
+            return instance.clone();
+        }
+        let instance = self
+            .wgpu_instance_init
+            .take()
+            .and_then(|init| {
+                init.join()
+                    .inspect_err(|_| warn!("[MAIN] Background WGPU instance creation panicked"))
+                    .ok()
+            })
+            .unwrap_or_else(create_wgpu_instance);
+        self.wgpu_instance = Some(instance.clone());
+        instance
+    }
+
+    /// Use the given GPU context for all surfaces created after this, e.g. a
+    /// device owned by the host created with `GpuContext::with_existing`
+    pub fn set_gpu_context(&mut self, gpu: GpuContext) {
+        if self.gpu.is_some() {
+            warn!("[COMMON] Replacing GPU context, existing surfaces keep the old device");
+        }
+        self.wgpu_instance = Some(gpu.instance.clone());
+        // Dropped without joining, the thread finishes on its own
+        self.wgpu_instance_init = None;
+        self.gpu = Some(gpu);
+    }
+
+    /// GPU context shared by all surfaces, created on first use with an
+    /// adapter compatible with the given surface
+    pub fn gpu_context(&mut self, compatible_surface: &wgpu::Surface<'_>) -> GpuContext {
+        if let Some(gpu) = &self.gpu {
+            if !gpu.adapter.is_surface_supported(compatible_surface) {
+                warn!("[COMMON] Shared adapter doesn't support the new surface");
+            }
+            return gpu.clone();
+        }
+        let gpu = GpuContext::new(self.wgpu_instance(), compatible_surface);
+        startup_timing::mark(Phase::DeviceCreated);
+        self.gpu = Some(gpu.clone());
+        gpu
+    }
+
+    /// Repeat rate and delay of the seat, the compositor may change them at
+    /// any time, e.g. from the desktop settings
+    pub fn repeat_info(&self, seat: &wl_seat::WlSeat) -> RepeatInfo {
+        self.seats
+            .get(seat)
+            .and_then(|resources| resources.repeat_info)
+            .unwrap_or(DEFAULT_REPEAT_INFO)
+    }
+
+    /// Start repeating the key after the delay, replaces any repeating key
+    /// of the seat
+    fn start_key_repeat(&mut self, seat: &wl_seat::WlSeat, event: KeyEvent) {
+        self.stop_key_repeat(seat);
+        if event.keysym.is_modifier_key() {
+            return;
+        }
+        let Some(resources) = self.seats.get_mut(seat) else {
+            return;
+        };
+        resources.key_repeat = Some(KeyRepeat {
+            event,
+            since: Instant::now(),
+            timer: None,
+        });
+        self.arm_key_repeat(seat);
+    }
+
+    /// Schedule the held key with the current repeat info of its seat, an
+    /// earlier schedule is replaced
+    fn arm_key_repeat(&mut self, seat: &wl_seat::WlSeat) {
+        let info = self.repeat_info(seat);
+        let Some(key_repeat) = self
+            .seats
+            .get_mut(seat)
+            .and_then(|resources| resources.key_repeat.as_mut())
+        else {
+            return;
+        };
+        if let Some(token) = key_repeat.timer.take() {
+            self.loop_handle.remove(token);
+        }
+        let Some((first, interval)) = repeat_schedule(info, key_repeat.since.elapsed()) else {
+            return;
+        };
+        let raw_code = key_repeat.event.raw_code;
+        let event = key_repeat.event.clone();
+        let repeat_seat = seat.clone();
+        let timer = Timer::from_duration(first);
+        let token = self
+            .loop_handle
+            .insert_source(timer, move |_, _, app| {
+                // Released without a release event, e.g. during a suspend
+                let Some(resources) = app.seats.get_mut(&repeat_seat) else {
+                    return TimeoutAction::Drop;
+                };
+                if !resources.pressed_keys.contains(&raw_code) {
+                    resources.key_repeat = None;
+                    return TimeoutAction::Drop;
+                }
+                app.dispatch_repeat_key(&repeat_seat, &event);
+                TimeoutAction::ToDuration(interval)
+            })
+            .expect("Failed to insert key repeat timer");
+        key_repeat.timer = Some(token);
+    }
+
+    fn stop_key_repeat(&mut self, seat: &wl_seat::WlSeat) {
+        if let Some(KeyRepeat {
+            timer: Some(token), ..
+        }) = self
+            .seats
+            .get_mut(seat)
+            .and_then(|resources| resources.key_repeat.take())
         {
+            self.loop_handle.remove(token);
+        }
+    }
+
+    /// Forget the held keys, repeat and compose sequence of the seat and
+    /// leave the surface its keyboard was on, unless another seat's keyboard
+    /// is still there
+    fn drop_keyboard_focus(&mut self, seat: &wl_seat::WlSeat, surface_id: Option<ObjectId>) {
+        self.stop_key_repeat(seat);
+        let Some(resources) = self.seats.get_mut(seat) else {
+            return;
+        };
+        resources.pressed_keys.clear();
+        if let Some(compose) = &mut resources.compose {
+            compose.reset();
+        }
+        let Some(surface_id) = surface_id.or_else(|| resources.keyboard_focus.clone()) else {
+            return;
+        };
+        // Keys go only to the surface of the latest enter
+        if resources.keyboard_focus.as_ref() == Some(&surface_id) {
+            resources.keyboard_focus = None;
+        }
+        if self.seats.keyboard_focused_by_other(&surface_id, seat) {
+            return;
+        }
+        if let Some(container) = input_target(&mut self.surfaces_by_id, Some(&surface_id)) {
+            container.leave();
+        }
+    }
+
+    /// Composing of the seat's input method ends on the surface its text
+    /// input was enabled for, e.g. before the text input is destroyed
+    fn drop_text_input_focus(&mut self, seat: &wl_seat::WlSeat) {
+        let surface_id = self
+            .seats
+            .get_mut(seat)
+            .and_then(|resources| resources.text_input.as_mut())
+            .and_then(SeatTextInput::take_surface);
+        if let Some(surface_id) = surface_id {
+            self.with_keyboard_container(&surface_id, |container| {
+                container.preedit(None, None);
+                container.text_input_focus(false);
+            });
+        }
+    }
+
+    /// Tell every container the repeat info of the seat that changed
+    fn dispatch_repeat_info_changed(&mut self, info: RepeatInfo) {
+        for kind in self.surfaces_by_id.values_mut() {
+            match kind {
+                Kind::Window(window) => {
+                    window.repeat_info_changed(info);
+                }
+                Kind::LayerSurface(layer_surface) => {
+                    layer_surface.repeat_info_changed(info);
+                }
+                Kind::Popup(popup) => {
+                    popup.repeat_info_changed(info);
+                }
+                Kind::Subsurface(subsurface) => {
+                    subsurface.repeat_info_changed(info);
+                }
+                Kind::LockSurface(lock_surface) => {
+                    lock_surface.repeat_info_changed(info);
+                }
+            }
+        }
+    }
+
+    fn dispatch_repeat_key(&mut self, seat: &wl_seat::WlSeat, event: &KeyEvent) {
+        let target = self.keyboard_target(seat);
+        if let Some(container) = input_target(&mut self.surfaces_by_id, target.as_ref()) {
+            container.repeat_key(event);
+        }
+    }
+
+    pub fn set_cursor(&mut self, shape: Shape) {
+        self.cursor = Some(shape);
+        self.apply_cursor();
+    }
+
+    /// Hide the cursor until the next `set_cursor`
+    pub fn hide_cursor(&mut self) {
+        self.cursor = None;
+        self.apply_cursor();
+    }
+
+    fn apply_cursor(&mut self) {
+        let (Some(serial), Some(pointer)) = (self.last_pointer_enter_serial, &self.last_pointer)
+        else {
+            return;
+        };
+        let Some(shape) = self.cursor else {
+            pointer.set_cursor(serial, None, 0, 0);
+            return;
+        };
+        if let Some(cursor_shape_manager) = &self.cursor_shape_manager {
             let pointer_id = pointer.id();
             let device = self
                 .pointer_shape_devices
@@ -199,10 +1209,30 @@ impl Application {
                         "[COMMON] Creating new cursor shape device for pointer id {}",
                         pointer.id()
                     );
-                    self.cursor_shape_manager
-                        .get_shape_device(pointer, &self.qh)
+                    cursor_shape_manager.get_shape_device(pointer, &self.qh)
                 });
             device.set_shape(serial, shape);
+        } else if let Some(themed_pointer) = self.seats.themed_pointer(pointer)
+            && let Err(e) = themed_pointer.set_cursor(&self.conn, shape_to_cursor_icon(shape))
+        {
+            trace!("[COMMON] Failed to set themed cursor: {:?}", e);
+        }
+    }
+
+    /// Drop the cursor state of a released pointer
+    fn forget_pointer(&mut self, pointer_id: &ObjectId) {
+        if let Some(device) = self.pointer_shape_devices.remove(pointer_id) {
+            device.destroy();
+        }
+        if self
+            .last_pointer
+            .as_ref()
+            .map(|pointer| pointer.id())
+            .as_ref()
+            == Some(pointer_id)
+        {
+            self.last_pointer = None;
+            self.last_pointer_enter_serial = None;
         }
     }
 
@@ -211,6 +1241,8 @@ impl Application {
         let boxed_window: Box<dyn WindowContainer> = Box::new(window);
         let surface_id = boxed_window.get_object_id();
         self.windows.push(surface_id.clone());
+        self.had_windows = true;
+        AppAdvice::expect_configure(self, &surface_id);
         self.surfaces_by_id
             .insert(surface_id, Kind::Window(boxed_window));
     }
@@ -220,15 +1252,56 @@ impl Application {
         let boxed_layer_surface: Box<dyn LayerSurfaceContainer> = Box::new(layer_surface);
         let surface_id = boxed_layer_surface.get_object_id();
         self.layer_surfaces.push(surface_id.clone());
+        AppAdvice::expect_configure(self, &surface_id);
         self.surfaces_by_id
             .insert(surface_id, Kind::LayerSurface(boxed_layer_surface));
     }
 
+    /// Map the window or layer surface only once every surface of the group
+    /// is configured, so they appear together, e.g. a bar, a dock and the
+    /// main window at startup
+    ///
+    /// Call before the surface is first configured, i.e. before the event
+    /// loop runs. The first frames are rendered one right after another in
+    /// the same dispatch. After 2 s the configured members are shown anyway,
+    /// the rest when they are configured.
+    pub fn reveal_together(&mut self, group: &str, surface_id: &ObjectId) {
+        if !self.readiness.join(group, surface_id) {
+            return;
+        }
+        let group = group.to_string();
+        self.loop_handle
+            .insert_source(
+                Timer::from_duration(readiness::READINESS_TIMEOUT),
+                move |_, _, app| {
+                    let configures = app.readiness.release(&group);
+                    if !configures.is_empty() {
+                        warn!(
+                            "[MAIN] Revealing {} surfaces of group {} without the rest",
+                            configures.len(),
+                            group
+                        );
+                    }
+                    app.deliver_configures(configures);
+                    TimeoutAction::Drop
+                },
+            )
+            .expect("Failed to insert readiness timer");
+    }
+
+    /// Configures released by the readiness groups
+    fn deliver_configures(&mut self, configures: Vec<(ObjectId, HeldConfigure)>) {
+        for (surface_id, configure) in configures {
+            deliver_configure(&mut self.surfaces_by_id, &surface_id, &configure);
+        }
+    }
+
     /// Push a popup container to the application
     pub fn push_popup<P: PopupContainer + 'static>(&mut self, popup: P) {
         let boxed_popup: Box<dyn PopupContainer> = Box::new(popup);
         let surface_id = boxed_popup.get_object_id();
         self.popups.push(surface_id.clone());
+        AppAdvice::expect_configure(self, &surface_id);
         self.surfaces_by_id
             .insert(surface_id, Kind::Popup(boxed_popup));
     }
@@ -242,27 +1315,424 @@ impl Application {
             .insert(surface_id, Kind::Subsurface(boxed_subsurface));
     }
 
+    /// Push a lock surface container, `lock_session` does this for each
+    /// output
+    fn push_lock_surface(&mut self, lock_surface: Box<dyn LockSurfaceContainer>) {
+        let surface_id = lock_surface.get_object_id();
+        self.lock_surfaces.push(surface_id.clone());
+        AppAdvice::expect_configure(self, &surface_id);
+        self.surfaces_by_id
+            .insert(surface_id, Kind::LockSurface(lock_surface));
+    }
+
+    fn remove_lock_surface(&mut self, surface_id: &ObjectId) {
+        self.lock_surfaces.retain(|id| id != surface_id);
+        self.advice.removed(surface_id);
+        self.presentation.remove_surface(surface_id);
+        self.surfaces_by_id.remove(surface_id);
+        self.remove_children(surface_id);
+    }
+
+    /// Lock the session, the factory creates the lock surface of each
+    /// output, now and for outputs added while locked
+    ///
+    /// Keyboard input goes to the lock surfaces until `unlock_session`. Fails
+    /// without `ext_session_lock_v1` or when the session is already locked by
+    /// this application.
+    pub fn lock_session<C, F>(&mut self, mut factory: F) -> Result<SessionLockHandle, GlobalError>
+    where
+        C: LockSurfaceContainer + 'static,
+        F: FnMut(&mut Application, &SessionLockHandle, &wl_output::WlOutput) -> C + 'static,
+    {
+        if let Some(active) = &self.session_lock {
+            warn!("[LOCK] Session is already locked");
+            return Ok(active.handle.clone());
+        }
+        let lock = self.session_lock_state.lock(&self.qh)?;
+        let handle = SessionLockHandle::new(lock, self.loop_handle.clone());
+        debug!("[LOCK] Locking the session");
+        self.session_lock = Some(ActiveLock {
+            handle: handle.clone(),
+            factory: Some(Box::new(
+                move |app: &mut Application,
+                      handle: &SessionLockHandle,
+                      output: &wl_output::WlOutput|
+                      -> Box<dyn LockSurfaceContainer> {
+                    Box::new(factory(app, handle, output))
+                },
+            )),
+            surfaces: Vec::new(),
+        });
+        // The compositor sends locked once every output has a lock surface
+        let outputs: Vec<_> = self.output_state.outputs().collect();
+        for output in outputs {
+            self.create_lock_surface(&output);
+        }
+        Ok(handle)
+    }
+
+    /// Unlock the session and destroy the lock surfaces, `SessionLockHandle`
+    /// has `unlock` for calling this from a lock surface
+    pub fn unlock_session(&mut self) {
+        let Some(active) = self.session_lock.take() else {
+            return;
+        };
+        debug!("[LOCK] Unlocking the session");
+        // The compositor handles requests in order, surfaces destroyed after
+        // the unlock request don't blank the outputs
+        active.handle.session_lock().unlock();
+        if let Err(err) = self.conn.flush() {
+            warn!("[LOCK] Flush after unlocking failed: {}", err);
+        }
+        for (_, surface_id) in active.surfaces {
+            self.remove_lock_surface(&surface_id);
+        }
+    }
+
+    /// Session locked by this application, `None` when not locked
+    pub fn session_lock(&self) -> Option<SessionLockHandle> {
+        self.session_lock
+            .as_ref()
+            .map(|active| active.handle.clone())
+    }
+
+    fn create_lock_surface(&mut self, output: &wl_output::WlOutput) {
+        // Outputs listed at lock time are announced again by new_output
+        // once their info arrives
+        let Some(mut factory) = self
+            .session_lock
+            .as_mut()
+            .filter(|active| active.surfaces.iter().all(|(o, _)| o != output))
+            .and_then(|active| active.factory.take())
+        else {
+            return;
+        };
+        let handle = self
+            .session_lock
+            .as_ref()
+            .map(|active| active.handle.clone())
+            .expect("Factory belongs to the active lock");
+        let lock_surface = factory(self, &handle, output);
+        let surface_id = lock_surface.get_object_id();
+        trace!(
+            "[LOCK] Lock surface {} for output {}",
+            surface_id,
+            output.id()
+        );
+        if let Some(active) = self.session_lock.as_mut() {
+            active.factory = Some(factory);
+            active.surfaces.push((output.clone(), surface_id));
+        }
+        self.push_lock_surface(lock_surface);
+    }
+
+    fn remove_output_lock_surface(&mut self, output: &wl_output::WlOutput) {
+        let Some(active) = self.session_lock.as_mut() else {
+            return;
+        };
+        let Some(index) = active.surfaces.iter().position(|(o, _)| o == output) else {
+            return;
+        };
+        let (_, surface_id) = active.surfaces.remove(index);
+        self.remove_lock_surface(&surface_id);
+    }
+
+    /// Surface keyboard events of the seat go to, a lock surface while
+    /// locked even if the focus is elsewhere
+    fn keyboard_target(&self, seat: &wl_seat::WlSeat) -> Option<ObjectId> {
+        let focused = self
+            .seats
+            .get(seat)
+            .and_then(|resources| resources.keyboard_focus.clone());
+        if self.session_lock.is_none()
+            || focused
+                .as_ref()
+                .is_some_and(|id| self.lock_surfaces.contains(id))
+        {
+            return focused;
+        }
+        self.lock_surfaces.first().cloned()
+    }
+
+    /// Create a desynchronized subsurface of the parent at the position in
+    /// parent surface coordinates, it's removed together with the parent
+    pub fn create_subsurface(
+        &mut self,
+        parent: &WlSurface,
+        x: i32,
+        y: i32,
+    ) -> (WlSubsurface, WlSurface) {
+        let (subsurface, wl_surface) = self
+            .subcompositor_state
+            .create_subsurface(parent.clone(), &self.qh);
+        subsurface.set_position(x, y);
+        subsurface.set_desync();
+        self.subsurface_parents.insert(wl_surface.id(), parent.id());
+        (subsurface, wl_surface)
+    }
+
     /// Remove a window by its Window reference
-    fn remove_window(&mut self, window: &Window) {
-        let surface_id = window.wl_surface().id();
-        self.windows.retain(|id| id != &surface_id);
-        self.surfaces_by_id.remove(&surface_id);
+    fn remove_window(&mut self, surface_id: &ObjectId) {
+        self.windows.retain(|id| id != surface_id);
+        self.surface_outputs.remove_surface(surface_id);
+        self.advice.removed(surface_id);
+        self.presentation.remove_surface(surface_id);
+        self.surfaces_by_id.remove(surface_id);
+        self.remove_children(surface_id);
+        let configures = self.readiness.remove(surface_id);
+        self.deliver_configures(configures);
+    }
+
+    /// Fullscreen the window on the selected output, `EguiWindow` has
+    /// `set_fullscreen_on` for this
+    pub fn set_window_fullscreen(&mut self, window: &Window, selector: OutputSelector) {
+        let toplevel = window.xdg_toplevel().clone();
+        self.set_fullscreen(window.wl_surface().id(), toplevel, selector);
+    }
+
+    /// Fullscreen the toplevel on the selected output. Enter and leave events
+    /// of a move arrive in the same dispatch, the selection waits until
+    /// they've all been handled.
+    fn set_fullscreen(
+        &mut self,
+        surface_id: ObjectId,
+        toplevel: XdgToplevel,
+        selector: OutputSelector,
+    ) {
+        if self.surface_outputs.is_changing() {
+            self.loop_handle.insert_idle(move |app| {
+                app.set_fullscreen(surface_id, toplevel, selector);
+            });
+            return;
+        }
+        let output = self
+            .surface_outputs
+            .select(&self.output_state, &surface_id, &selector);
+        debug!(
+            "[MAIN] Fullscreen {} with {:?} on {:?}",
+            surface_id,
+            selector,
+            output.as_ref().map(|output| output.id())
+        );
+        toplevel.set_fullscreen(output.as_ref());
+    }
+
+    /// Ask the window container and remove it if it allows closing
+    fn close_window(&mut self, surface_id: &ObjectId) {
+        if let Some(Kind::Window(window)) = self.get_by_surface_id_mut(surface_id) {
+            window.request_close();
+            if window.allowed_to_close() {
+                self.remove_window(surface_id);
+            } else {
+                self.advice.close_refused(surface_id);
+            }
+        }
     }
 
     /// Remove a layer surface by its LayerSurface reference
-    #[allow(dead_code)]
     fn remove_layer_surface(&mut self, layer_surface: &LayerSurface) {
         let surface_id = layer_surface.wl_surface().id();
         self.layer_surfaces.retain(|id| id != &surface_id);
+        self.surface_outputs.remove_surface(&surface_id);
+        self.advice.removed(&surface_id);
+        self.presentation.remove_surface(&surface_id);
         self.surfaces_by_id.remove(&surface_id);
+        self.remove_children(&surface_id);
+        let configures = self.readiness.remove(&surface_id);
+        self.deliver_configures(configures);
+    }
+
+    /// Run with the keyboard handler of the surface container
+    pub(crate) fn with_keyboard_container(
+        &mut self,
+        surface_id: &ObjectId,
+        f: impl FnOnce(&mut dyn KeyboardHandlerContainer),
+    ) {
+        if let Some(kind) = self.get_by_surface_id_mut(surface_id) {
+            f(kind.container());
+        }
+    }
+
+    /// Run with the drag and drop handler of the surface container
+    fn with_dnd_container(
+        &mut self,
+        surface_id: &ObjectId,
+        f: impl FnOnce(&mut dyn DndHandlerContainer),
+    ) {
+        if let Some(kind) = self.get_by_surface_id_mut(surface_id) {
+            f(kind.container());
+        }
+    }
+
+    fn drag_offer(&self) -> Option<DragOffer> {
+        self.data_device.as_ref()?.data().drag_offer()
+    }
+
+    /// Read the dropped data on a background thread, the source may write
+    /// it slowly or only after this client has returned to its event loop
+    fn read_drop(&mut self, offer: DragOffer, drag: DragState, mime: String) {
+        let pipe = match offer.receive(mime.clone()) {
+            Ok(pipe) => pipe,
+            Err(err) => {
+                warn!("[DND] Failed to receive {}: {}", mime, err);
+                offer.destroy();
+                self.with_dnd_container(&drag.surface, |container| container.dnd_leave());
+                return;
+            }
+        };
+        self.drops += 1;
+        let id = self.drops;
+        self.dropping = Some((id, offer));
+        let limits = self.transfer_limits;
+        let surface = drag.surface.clone();
+        let sender = self.drop_sender.clone();
+        std::thread::Builder::new()
+            .name("wayapp drop".into())
+            .spawn(move || {
+                let data = dnd::read(&mime, File::from(OwnedFd::from(pipe)), &limits);
+                let _ = sender.send(DropResult {
+                    id,
+                    surface: drag.surface,
+                    position: drag.position,
+                    data,
+                });
+            })
+            .expect("Failed to spawn drop reader thread");
+        // A source that never closes the pipe blocks the thread, not the drop
+        self.loop_handle
+            .insert_source(
+                Timer::from_duration(limits.read_timeout),
+                move |_, _, app| {
+                    if app
+                        .dropping
+                        .as_ref()
+                        .is_some_and(|(dropping, _)| *dropping == id)
+                    {
+                        warn!(
+                            "[DND] Drop not read in {:?}, cancelled",
+                            limits.read_timeout
+                        );
+                        if let Some((_, offer)) = app.dropping.take() {
+                            offer.destroy();
+                        }
+                        app.with_dnd_container(&surface, |container| container.dnd_leave());
+                    }
+                    TimeoutAction::Drop
+                },
+            )
+            .expect("Failed to insert drop timeout");
+    }
+
+    fn finish_drop(&mut self, dropped: DropResult) {
+        if self
+            .dropping
+            .as_ref()
+            .is_none_or(|(id, _)| *id != dropped.id)
+        {
+            trace!("[DND] Ignoring the data of a cancelled drop");
+            return;
+        }
+        if let Some((_, offer)) = self.dropping.take() {
+            offer.finish();
+            offer.destroy();
+        }
+        let (x, y) = dropped.position;
+        match dropped.data {
+            Ok(data) => {
+                debug!("[DND] Dropped {:?}", data);
+                self.with_dnd_container(&dropped.surface, |container| {
+                    container.dnd_drop(x, y, &data)
+                });
+            }
+            Err(err) => {
+                warn!("[DND] Failed to read the drop: {}", err);
+                self.with_dnd_container(&dropped.surface, |container| container.dnd_leave());
+            }
+        }
+    }
+
+    /// Cursor area of the focused text field in surface coordinates, the
+    /// input method is enabled on the surface and places its candidate
+    /// window next to it
+    pub fn set_ime_cursor_area(&mut self, surface_id: &ObjectId, x: i32, y: i32, w: i32, h: i32) {
+        for resources in self.seats.resources_mut() {
+            if let Some(text_input) = &mut resources.text_input {
+                text_input.focus_text_field(surface_id, x, y, w, h);
+            }
+        }
+    }
+
+    /// No text field of the surface has the focus, the input method stops
+    /// taking its keys
+    pub fn disable_ime(&mut self, surface_id: &ObjectId) {
+        let mut disabled = false;
+        for resources in self.seats.resources_mut() {
+            if let Some(text_input) = &mut resources.text_input {
+                disabled |= text_input.unfocus_text_field(surface_id);
+            }
+        }
+        if disabled {
+            self.with_keyboard_container(surface_id, |container| container.preedit(None, None));
+        }
+    }
+
+    /// Name of the seat the latest input came from, e.g. "seat0", so
+    /// containers can tell the seats apart while handling it
+    pub fn input_seat_name(&self) -> Option<String> {
+        let seat = self.input_seat.as_ref()?;
+        self.seat_state.info(seat)?.name
+    }
+
+    fn set_input_seat(&mut self, seat: Option<wl_seat::WlSeat>) {
+        if seat.is_some() && seat != self.input_seat {
+            trace!(
+                "[INPUT] Input from seat {:?}",
+                seat.as_ref().map(|seat| seat.id())
+            );
+            self.input_seat = seat;
+        }
+    }
+
+    /// Seat and serial of the latest pointer button or key press
+    pub(crate) fn grab_serial(&self) -> Option<(wl_seat::WlSeat, u32)> {
+        Some((self.input_seat.clone()?, self.last_input_serial?))
+    }
+
+    /// Close the popup and remove its container
+    pub fn close_popup(&mut self, popup: &Popup) {
+        self.remove_popup(popup);
     }
 
     /// Remove a popup by its Popup reference
-    #[allow(dead_code)]
     fn remove_popup(&mut self, popup: &Popup) {
         let surface_id = popup.wl_surface().id();
         self.popups.retain(|id| id != &surface_id);
+        self.surface_outputs.remove_surface(&surface_id);
+        self.advice.removed(&surface_id);
+        self.presentation.remove_surface(&surface_id);
         self.surfaces_by_id.remove(&surface_id);
+        self.remove_children(&surface_id);
+        let configures = self.readiness.remove(&surface_id);
+        self.deliver_configures(configures);
+        if self.pointer_focus.as_ref() == Some(&surface_id) {
+            self.watch_stuck_grab();
+        }
+    }
+
+    /// Remove subsurfaces of the parent, recursively
+    fn remove_children(&mut self, parent_id: &ObjectId) {
+        let children: Vec<ObjectId> = self
+            .subsurface_parents
+            .iter()
+            .filter(|(_, parent)| *parent == parent_id)
+            .map(|(child, _)| child.clone())
+            .collect();
+        for child in children {
+            self.subsurface_parents.remove(&child);
+            self.subsurfaces.retain(|id| id != &child);
+            self.surfaces_by_id.remove(&child);
+            self.remove_children(&child);
+        }
     }
 
     /// Remove a subsurface by its WlSurface reference
@@ -273,9 +1743,108 @@ impl Application {
         self.surfaces_by_id.remove(&surface_id);
     }
 
+    /// Dispatch events of a single surface
+    pub(crate) fn dispatch_pointer_events(&mut self, events: &[PointerEvent]) {
+        let Some(first) = events.first() else {
+            return;
+        };
+        let surface_id = first.surface.id();
+        if let Some(container) = input_target(&mut self.surfaces_by_id, Some(&surface_id)) {
+            container.pointer_frame(events);
+        }
+    }
+
+    /// After a popup with the pointer focus is destroyed the compositor should
+    /// send pointer events to the surface under it, if nothing arrives the grab
+    /// is assumed to be stuck
+    fn watch_stuck_grab(&mut self) {
+        let desktop = stuck_grab::current_desktop();
+        if self.last_parent_pointer.is_none()
+            || !stuck_grab::has_quirk(&desktop, &self.stuck_grab_compositors)
+        {
+            return;
+        }
+        let frame_count = self.pointer_frame_count;
+        let dismissed = Instant::now();
+        let timer = Timer::from_duration(STUCK_GRAB_TIMEOUT);
+        self.loop_handle
+            .insert_source(timer, move |_, _, app| {
+                let frames = app.pointer_frame_count - frame_count;
+                let compositors = &app.stuck_grab_compositors;
+                if stuck_grab::is_stuck(&desktop, compositors, frames, dismissed.elapsed()) {
+                    app.reset_parent_pointer();
+                }
+                TimeoutAction::Drop
+            })
+            .expect("Failed to insert stuck grab timer");
+    }
+
+    /// Synthesize leave and enter at the last known position so hover and
+    /// pressed state of the parent recovers without a click
+    fn reset_parent_pointer(&mut self) {
+        let Some((surface, position)) = self.last_parent_pointer.clone() else {
+            return;
+        };
+        let now = Instant::now();
+        if self
+            .stuck_grab_warned
+            .is_none_or(|warned| now.duration_since(warned) >= Duration::from_secs(60))
+        {
+            self.stuck_grab_warned = Some(now);
+            warn!(
+                "[MAIN] No pointer events after popup dismissal, suspected stuck grab compositor \
+                 quirk, resetting pointer state of surface {}",
+                surface.id()
+            );
+        } else {
+            debug!(
+                "[MAIN] Stuck grab, resetting pointer state of surface {}",
+                surface.id()
+            );
+        }
+        self.pointer_focus = Some(surface.id());
+        let serial = self.last_pointer_enter_serial.unwrap_or(0);
+        let events = [
+            PointerEventKind::Leave { serial },
+            PointerEventKind::Enter { serial },
+        ]
+        .map(|kind| PointerEvent {
+            surface: surface.clone(),
+            position,
+            kind,
+        });
+        self.dispatch_pointer_events(&events);
+    }
+
+    pub(crate) fn has_surface(&self, surface_id: &ObjectId) -> bool {
+        self.surfaces_by_id.contains_key(surface_id)
+    }
+
     fn get_by_surface_id_mut(&mut self, surface_id: &ObjectId) -> Option<&mut Kind> {
         self.surfaces_by_id.get_mut(surface_id)
     }
+
+    /// Snapshot metrics of all containers
+    #[cfg(feature = "metrics")]
+    pub(crate) fn surface_metrics(&self) -> Vec<crate::LabeledSurfaceMetrics> {
+        self.surfaces_by_id
+            .iter()
+            .map(|(surface_id, kind)| {
+                let (role, metrics) = match kind {
+                    Kind::Window(window) => ("window", window.metrics()),
+                    Kind::LayerSurface(layer_surface) => ("layer_surface", layer_surface.metrics()),
+                    Kind::Popup(popup) => ("popup", popup.metrics()),
+                    Kind::Subsurface(subsurface) => ("subsurface", subsurface.metrics()),
+                    Kind::LockSurface(lock_surface) => ("lock_surface", lock_surface.metrics()),
+                };
+                crate::LabeledSurfaceMetrics {
+                    role,
+                    surface: surface_id.to_string(),
+                    metrics,
+                }
+            })
+            .collect()
+    }
 }
 
 impl CompositorHandler for Application {
@@ -286,26 +1855,9 @@ impl CompositorHandler for Application {
         surface: &WlSurface,
         new_factor: i32,
     ) {
-        let surface_id = surface.id();
-        if let Some(kind) = self.get_by_surface_id_mut(&surface_id) {
-            match kind {
-                Kind::Window(window) => {
-                    window.scale_factor_changed(new_factor);
-                }
-                Kind::LayerSurface(layer_surface) => {
-                    layer_surface.scale_factor_changed(new_factor);
-                }
-                Kind::Popup(popup) => {
-                    popup.scale_factor_changed(new_factor);
-                }
-                Kind::Subsurface(subsurface) => {
-                    subsurface.scale_factor_changed(new_factor);
-                }
-            }
+        if let Some(container) = surface_target(&mut self.surfaces_by_id, &surface.id()) {
+            container.scale_factor_changed(new_factor);
         }
-
-        // _surface.frame(qh, _surface.clone());
-        // _surface.commit();
     }
 
     fn transform_changed(
@@ -315,22 +1867,8 @@ impl CompositorHandler for Application {
         surface: &WlSurface,
         new_transform: wl_output::Transform,
     ) {
-        let surface_id = surface.id();
-        if let Some(kind) = self.get_by_surface_id_mut(&surface_id) {
-            match kind {
-                Kind::Window(window) => {
-                    window.transform_changed(&new_transform);
-                }
-                Kind::LayerSurface(layer_surface) => {
-                    layer_surface.transform_changed(&new_transform);
-                }
-                Kind::Popup(popup) => {
-                    popup.transform_changed(&new_transform);
-                }
-                Kind::Subsurface(subsurface) => {
-                    subsurface.transform_changed(&new_transform);
-                }
-            }
+        if let Some(container) = surface_target(&mut self.surfaces_by_id, &surface.id()) {
+            container.transform_changed(&new_transform);
         }
     }
 
@@ -341,22 +1879,8 @@ impl CompositorHandler for Application {
         surface: &WlSurface,
         time: u32,
     ) {
-        let surface_id = surface.id();
-        if let Some(kind) = self.get_by_surface_id_mut(&surface_id) {
-            match kind {
-                Kind::Window(window) => {
-                    window.frame(time);
-                }
-                Kind::LayerSurface(layer_surface) => {
-                    layer_surface.frame(time);
-                }
-                Kind::Popup(popup) => {
-                    popup.frame(time);
-                }
-                Kind::Subsurface(subsurface) => {
-                    subsurface.frame(time);
-                }
-            }
+        if let Some(container) = surface_target(&mut self.surfaces_by_id, &surface.id()) {
+            container.frame(time);
         }
     }
 
@@ -368,19 +1892,64 @@ impl CompositorHandler for Application {
         output: &wl_output::WlOutput,
     ) {
         let surface_id = surface.id();
+        if self.windows.contains(&surface_id) && self.surface_outputs.enter(&surface_id, output) {
+            self.loop_handle
+                .insert_idle(|app| app.surface_outputs.settled());
+        }
+        let info = self.output_state.info(output);
+        let refresh_rate = info.as_ref().and_then(|info| {
+            info.modes
+                .iter()
+                .find(|mode| mode.current)
+                .map(|mode| mode.refresh_rate)
+        });
+        let subpixel = info.map(|info| info.subpixel);
         if let Some(kind) = self.get_by_surface_id_mut(&surface_id) {
             match kind {
                 Kind::Window(window) => {
                     window.surface_enter(output);
+                    if let Some(rate) = refresh_rate {
+                        window.refresh_rate_changed(rate);
+                    }
+                    if let Some(subpixel) = subpixel {
+                        window.subpixel_changed(subpixel);
+                    }
                 }
                 Kind::LayerSurface(layer_surface) => {
                     layer_surface.surface_enter(output);
+                    if let Some(rate) = refresh_rate {
+                        layer_surface.refresh_rate_changed(rate);
+                    }
+                    if let Some(subpixel) = subpixel {
+                        layer_surface.subpixel_changed(subpixel);
+                    }
                 }
                 Kind::Popup(popup) => {
                     popup.surface_enter(output);
+                    if let Some(rate) = refresh_rate {
+                        popup.refresh_rate_changed(rate);
+                    }
+                    if let Some(subpixel) = subpixel {
+                        popup.subpixel_changed(subpixel);
+                    }
                 }
                 Kind::Subsurface(subsurface) => {
                     subsurface.surface_enter(output);
+                    if let Some(rate) = refresh_rate {
+                        subsurface.refresh_rate_changed(rate);
+                    }
+                    if let Some(subpixel) = subpixel {
+                        subsurface.subpixel_changed(subpixel);
+                    }
+                }
+                Kind::LockSurface(lock_surface) => {
+                    lock_surface.surface_enter(output);
+                    if let Some(rate) = refresh_rate {
+                        lock_surface.refresh_rate_changed(rate);
+                    }
+                    if let Some(subpixel) = subpixel {
+                        lock_surface.subpixel_changed(subpixel);
+                    }
                 }
             }
         }
@@ -394,6 +1963,10 @@ impl CompositorHandler for Application {
         output: &wl_output::WlOutput,
     ) {
         let surface_id = surface.id();
+        if self.windows.contains(&surface_id) && self.surface_outputs.leave(&surface_id, output) {
+            self.loop_handle
+                .insert_idle(|app| app.surface_outputs.settled());
+        }
         if let Some(kind) = self.get_by_surface_id_mut(&surface_id) {
             match kind {
                 Kind::Window(window) => {
@@ -408,140 +1981,341 @@ impl CompositorHandler for Application {
                 Kind::Subsurface(subsurface) => {
                     subsurface.surface_leave(output);
                 }
+                Kind::LockSurface(lock_surface) => {
+                    lock_surface.surface_leave(output);
+                }
             }
         }
     }
 }
 
-impl OutputHandler for Application {
-    fn output_state(&mut self) -> &mut OutputState {
-        &mut self.output_state
+impl OutputHandler for Application {
+    fn output_state(&mut self) -> &mut OutputState {
+        &mut self.output_state
+    }
+
+    fn new_output(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        output: wl_output::WlOutput,
+    ) {
+        self.create_lock_surface(&output);
+    }
+
+    fn update_output(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _output: wl_output::WlOutput,
+    ) {
+    }
+
+    fn output_destroyed(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        output: wl_output::WlOutput,
+    ) {
+        self.surface_outputs.remove_output(&output);
+        self.remove_output_lock_surface(&output);
+    }
+}
+
+impl LayerShellHandler for Application {
+    fn closed(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, target_layer: &LayerSurface) {
+        let surface_id = target_layer.wl_surface().id();
+        if let Some(Kind::LayerSurface(layer_surface)) = self.get_by_surface_id_mut(&surface_id) {
+            layer_surface.closed();
+        }
+        self.remove_layer_surface(target_layer);
+    }
+
+    fn configure(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        target_layer: &LayerSurface,
+        configure: LayerSurfaceConfigure,
+        _serial: u32,
+    ) {
+        trace!("[COMMON] XDG layer configure");
+
+        let surface_id = target_layer.wl_surface().id();
+        self.advice.configured(&surface_id);
+        let configures = self
+            .readiness
+            .configure(&surface_id, HeldConfigure::LayerSurface(configure));
+        self.deliver_configures(configures);
+    }
+}
+
+impl PopupHandler for Application {
+    fn configure(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        target_popup: &Popup,
+        config: PopupConfigure,
+    ) {
+        trace!("[COMMON] XDG popup configure");
+
+        let surface_id = target_popup.wl_surface().id();
+        self.advice.configured(&surface_id);
+        deliver_configure(&mut self.surfaces_by_id, &surface_id, &config);
+    }
+
+    fn done(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, target_popup: &Popup) {
+        trace!("[COMMON] XDG popup done");
+
+        let surface_id = target_popup.wl_surface().id();
+        if let Some(kind) = self.get_by_surface_id_mut(&surface_id) {
+            if let Kind::Popup(popup) = kind {
+                popup.done();
+            }
+        }
+        self.remove_popup(target_popup);
+    }
+}
+
+impl WindowHandler for Application {
+    fn request_close(&mut self, _: &Connection, _: &QueueHandle<Self>, target_window: &Window) {
+        trace!("[COMMON] XDG window close requested");
+        let surface_id = target_window.wl_surface().id();
+        self.close_window(&surface_id);
+    }
+
+    fn configure(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        target_window: &Window,
+        configure: WindowConfigure,
+        _serial: u32,
+    ) {
+        trace!("[COMMON] XDG window configure");
+
+        let surface_id = target_window.wl_surface().id();
+        self.advice.configured(&surface_id);
+        let configures = self
+            .readiness
+            .configure(&surface_id, HeldConfigure::Window(configure));
+        self.deliver_configures(configures);
+        // Launched by a launcher that gave a token, it's for the first window
+        if let (Some(activation), Some(token)) = (&self.activation, self.startup_token.take()) {
+            debug!("[MAIN] Activating window with the startup token");
+            activation.activate::<Self>(target_window.wl_surface(), token);
+        }
+    }
+}
+
+impl ActivationHandler for Application {
+    type RequestData = RequestData;
+
+    fn new_token(&mut self, token: String, data: &RequestData) {
+        let (Some(activation), Some(surface)) = (&self.activation, &data.surface) else {
+            return;
+        };
+        debug!("[MAIN] Activating surface {}", surface.id());
+        activation.activate::<Self>(surface, token);
+    }
+}
+
+impl SessionLockHandler for Application {
+    fn locked(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _lock: SessionLock) {
+        debug!("[LOCK] Session locked");
+    }
+
+    fn finished(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _lock: SessionLock) {
+        // Denied, e.g. another locker is running, or the lock was removed
+        warn!("[LOCK] Session lock finished by the compositor");
+        let Some(active) = self.session_lock.take() else {
+            return;
+        };
+        for (_, surface_id) in active.surfaces {
+            self.remove_lock_surface(&surface_id);
+        }
+    }
+
+    fn configure(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        surface: SessionLockSurface,
+        configure: SessionLockSurfaceConfigure,
+        _serial: u32,
+    ) {
+        trace!("[LOCK] Lock surface configure");
+
+        let surface_id = surface.wl_surface().id();
+        self.advice.configured(&surface_id);
+        deliver_configure(&mut self.surfaces_by_id, &surface_id, &configure);
+    }
+}
+
+impl DataDeviceHandler for Application {
+    fn enter(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _data_device: &WlDataDevice,
+        x: f64,
+        y: f64,
+        wl_surface: &WlSurface,
+    ) {
+        let Some(offer) = self.drag_offer() else {
+            return;
+        };
+        let mut mime_types = offer.with_mime_types(|mime_types| mime_types.to_vec());
+        dnd::limit_mime_types(&mut mime_types, &self.transfer_limits);
+        let mime = dnd::preferred_mime(&mime_types);
+        trace!(
+            "[DND] Drag entered with {:?}, reading {:?}",
+            mime_types, mime
+        );
+        // Accepting nothing shows the compositor's no-drop cursor
+        offer.accept_mime_type(offer.serial, mime.clone());
+        let action = if mime.is_some() {
+            DndAction::Copy
+        } else {
+            DndAction::empty()
+        };
+        offer.set_actions(action, action);
+        let surface_id = wl_surface.id();
+        self.drag = Some(DragState {
+            surface: surface_id.clone(),
+            position: (x, y),
+            mime,
+        });
+        self.with_dnd_container(&surface_id, |container| {
+            container.dnd_enter(x, y, &mime_types)
+        });
     }
 
-    fn new_output(
+    fn leave(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _data_device: &WlDataDevice) {
+        // Also the end of a cancelled drag, a drop takes the state first
+        let Some(drag) = self.drag.take() else {
+            return;
+        };
+        trace!("[DND] Drag left");
+        self.with_dnd_container(&drag.surface, |container| container.dnd_leave());
+    }
+
+    fn motion(
         &mut self,
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
-        _output: wl_output::WlOutput,
+        _data_device: &WlDataDevice,
+        x: f64,
+        y: f64,
     ) {
+        let Some(drag) = self.drag.as_mut() else {
+            return;
+        };
+        drag.position = (x, y);
+        let surface_id = drag.surface.clone();
+        self.with_dnd_container(&surface_id, |container| container.dnd_motion(x, y));
     }
 
-    fn update_output(
+    fn selection(
         &mut self,
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
-        _output: wl_output::WlOutput,
+        _data_device: &WlDataDevice,
     ) {
+        // The clipboard has its own connection, see clipboard_for_connection
     }
 
-    fn output_destroyed(
+    fn drop_performed(
         &mut self,
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
-        _output: wl_output::WlOutput,
+        _data_device: &WlDataDevice,
     ) {
+        let Some(drag) = self.drag.take() else {
+            return;
+        };
+        let Some(offer) = self.drag_offer() else {
+            self.with_dnd_container(&drag.surface, |container| container.dnd_leave());
+            return;
+        };
+        let Some(mime) = drag.mime.clone() else {
+            debug!("[DND] Dropped without a supported mime type");
+            offer.destroy();
+            self.with_dnd_container(&drag.surface, |container| container.dnd_leave());
+            return;
+        };
+        if let Some((_, previous)) = self.dropping.take() {
+            // Still reading the previous drop, it's dropped unfinished
+            previous.destroy();
+        }
+        self.read_drop(offer, drag, mime);
     }
 }
 
-impl LayerShellHandler for Application {
-    fn closed(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, target_layer: &LayerSurface) {
-        let surface_id = target_layer.wl_surface().id();
-        let index = self
-            .layer_surfaces
-            .iter()
-            .position(|id| id == &surface_id)
-            .expect("Layer surface is not added to application");
-
-        if let Some(kind) = self.get_by_surface_id_mut(&surface_id) {
-            if let Kind::LayerSurface(layer_surface) = kind {
-                layer_surface.closed();
-            }
-        }
-
-        // TODO: Should it be removed?
-        self.layer_surfaces.remove(index);
+impl DataOfferHandler for Application {
+    fn source_actions(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _offer: &mut DragOffer,
+        actions: DndAction,
+    ) {
+        trace!("[DND] Source actions {:?}", actions);
     }
 
-    fn configure(
+    fn selected_action(
         &mut self,
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
-        target_layer: &LayerSurface,
-        configure: LayerSurfaceConfigure,
-        _serial: u32,
+        _offer: &mut DragOffer,
+        actions: DndAction,
     ) {
-        trace!("[COMMON] XDG layer configure");
-
-        let surface_id = target_layer.wl_surface().id();
-        if let Some(kind) = self.get_by_surface_id_mut(&surface_id) {
-            if let Kind::LayerSurface(layer_surface) = kind {
-                layer_surface.configure(&configure);
-            }
-        }
+        trace!("[DND] Selected action {:?}", actions);
     }
 }
 
-impl PopupHandler for Application {
-    fn configure(
+/// Drags are only received, this client never offers data
+impl DataSourceHandler for Application {
+    fn accept_mime(
         &mut self,
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
-        target_popup: &Popup,
-        config: PopupConfigure,
+        _source: &WlDataSource,
+        _mime: Option<String>,
     ) {
-        trace!("[COMMON] XDG popup configure");
-
-        let surface_id = target_popup.wl_surface().id();
-        if let Some(kind) = self.get_by_surface_id_mut(&surface_id) {
-            if let Kind::Popup(popup) = kind {
-                popup.configure(&config);
-            }
-        }
     }
 
-    fn done(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, target_popup: &Popup) {
-        trace!("[COMMON] XDG popup done");
-
-        let surface_id = target_popup.wl_surface().id();
-        if let Some(kind) = self.get_by_surface_id_mut(&surface_id) {
-            if let Kind::Popup(popup) = kind {
-                popup.done();
-            }
-        }
+    fn send_request(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _source: &WlDataSource,
+        _mime: String,
+        _fd: WritePipe,
+    ) {
     }
-}
 
-impl WindowHandler for Application {
-    fn request_close(&mut self, _: &Connection, _: &QueueHandle<Self>, target_window: &Window) {
-        trace!("[COMMON] XDG window close requested");
-        let surface_id = target_window.wl_surface().id();
+    fn cancelled(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _source: &WlDataSource) {}
 
-        if let Some(kind) = self.get_by_surface_id_mut(&surface_id) {
-            if let Kind::Window(window) = kind {
-                window.request_close();
-                if window.allowed_to_close() {
-                    self.remove_window(target_window);
-                }
-            }
-        }
+    fn dnd_dropped(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _source: &WlDataSource) {
     }
 
-    fn configure(
+    fn dnd_finished(
         &mut self,
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
-        target_window: &Window,
-        configure: WindowConfigure,
-        _serial: u32,
+        _source: &WlDataSource,
     ) {
-        trace!("[COMMON] XDG window configure");
+    }
 
-        let surface_id = target_window.wl_surface().id();
-        if let Some(kind) = self.get_by_surface_id_mut(&surface_id) {
-            if let Kind::Window(window) = kind {
-                window.configure(&configure);
-            }
-        }
+    fn action(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _source: &WlDataSource,
+        _action: DndAction,
+    ) {
     }
 }
 
@@ -554,35 +2328,49 @@ impl PointerHandler for Application {
         events: &[PointerEvent],
     ) {
         trace!("[MAIN] Pointer frame with {} events", events.len());
+        self.pointer_frame_count += 1;
 
         for event in events {
             match event.kind {
                 // Changing cursor shape requires last enter serial number, we are storing it here
                 PointerEventKind::Enter { serial } => {
+                    self.pointer_focus = Some(event.surface.id());
                     self.last_pointer_enter_serial = Some(serial);
                     self.last_pointer = Some(pointer.clone());
+                    self.apply_cursor();
+                }
+                PointerEventKind::Leave { .. }
+                    if self.pointer_focus.as_ref() == Some(&event.surface.id()) =>
+                {
+                    self.pointer_focus = None;
+                }
+                PointerEventKind::Press { serial, .. } => {
+                    self.last_input_serial = Some(serial);
+                    self.set_input_seat(self.seats.seat_of_pointer(pointer));
                 }
                 _ => {}
             }
 
+            // Pointer moving into a popup leaves the parent, the parent is
+            // still remembered for recovering from a stuck grab
             let surface_id = event.surface.id();
-            if let Some(kind) = self.get_by_surface_id_mut(&surface_id) {
-                match kind {
-                    Kind::Window(window) => {
-                        window.pointer_frame(event);
-                    }
-                    Kind::LayerSurface(layer_surface) => {
-                        layer_surface.pointer_frame(event);
+            if !self.popups.contains(&surface_id) {
+                match event.kind {
+                    PointerEventKind::Leave { .. } if self.popups.is_empty() => {
+                        self.last_parent_pointer = None;
                     }
-                    Kind::Popup(popup) => {
-                        popup.pointer_frame(event);
-                    }
-                    Kind::Subsurface(subsurface) => {
-                        subsurface.pointer_frame(event);
+                    PointerEventKind::Leave { .. } => {}
+                    _ => {
+                        self.last_parent_pointer = Some((event.surface.clone(), event.position));
                     }
                 }
             }
         }
+
+        // A frame may span surfaces, e.g. leave from one and enter to another
+        for surface_events in events.chunk_by(|a, b| a.surface == b.surface) {
+            self.dispatch_pointer_events(surface_events);
+        }
     }
 }
 
@@ -591,30 +2379,30 @@ impl KeyboardHandler for Application {
         &mut self,
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
-        _keyboard: &WlKeyboard,
+        keyboard: &WlKeyboard,
         surface: &WlSurface,
         _serial: u32,
-        _raw: &[u32],
+        raw: &[u32],
         _keysyms: &[Keysym],
     ) {
         trace!("[MAIN] Keyboard focus gained on surface {:?}", surface.id());
+        let Some(seat) = self.seats.seat_of_keyboard(keyboard) else {
+            return;
+        };
+        self.set_input_seat(Some(seat.clone()));
         let surface_id = surface.id();
-        self.keyboard_focused_surface = Some(surface_id.clone());
-        if let Some(kind) = self.get_by_surface_id_mut(&surface_id) {
-            match kind {
-                Kind::Window(window) => {
-                    window.enter();
-                }
-                Kind::LayerSurface(layer_surface) => {
-                    layer_surface.enter();
-                }
-                Kind::Popup(popup) => {
-                    popup.enter();
-                }
-                Kind::Subsurface(subsurface) => {
-                    subsurface.enter();
-                }
-            }
+        let already_focused = self.seats.keyboard_focused_by_other(&surface_id, &seat);
+        let resources = self.seats.resources(&seat);
+        // Keys already held are only recorded, synthesizing presses for them
+        // would type text that wasn't typed into this surface. Their releases
+        // are still delivered and they don't repeat.
+        resources.pressed_keys = raw.iter().copied().collect();
+        resources.keyboard_focus = Some(surface_id.clone());
+        if already_focused {
+            return;
+        }
+        if let Some(container) = input_target(&mut self.surfaces_by_id, Some(&surface_id)) {
+            container.enter();
         }
     }
 
@@ -622,86 +2410,68 @@ impl KeyboardHandler for Application {
         &mut self,
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
-        _keyboard: &WlKeyboard,
+        keyboard: &WlKeyboard,
         surface: &WlSurface,
         _serial: u32,
     ) {
         trace!("[MAIN] Keyboard focus lost");
-        let surface_id = surface.id();
-        if let Some(kind) = self.get_by_surface_id_mut(&surface_id) {
-            match kind {
-                Kind::Window(window) => {
-                    window.leave();
-                }
-                Kind::LayerSurface(layer_surface) => {
-                    layer_surface.leave();
-                }
-                Kind::Popup(popup) => {
-                    popup.leave();
-                }
-                Kind::Subsurface(subsurface) => {
-                    subsurface.leave();
-                }
-            }
+        if let Some(seat) = self.seats.seat_of_keyboard(keyboard) {
+            self.drop_keyboard_focus(&seat, Some(surface.id()));
         }
-        self.keyboard_focused_surface = None;
     }
 
     fn press_key(
         &mut self,
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
-        _keyboard: &WlKeyboard,
-        _serial: u32,
-        event: KeyEvent,
+        keyboard: &WlKeyboard,
+        serial: u32,
+        mut event: KeyEvent,
     ) {
         trace!("[MAIN] Key pressed: keycode={}", event.raw_code);
+        let Some(seat) = self.seats.seat_of_keyboard(keyboard) else {
+            return;
+        };
+        let resources = self.seats.resources(&seat);
+        if let Some(compose) = &mut resources.compose {
+            compose.feed(&mut event);
+        }
+        resources.pressed_keys.insert(event.raw_code);
+        self.last_input_serial = Some(serial);
+        self.set_input_seat(Some(seat.clone()));
 
-        if let Some(surface_id) = self.keyboard_focused_surface.clone() {
-            if let Some(kind) = self.get_by_surface_id_mut(&surface_id) {
-                match kind {
-                    Kind::Window(window) => {
-                        window.press_key(&event);
-                    }
-                    Kind::LayerSurface(layer_surface) => {
-                        layer_surface.press_key(&event);
-                    }
-                    Kind::Popup(popup) => {
-                        popup.press_key(&event);
-                    }
-                    Kind::Subsurface(subsurface) => {
-                        subsurface.press_key(&event);
-                    }
-                }
-            }
+        let target = self.keyboard_target(&seat);
+        if let Some(container) = input_target(&mut self.surfaces_by_id, target.as_ref()) {
+            container.press_key(&event);
         }
+
+        self.start_key_repeat(&seat, event);
     }
 
     fn release_key(
         &mut self,
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
-        _keyboard: &WlKeyboard,
+        keyboard: &WlKeyboard,
         _serial: u32,
         event: KeyEvent,
     ) {
-        if let Some(surface_id) = self.keyboard_focused_surface.clone() {
-            if let Some(kind) = self.get_by_surface_id_mut(&surface_id) {
-                match kind {
-                    Kind::Window(window) => {
-                        window.release_key(&event);
-                    }
-                    Kind::LayerSurface(layer_surface) => {
-                        layer_surface.release_key(&event);
-                    }
-                    Kind::Popup(popup) => {
-                        popup.release_key(&event);
-                    }
-                    Kind::Subsurface(subsurface) => {
-                        subsurface.release_key(&event);
-                    }
-                }
-            }
+        let Some(seat) = self.seats.seat_of_keyboard(keyboard) else {
+            return;
+        };
+        let resources = self.seats.resources(&seat);
+        resources.pressed_keys.remove(&event.raw_code);
+        let repeating = resources
+            .key_repeat
+            .as_ref()
+            .map(|key_repeat| key_repeat.event.raw_code);
+        if repeating == Some(event.raw_code) {
+            self.stop_key_repeat(&seat);
+        }
+
+        let target = self.keyboard_target(&seat);
+        if let Some(container) = input_target(&mut self.surfaces_by_id, target.as_ref()) {
+            container.release_key(&event);
         }
     }
 
@@ -709,29 +2479,21 @@ impl KeyboardHandler for Application {
         &mut self,
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
-        _keyboard: &WlKeyboard,
+        keyboard: &WlKeyboard,
         _serial: u32,
         modifiers: smithay_client_toolkit::seat::keyboard::Modifiers,
         _raw_modifiers: smithay_client_toolkit::seat::keyboard::RawModifiers,
-        _layout: u32,
+        layout: u32,
     ) {
-        if let Some(surface_id) = self.keyboard_focused_surface.clone() {
-            if let Some(kind) = self.get_by_surface_id_mut(&surface_id) {
-                match kind {
-                    Kind::Window(window) => {
-                        window.update_modifiers(&modifiers);
-                    }
-                    Kind::LayerSurface(layer_surface) => {
-                        layer_surface.update_modifiers(&modifiers);
-                    }
-                    Kind::Popup(popup) => {
-                        popup.update_modifiers(&modifiers);
-                    }
-                    Kind::Subsurface(subsurface) => {
-                        subsurface.update_modifiers(&modifiers);
-                    }
-                }
-            }
+        if self.keyboard_layouts.set_active(layout) {
+            self.dispatch_layout_changed();
+        }
+        let Some(seat) = self.seats.seat_of_keyboard(keyboard) else {
+            return;
+        };
+        let target = self.keyboard_target(&seat);
+        if let Some(container) = input_target(&mut self.surfaces_by_id, target.as_ref()) {
+            container.update_modifiers(&modifiers);
         }
     }
 
@@ -739,28 +2501,64 @@ impl KeyboardHandler for Application {
         &mut self,
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
-        _keyboard: &WlKeyboard,
+        keyboard: &WlKeyboard,
         _serial: u32,
         event: KeyEvent,
     ) {
-        if let Some(surface_id) = self.keyboard_focused_surface.clone() {
-            if let Some(kind) = self.get_by_surface_id_mut(&surface_id) {
-                match kind {
-                    Kind::Window(window) => {
-                        window.repeat_key(&event);
-                    }
-                    Kind::LayerSurface(layer_surface) => {
-                        layer_surface.repeat_key(&event);
-                    }
-                    Kind::Popup(popup) => {
-                        popup.repeat_key(&event);
-                    }
-                    Kind::Subsurface(subsurface) => {
-                        subsurface.repeat_key(&event);
-                    }
-                }
+        if let Some(seat) = self.seats.seat_of_keyboard(keyboard) {
+            self.dispatch_repeat_key(&seat, &event);
+        }
+    }
+
+    fn update_repeat_info(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        keyboard: &WlKeyboard,
+        info: RepeatInfo,
+    ) {
+        trace!("[MAIN] Keyboard repeat info: {:?}", info);
+        let Some(seat) = self.seats.seat_of_keyboard(keyboard) else {
+            return;
+        };
+        let previous = self.seats.resources(&seat).repeat_info.replace(info);
+        let unchanged = match (previous, info) {
+            (
+                Some(RepeatInfo::Repeat { rate, delay }),
+                RepeatInfo::Repeat {
+                    rate: new_rate,
+                    delay: new_delay,
+                },
+            ) => rate == new_rate && delay == new_delay,
+            (Some(RepeatInfo::Disable), RepeatInfo::Disable) => true,
+            _ => false,
+        };
+        if unchanged {
+            return;
+        }
+        if let Some(key_repeat) = self
+            .seats
+            .get_mut(&seat)
+            .and_then(|resources| resources.key_repeat.as_mut())
+        {
+            // Enabled while the key is held, it repeats after the delay
+            if matches!(previous.unwrap_or(DEFAULT_REPEAT_INFO), RepeatInfo::Disable) {
+                key_repeat.since = Instant::now();
             }
+            self.arm_key_repeat(&seat);
         }
+        self.dispatch_repeat_info_changed(info);
+    }
+
+    fn update_keymap(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _keyboard: &WlKeyboard,
+        keymap: Keymap<'_>,
+    ) {
+        self.keyboard_layouts.set_keymap(keymap.as_string());
+        self.dispatch_layout_changed();
     }
 }
 
@@ -769,7 +2567,15 @@ impl SeatHandler for Application {
         &mut self.seat_state
     }
 
-    fn new_seat(&mut self, _: &Connection, _: &QueueHandle<Self>, _: wl_seat::WlSeat) {}
+    fn new_seat(&mut self, _: &Connection, qh: &QueueHandle<Self>, seat: wl_seat::WlSeat) {
+        self.protocol_versions.record(Global::Seat, seat.version());
+        // Drags come from the first seat only, as does the other input
+        if self.data_device.is_none()
+            && let Some(manager) = &self.data_device_manager
+        {
+            self.data_device = Some(manager.get_data_device(qh, &seat));
+        }
+    }
 
     fn new_capability(
         &mut self,
@@ -778,21 +2584,47 @@ impl SeatHandler for Application {
         seat: wl_seat::WlSeat,
         capability: Capability,
     ) {
-        trace!("[MAIN] New seat capability: {:?}", capability);
-        if capability == Capability::Keyboard {
+        trace!("[MAIN] New seat {} capability: {:?}", seat.id(), capability);
+        if self.input_seat.is_none() {
+            self.input_seat = Some(seat.clone());
+        }
+        if capability == Capability::Keyboard && self.seats.resources(&seat).keyboard.is_none() {
             trace!("[MAIN] Creating wl_keyboard");
             match self.seat_state.get_keyboard(qh, &seat, None) {
-                Ok(_wl_keyboard) => {
-                    trace!("[MAIN] wl_keyboard created successfully");
-                }
-                Err(e) => {
-                    trace!("[MAIN] Failed to create wl_keyboard: {:?}", e);
+                Ok(keyboard) => {
+                    let text_input = self.text_input.create_for_seat(&seat, qh);
+                    let resources = self.seats.resources(&seat);
+                    resources.keyboard = Some(keyboard);
+                    resources.compose = Some(Compose::new());
+                    resources.text_input = text_input;
                 }
+                Err(e) => trace!("[MAIN] Failed to create wl_keyboard: {:?}", e),
             }
         }
-        if capability == Capability::Pointer {
-            let _ = self.seat_state.get_pointer(&qh, &seat);
+        if capability == Capability::Pointer && self.seats.resources(&seat).pointer.is_none() {
+            if self.cursor_shape_manager.is_some() {
+                match self.seat_state.get_pointer(qh, &seat) {
+                    Ok(pointer) => self.seats.resources(&seat).pointer = Some(pointer),
+                    Err(e) => trace!("[MAIN] Failed to create wl_pointer: {:?}", e),
+                }
+                return;
+            }
             trace!("[MAIN] Creating themed pointer");
+            let surface = self.compositor_state.create_surface(qh);
+            match self.seat_state.get_pointer_with_theme(
+                qh,
+                &seat,
+                self.shm_state.wl_shm(),
+                surface,
+                ThemeSpec::default(),
+            ) {
+                Ok(themed_pointer) => {
+                    let resources = self.seats.resources(&seat);
+                    resources.pointer = Some(themed_pointer.pointer().clone());
+                    resources.themed_pointer = Some(themed_pointer);
+                }
+                Err(e) => trace!("[MAIN] Failed to create themed pointer: {:?}", e),
+            }
         }
     }
 
@@ -800,12 +2632,44 @@ impl SeatHandler for Application {
         &mut self,
         _conn: &Connection,
         _: &QueueHandle<Self>,
-        _: wl_seat::WlSeat,
-        _capability: Capability,
+        seat: wl_seat::WlSeat,
+        capability: Capability,
     ) {
+        trace!(
+            "[MAIN] Seat {} lost capability: {:?}",
+            seat.id(),
+            capability
+        );
+        match capability {
+            Capability::Keyboard => {
+                // No leave event comes for the focused surface
+                self.drop_keyboard_focus(&seat, None);
+                self.drop_text_input_focus(&seat);
+                self.seats.resources(&seat).release_keyboard();
+            }
+            Capability::Pointer => {
+                if let Some(pointer) = self.seats.resources(&seat).pointer.clone() {
+                    self.forget_pointer(&pointer.id());
+                }
+                self.seats.resources(&seat).release_pointer();
+            }
+            _ => {}
+        }
     }
 
-    fn remove_seat(&mut self, _: &Connection, _: &QueueHandle<Self>, _: wl_seat::WlSeat) {}
+    fn remove_seat(&mut self, _: &Connection, _: &QueueHandle<Self>, seat: wl_seat::WlSeat) {
+        trace!("[MAIN] Seat {} removed", seat.id());
+        if let Some(pointer) = self.seats.resources(&seat).pointer.clone() {
+            self.forget_pointer(&pointer.id());
+        }
+        self.drop_keyboard_focus(&seat, None);
+        self.drop_text_input_focus(&seat);
+        self.seats.remove(&seat);
+        if self.input_seat.as_ref() == Some(&seat) {
+            self.input_seat = self.seats.first();
+            self.last_input_serial = None;
+        }
+    }
 }
 
 impl ShmHandler for Application {
@@ -822,7 +2686,9 @@ impl ProvidesRegistryState for Application {
     }
 }
 
+delegate_activation!(Application);
 delegate_compositor!(Application);
+delegate_data_device!(Application);
 delegate_subcompositor!(Application);
 delegate_output!(Application);
 delegate_shm!(Application);
@@ -832,9 +2698,283 @@ delegate_keyboard!(Application);
 delegate_pointer!(Application);
 
 delegate_layer!(Application);
+delegate_session_lock!(Application);
 
 delegate_xdg_shell!(Application);
 delegate_xdg_window!(Application);
 delegate_xdg_popup!(Application);
 
 delegate_registry!(Application);
+
+fn create_wgpu_instance() -> wgpu::Instance {
+    wgpu::Instance::new(&wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::all(),
+        ..Default::default()
+    })
+}
+
+/// Activation token given by the launcher, left in the environment
+fn startup_token() -> Option<String> {
+    let token = std::env::var("XDG_ACTIVATION_TOKEN").ok()?;
+    (!token.is_empty()).then_some(token)
+}
+
+/// Remove the launcher's activation token from the environment so child
+/// processes don't reuse it, as the spec asks. `Application::new` only reads
+/// it, give the returned token to `Application::set_startup_token` when it
+/// was removed before the application was created.
+///
+/// # Safety
+///
+/// Same as `std::env::remove_var`, no other thread may read or write the
+/// environment meanwhile. Call it at the start of `main` before spawning
+/// threads, `Application::new` starts one loading the graphics drivers.
+pub unsafe fn take_startup_token() -> Option<String> {
+    let token = startup_token();
+    // SAFETY: the caller guarantees no other thread uses the environment
+    unsafe { std::env::remove_var("XDG_ACTIVATION_TOKEN") };
+    token
+}
+
+/// Cursor theme name of the shape, used when cursor shape protocol is missing
+fn shape_to_cursor_icon(shape: Shape) -> CursorIcon {
+    match shape {
+        Shape::ContextMenu => CursorIcon::ContextMenu,
+        Shape::Help => CursorIcon::Help,
+        Shape::Pointer => CursorIcon::Pointer,
+        Shape::Progress => CursorIcon::Progress,
+        Shape::Wait => CursorIcon::Wait,
+        Shape::Cell => CursorIcon::Cell,
+        Shape::Crosshair => CursorIcon::Crosshair,
+        Shape::Text => CursorIcon::Text,
+        Shape::VerticalText => CursorIcon::VerticalText,
+        Shape::Alias => CursorIcon::Alias,
+        Shape::Copy => CursorIcon::Copy,
+        Shape::Move => CursorIcon::Move,
+        Shape::NoDrop => CursorIcon::NoDrop,
+        Shape::NotAllowed => CursorIcon::NotAllowed,
+        Shape::Grab => CursorIcon::Grab,
+        Shape::Grabbing => CursorIcon::Grabbing,
+        Shape::EResize => CursorIcon::EResize,
+        Shape::NResize => CursorIcon::NResize,
+        Shape::NeResize => CursorIcon::NeResize,
+        Shape::NwResize => CursorIcon::NwResize,
+        Shape::SResize => CursorIcon::SResize,
+        Shape::SeResize => CursorIcon::SeResize,
+        Shape::SwResize => CursorIcon::SwResize,
+        Shape::WResize => CursorIcon::WResize,
+        Shape::EwResize => CursorIcon::EwResize,
+        Shape::NsResize => CursorIcon::NsResize,
+        Shape::NeswResize => CursorIcon::NeswResize,
+        Shape::NwseResize => CursorIcon::NwseResize,
+        Shape::ColResize => CursorIcon::ColResize,
+        Shape::RowResize => CursorIcon::RowResize,
+        Shape::AllScroll => CursorIcon::AllScroll,
+        Shape::ZoomIn => CursorIcon::ZoomIn,
+        Shape::ZoomOut => CursorIcon::ZoomOut,
+        _ => CursorIcon::Default,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CompositorHandlerContainer;
+    use crate::PointerHandlerContainer;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// Container recording the input it gets
+    struct Recorder {
+        name: &'static str,
+        received: Rc<RefCell<Vec<String>>>,
+    }
+
+    impl CompositorHandlerContainer for Recorder {
+        fn scale_factor_changed(&mut self, new_factor: i32) {
+            let received = format!("{} scale {}", self.name, new_factor);
+            self.received.borrow_mut().push(received);
+        }
+
+        fn frame(&mut self, time: u32) {
+            let received = format!("{} frame {}", self.name, time);
+            self.received.borrow_mut().push(received);
+        }
+    }
+
+    impl DndHandlerContainer for Recorder {}
+
+    impl KeyboardHandlerContainer for Recorder {
+        fn press_key(&mut self, event: &KeyEvent) {
+            let received = format!("{} key {}", self.name, event.raw_code);
+            self.received.borrow_mut().push(received);
+        }
+    }
+
+    impl PointerHandlerContainer for Recorder {
+        fn pointer_frame(&mut self, _events: &[PointerEvent]) {
+            let received = format!("{} pointer", self.name);
+            self.received.borrow_mut().push(received);
+        }
+    }
+
+    impl BaseTrait for Recorder {
+        fn get_object_id(&self) -> ObjectId {
+            ObjectId::null()
+        }
+    }
+
+    impl WindowContainer for Recorder {
+        fn configure(&mut self, _configure: &WindowConfigure) {}
+    }
+
+    impl LayerSurfaceContainer for Recorder {
+        fn configure(&mut self, _config: &LayerSurfaceConfigure) {}
+    }
+
+    impl PopupContainer for Recorder {
+        fn configure(&mut self, _config: &PopupConfigure) {}
+    }
+
+    impl SubsurfaceContainer for Recorder {
+        fn configure(&mut self, width: u32, height: u32) {
+            let received = format!("{} configure {}x{}", self.name, width, height);
+            self.received.borrow_mut().push(received);
+        }
+    }
+
+    impl LockSurfaceContainer for Recorder {
+        fn configure(&mut self, _config: &SessionLockSurfaceConfigure) {}
+    }
+
+    /// Surface of every kind, keyed 1 to 5
+    fn surfaces(received: &Rc<RefCell<Vec<String>>>) -> HashMap<u32, Kind> {
+        let recorder = |name| Recorder {
+            name,
+            received: received.clone(),
+        };
+        HashMap::from([
+            (1, Kind::Window(Box::new(recorder("window")))),
+            (2, Kind::LayerSurface(Box::new(recorder("layer_surface")))),
+            (3, Kind::Popup(Box::new(recorder("popup")))),
+            (4, Kind::Subsurface(Box::new(recorder("subsurface")))),
+            (5, Kind::LockSurface(Box::new(recorder("lock_surface")))),
+        ])
+    }
+
+    /// Configure of the subsurface role, the configures of the other roles
+    /// can't be built outside the toolkit
+    struct Size(u32, u32);
+
+    impl RoleConfigure for Size {
+        fn apply(&self, kind: &mut Kind) {
+            if let Kind::Subsurface(subsurface) = kind {
+                subsurface.configure(self.0, self.1);
+            }
+        }
+    }
+
+    fn key(raw_code: u32) -> KeyEvent {
+        KeyEvent {
+            time: 0,
+            raw_code,
+            keysym: Keysym::a,
+            utf8: Some("a".to_string()),
+        }
+    }
+
+    #[test]
+    fn input_reaches_the_container_of_its_surface() {
+        let received = Rc::default();
+        let mut surfaces = surfaces(&received);
+        for surface_id in [3, 1, 5, 2, 4] {
+            let container = input_target(&mut surfaces, Some(&surface_id)).unwrap();
+            container.press_key(&key(surface_id));
+            container.pointer_frame(&[]);
+        }
+        assert_eq!(
+            *received.borrow(),
+            [
+                "popup key 3",
+                "popup pointer",
+                "window key 1",
+                "window pointer",
+                "lock_surface key 5",
+                "lock_surface pointer",
+                "layer_surface key 2",
+                "layer_surface pointer",
+                "subsurface key 4",
+                "subsurface pointer",
+            ]
+        );
+    }
+
+    #[test]
+    fn input_of_unknown_surfaces_is_dropped() {
+        let received = Rc::default();
+        let mut surfaces = surfaces(&received);
+        assert!(input_target(&mut surfaces, Some(&9)).is_none());
+        // No keyboard focus
+        assert!(input_target(&mut surfaces, None).is_none());
+        assert!(received.borrow().is_empty());
+    }
+
+    #[test]
+    fn configures_reach_only_the_container_of_their_surface() {
+        let received = Rc::default();
+        let mut surfaces = surfaces(&received);
+        let other = Recorder {
+            name: "other_subsurface",
+            received: received.clone(),
+        };
+        surfaces.insert(6, Kind::Subsurface(Box::new(other)));
+        deliver_configure(&mut surfaces, &6, &Size(10, 20));
+        deliver_configure(&mut surfaces, &4, &Size(30, 40));
+        // Another role and a removed surface
+        deliver_configure(&mut surfaces, &1, &Size(50, 60));
+        deliver_configure(&mut surfaces, &9, &Size(70, 80));
+        assert_eq!(
+            *received.borrow(),
+            [
+                "other_subsurface configure 10x20",
+                "subsurface configure 30x40"
+            ]
+        );
+    }
+
+    #[test]
+    fn frames_reach_only_the_container_of_their_surface() {
+        let received = Rc::default();
+        let mut surfaces = surfaces(&received);
+        surface_target(&mut surfaces, &2).unwrap().frame(16);
+        surface_target(&mut surfaces, &1).unwrap().frame(33);
+        assert!(surface_target(&mut surfaces, &9).is_none());
+        assert_eq!(
+            *received.borrow(),
+            ["layer_surface frame 16", "window frame 33"]
+        );
+    }
+
+    #[test]
+    fn scale_changes_reach_only_the_container_of_their_surface() {
+        let received = Rc::default();
+        let mut surfaces = surfaces(&received);
+        surface_target(&mut surfaces, &3)
+            .unwrap()
+            .scale_factor_changed(2);
+        surface_target(&mut surfaces, &5)
+            .unwrap()
+            .scale_factor_changed(1);
+        assert!(surface_target(&mut surfaces, &9).is_none());
+        assert_eq!(
+            *received.borrow(),
+            ["popup scale 2", "lock_surface scale 1"]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "get_init_app")]
+    fn get_app_panics_without_the_global() {
+        get_app();
+    }
+}
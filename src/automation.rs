@@ -0,0 +1,251 @@
+//! Input synthesis for UI automation and demos, enabled with the
+//! `automation` feature.
+//!
+//! Events are given to the surface container the same way as the events of
+//! the seat, so input handling, shortcuts and frame coalescing all apply.
+//! Touch is not synthesized, containers don't handle touch input.
+use crate::Application;
+use smithay_client_toolkit::reexports::calloop::timer::TimeoutAction;
+use smithay_client_toolkit::reexports::calloop::timer::Timer;
+use smithay_client_toolkit::seat::keyboard::KeyEvent;
+use smithay_client_toolkit::seat::keyboard::Keysym;
+use smithay_client_toolkit::seat::keyboard::Modifiers;
+use smithay_client_toolkit::seat::pointer::AxisScroll;
+use smithay_client_toolkit::seat::pointer::BTN_LEFT;
+use smithay_client_toolkit::seat::pointer::PointerEvent;
+use smithay_client_toolkit::seat::pointer::PointerEventKind;
+use std::collections::VecDeque;
+use std::time::Duration;
+use wayland_client::Proxy;
+use wayland_client::protocol::wl_surface::WlSurface;
+
+#[derive(Debug, Clone)]
+pub enum SyntheticEvent {
+    /// Pointer position in surface coordinates
+    PointerMove {
+        x: f64,
+        y: f64,
+    },
+    /// Linux button code, e.g. `BTN_LEFT`
+    PointerButton {
+        button: u32,
+        pressed: bool,
+    },
+    /// Wheel steps, positive scrolls down and right
+    Scroll {
+        dx: i32,
+        dy: i32,
+    },
+    Key {
+        keysym: Keysym,
+        pressed: bool,
+    },
+    Modifiers(Modifiers),
+    /// Each character is pressed and released
+    Text(String),
+    Wait(Duration),
+}
+
+/// Sequence of synthetic events with helpers for common gestures
+#[derive(Debug, Clone, Default)]
+pub struct Script {
+    pub events: Vec<SyntheticEvent>,
+}
+
+impl Script {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(mut self, event: SyntheticEvent) -> Self {
+        self.events.push(event);
+        self
+    }
+
+    pub fn wait(self, duration: Duration) -> Self {
+        self.push(SyntheticEvent::Wait(duration))
+    }
+
+    pub fn click_at(self, x: f64, y: f64) -> Self {
+        self.push(SyntheticEvent::PointerMove { x, y })
+            .push(SyntheticEvent::PointerButton {
+                button: BTN_LEFT,
+                pressed: true,
+            })
+            .push(SyntheticEvent::PointerButton {
+                button: BTN_LEFT,
+                pressed: false,
+            })
+    }
+
+    /// Drag with the left button in `steps` moves over `duration`
+    pub fn drag(self, from: (f64, f64), to: (f64, f64), steps: u32, duration: Duration) -> Self {
+        let steps = steps.max(1);
+        let mut script = self
+            .push(SyntheticEvent::PointerMove {
+                x: from.0,
+                y: from.1,
+            })
+            .push(SyntheticEvent::PointerButton {
+                button: BTN_LEFT,
+                pressed: true,
+            });
+        for step in 1..=steps {
+            let t = step as f64 / steps as f64;
+            script = script
+                .wait(duration / steps)
+                .push(SyntheticEvent::PointerMove {
+                    x: from.0 + (to.0 - from.0) * t,
+                    y: from.1 + (to.1 - from.1) * t,
+                });
+        }
+        script.push(SyntheticEvent::PointerButton {
+            button: BTN_LEFT,
+            pressed: false,
+        })
+    }
+
+    pub fn type_text(self, text: impl Into<String>) -> Self {
+        self.push(SyntheticEvent::Text(text.into()))
+    }
+
+    pub fn scroll_by(self, dx: i32, dy: i32) -> Self {
+        self.push(SyntheticEvent::Scroll { dx, dy })
+    }
+}
+
+/// Run once the last event of a script is replayed
+type OnDone = Box<dyn FnOnce(&mut Application)>;
+
+/// Script being replayed, runs until the next wait
+struct Replay {
+    surface: WlSurface,
+    events: VecDeque<SyntheticEvent>,
+    position: (f64, f64),
+    time_scale: f64,
+    on_done: Option<OnDone>,
+}
+
+impl Replay {
+    fn run(mut self, app: &mut Application) {
+        while let Some(event) = self.events.pop_front() {
+            match event {
+                SyntheticEvent::Wait(duration) => {
+                    let delay = duration.div_f64(self.time_scale);
+                    let mut replay = Some(self);
+                    app.loop_handle
+                        .insert_source(Timer::from_duration(delay), move |_, _, app| {
+                            if let Some(replay) = replay.take() {
+                                replay.run(app);
+                            }
+                            TimeoutAction::Drop
+                        })
+                        .expect("Failed to insert automation timer");
+                    return;
+                }
+                event => self.dispatch(app, event),
+            }
+        }
+        if let Some(on_done) = self.on_done.take() {
+            on_done(app);
+        }
+    }
+
+    fn dispatch(&mut self, app: &mut Application, event: SyntheticEvent) {
+        let kind = match event {
+            SyntheticEvent::PointerMove { x, y } => {
+                self.position = (x, y);
+                PointerEventKind::Motion { time: 0 }
+            }
+            SyntheticEvent::PointerButton { button, pressed } if pressed => {
+                PointerEventKind::Press {
+                    time: 0,
+                    button,
+                    serial: 0,
+                }
+            }
+            SyntheticEvent::PointerButton { button, .. } => PointerEventKind::Release {
+                time: 0,
+                button,
+                serial: 0,
+            },
+            SyntheticEvent::Scroll { dx, dy } => PointerEventKind::Axis {
+                time: 0,
+                horizontal: AxisScroll {
+                    discrete: dx,
+                    value120: dx * 120,
+                    ..Default::default()
+                },
+                vertical: AxisScroll {
+                    discrete: dy,
+                    value120: dy * 120,
+                    ..Default::default()
+                },
+                source: None,
+            },
+            SyntheticEvent::Key { keysym, pressed } => {
+                return self.key(app, keysym, None, pressed);
+            }
+            SyntheticEvent::Modifiers(modifiers) => {
+                return app.with_keyboard_container(&self.surface.id(), |container| {
+                    container.update_modifiers(&modifiers)
+                });
+            }
+            SyntheticEvent::Text(text) => {
+                for c in text.chars() {
+                    let keysym = match c {
+                        '\n' => Keysym::Return,
+                        c => Keysym::from_char(c),
+                    };
+                    self.key(app, keysym, Some(c.to_string()), true);
+                    self.key(app, keysym, None, false);
+                }
+                return;
+            }
+            SyntheticEvent::Wait(_) => return,
+        };
+        app.dispatch_pointer_events(&[PointerEvent {
+            surface: self.surface.clone(),
+            position: self.position,
+            kind,
+        }]);
+    }
+
+    fn key(&self, app: &mut Application, keysym: Keysym, utf8: Option<String>, pressed: bool) {
+        let event = KeyEvent {
+            time: 0,
+            raw_code: 0,
+            keysym,
+            utf8: utf8.or_else(|| keysym.key_char().filter(|_| pressed).map(String::from)),
+        };
+        app.with_keyboard_container(&self.surface.id(), |container| {
+            if pressed {
+                container.press_key(&event);
+            } else {
+                container.release_key(&event);
+            }
+        });
+    }
+}
+
+impl Application {
+    /// Replay the script on the surface, waits are divided by `time_scale`
+    /// so 2.0 replays at double speed. `on_done` runs after the last event.
+    pub fn inject(
+        &mut self,
+        surface: &WlSurface,
+        script: Script,
+        time_scale: f64,
+        on_done: impl FnOnce(&mut Application) + 'static,
+    ) {
+        let replay = Replay {
+            surface: surface.clone(),
+            events: script.events.into(),
+            position: (0.0, 0.0),
+            time_scale: if time_scale > 0.0 { time_scale } else { 1.0 },
+            on_done: Some(Box::new(on_done)),
+        };
+        // Containers can't be dispatched to while one of them is rendering
+        self.loop_handle.insert_idle(move |app| replay.run(app));
+    }
+}
@@ -0,0 +1,130 @@
+//! Renders the registered examples into PNGs without a compositor
+//!
+//! ```sh
+//! cargo run --bin render-docs --features docs-render -- \
+//!     --out target/docs-render --size 320x240 --theme dark --scale 1 --scale 2 --html
+//! ```
+//!
+//! Without `--size` each example is rendered at its registered size, without
+//! `--theme` in both themes and without `--scale` at scale 1.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+use wayapp::Gallery;
+use wayapp::GpuContext;
+use wayapp::OffscreenRenderer;
+use wayapp::ShotMatrix;
+use wayapp::render_gallery;
+use wayapp::write_html_index;
+
+#[path = "../../examples/egui_example.rs"]
+#[allow(dead_code)]
+mod egui_example;
+#[path = "../../examples/egui_unicode_entry.rs"]
+#[allow(dead_code)]
+mod egui_unicode_entry;
+
+const USAGE: &str = "Usage: render-docs [--out DIR] [--size WxH]... [--theme light|dark]... \
+                     [--scale N]... [--only NAME]... [--html]";
+
+struct Args {
+    out: PathBuf,
+    matrix: ShotMatrix,
+    only: Vec<String>,
+    html: bool,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut out = PathBuf::from("target/docs-render");
+    let mut sizes = Vec::new();
+    let mut themes = Vec::new();
+    let mut scales = Vec::new();
+    let mut only = Vec::new();
+    let mut html = false;
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--out" => out = PathBuf::from(value()?),
+            "--size" => {
+                let size = value()?;
+                let parsed = size
+                    .split_once('x')
+                    .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+                    .filter(|&(w, h)| w > 0 && h > 0);
+                sizes.push(parsed.ok_or_else(|| format!("Invalid size {:?}", size))?);
+            }
+            "--theme" => themes.push(match value()?.as_str() {
+                "light" => egui::Theme::Light,
+                "dark" => egui::Theme::Dark,
+                other => return Err(format!("Invalid theme {:?}", other)),
+            }),
+            "--scale" => {
+                let scale = value()?;
+                match scale.parse::<f32>() {
+                    Ok(parsed) if parsed > 0.0 => scales.push(parsed),
+                    _ => return Err(format!("Invalid scale {:?}", scale)),
+                }
+            }
+            "--only" => only.push(value()?),
+            "--html" => html = true,
+            "--help" | "-h" => return Err(USAGE.to_string()),
+            other => return Err(format!("Unknown argument {:?}\n{}", other, USAGE)),
+        }
+    }
+    let mut matrix = ShotMatrix {
+        sizes,
+        ..Default::default()
+    };
+    if !themes.is_empty() {
+        matrix.themes = themes;
+    }
+    if !scales.is_empty() {
+        matrix.scales = scales;
+    }
+    Ok(Args {
+        out,
+        matrix,
+        only,
+        html,
+    })
+}
+
+fn main() -> ExitCode {
+    env_logger::init();
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut gallery = Gallery::new();
+    egui_example::register_docs(&mut gallery);
+    egui_unicode_entry::register_docs(&mut gallery);
+    if !args.only.is_empty() {
+        gallery.retain(|entry| args.only.contains(&entry.name));
+    }
+
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+    let renderer = OffscreenRenderer::new(GpuContext::headless(instance));
+    let written = match render_gallery(&gallery, &args.matrix, &renderer, &args.out) {
+        Ok(written) => written,
+        Err(err) => {
+            eprintln!("Failed to render into {}: {}", args.out.display(), err);
+            return ExitCode::FAILURE;
+        }
+    };
+    if args.html
+        && let Err(err) = write_html_index(&args.out, &written)
+    {
+        eprintln!("Failed to write the index: {}", err);
+        return ExitCode::FAILURE;
+    }
+    println!(
+        "Wrote {} screenshots to {}",
+        written.len(),
+        args.out.display()
+    );
+    ExitCode::SUCCESS
+}
@@ -1,18 +1,28 @@
 #![allow(unused_variables)]
 
+use crate::DroppedData;
+use crate::Presented;
 use smithay_client_toolkit::seat::keyboard::KeyEvent;
 use smithay_client_toolkit::seat::keyboard::Modifiers;
+use smithay_client_toolkit::seat::keyboard::RepeatInfo;
 use smithay_client_toolkit::seat::pointer::PointerEvent;
+use smithay_client_toolkit::session_lock::SessionLockSurfaceConfigure;
 use smithay_client_toolkit::shell::wlr_layer::LayerSurfaceConfigure;
 use smithay_client_toolkit::shell::xdg::popup::PopupConfigure;
 use smithay_client_toolkit::shell::xdg::window::WindowConfigure;
 use std::cell::RefCell;
 use std::rc::Rc;
+use std::time::Duration;
+use std::time::Instant;
 use wayland_backend::client::ObjectId;
+use wayland_client::protocol::wl_output::Subpixel;
 use wayland_client::protocol::wl_output::Transform;
 use wayland_client::protocol::wl_output::WlOutput;
+use wayland_protocols::wp::cursor_shape::v1::client::wp_cursor_shape_device_v1::Shape;
 
 pub trait KeyboardHandlerContainer {
+    /// Keys held while the focus is gained get no `press_key`, only
+    /// `release_key` when they are released
     fn enter(&mut self) {}
 
     fn leave(&mut self) {}
@@ -24,10 +34,52 @@ pub trait KeyboardHandlerContainer {
     fn update_modifiers(&mut self, modifiers: &Modifiers) {}
 
     fn repeat_key(&mut self, event: &KeyEvent) {}
+
+    /// Compositor changed the key repeat rate or delay of a seat, sent to
+    /// every container. `Application::repeat_info` has the current one.
+    fn repeat_info_changed(&mut self, info: RepeatInfo) {}
+
+    /// Active keyboard layout changed, sent to every container regardless of
+    /// the keyboard focus. `Application::keyboard_layout` has the current one.
+    fn layout_changed(&mut self, layout_name: String) {}
+
+    /// Input method composition text, `None` clears it. Cursor is a byte
+    /// range in the text, `None` hides the cursor.
+    fn preedit(&mut self, text: Option<&str>, cursor: Option<(i32, i32)>) {}
+
+    /// Text from the input method, inserted at the cursor replacing preedit
+    fn commit_text(&mut self, text: &str) {}
+
+    /// Delete bytes around the cursor, sent before `commit_text`
+    fn delete_surrounding_text(&mut self, before_length: u32, after_length: u32) {}
+
+    /// Text inserted by the application as if typed, e.g. from an emoji
+    /// picker, see `Application::insert_text`
+    fn insert_text(&mut self, text: &str) {}
+
+    /// Input method has entered or left the surface
+    fn text_input_focus(&mut self, focused: bool) {}
 }
 
 pub trait PointerHandlerContainer {
-    fn pointer_frame(&mut self, events: &PointerEvent) {}
+    /// Events of one wl_pointer frame on this surface, e.g. several axis
+    /// events of a high-resolution wheel
+    fn pointer_frame(&mut self, events: &[PointerEvent]) {}
+}
+
+/// Drag and drop from other clients, positions are in surface coordinates
+pub trait DndHandlerContainer {
+    /// Drag entered the surface, the mime types are all those offered even
+    /// if none of them can be dropped
+    fn dnd_enter(&mut self, x: f64, y: f64, mime_types: &[String]) {}
+
+    fn dnd_motion(&mut self, x: f64, y: f64) {}
+
+    /// Drag left the surface, was cancelled or its drop couldn't be read
+    fn dnd_leave(&mut self) {}
+
+    /// Data of the drop, read after the drag has ended
+    fn dnd_drop(&mut self, x: f64, y: f64, data: &DroppedData) {}
 }
 
 pub trait CompositorHandlerContainer {
@@ -37,15 +89,87 @@ pub trait CompositorHandlerContainer {
 
     fn frame(&mut self, time: u32) {}
 
+    /// A frame callback armed with a fallback didn't arrive in time, e.g.
+    /// the compositor withholds it from a covered surface
+    fn frame_fallback(&mut self) {}
+
     fn surface_enter(&mut self, output: &WlOutput) {}
 
+    /// Refresh rate in mHz of the output the surface entered
+    fn refresh_rate_changed(&mut self, refresh_rate: i32) {}
+
+    /// Subpixel layout of the output the surface entered
+    fn subpixel_changed(&mut self, subpixel: Subpixel) {}
+
     fn surface_leave(&mut self, output: &WlOutput) {}
+
+    /// A frame of the surface was shown, only with the presentation time
+    /// protocol
+    fn presented(&mut self, presented: &Presented) {}
+
+    /// The event loop didn't run for the gap, e.g. the system was suspended
+    /// or the wall clock jumped, sent to every container
+    fn system_resumed(&mut self, gap: Duration) {}
+}
+
+/// Counters reported by a container, used by the metrics exporter
+#[derive(Debug, Clone, Default)]
+pub struct SurfaceMetrics {
+    pub frames_presented: u64,
+    pub frames_skipped: u64,
+    /// Renders postponed while suspended or waiting for a frame callback,
+    /// input is kept for the next presented frame
+    pub frames_deferred: u64,
+    /// Frames not presented since the EGUI output didn't change
+    pub frames_unchanged: u64,
+    pub degradation_events: u64,
+    /// Degradation steps of the frame budget active now
+    pub degradation_steps: u64,
+    /// CPU and GPU time, the GPU part only with timestamp queries
+    pub frame_time_p95: Option<Duration>,
+    /// GPU time of the latest measured frame
+    pub gpu_frame_time: Option<Duration>,
+    /// Name given to the container for telling surfaces apart
+    pub debug_name: Option<String>,
+    /// Swapchains reconfigured after the surface was lost or outdated
+    pub swapchain_reconnects: u64,
+    /// Size of the swapchain textures, estimated from the configuration
+    pub swapchain_memory_bytes: u64,
+    /// 95th percentile of the time from receiving input to presenting the
+    /// frame with it
+    pub input_latency_p95: Option<Duration>,
 }
 
 pub trait BaseTrait:
-    CompositorHandlerContainer + KeyboardHandlerContainer + PointerHandlerContainer
+    CompositorHandlerContainer
+    + KeyboardHandlerContainer
+    + PointerHandlerContainer
+    + DndHandlerContainer
 {
     fn get_object_id(&self) -> ObjectId;
+
+    fn metrics(&self) -> SurfaceMetrics {
+        SurfaceMetrics::default()
+    }
+
+    /// Redraw was requested through `Application::request_redraw` or a
+    /// `RedrawHandle`
+    fn request_redraw(&mut self) {}
+
+    /// Redraw so the frame is shown at the vblank nearest to the target,
+    /// requested through `RedrawHandle::present_at`
+    fn present_at(&mut self, target: Instant) {
+        self.request_redraw();
+    }
+
+    /// Cursor shown over the surface whatever is under the pointer, `None`
+    /// goes back to the cursor of the content. Kept while the pointer is
+    /// away, shown when it enters the surface.
+    fn set_cursor_override(&mut self, shape: Option<Shape>) {}
+
+    /// Render the input queued by the handlers, called once after the
+    /// dispatch of the event loop that delivered it
+    fn flush_input(&mut self) {}
 }
 
 pub trait WindowContainer: BaseTrait {
@@ -62,6 +186,9 @@ pub trait LayerSurfaceContainer: BaseTrait {
     fn configure(&mut self, config: &LayerSurfaceConfigure);
 
     fn closed(&mut self) {}
+
+    /// Timer of an auto-hiding dock fired, see `EguiLayerSurface::with_dock`
+    fn dock_timer(&mut self) {}
 }
 
 pub trait PopupContainer: BaseTrait {
@@ -74,6 +201,11 @@ pub trait SubsurfaceContainer: BaseTrait {
     fn configure(&mut self, width: u32, height: u32);
 }
 
+pub trait LockSurfaceContainer: BaseTrait {
+    /// The size is the size of the output and must be used exactly
+    fn configure(&mut self, config: &SessionLockSurfaceConfigure);
+}
+
 // Blanket implementations for Rc<RefCell<T>> to allow shared mutable access
 impl<T: KeyboardHandlerContainer + ?Sized> KeyboardHandlerContainer for Rc<RefCell<T>> {
     fn enter(&mut self) {
@@ -99,14 +231,61 @@ impl<T: KeyboardHandlerContainer + ?Sized> KeyboardHandlerContainer for Rc<RefCe
     fn repeat_key(&mut self, event: &KeyEvent) {
         self.borrow_mut().repeat_key(event);
     }
+
+    fn repeat_info_changed(&mut self, info: RepeatInfo) {
+        self.borrow_mut().repeat_info_changed(info);
+    }
+
+    fn layout_changed(&mut self, layout_name: String) {
+        self.borrow_mut().layout_changed(layout_name);
+    }
+
+    fn preedit(&mut self, text: Option<&str>, cursor: Option<(i32, i32)>) {
+        self.borrow_mut().preedit(text, cursor);
+    }
+
+    fn commit_text(&mut self, text: &str) {
+        self.borrow_mut().commit_text(text);
+    }
+
+    fn delete_surrounding_text(&mut self, before_length: u32, after_length: u32) {
+        self.borrow_mut()
+            .delete_surrounding_text(before_length, after_length);
+    }
+
+    fn insert_text(&mut self, text: &str) {
+        self.borrow_mut().insert_text(text);
+    }
+
+    fn text_input_focus(&mut self, focused: bool) {
+        self.borrow_mut().text_input_focus(focused);
+    }
 }
 
 impl<T: PointerHandlerContainer + ?Sized> PointerHandlerContainer for Rc<RefCell<T>> {
-    fn pointer_frame(&mut self, events: &PointerEvent) {
+    fn pointer_frame(&mut self, events: &[PointerEvent]) {
         self.borrow_mut().pointer_frame(events);
     }
 }
 
+impl<T: DndHandlerContainer + ?Sized> DndHandlerContainer for Rc<RefCell<T>> {
+    fn dnd_enter(&mut self, x: f64, y: f64, mime_types: &[String]) {
+        self.borrow_mut().dnd_enter(x, y, mime_types);
+    }
+
+    fn dnd_motion(&mut self, x: f64, y: f64) {
+        self.borrow_mut().dnd_motion(x, y);
+    }
+
+    fn dnd_leave(&mut self) {
+        self.borrow_mut().dnd_leave();
+    }
+
+    fn dnd_drop(&mut self, x: f64, y: f64, data: &DroppedData) {
+        self.borrow_mut().dnd_drop(x, y, data);
+    }
+}
+
 impl<T: CompositorHandlerContainer + ?Sized> CompositorHandlerContainer for Rc<RefCell<T>> {
     fn scale_factor_changed(&mut self, new_factor: i32) {
         self.borrow_mut().scale_factor_changed(new_factor);
@@ -124,15 +303,55 @@ impl<T: CompositorHandlerContainer + ?Sized> CompositorHandlerContainer for Rc<R
         self.borrow_mut().surface_enter(output);
     }
 
+    fn refresh_rate_changed(&mut self, refresh_rate: i32) {
+        self.borrow_mut().refresh_rate_changed(refresh_rate);
+    }
+
+    fn subpixel_changed(&mut self, subpixel: Subpixel) {
+        self.borrow_mut().subpixel_changed(subpixel);
+    }
+
     fn surface_leave(&mut self, output: &WlOutput) {
         self.borrow_mut().surface_leave(output);
     }
+
+    fn presented(&mut self, presented: &Presented) {
+        self.borrow_mut().presented(presented);
+    }
+
+    fn system_resumed(&mut self, gap: Duration) {
+        self.borrow_mut().system_resumed(gap);
+    }
+
+    fn frame_fallback(&mut self) {
+        self.borrow_mut().frame_fallback();
+    }
 }
 
 impl<T: BaseTrait + ?Sized> BaseTrait for Rc<RefCell<T>> {
     fn get_object_id(&self) -> ObjectId {
         self.borrow().get_object_id()
     }
+
+    fn metrics(&self) -> SurfaceMetrics {
+        self.borrow().metrics()
+    }
+
+    fn request_redraw(&mut self) {
+        self.borrow_mut().request_redraw();
+    }
+
+    fn present_at(&mut self, target: Instant) {
+        self.borrow_mut().present_at(target);
+    }
+
+    fn set_cursor_override(&mut self, shape: Option<Shape>) {
+        self.borrow_mut().set_cursor_override(shape);
+    }
+
+    fn flush_input(&mut self) {
+        self.borrow_mut().flush_input();
+    }
 }
 
 impl<T: WindowContainer + ?Sized> WindowContainer for Rc<RefCell<T>> {
@@ -157,6 +376,10 @@ impl<T: LayerSurfaceContainer + ?Sized> LayerSurfaceContainer for Rc<RefCell<T>>
     fn closed(&mut self) {
         self.borrow_mut().closed();
     }
+
+    fn dock_timer(&mut self) {
+        self.borrow_mut().dock_timer();
+    }
 }
 
 impl<T: PopupContainer + ?Sized> PopupContainer for Rc<RefCell<T>> {
@@ -174,3 +397,207 @@ impl<T: SubsurfaceContainer + ?Sized> SubsurfaceContainer for Rc<RefCell<T>> {
         self.borrow_mut().configure(width, height);
     }
 }
+
+impl<T: LockSurfaceContainer + ?Sized> LockSurfaceContainer for Rc<RefCell<T>> {
+    fn configure(&mut self, config: &SessionLockSurfaceConfigure) {
+        self.borrow_mut().configure(config);
+    }
+}
+
+// Blanket implementations for Box<T> so boxed containers of different types
+// can be kept together and pushed
+impl<T: KeyboardHandlerContainer + ?Sized> KeyboardHandlerContainer for Box<T> {
+    fn enter(&mut self) {
+        (**self).enter();
+    }
+
+    fn leave(&mut self) {
+        (**self).leave();
+    }
+
+    fn press_key(&mut self, event: &KeyEvent) {
+        (**self).press_key(event);
+    }
+
+    fn release_key(&mut self, event: &KeyEvent) {
+        (**self).release_key(event);
+    }
+
+    fn update_modifiers(&mut self, modifiers: &Modifiers) {
+        (**self).update_modifiers(modifiers);
+    }
+
+    fn repeat_key(&mut self, event: &KeyEvent) {
+        (**self).repeat_key(event);
+    }
+
+    fn repeat_info_changed(&mut self, info: RepeatInfo) {
+        (**self).repeat_info_changed(info);
+    }
+
+    fn layout_changed(&mut self, layout_name: String) {
+        (**self).layout_changed(layout_name);
+    }
+
+    fn preedit(&mut self, text: Option<&str>, cursor: Option<(i32, i32)>) {
+        (**self).preedit(text, cursor);
+    }
+
+    fn commit_text(&mut self, text: &str) {
+        (**self).commit_text(text);
+    }
+
+    fn delete_surrounding_text(&mut self, before_length: u32, after_length: u32) {
+        (**self).delete_surrounding_text(before_length, after_length);
+    }
+
+    fn insert_text(&mut self, text: &str) {
+        (**self).insert_text(text);
+    }
+
+    fn text_input_focus(&mut self, focused: bool) {
+        (**self).text_input_focus(focused);
+    }
+}
+
+impl<T: PointerHandlerContainer + ?Sized> PointerHandlerContainer for Box<T> {
+    fn pointer_frame(&mut self, events: &[PointerEvent]) {
+        (**self).pointer_frame(events);
+    }
+}
+
+impl<T: DndHandlerContainer + ?Sized> DndHandlerContainer for Box<T> {
+    fn dnd_enter(&mut self, x: f64, y: f64, mime_types: &[String]) {
+        (**self).dnd_enter(x, y, mime_types);
+    }
+
+    fn dnd_motion(&mut self, x: f64, y: f64) {
+        (**self).dnd_motion(x, y);
+    }
+
+    fn dnd_leave(&mut self) {
+        (**self).dnd_leave();
+    }
+
+    fn dnd_drop(&mut self, x: f64, y: f64, data: &DroppedData) {
+        (**self).dnd_drop(x, y, data);
+    }
+}
+
+impl<T: CompositorHandlerContainer + ?Sized> CompositorHandlerContainer for Box<T> {
+    fn scale_factor_changed(&mut self, new_factor: i32) {
+        (**self).scale_factor_changed(new_factor);
+    }
+
+    fn transform_changed(&mut self, new_transform: &Transform) {
+        (**self).transform_changed(new_transform);
+    }
+
+    fn frame(&mut self, time: u32) {
+        (**self).frame(time);
+    }
+
+    fn surface_enter(&mut self, output: &WlOutput) {
+        (**self).surface_enter(output);
+    }
+
+    fn refresh_rate_changed(&mut self, refresh_rate: i32) {
+        (**self).refresh_rate_changed(refresh_rate);
+    }
+
+    fn subpixel_changed(&mut self, subpixel: Subpixel) {
+        (**self).subpixel_changed(subpixel);
+    }
+
+    fn surface_leave(&mut self, output: &WlOutput) {
+        (**self).surface_leave(output);
+    }
+
+    fn presented(&mut self, presented: &Presented) {
+        (**self).presented(presented);
+    }
+
+    fn system_resumed(&mut self, gap: Duration) {
+        (**self).system_resumed(gap);
+    }
+
+    fn frame_fallback(&mut self) {
+        (**self).frame_fallback();
+    }
+}
+
+impl<T: BaseTrait + ?Sized> BaseTrait for Box<T> {
+    fn get_object_id(&self) -> ObjectId {
+        (**self).get_object_id()
+    }
+
+    fn metrics(&self) -> SurfaceMetrics {
+        (**self).metrics()
+    }
+
+    fn request_redraw(&mut self) {
+        (**self).request_redraw();
+    }
+
+    fn present_at(&mut self, target: Instant) {
+        (**self).present_at(target);
+    }
+
+    fn set_cursor_override(&mut self, shape: Option<Shape>) {
+        (**self).set_cursor_override(shape);
+    }
+
+    fn flush_input(&mut self) {
+        (**self).flush_input();
+    }
+}
+
+impl<T: WindowContainer + ?Sized> WindowContainer for Box<T> {
+    fn configure(&mut self, configure: &WindowConfigure) {
+        (**self).configure(configure);
+    }
+
+    fn allowed_to_close(&self) -> bool {
+        (**self).allowed_to_close()
+    }
+
+    fn request_close(&mut self) {
+        (**self).request_close();
+    }
+}
+
+impl<T: LayerSurfaceContainer + ?Sized> LayerSurfaceContainer for Box<T> {
+    fn configure(&mut self, config: &LayerSurfaceConfigure) {
+        (**self).configure(config);
+    }
+
+    fn closed(&mut self) {
+        (**self).closed();
+    }
+
+    fn dock_timer(&mut self) {
+        (**self).dock_timer();
+    }
+}
+
+impl<T: PopupContainer + ?Sized> PopupContainer for Box<T> {
+    fn configure(&mut self, config: &PopupConfigure) {
+        (**self).configure(config);
+    }
+
+    fn done(&mut self) {
+        (**self).done();
+    }
+}
+
+impl<T: SubsurfaceContainer + ?Sized> SubsurfaceContainer for Box<T> {
+    fn configure(&mut self, width: u32, height: u32) {
+        (**self).configure(width, height);
+    }
+}
+
+impl<T: LockSurfaceContainer + ?Sized> LockSurfaceContainer for Box<T> {
+    fn configure(&mut self, config: &SessionLockSurfaceConfigure) {
+        (**self).configure(config);
+    }
+}
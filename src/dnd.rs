@@ -0,0 +1,391 @@
+use log::warn;
+use std::ffi::OsString;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Read;
+use std::os::unix::ffi::OsStringExt;
+use std::path::PathBuf;
+use std::time::Duration;
+use wayland_backend::client::ObjectId;
+
+/// Mime types read from drag offers, most preferred first
+const MIME_TYPES: [&str; 4] = [
+    "text/uri-list",
+    "text/plain;charset=utf-8",
+    "text/plain",
+    "UTF8_STRING",
+];
+
+/// Limits for data read from other clients, see
+/// `Application::set_transfer_limits`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferLimits {
+    /// Bytes of dropped or pasted text, longer text is truncated
+    pub text: usize,
+    /// Bytes of a dropped `text/uri-list`, longer lists are refused
+    pub uri_list: usize,
+    /// Mime types looked at per offer, the rest are ignored
+    pub mime_types: usize,
+    /// Drops not read by then are cancelled
+    pub read_timeout: Duration,
+}
+
+impl Default for TransferLimits {
+    fn default() -> Self {
+        Self {
+            text: 10 * 1024 * 1024,
+            uri_list: 1024 * 1024,
+            mime_types: 64,
+            read_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Data dropped on a surface
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DroppedData {
+    /// Local files of a `text/uri-list`, other URIs are skipped
+    Files(Vec<PathBuf>),
+    Text(String),
+}
+
+/// Mime types looked at, an offer listing thousands is cut to the limit
+pub(crate) fn limit_mime_types(offered: &mut Vec<String>, limits: &TransferLimits) {
+    if offered.len() > limits.mime_types {
+        warn!(
+            "[DND] Offer has {} mime types, only the first {} are used",
+            offered.len(),
+            limits.mime_types
+        );
+        offered.truncate(limits.mime_types);
+    }
+}
+
+/// Mime type to read from the offer, `None` if none is supported
+pub(crate) fn preferred_mime(offered: &[String]) -> Option<String> {
+    MIME_TYPES
+        .iter()
+        .find(|mime| offered.iter().any(|offered| offered == *mime))
+        .map(|mime| mime.to_string())
+}
+
+/// Read the drop within the limits, invalid UTF-8 is replaced
+pub(crate) fn read(
+    mime: &str,
+    reader: impl Read,
+    limits: &TransferLimits,
+) -> io::Result<DroppedData> {
+    if mime == "text/uri-list" {
+        return read_uri_list(reader, limits.uri_list).map(DroppedData::Files);
+    }
+    let mut bytes = Vec::new();
+    // One byte more tells a payload of exactly the limit from a longer one
+    reader
+        .take(limits.text as u64 + 1)
+        .read_to_end(&mut bytes)?;
+    let mut text = &bytes[..];
+    if bytes.len() > limits.text {
+        warn!(
+            "[DND] Dropped text is over {} bytes, truncated",
+            limits.text
+        );
+        text = without_cut_sequence(&bytes[..limits.text]);
+    }
+    Ok(DroppedData::Text(
+        String::from_utf8_lossy(text).into_owned(),
+    ))
+}
+
+/// UTF-8 sequence cut at the end isn't part of the text, it would become
+/// a replacement character
+fn without_cut_sequence(bytes: &[u8]) -> &[u8] {
+    // Sequences are at most 4 bytes, continuation bytes are 0b10xxxxxx
+    let tail = bytes.len().saturating_sub(4);
+    let Some(start) = bytes[tail..].iter().rposition(|byte| byte & 0xC0 != 0x80) else {
+        return bytes;
+    };
+    let start = tail + start;
+    let len = match bytes[start] {
+        byte if byte >= 0xF0 => 4,
+        byte if byte >= 0xE0 => 3,
+        byte if byte >= 0xC0 => 2,
+        _ => 1,
+    };
+    if bytes.len() - start < len {
+        &bytes[..start]
+    } else {
+        bytes
+    }
+}
+
+/// Cut the text to at most the limit in bytes, on a character boundary
+pub(crate) fn truncate_text(text: &mut String, limit: usize) -> bool {
+    if text.len() <= limit {
+        return false;
+    }
+    let mut end = limit;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text.truncate(end);
+    true
+}
+
+/// Paths of the `file://` URIs parsed line by line, comments and remote
+/// hosts are skipped. Lists over the limit are refused as a whole, a
+/// partial selection would be acted on as if it was complete.
+fn read_uri_list(reader: impl Read, limit: usize) -> io::Result<Vec<PathBuf>> {
+    let mut reader = BufReader::new(reader.take(limit as u64 + 1));
+    let mut line = Vec::new();
+    let mut read = 0;
+    let mut paths = Vec::new();
+    loop {
+        line.clear();
+        let len = reader.read_until(b'\n', &mut line)?;
+        if len == 0 {
+            return Ok(paths);
+        }
+        read += len;
+        if read > limit {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("uri-list is over {} bytes", limit),
+            ));
+        }
+        if let Some(path) = parse_uri(line.trim_ascii()) {
+            paths.push(path);
+        }
+    }
+}
+
+/// Path of a `file://` URI, file names aren't necessarily UTF-8 so the path
+/// is built from the bytes
+fn parse_uri(line: &[u8]) -> Option<PathBuf> {
+    if line.is_empty() || line.starts_with(b"#") {
+        return None;
+    }
+    let rest = line.strip_prefix(b"file://")?;
+    // Host is empty or localhost, the path starts after it
+    let start = rest.iter().position(|&byte| byte == b'/')?;
+    let (host, path) = rest.split_at(start);
+    (host.is_empty() || host == b"localhost")
+        .then(|| PathBuf::from(OsString::from_vec(percent_decode(path))))
+}
+
+/// `%XX` with two hex digits is the byte, anything else is kept as is
+fn percent_decode(bytes: &[u8]) -> Vec<u8> {
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = match bytes.get(i + 1..i + 3) {
+            Some(&[high, low]) => hex_value(high).zip(hex_value(low)),
+            _ => None,
+        };
+        match (bytes[i], hex) {
+            (b'%', Some((high, low))) => {
+                decoded.push(high << 4 | low);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    decoded
+}
+
+fn hex_value(digit: u8) -> Option<u8> {
+    match digit {
+        b'0'..=b'9' => Some(digit - b'0'),
+        b'a'..=b'f' => Some(digit - b'a' + 10),
+        b'A'..=b'F' => Some(digit - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Drag over a surface of this application
+pub(crate) struct DragState {
+    pub surface: ObjectId,
+    pub position: (f64, f64),
+    /// `None` when the offer has nothing readable, the drop is refused
+    pub mime: Option<String>,
+}
+
+/// Data of a drop read on a background thread
+pub(crate) struct DropResult {
+    /// Results of cancelled drops are ignored
+    pub id: u64,
+    pub surface: ObjectId,
+    pub position: (f64, f64),
+    pub data: io::Result<DroppedData>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::ffi::OsStrExt;
+
+    fn limits(text: usize, uri_list: usize) -> TransferLimits {
+        TransferLimits {
+            text,
+            uri_list,
+            ..Default::default()
+        }
+    }
+
+    /// Deterministic bytes for the property tests, xorshift
+    fn random_bytes(seed: u64, len: usize) -> Vec<u8> {
+        let mut state = seed.max(1);
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    fn percent_encode(bytes: &[u8]) -> Vec<u8> {
+        bytes
+            .iter()
+            .flat_map(|byte| format!("%{:02X}", byte).into_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn percent_decode_needs_two_hex_digits() {
+        assert_eq!(percent_decode(b"a%20b"), b"a b");
+        assert_eq!(percent_decode(b"%c3%A9"), "é".as_bytes());
+        assert_eq!(percent_decode(b"%+F"), b"%+F");
+        assert_eq!(percent_decode(b"%-1"), b"%-1");
+        assert_eq!(percent_decode(b"%g0"), b"%g0");
+        assert_eq!(percent_decode(b"100%"), b"100%");
+        assert_eq!(percent_decode(b"%4"), b"%4");
+    }
+
+    #[test]
+    fn percent_decode_round_trips_any_bytes() {
+        for seed in 1..200 {
+            let bytes = random_bytes(seed, seed as usize % 40);
+            assert_eq!(percent_decode(&percent_encode(&bytes)), bytes);
+        }
+    }
+
+    #[test]
+    fn percent_decode_keeps_unescaped_bytes() {
+        for seed in 1..200 {
+            let bytes: Vec<u8> = random_bytes(seed, 32)
+                .into_iter()
+                .filter(|&byte| byte != b'%')
+                .collect();
+            assert_eq!(percent_decode(&bytes), bytes);
+        }
+    }
+
+    #[test]
+    fn parse_uri_takes_local_files_only() {
+        assert_eq!(
+            parse_uri(b"file:///home/user/a%20b.txt"),
+            Some(PathBuf::from("/home/user/a b.txt"))
+        );
+        assert_eq!(
+            parse_uri(b"file://localhost/tmp/x"),
+            Some(PathBuf::from("/tmp/x"))
+        );
+        assert_eq!(parse_uri(b"file://remote/tmp/x"), None);
+        assert_eq!(parse_uri(b"https://example.com/x"), None);
+        assert_eq!(parse_uri(b"# comment"), None);
+        assert_eq!(parse_uri(b""), None);
+        assert_eq!(parse_uri(b"file://"), None);
+    }
+
+    #[test]
+    fn parse_uri_keeps_non_utf8_file_names() {
+        let path = parse_uri(b"file:///tmp/caf%E9").unwrap();
+        assert_eq!(path.as_os_str().as_bytes(), b"/tmp/caf\xE9");
+        let path = parse_uri(b"file:///tmp/raw\xFF").unwrap();
+        assert_eq!(path.as_os_str().as_bytes(), b"/tmp/raw\xFF");
+    }
+
+    #[test]
+    fn uri_list_skips_comments_and_refuses_long_lists() {
+        let list = b"# from a file manager\r\nfile:///a\r\nhttp://b/c\r\nfile:///d%20e\r\n";
+        assert_eq!(
+            read_uri_list(&list[..], 1024).unwrap(),
+            vec![PathBuf::from("/a"), PathBuf::from("/d e")]
+        );
+        assert_eq!(read_uri_list(&list[..], list.len()).unwrap().len(), 2);
+        let err = read_uri_list(&list[..], list.len() - 1).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn uri_list_survives_random_input() {
+        for seed in 1..200 {
+            let bytes = random_bytes(seed, 256);
+            // Never panics, whatever comes out is an absolute path
+            if let Ok(paths) = read_uri_list(&bytes[..], 1024) {
+                assert!(paths.iter().all(|path| path.is_absolute()));
+            }
+        }
+    }
+
+    #[test]
+    fn cut_sequence_is_dropped() {
+        let text = "aé€😀".as_bytes();
+        assert_eq!(without_cut_sequence(text), text);
+        // Every cut leaves only whole characters
+        for end in 0..=text.len() {
+            let kept = without_cut_sequence(&text[..end]);
+            assert!(std::str::from_utf8(kept).is_ok(), "cut at {}", end);
+            assert!(end - kept.len() < 4);
+        }
+    }
+
+    #[test]
+    fn truncate_text_ends_on_a_char_boundary() {
+        let original = "aé€😀b".to_string();
+        for limit in 0..=original.len() + 1 {
+            let mut text = original.clone();
+            let truncated = truncate_text(&mut text, limit);
+            assert_eq!(truncated, limit < original.len());
+            assert!(text.len() <= limit);
+            assert!(original.starts_with(&text));
+        }
+    }
+
+    #[test]
+    fn read_text_within_the_limit() {
+        let data = read("text/plain", &b"hello"[..], &limits(5, 0)).unwrap();
+        assert_eq!(data, DroppedData::Text("hello".to_string()));
+        let data = read("text/plain", "héllo".as_bytes(), &limits(2, 0)).unwrap();
+        assert_eq!(data, DroppedData::Text("h".to_string()));
+    }
+
+    #[test]
+    fn read_random_text_never_exceeds_the_limit() {
+        const CHARS: [char; 5] = ['a', 'é', '€', '😀', '\n'];
+        for seed in 1..200 {
+            let original: String = random_bytes(seed, 32)
+                .into_iter()
+                .map(|byte| CHARS[byte as usize % CHARS.len()])
+                .collect();
+            let limit = seed as usize % 100;
+            let data = read("UTF8_STRING", original.as_bytes(), &limits(limit, 0)).unwrap();
+            let DroppedData::Text(text) = data else {
+                panic!("text drop read as files");
+            };
+            assert!(text.len() <= limit);
+            assert!(original.starts_with(&text));
+        }
+    }
+
+    #[test]
+    fn read_uri_list_mime() {
+        let data = read("text/uri-list", &b"file:///x\n"[..], &limits(0, 64)).unwrap();
+        assert_eq!(data, DroppedData::Files(vec![PathBuf::from("/x")]));
+    }
+}
@@ -0,0 +1,336 @@
+use crate::EguiAppData;
+use crate::EguiWgpuRenderer;
+use crate::GpuContext;
+use egui_wgpu::ScreenDescriptor;
+use log::debug;
+use std::fmt::Write as _;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Passes run before the captured one, the first frame of a context lays
+/// out some widgets before their size is known
+const WARMUP_PASSES: usize = 2;
+
+/// Format of the render target, PNGs are written in sRGB as is
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+type Factory = Box<dyn Fn() -> Box<dyn EguiAppData>>;
+
+/// App registered for screenshots
+pub struct GalleryEntry {
+    pub name: String,
+    /// Logical size used when the matrix doesn't give sizes
+    pub size: (u32, u32),
+    factory: Factory,
+}
+
+/// Apps rendered by the `render-docs` bin, each example registers its own
+#[derive(Default)]
+pub struct Gallery {
+    entries: Vec<GalleryEntry>,
+}
+
+impl Gallery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The factory is called for each screenshot, so every render starts
+    /// from the same state
+    pub fn register<A, F>(&mut self, name: &str, size: (u32, u32), factory: F)
+    where
+        A: EguiAppData + 'static,
+        F: Fn() -> A + 'static,
+    {
+        self.entries.push(GalleryEntry {
+            name: name.to_string(),
+            size,
+            factory: Box::new(move || Box::new(factory())),
+        });
+    }
+
+    /// Keep only the matching entries, e.g. to render a single example
+    pub fn retain(&mut self, keep: impl FnMut(&GalleryEntry) -> bool) {
+        self.entries.retain(keep);
+    }
+
+    pub fn entries(&self) -> &[GalleryEntry] {
+        &self.entries
+    }
+}
+
+/// One screenshot of the matrix
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Shot {
+    pub width: u32,
+    pub height: u32,
+    pub theme: egui::Theme,
+    pub scale: f32,
+}
+
+impl Shot {
+    /// Deterministic file name, e.g. `egui_example-320x240-dark-1.5x.png`
+    pub fn file_name(&self, name: &str) -> String {
+        let theme = match self.theme {
+            egui::Theme::Dark => "dark",
+            egui::Theme::Light => "light",
+        };
+        format!(
+            "{}-{}x{}-{}-{}x.png",
+            name, self.width, self.height, theme, self.scale
+        )
+    }
+
+    fn physical_size(&self) -> (u32, u32) {
+        (
+            ((self.width as f32 * self.scale).round() as u32).max(1),
+            ((self.height as f32 * self.scale).round() as u32).max(1),
+        )
+    }
+}
+
+/// Sizes, themes and scales to render every entry at
+#[derive(Debug, Clone)]
+pub struct ShotMatrix {
+    /// Empty renders each entry at its registered size
+    pub sizes: Vec<(u32, u32)>,
+    pub themes: Vec<egui::Theme>,
+    pub scales: Vec<f32>,
+}
+
+impl Default for ShotMatrix {
+    fn default() -> Self {
+        Self {
+            sizes: Vec::new(),
+            themes: vec![egui::Theme::Light, egui::Theme::Dark],
+            scales: vec![1.0],
+        }
+    }
+}
+
+impl ShotMatrix {
+    pub fn shots(&self, entry: &GalleryEntry) -> Vec<Shot> {
+        let sizes = if self.sizes.is_empty() {
+            vec![entry.size]
+        } else {
+            self.sizes.clone()
+        };
+        let mut shots = Vec::new();
+        for &(width, height) in &sizes {
+            for &theme in &self.themes {
+                for &scale in &self.scales {
+                    shots.push(Shot {
+                        width,
+                        height,
+                        theme,
+                        scale,
+                    });
+                }
+            }
+        }
+        shots
+    }
+}
+
+/// Renders apps into textures and reads them back, all shots share one
+/// device
+pub struct OffscreenRenderer {
+    gpu: GpuContext,
+}
+
+impl OffscreenRenderer {
+    pub fn new(gpu: GpuContext) -> Self {
+        Self { gpu }
+    }
+
+    /// RGBA8 pixels of the shot, rows are `width * 4` bytes
+    ///
+    /// Every shot gets a fresh EGUI context at time zero, so animations,
+    /// cursor blinking and the font atlas are the same on every run. Fonts
+    /// are EGUI's bundled defaults, system fonts are never loaded.
+    pub fn render(&self, app: &mut dyn EguiAppData, shot: &Shot) -> Vec<u8> {
+        let GpuContext { device, queue, .. } = &self.gpu;
+        let (width, height) = shot.physical_size();
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("docs render target"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let mut renderer = EguiWgpuRenderer::new(device, FORMAT, None, 1);
+        renderer.context().set_theme(shot.theme);
+        for _ in 0..=WARMUP_PASSES {
+            let mut raw_input = egui::RawInput {
+                screen_rect: Some(egui::Rect::from_min_size(
+                    egui::Pos2::ZERO,
+                    egui::vec2(shot.width as f32, shot.height as f32),
+                )),
+                time: Some(0.0),
+                focused: true,
+                ..Default::default()
+            };
+            raw_input
+                .viewports
+                .entry(egui::ViewportId::ROOT)
+                .or_default()
+                .native_pixels_per_point = Some(shot.scale);
+            let mut full_output = renderer.context().run(raw_input, |ctx| app.ui(ctx));
+
+            // Drawn every pass so the texture uploads of all passes apply
+            let mut encoder = device.create_command_encoder(&Default::default());
+            clear(&mut encoder, &view, shot.theme);
+            renderer.draw(
+                device,
+                queue,
+                &mut encoder,
+                &view,
+                ScreenDescriptor {
+                    size_in_pixels: [width, height],
+                    pixels_per_point: shot.scale,
+                },
+                &mut full_output,
+            );
+            queue.submit(Some(encoder.finish()));
+        }
+        read_back(device, queue, &texture)
+    }
+}
+
+fn clear(encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView, theme: egui::Theme) {
+    let [r, g, b, a] = egui::Rgba::from(theme.default_visuals().panel_fill).to_array();
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            depth_slice: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color {
+                    r: r as f64,
+                    g: g as f64,
+                    b: b as f64,
+                    a: a as f64,
+                }),
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        timestamp_writes: None,
+        label: Some("docs render clear"),
+        occlusion_query_set: None,
+    });
+}
+
+fn read_back(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture) -> Vec<u8> {
+    let (width, height) = (texture.width(), texture.height());
+    let row = width * 4;
+    let padded_row =
+        row.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("docs render readback"),
+        size: (padded_row * height) as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device.create_command_encoder(&Default::default());
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::TexelCopyBufferInfo {
+            buffer: &buffer,
+            layout: wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row),
+                rows_per_image: Some(height),
+            },
+        },
+        texture.size(),
+    );
+    queue.submit(Some(encoder.finish()));
+
+    let slice = buffer.slice(..);
+    slice.map_async(wgpu::MapMode::Read, |result| {
+        result.expect("Failed to map the readback buffer")
+    });
+    device
+        .poll(wgpu::PollType::wait_indefinitely())
+        .expect("Failed to wait for the readback");
+    let mapped = slice.get_mapped_range();
+    let mut pixels = Vec::with_capacity((row * height) as usize);
+    for padded in mapped.chunks(padded_row as usize) {
+        pixels.extend_from_slice(&padded[..row as usize]);
+    }
+    drop(mapped);
+    buffer.unmap();
+    pixels
+}
+
+pub fn write_png(path: &Path, width: u32, height: u32, pixels: &[u8]) -> io::Result<()> {
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    writer.write_image_data(pixels).map_err(io::Error::other)?;
+    writer.finish().map_err(io::Error::other)
+}
+
+/// Render every entry of the gallery over the matrix into `out_dir`,
+/// returns the written files in the order they were rendered
+///
+/// The PNGs hold no timestamps or other metadata, two runs on the same
+/// adapter give identical files.
+pub fn render_gallery(
+    gallery: &Gallery,
+    matrix: &ShotMatrix,
+    renderer: &OffscreenRenderer,
+    out_dir: &Path,
+) -> io::Result<Vec<PathBuf>> {
+    fs::create_dir_all(out_dir)?;
+    let mut written = Vec::new();
+    for entry in gallery.entries() {
+        for shot in matrix.shots(entry) {
+            let mut app = (entry.factory)();
+            let pixels = renderer.render(app.as_mut(), &shot);
+            let (width, height) = shot.physical_size();
+            let path = out_dir.join(shot.file_name(&entry.name));
+            write_png(&path, width, height, &pixels)?;
+            debug!("[DOCS] Wrote {}", path.display());
+            written.push(path);
+        }
+    }
+    Ok(written)
+}
+
+/// Write `index.html` listing the rendered files next to them
+pub fn write_html_index(out_dir: &Path, files: &[PathBuf]) -> io::Result<PathBuf> {
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>wayapp \
+         gallery</title></head>\n<body>\n",
+    );
+    for file in files {
+        let Some(name) = file.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let _ = writeln!(
+            html,
+            "<figure><img src=\"{0}\" alt=\"{0}\"><figcaption>{0}</figcaption></figure>",
+            name
+        );
+    }
+    html.push_str("</body>\n</html>\n");
+    let path = out_dir.join("index.html");
+    fs::write(&path, html)?;
+    Ok(path)
+}
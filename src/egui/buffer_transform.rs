@@ -0,0 +1,254 @@
+use wayland_client::protocol::wl_output::Transform;
+
+const SHADER: &str = r#"
+struct Mapping {
+    x: vec4<f32>,
+    y: vec4<f32>,
+};
+
+@group(0) @binding(0) var source: texture_2d<f32>;
+@group(0) @binding(1) var source_sampler: sampler;
+@group(0) @binding(2) var<uniform> mapping: Mapping;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    // Fullscreen triangle
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let b = vec3<f32>(in.uv, 1.0);
+    let s = vec2<f32>(dot(mapping.x.xyz, b), dot(mapping.y.xyz, b));
+    return textureSample(source, source_sampler, s);
+}
+"#;
+
+/// Transforms that rotate a quarter turn, buffer width and height are
+/// swapped for these
+pub(crate) fn swaps_axes(transform: Transform) -> bool {
+    matches!(
+        transform,
+        Transform::_90 | Transform::_270 | Transform::Flipped90 | Transform::Flipped270
+    )
+}
+
+/// Surface coordinate for a buffer coordinate, both normalized, as rows of
+/// an affine matrix. The buffer holds the content already transformed, the
+/// compositor applies the inverse. Rotations are counter-clockwise, flipped
+/// variants flip around the vertical axis before rotating.
+fn mapping(transform: Transform) -> [[f32; 4]; 2] {
+    let (x, y) = match transform {
+        Transform::_90 => ([0.0, -1.0, 1.0], [1.0, 0.0, 0.0]),
+        Transform::_180 => ([-1.0, 0.0, 1.0], [0.0, -1.0, 1.0]),
+        Transform::_270 => ([0.0, 1.0, 0.0], [-1.0, 0.0, 1.0]),
+        Transform::Flipped => ([-1.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+        Transform::Flipped90 => ([0.0, 1.0, 0.0], [1.0, 0.0, 0.0]),
+        Transform::Flipped180 => ([1.0, 0.0, 0.0], [0.0, -1.0, 1.0]),
+        Transform::Flipped270 => ([0.0, -1.0, 1.0], [-1.0, 0.0, 1.0]),
+        _ => ([1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+    };
+    [[x[0], x[1], x[2], 0.0], [y[0], y[1], y[2], 0.0]]
+}
+
+/// EGUI is drawn upright into an offscreen texture which is copied to the
+/// swapchain with the buffer transform applied, so the compositor doesn't
+/// need to rotate the surface on a rotated output
+pub(crate) struct BufferTransform {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    mapping: wgpu::Buffer,
+    /// Offscreen texture in surface orientation and its bind group
+    target: Option<(wgpu::Texture, wgpu::TextureView, wgpu::BindGroup)>,
+}
+
+impl BufferTransform {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("buffer transform shader"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("buffer transform bind group layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("buffer transform pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("buffer transform pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+        // Pixels map one to one, nearest keeps them exact
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("buffer transform sampler"),
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+        let mapping = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("buffer transform mapping"),
+            size: std::mem::size_of::<[[f32; 4]; 2]>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Self {
+            pipeline,
+            bind_group_layout,
+            sampler,
+            mapping,
+            target: None,
+        }
+    }
+
+    /// Offscreen texture view of the size in surface orientation, recreated
+    /// when the size or format changes
+    pub fn target(
+        &mut self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> wgpu::TextureView {
+        let reusable = self.target.as_ref().is_some_and(|(texture, ..)| {
+            texture.width() == width && texture.height() == height && texture.format() == format
+        });
+        if !reusable {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("buffer transform target"),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("buffer transform bind group"),
+                layout: &self.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: self.mapping.as_entire_binding(),
+                    },
+                ],
+            });
+            self.target = Some((texture, view, bind_group));
+        }
+        let (_, view, _) = self.target.as_ref().expect("Target was just created");
+        view.clone()
+    }
+
+    /// Copy the offscreen texture to the swapchain texture with the transform
+    pub fn copy(
+        &self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        output: &wgpu::TextureView,
+        transform: Transform,
+    ) {
+        let Some((_, _, bind_group)) = &self.target else {
+            return;
+        };
+        let mapping = mapping(transform);
+        let bytes: Vec<u8> = mapping
+            .iter()
+            .flatten()
+            .flat_map(|value| value.to_ne_bytes())
+            .collect();
+        queue.write_buffer(&self.mapping, 0, &bytes);
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("buffer transform pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output,
+                resolve_target: None,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
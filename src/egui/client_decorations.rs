@@ -0,0 +1,157 @@
+use crate::RedrawHandle;
+use crate::egui::size_request::SizeRequest;
+use crate::redraw::SurfaceRequestKind;
+use egui::Align;
+use egui::CursorIcon;
+use egui::Layout;
+use egui::PointerButton;
+use egui::Sense;
+use egui::TopBottomPanel;
+use smithay_client_toolkit::reexports::csd_frame::WindowState;
+use smithay_client_toolkit::reexports::protocols::xdg::shell::client::xdg_toplevel::ResizeEdge;
+use smithay_client_toolkit::shell::xdg::window::Window;
+use std::time::Instant;
+
+const TITLEBAR_HEIGHT: f32 = 28.0;
+
+/// Width of the resize area inside the window edges
+const RESIZE_BORDER: f32 = 6.0;
+
+/// Action from the client-side titlebar or the resize borders
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DecorationAction {
+    Move,
+    Resize(ResizeEdge),
+    /// Position in surface coordinates
+    WindowMenu(i32, i32),
+    Close,
+    ToggleMaximize,
+    Minimize,
+}
+
+/// Minimal titlebar drawn with EGUI when the compositor doesn't decorate
+/// the window, e.g. on GNOME
+pub(crate) struct ClientDecorations {
+    window: Window,
+    size_request: SizeRequest,
+    /// Off for apps that draw their own chrome
+    pub enabled: bool,
+    /// Compositor chose client-side decorations
+    pub client_side: bool,
+    pub maximized: bool,
+    pub title: String,
+}
+
+impl ClientDecorations {
+    pub fn new(window: Window, size_request: SizeRequest) -> Self {
+        Self {
+            window,
+            size_request,
+            enabled: true,
+            client_side: false,
+            maximized: false,
+            title: String::new(),
+        }
+    }
+
+    pub fn is_shown(&self) -> bool {
+        self.enabled && self.client_side
+    }
+
+    /// Titlebar panel, added before the app's panels so they fill the rest
+    pub fn titlebar(&self, ctx: &egui::Context) -> Option<DecorationAction> {
+        let mut action = None;
+        TopBottomPanel::top("wayapp_titlebar")
+            .exact_height(TITLEBAR_HEIGHT)
+            .show(ctx, |ui| {
+                let response = ui.interact(
+                    ui.max_rect(),
+                    ui.id().with("wayapp_titlebar_drag"),
+                    Sense::click_and_drag(),
+                );
+                if response.double_clicked() {
+                    action = Some(DecorationAction::ToggleMaximize);
+                } else if response.drag_started_by(PointerButton::Primary) {
+                    action = Some(DecorationAction::Move);
+                } else if response.secondary_clicked() {
+                    let pos = response.interact_pointer_pos().unwrap_or_default();
+                    action = Some(DecorationAction::WindowMenu(pos.x as i32, pos.y as i32));
+                }
+                ui.horizontal_centered(|ui| {
+                    ui.label(&self.title);
+                    ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                        if ui.button("✕").clicked() {
+                            action = Some(DecorationAction::Close);
+                        }
+                        if ui.button(if self.maximized { "🗗" } else { "🗖" }).clicked() {
+                            action = Some(DecorationAction::ToggleMaximize);
+                        }
+                        if ui.button("🗕").clicked() {
+                            action = Some(DecorationAction::Minimize);
+                        }
+                    });
+                });
+            });
+        action
+    }
+
+    /// Resize cursor near the edges and resize on press, called after the
+    /// app's UI so the cursor isn't overridden
+    pub fn resize_borders(&self, ctx: &egui::Context) -> Option<DecorationAction> {
+        if self.maximized {
+            return None;
+        }
+        let (screen, hover, pressed) = ctx.input(|i| {
+            (
+                i.raw.screen_rect,
+                i.pointer.hover_pos(),
+                i.pointer.primary_pressed(),
+            )
+        });
+        let (screen, pos) = (screen?, hover?);
+        let left = pos.x < screen.left() + RESIZE_BORDER;
+        let right = pos.x > screen.right() - RESIZE_BORDER;
+        let top = pos.y < screen.top() + RESIZE_BORDER;
+        let bottom = pos.y > screen.bottom() - RESIZE_BORDER;
+        let (edge, cursor) = match (left, right, top, bottom) {
+            (true, _, true, _) => (ResizeEdge::TopLeft, CursorIcon::ResizeNorthWest),
+            (_, true, true, _) => (ResizeEdge::TopRight, CursorIcon::ResizeNorthEast),
+            (true, _, _, true) => (ResizeEdge::BottomLeft, CursorIcon::ResizeSouthWest),
+            (_, true, _, true) => (ResizeEdge::BottomRight, CursorIcon::ResizeSouthEast),
+            (true, ..) => (ResizeEdge::Left, CursorIcon::ResizeWest),
+            (_, true, ..) => (ResizeEdge::Right, CursorIcon::ResizeEast),
+            (_, _, true, _) => (ResizeEdge::Top, CursorIcon::ResizeNorth),
+            (.., true) => (ResizeEdge::Bottom, CursorIcon::ResizeSouth),
+            _ => return None,
+        };
+        ctx.set_cursor_icon(cursor);
+        pressed.then_some(DecorationAction::Resize(edge))
+    }
+
+    /// Move and resize need the seat and serial of the press, the
+    /// application has them. Closing removes the container so it's deferred
+    /// to the application too.
+    pub fn apply(&self, action: DecorationAction, handle: &RedrawHandle) {
+        let toplevel = self.window.xdg_toplevel().clone();
+        match action {
+            DecorationAction::Move => handle.send(SurfaceRequestKind::Move(toplevel)),
+            DecorationAction::Resize(edge) => {
+                handle.send(SurfaceRequestKind::Resize(toplevel, edge))
+            }
+            DecorationAction::WindowMenu(x, y) => {
+                handle.send(SurfaceRequestKind::WindowMenu(toplevel, x, y))
+            }
+            DecorationAction::Close => handle.send(SurfaceRequestKind::Close),
+            DecorationAction::ToggleMaximize => {
+                self.size_request
+                    .request(WindowState::MAXIMIZED, !self.maximized, Instant::now());
+                if self.maximized {
+                    self.window.unset_maximized();
+                } else {
+                    self.window.set_maximized();
+                }
+            }
+            DecorationAction::Minimize => self.window.set_minimized(),
+        }
+    }
+}
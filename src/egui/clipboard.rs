@@ -0,0 +1,124 @@
+use log::debug;
+use log::warn;
+use smithay_clipboard::Clipboard;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::PoisonError;
+use std::sync::mpsc;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Reads taking longer are given up, nothing is pasted
+pub(crate) const CLIPBOARD_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Reads slower than this are logged
+const SLOW_READ: Duration = Duration::from_millis(50);
+
+/// Clipboard with reads bounded in time
+///
+/// smithay-clipboard answers on its own thread with its own Wayland queue,
+/// so a read progresses while the application is inside a dispatch or a
+/// render. Own selections and cooperative owners answer in a few
+/// milliseconds. An owner that never writes the data blocks that thread
+/// though, so the read waits on a helper thread and is given up after
+/// `CLIPBOARD_TIMEOUT`. Until the owner answers, later reads time out
+/// right away instead of piling up helper threads behind the stuck one.
+#[derive(Clone)]
+pub(crate) struct TimedClipboard {
+    clipboard: Arc<Mutex<Clipboard>>,
+    /// Read that timed out and is still waiting for its owner, its answer
+    /// is dropped as too late
+    stuck: Arc<Mutex<Option<mpsc::Receiver<Option<String>>>>>,
+}
+
+/// Result of a read, the latency is reported in `RenderStats`
+pub(crate) struct ClipboardRead {
+    pub text: Option<String>,
+    pub latency: Duration,
+    pub timed_out: bool,
+}
+
+impl TimedClipboard {
+    pub fn new(clipboard: Clipboard) -> Self {
+        Self {
+            clipboard: Arc::new(Mutex::new(clipboard)),
+            stuck: Arc::default(),
+        }
+    }
+
+    /// Clipboard or primary selection
+    pub fn load(&self, primary: bool) -> ClipboardRead {
+        let started = Instant::now();
+        let mut stuck = lock(&self.stuck);
+        if let Some(receiver) = &*stuck {
+            if let Err(mpsc::TryRecvError::Empty) = receiver.try_recv() {
+                warn!("[INPUT] Clipboard owner still hasn't answered, nothing pasted");
+                return ClipboardRead {
+                    text: None,
+                    latency: started.elapsed(),
+                    timed_out: true,
+                };
+            }
+            *stuck = None;
+        }
+        let (sender, receiver) = mpsc::channel();
+        let clipboard = self.clipboard.clone();
+        let spawned = std::thread::Builder::new()
+            .name("wayapp clipboard read".into())
+            .spawn(move || {
+                let clipboard = lock(&clipboard);
+                let text = if primary {
+                    clipboard.load_primary()
+                } else {
+                    clipboard.load()
+                };
+                let _ = sender.send(text.ok());
+            });
+        if let Err(err) = spawned {
+            warn!("[INPUT] Failed to spawn the clipboard reader: {}", err);
+        }
+        let result = receiver.recv_timeout(CLIPBOARD_TIMEOUT);
+        let latency = started.elapsed();
+        let timed_out = matches!(result, Err(mpsc::RecvTimeoutError::Timeout));
+        if timed_out {
+            warn!(
+                "[INPUT] Clipboard owner didn't answer in {} ms, nothing pasted",
+                CLIPBOARD_TIMEOUT.as_millis()
+            );
+        } else if latency >= SLOW_READ {
+            debug!("[INPUT] Clipboard read took {} ms", latency.as_millis());
+        }
+        if timed_out {
+            *stuck = Some(receiver);
+        }
+        ClipboardRead {
+            text: result.ok().flatten(),
+            latency,
+            timed_out,
+        }
+    }
+
+    /// Stores don't wait for a read stuck on its owner
+    pub fn store(&self, text: String, primary: bool) {
+        let store = move |clipboard: &Clipboard| {
+            if primary {
+                clipboard.store_primary(text);
+            } else {
+                clipboard.store(text);
+            }
+        };
+        match self.clipboard.try_lock() {
+            Ok(clipboard) => store(&clipboard),
+            Err(std::sync::TryLockError::Poisoned(err)) => store(&err.into_inner()),
+            Err(std::sync::TryLockError::WouldBlock) => {
+                let clipboard = self.clipboard.clone();
+                std::thread::spawn(move || store(&lock(&clipboard)));
+            }
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
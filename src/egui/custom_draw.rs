@@ -0,0 +1,19 @@
+/// Target of a custom draw, called after the clear and before EGUI draws
+/// on top with alpha blending
+///
+/// The encoder is submitted with the frame. The format may change when the
+/// surface renegotiates it, recreate pipelines when it differs from the one
+/// they were created for. Thumbnails and captured frames don't include the
+/// custom draw.
+pub struct CustomDrawFrame<'a> {
+    pub device: &'a wgpu::Device,
+    pub queue: &'a wgpu::Queue,
+    pub encoder: &'a mut wgpu::CommandEncoder,
+    /// Cleared with the background color, always upright
+    pub view: &'a wgpu::TextureView,
+    pub format: wgpu::TextureFormat,
+    pub size_in_pixels: [u32; 2],
+    pub pixels_per_point: f32,
+}
+
+pub(crate) type CustomDraw = Box<dyn FnMut(&mut CustomDrawFrame<'_>)>;
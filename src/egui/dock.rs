@@ -0,0 +1,256 @@
+use crate::LayerSurfaceSettings;
+use crate::layer_surface::exclusive_edge;
+use smithay_client_toolkit::compositor::CompositorState;
+use smithay_client_toolkit::compositor::Region;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::wlr_layer::Anchor;
+use smithay_client_toolkit::shell::wlr_layer::LayerSurface;
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Zone changes of pinning wait this long, toggling back within it causes
+/// no relayout at all
+const PIN_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Rectangles of the input region reach past any surface size
+const UNBOUNDED: i32 = i32::MAX / 4;
+
+/// Auto-hiding dock along the exclusive edge of a layer surface
+///
+/// The surface always has its expanded size and the exclusive zone stays
+/// the same while it expands and collapses, so windows aren't laid out
+/// again and the expanded dock overlaps them. Collapsed, only a strip along
+/// the edge takes pointer input. Only pinning changes the zone.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DockMode {
+    /// Thickness of the pointer trigger along the edge when collapsed
+    pub strip: u32,
+    /// Reserved space while not pinned
+    pub exclusive_zone: i32,
+    /// Time to expand or collapse
+    pub duration: Duration,
+    /// Time the dock stays expanded after the pointer leaves
+    pub collapse_delay: Duration,
+}
+
+impl Default for DockMode {
+    fn default() -> Self {
+        Self {
+            strip: 4,
+            exclusive_zone: 4,
+            duration: Duration::from_millis(200),
+            collapse_delay: Duration::from_millis(500),
+        }
+    }
+}
+
+#[derive(Default)]
+struct DockShared {
+    pinned: Cell<bool>,
+    faded_out: Cell<bool>,
+    progress: Cell<f32>,
+}
+
+/// Pin the dock and read its state in `ui()`, clones share the dock
+#[derive(Clone, Default)]
+pub struct DockHandle(Rc<DockShared>);
+
+impl DockHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pinned docks stay expanded and reserve their whole thickness
+    pub fn set_pinned(&self, pinned: bool) {
+        self.0.pinned.set(pinned);
+    }
+
+    pub fn is_pinned(&self) -> bool {
+        self.0.pinned.get()
+    }
+
+    /// Content drawn while fully collapsed is invisible, e.g. faded out.
+    /// The collapsed dock then isn't drawn nor committed again until it
+    /// expands, only the strip keeps taking pointer input.
+    pub fn set_faded_out(&self, faded_out: bool) {
+        self.0.faded_out.set(faded_out);
+    }
+
+    /// Eased from 0.0 collapsed to 1.0 expanded, slide the content with it
+    /// e.g. by `(1.0 - progress) * (thickness - strip)` toward the edge
+    pub fn progress(&self) -> f32 {
+        egui::emath::easing::cubic_in_out(self.0.progress.get())
+    }
+}
+
+/// What the surface does after the dock advanced
+pub(crate) struct DockStep {
+    /// Layer surface state changed or the content moves, render and commit
+    pub render: bool,
+    /// Advance again after this even without a frame, `None` while an
+    /// earlier wake is already pending
+    pub wake_after: Option<Duration>,
+}
+
+pub(crate) struct Dock {
+    mode: DockMode,
+    handle: DockHandle,
+    /// Thickness of the surface across the edge
+    thickness: u32,
+    strip_region: Region,
+    /// Linear from 0.0 collapsed to 1.0 expanded
+    position: f32,
+    hovered: bool,
+    collapse_at: Option<Instant>,
+    last_step: Option<Instant>,
+    /// Whole surface takes input, else the strip only
+    full_input: bool,
+    zone: i32,
+    zone_at: Option<Instant>,
+    /// Deadline of the pending wake
+    wake_at: Option<Instant>,
+}
+
+impl Dock {
+    /// `None` without an exclusive edge or a size across it. The zone of
+    /// the mode is set right away, committed with the next frame.
+    pub fn new(
+        compositor: &CompositorState,
+        layer_surface: &LayerSurface,
+        settings: &LayerSurfaceSettings,
+        mode: DockMode,
+        handle: DockHandle,
+    ) -> Option<Self> {
+        let edge = exclusive_edge(settings.anchor)?;
+        let thickness = match edge {
+            Anchor::TOP | Anchor::BOTTOM => settings.height,
+            _ => settings.width,
+        };
+        if thickness == 0 {
+            return None;
+        }
+        let strip = mode.strip.min(thickness) as i32;
+        let near = thickness as i32 - strip;
+        let strip_region = Region::new(compositor).ok()?;
+        match edge {
+            Anchor::TOP => strip_region.add(0, 0, UNBOUNDED, strip),
+            Anchor::BOTTOM => strip_region.add(0, near, UNBOUNDED, strip),
+            Anchor::LEFT => strip_region.add(0, 0, strip, UNBOUNDED),
+            _ => strip_region.add(near, 0, strip, UNBOUNDED),
+        }
+        layer_surface.set_exclusive_zone(mode.exclusive_zone);
+        Some(Self {
+            mode,
+            handle,
+            thickness,
+            strip_region,
+            position: 0.0,
+            hovered: false,
+            collapse_at: None,
+            last_step: None,
+            full_input: true,
+            zone: mode.exclusive_zone,
+            zone_at: None,
+            wake_at: None,
+        })
+    }
+
+    /// Fully collapsed with content the app has faded out
+    pub fn is_hidden(&self) -> bool {
+        self.position == 0.0 && self.handle.0.faded_out.get()
+    }
+
+    /// Expanding or collapsing, each frame moves it further
+    pub fn is_animating(&self) -> bool {
+        self.last_step.is_some()
+    }
+
+    pub fn pointer_entered(&mut self) {
+        self.hovered = true;
+        self.collapse_at = None;
+    }
+
+    pub fn pointer_left(&mut self, now: Instant) {
+        self.hovered = false;
+        self.collapse_at = Some(now + self.mode.collapse_delay);
+    }
+
+    /// Move toward the wanted state and set the input region and zone on
+    /// the layer surface, committed with the next rendered frame
+    pub fn advance(&mut self, layer_surface: &LayerSurface, now: Instant) -> DockStep {
+        let pinned = self.handle.is_pinned();
+        let delayed = self.collapse_at.is_some_and(|at| now < at);
+        let target = if pinned || self.hovered || delayed {
+            1.0
+        } else {
+            0.0
+        };
+        let elapsed = self
+            .last_step
+            .map_or(Duration::ZERO, |last| now.saturating_duration_since(last));
+        self.last_step = Some(now);
+        let step = if self.mode.duration.is_zero() {
+            1.0
+        } else {
+            elapsed.as_secs_f32() / self.mode.duration.as_secs_f32()
+        };
+        let moving = self.position != target;
+        self.position = if target > self.position {
+            (self.position + step).min(target)
+        } else {
+            (self.position - step).max(target)
+        };
+        self.handle.0.progress.set(self.position);
+        if self.position == target {
+            self.last_step = None;
+        }
+        let mut render = moving;
+
+        // Input is limited to the strip only once fully collapsed
+        let full_input = self.position > 0.0 || target > 0.0;
+        if full_input != self.full_input {
+            self.full_input = full_input;
+            let region = (!full_input).then(|| self.strip_region.wl_region());
+            layer_surface.wl_surface().set_input_region(region);
+            render = true;
+        }
+
+        let zone = if pinned {
+            self.thickness as i32
+        } else {
+            self.mode.exclusive_zone
+        };
+        let mut wake_after = self.collapse_at.filter(|at| now < *at).map(|at| at - now);
+        if zone == self.zone {
+            self.zone_at = None;
+        } else {
+            let at = *self.zone_at.get_or_insert(now + PIN_DEBOUNCE);
+            if now >= at {
+                self.zone = zone;
+                self.zone_at = None;
+                layer_surface.set_exclusive_zone(zone);
+                render = true;
+            } else {
+                let wait = at - now;
+                wake_after = Some(wake_after.map_or(wait, |after| after.min(wait)));
+            }
+        }
+        let wake_after = wake_after.filter(|after| self.schedule_wake(now, now + *after));
+        DockStep { render, wake_after }
+    }
+
+    /// `true` if a timer is needed for the deadline, one pending for an
+    /// earlier or the same time wakes the dock anyway
+    fn schedule_wake(&mut self, now: Instant, at: Instant) -> bool {
+        if self
+            .wake_at
+            .is_some_and(|pending| pending > now && pending <= at)
+        {
+            return false;
+        }
+        self.wake_at = Some(at);
+        true
+    }
+}
@@ -1,291 +1,3120 @@
 use crate::Application;
+use crate::ArrangeCapabilities;
 use crate::BaseTrait;
+use crate::CaptureError;
+use crate::CapturedFrame;
 use crate::CompositorHandlerContainer;
+use crate::CustomDrawFrame;
+use crate::DegradationEvent;
+use crate::DegradationStep;
+use crate::DndHandlerContainer;
+use crate::DockHandle;
+use crate::DockMode;
+use crate::DroppedData;
 use crate::EguiWgpuRenderer;
+use crate::FrameBudget;
+use crate::FrameStatus;
+use crate::InvalidLayerSurface;
+use crate::KeyboardArrange;
 use crate::KeyboardHandlerContainer;
+use crate::KineticScrolling;
+use crate::LayerSurfaceBuilder;
 use crate::LayerSurfaceContainer;
+use crate::LayerSurfaceSettings;
+use crate::LockSurfaceContainer;
+use crate::OffsetNotAllowed;
+use crate::OutputSelector;
 use crate::PointerHandlerContainer;
 use crate::PopupContainer;
+use crate::Presented;
+use crate::ProtocolFeature;
+use crate::RawInputEvent;
+use crate::RedrawHandle;
+use crate::RenderStats;
+use crate::SessionLockHandle;
 use crate::SubsurfaceContainer;
+use crate::SurfaceMetrics;
+use crate::SurfaceOffset;
+use crate::SurfaceOptions;
 use crate::WaylandToEguiInput;
 use crate::WindowContainer;
+use crate::advice::SurfaceAdvice;
+use crate::egui::buffer_transform::BufferTransform;
+use crate::egui::buffer_transform::swaps_axes;
+use crate::egui::client_decorations::ClientDecorations;
+use crate::egui::custom_draw::CustomDraw;
+use crate::egui::dock::Dock;
+use crate::egui::frame_capture;
+use crate::egui::frame_fallback::FrameFallback;
+use crate::egui::frame_fallback::NOMINAL_FRAME_INTERVAL;
+use crate::egui::keyboard_arrange::ArrangeKey;
+use crate::egui::keyboard_arrange::ArrangeStep;
+use crate::egui::map_fence::MapFence;
+use crate::egui::size_request::SizeRequest;
+use crate::egui::theme_transition::apply_theme_transition;
+use crate::egui::thumbnails::Thumbnails;
 use crate::get_app;
+use crate::gpu_timer::GpuTimer;
+use crate::graveyard::Graveyard;
+use crate::input_batch::InputBatch;
+use crate::presentation::FramePacer;
+use crate::presentation::FrameTransactions;
+use crate::presentation::request_feedback;
+use crate::raw_handles::WaylandWgpuSurface;
+use crate::raw_handles::clipboard_for_connection;
+use crate::redraw::SurfaceRequestKind;
+use crate::render_stats::FramePhases;
+use crate::render_stats::RenderStatsCollector;
+use crate::stall_watch::UiCall;
+use crate::startup_timing;
+use crate::startup_timing::Phase;
+use crate::strict::StrictSurface;
+use crate::strict::SurfaceRole;
 use egui::PlatformOutput;
+use log::debug;
 use log::trace;
-use pollster::block_on;
-use raw_window_handle::RawDisplayHandle;
-use raw_window_handle::RawWindowHandle;
-use raw_window_handle::WaylandDisplayHandle;
-use raw_window_handle::WaylandWindowHandle;
+use log::warn;
+use smithay_client_toolkit::reexports::csd_frame::WindowState;
 use smithay_client_toolkit::seat::keyboard::KeyEvent;
 use smithay_client_toolkit::seat::keyboard::Modifiers;
+use smithay_client_toolkit::seat::keyboard::RepeatInfo;
 use smithay_client_toolkit::seat::pointer::PointerEvent;
+use smithay_client_toolkit::seat::pointer::PointerEventKind;
+use smithay_client_toolkit::session_lock::SessionLockSurface;
+use smithay_client_toolkit::session_lock::SessionLockSurfaceConfigure;
 use smithay_client_toolkit::shell::WaylandSurface;
 use smithay_client_toolkit::shell::wlr_layer::LayerSurface;
 use smithay_client_toolkit::shell::wlr_layer::LayerSurfaceConfigure;
 use smithay_client_toolkit::shell::xdg::popup::Popup;
 use smithay_client_toolkit::shell::xdg::popup::PopupConfigure;
+use smithay_client_toolkit::shell::xdg::window::DecorationMode;
 use smithay_client_toolkit::shell::xdg::window::Window;
 use smithay_client_toolkit::shell::xdg::window::WindowConfigure;
-use smithay_clipboard::Clipboard;
-use std::ptr::NonNull;
+use std::any::Any;
+use std::collections::VecDeque;
+use std::fmt;
+use std::mem::ManuallyDrop;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
 use wayland_client::Proxy;
 use wayland_client::QueueHandle;
+use wayland_client::protocol::wl_display::WlDisplay;
+use wayland_client::protocol::wl_output::Subpixel;
+use wayland_client::protocol::wl_output::Transform;
+use wayland_client::protocol::wl_output::WlOutput;
+use wayland_client::protocol::wl_subsurface::WlSubsurface;
 use wayland_client::protocol::wl_surface::WlSurface;
 use wayland_protocols::wp::cursor_shape::v1::client::wp_cursor_shape_device_v1::Shape;
+use wayland_protocols::wp::presentation_time::client::wp_presentation::WpPresentation;
+
+/// Capabilities are queried again at most this many times in a row without a
+/// frame presented in between, the errors are then left to the next configure
+const MAX_FORMAT_RENEGOTIATIONS: u32 = 3;
+
+/// Input latencies of this many frames are used for the percentile
+const INPUT_LATENCY_WINDOW: usize = 60;
+
+/// How the content is fit when the compositor configures a size different
+/// from the intrinsic size the surface was created with
+///
+/// There is no scrolling fit, EGUI apps lay out their panels on the context
+/// directly so the content can't be wrapped in a scroll area from outside.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContentFit {
+    /// Lay the content out at the configured size
+    #[default]
+    Resize,
+    /// Keep the layout at least the intrinsic size and scale it down to fit
+    /// the configured size, keeping proportions
+    ScaleDown,
+    /// Render at least the intrinsic size and let the compositor clip
+    Clamp,
+}
+
+/// Limits of the content size in logical pixels, applied after the content
+/// fit
+///
+/// The surface keeps the configured size so it never fights the compositor,
+/// content that doesn't fill it is centered and the rest is cleared with the
+/// background. Content larger than the surface because of `min` is scaled
+/// down to fit.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SizeConstraints {
+    pub min: Option<(u32, u32)>,
+    pub max: Option<(u32, u32)>,
+    /// Width divided by height
+    pub aspect: Option<f32>,
+}
+
+impl SizeConstraints {
+    /// Content size for the available size, `fill` ignores the maximum and
+    /// the aspect ratio, e.g. when maximized
+    pub fn content_size(&self, available: (f32, f32), fill: bool) -> (f32, f32) {
+        let (mut width, mut height) = available;
+        if !fill {
+            if let Some((max_width, max_height)) = self.max {
+                width = width.min(max_width as f32);
+                height = height.min(max_height as f32);
+            }
+            if let Some(aspect) = self.aspect.filter(|aspect| *aspect > 0.0) {
+                if width / height > aspect {
+                    width = height * aspect;
+                } else {
+                    height = width / aspect;
+                }
+            }
+        }
+        if let Some((min_width, min_height)) = self.min {
+            width = width.max(min_width as f32);
+            height = height.max(min_height as f32);
+        }
+        (width.max(1.0), height.max(1.0))
+    }
+
+    /// Size within the constraints for a configure that leaves it to the
+    /// client
+    pub fn clamp(&self, (width, height): (u32, u32)) -> (u32, u32) {
+        let (width, height) = self.content_size((width as f32, height as f32), false);
+        (width.round() as u32, height.round() as u32)
+    }
+}
+
+/// What happens to the focused widget when keyboard focus leaves the surface
+///
+/// Either way only the surface with keyboard focus draws a caret.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FocusPolicy {
+    /// Keep it, focus returns to the same widget when the surface regains
+    /// keyboard focus
+    #[default]
+    Remember,
+    /// Clear it, only one widget application-wide is ever focused
+    Exclusive,
+}
+
+/// Whether text is snapped to whole pixels
+///
+/// EGUI rasterizes glyphs without hinting and antialiases them in grayscale
+/// only, snapping the glyph positions is the hinting it can do. Snapped text
+/// is sharper at scale 1 and spaced a little unevenly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextHinting {
+    /// Snap on outputs of scale 1, unsnapped text looks fuzzy there
+    #[default]
+    Auto,
+    On,
+    Off,
+}
+
+/// Problem of a surface reported to `EguiAppData::surface_error`, the
+/// surface keeps running
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SurfaceError {
+    /// Configured larger than the device can allocate, e.g. on a 16k output.
+    /// The buffer is clamped to `max` and the rest of the surface is left
+    /// transparent.
+    TooLarge { width: u32, height: u32, max: u32 },
+    /// Swapchain texture couldn't be allocated, the frame is skipped
+    OutOfMemory,
+}
+
+impl fmt::Display for SurfaceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SurfaceError::TooLarge { width, height, max } => write!(
+                f,
+                "Configured to {}x{}, over the {} supported by the device",
+                width, height, max
+            ),
+            SurfaceError::OutOfMemory => write!(f, "Out of memory for the swapchain texture"),
+        }
+    }
+}
+
+impl std::error::Error for SurfaceError {}
 
 pub trait EguiAppData {
     fn ui(&mut self, ctx: &egui::Context);
+
+    /// Called before the first `ui` and before the next one whenever the
+    /// size of the content in points changes, e.g. to switch layouts below
+    /// a width. The EGUI context has the size during `ui` too.
+    fn resized(&mut self, _width: u32, _height: u32) {}
+
+    /// Called before the first `ui` and before the next one whenever the
+    /// pixels per point change, e.g. after moving to an output of another
+    /// scale
+    fn scale_factor_changed(&mut self, _pixels_per_point: f32) {}
+
+    /// Called when the toplevel state changes (maximized, tiled, activated...),
+    /// only windows receive this
+    fn window_state_changed(&mut self, _state: WindowState) {}
+
+    /// Called when input is rejected, e.g. an invalid codepoint in Unicode
+    /// entry, EGUI has no bell of its own
+    fn bell(&mut self) {}
+
+    /// Called when the emoji picker shortcut is pressed, see
+    /// `with_emoji_picker_shortcut`. Insert the picked emoji with
+    /// `RedrawHandle::insert_text` of this surface.
+    fn emoji_picker_requested(&mut self) {}
+
+    /// Called when the active keyboard layout changes, on every surface
+    fn keyboard_layout_changed(&mut self, _layout_name: &str) {}
+
+    /// Called when the compositor changes the key repeat rate or delay, on
+    /// every surface
+    fn repeat_info_changed(&mut self, _info: RepeatInfo) {}
+
+    /// Called after the event loop didn't run for the gap, e.g. a system
+    /// suspend, on every surface. Refresh external data here, e.g. the
+    /// weather or the battery state.
+    fn system_resumed(&mut self, _gap: Duration) {}
+
+    /// Called when a frame of the surface was shown, only with the
+    /// presentation time protocol. E.g. for measuring latency.
+    fn presented(&mut self, _presented: &Presented) {}
+
+    /// Called when the compositor or the titlebar asks to close the window,
+    /// only windows receive this. Return false to keep it open, e.g. to ask
+    /// about unsaved changes, and close it later with `RedrawHandle::close`.
+    fn close_requested(&mut self) -> bool {
+        true
+    }
+
+    /// Called when the surface can't be rendered as configured, e.g. to
+    /// tell the user or close the surface. It's also logged.
+    fn surface_error(&mut self, _error: SurfaceError) {}
 }
 
-struct EguiSurfaceState<A: EguiAppData> {
+/// Renderer and input state of an EGUI container, reached through
+/// `EguiSurface`
+pub struct EguiSurfaceState<A: EguiAppData> {
     wl_surface: WlSurface,
     // instance: wgpu::Instance, // docs says it doesn't need to be kept alive
-    surface: wgpu::Surface<'static>,
-    // adapter: wgpu::Adapter, // docs says it doesn't need to be kept alive
+    /// Swapchain and renderer are buried on drop, see `Graveyard`
+    surface: ManuallyDrop<WaylandWgpuSurface>,
+    /// Capabilities are queried again from it when the swapchain fails,
+    /// e.g. after a driver update changed the supported formats
+    adapter: wgpu::Adapter,
     device: wgpu::Device,
     queue: wgpu::Queue,
-    renderer: EguiWgpuRenderer,
+    renderer: ManuallyDrop<EguiWgpuRenderer>,
     egui_app: A,
     input_state: WaylandToEguiInput,
     queue_handle: QueueHandle<Application>,
     width: u32,
     height: u32,
+    intrinsic_size: (u32, u32),
+    content_fit: ContentFit,
+    constraints: SizeConstraints,
+    /// Content fills the surface ignoring the maximum and aspect ratio, set
+    /// while maximized or fullscreen
+    fill: bool,
     scale_factor: i32,
+    text_hinting: TextHinting,
+    /// Subpixel layout of the output entered last, only reported
+    subpixel: Option<Subpixel>,
+    /// Buffer scale and transform last set on the wl_surface, both are set
+    /// in the commit of the first buffer drawn for them
+    applied_buffer: (i32, Transform),
     surface_config: Option<wgpu::SurfaceConfiguration>,
     output_format: wgpu::TextureFormat,
+    formats: Vec<wgpu::TextureFormat>,
+    present_modes: Vec<wgpu::PresentMode>,
+    alpha_modes: Vec<wgpu::CompositeAlphaMode>,
+    /// Capability queries since the last presented frame
+    format_renegotiations: u32,
+    /// Options with the present mode already validated
+    options: SurfaceOptions,
+    /// Surface is cleared with this before EGUI draws, premultiplied
+    background: egui::Color32,
+    frame_budget: FrameBudget,
+    /// `None` without timestamp queries, frame cost is then CPU time only
+    gpu_timer: Option<GpuTimer>,
+    /// Buffer scale the swapchain is configured for
+    configured_scale: u32,
+    /// Nothing is rendered before the first configure has been applied
+    configured: bool,
+    /// Only one frame callback is kept in flight at a time
+    frame_callback_pending: bool,
+    /// Animations of frame gated surfaces still end when the compositor
+    /// withholds the callback
+    frame_fallback: FrameFallback,
+    frames_presented: u64,
+    frames_skipped: u64,
+    frames_deferred: u64,
+    frames_unchanged: u64,
+    /// Swapchains reconfigured after the surface was lost or outdated
+    swapchain_reconnects: u64,
+    /// Recent times from receiving input to presenting it
+    input_latencies: VecDeque<Duration>,
+    debug_name: Option<String>,
+    /// Content is invisible whatever EGUI draws, e.g. a faded out dock
+    content_hidden: bool,
+    /// Last presented frame was fully transparent, following ones are
+    /// elided until something is visible again
+    transparent_presented: bool,
+    /// Shapes and screen size and scale of the last presented frame
+    last_shapes: Vec<egui::epaint::ClippedShape>,
+    last_screen: Option<([u32; 2], f32)>,
+    /// Present the next frame even if EGUI output didn't change
+    damaged: bool,
+    /// Native textures change without EGUI knowing, every frame is presented
+    native_textures: bool,
+    /// Drawn under EGUI, every frame is presented too
+    custom_draw: Option<CustomDraw>,
+    /// Compositor has suspended the toplevel, e.g. on another workspace
+    suspended: bool,
+    /// Render on input only once the frame callback of the previous commit
+    /// has arrived, surfaces hidden without a suspended state never get one
+    frame_gated: bool,
+    /// Render on the next frame callback even if there's no input
+    redraw_requested: bool,
+    /// Content size in points and pixels per point the app was told about
+    announced: Option<((u32, u32), f32)>,
+    /// Input queued by the handlers, rendered once after the dispatch
+    input_queued: bool,
+    /// Pointer input is queued, the cursor follows the EGUI output
+    cursor_queued: bool,
+    /// Configured since the last dispatch, rendered without waiting for a
+    /// frame callback
+    configure_queued: bool,
+    /// Shown instead of the cursor of the content, see `set_cursor_override`
+    cursor_override: Option<Shape>,
+    /// Pointer is over the surface, the override is shown right away
+    pointer_inside: bool,
+    /// Unmapped with a null buffer, input is ignored until shown again
+    hidden: bool,
+    /// Configures of the mapping before hiding are ignored after showing
+    map_fence: MapFence,
+    /// Shows are fenced with a sync on it
+    display: WlDisplay,
+    /// When the surface was shown again, for logging the time to re-map
+    shown_at: Option<Instant>,
+    offset: SurfaceOffset,
+    /// Cursor and IME requests go to the application through this
+    handle: RedrawHandle,
+    strict: StrictSurface,
+    /// Titlebar drawn by the window when the compositor doesn't decorate it
+    decorations: Option<ClientDecorations>,
+    focus_policy: FocusPolicy,
+    /// Moving and resizing with the keyboard, only windows and layer
+    /// surfaces enable it
+    arrange: Option<KeyboardArrange>,
+    /// Per-phase frame timing, only collected when enabled
+    stats: Option<RenderStatsCollector>,
+    /// Preferred buffer transform of the output, e.g. a rotated monitor
+    transform: Transform,
+    /// Rotation pass, created on the first frame with a transform other than
+    /// normal
+    buffer_transform: Option<BufferTransform>,
+    advice: SurfaceAdvice,
+    thumbnails: Thumbnails,
+    /// Feedback is requested for every presented frame when available
+    presentation: Option<WpPresentation>,
+    pacer: FramePacer,
+    transactions: FrameTransactions,
+    input_batch: InputBatch,
+    /// Commits with frame tokens resolved by the next frame callback, used
+    /// without presentation time
+    callback_commits: Vec<u64>,
+    graveyard: Graveyard,
+    /// Role object owning the wl_surface, e.g. the toplevel, buried with the
+    /// swapchain so the wl_surface is destroyed after it
+    role: Option<Box<dyn Any>>,
 }
 
 impl<A: EguiAppData> EguiSurfaceState<A> {
-    fn new(wl_surface: WlSurface, egui_app: A) -> Self {
-        let app = get_app();
-        let raw_display_handle = RawDisplayHandle::Wayland(WaylandDisplayHandle::new(
-            NonNull::new(app.conn.backend().display_ptr() as *mut _)
-                .expect("Wayland display pointer was null"),
-        ));
-        let raw_window_handle = RawWindowHandle::Wayland(WaylandWindowHandle::new(
-            NonNull::new(wl_surface.id().as_ptr() as *mut _)
-                .expect("Wayland surface handle was null"),
-        ));
-
-        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::all(),
-            ..Default::default()
-        });
-        let surface = unsafe {
-            instance
-                .create_surface_unsafe(wgpu::SurfaceTargetUnsafe::RawHandle {
-                    raw_display_handle,
-                    raw_window_handle,
-                })
-                .expect("Failed to create WGPU surface")
-        };
-
-        let adapter = block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
-            compatible_surface: Some(&surface),
-            ..Default::default()
-        }))
-        .expect("Failed to find a suitable adapter");
+    fn new(app: &mut Application, wl_surface: WlSurface, egui_app: A) -> Self {
+        let instance = app.wgpu_instance();
+        // SAFETY: The containers set `role` to the object destroying the
+        // wl_surface, it's buried after the swapchain in `drop`. Subsurfaces
+        // don't destroy their wl_surface.
+        let surface = unsafe { WaylandWgpuSurface::new(&instance, &app.conn, &wl_surface) }
+            .expect("Failed to create WGPU surface");
+        let gpu = app.gpu_context(&surface);
+        let device = gpu.device;
+        let queue = gpu.queue;
+        let gpu_timer = GpuTimer::new(&device, &queue);
+
+        let caps = surface.get_capabilities(&gpu.adapter);
+        let mut options = SurfaceOptions::default();
+        let output_format = options.select_format(&caps.formats);
+        options.present_mode = options.select_present_mode(&caps.present_modes);
 
-        let (device, queue) = block_on(adapter.request_device(&wgpu::DeviceDescriptor {
-            memory_hints: wgpu::MemoryHints::MemoryUsage,
-            ..Default::default()
-        }))
-        .expect("Failed to request WGPU device");
+        let renderer = EguiWgpuRenderer::new(&device, output_format, None, 1);
 
-        let caps = surface.get_capabilities(&adapter);
-        let output_format = *caps
-            .formats
-            .get(0)
-            .unwrap_or(&wgpu::TextureFormat::Bgra8Unorm);
+        // Repaints requested through the EGUI context, also from other threads
+        let handle = app.redraw_handle(&wl_surface.id());
+        let redraw = Mutex::new(handle.clone());
+        renderer
+            .context()
+            .set_request_repaint_callback(move |info| {
+                if let Ok(redraw) = redraw.lock() {
+                    redraw.request_redraw_after(info.delay);
+                }
+            });
 
-        let renderer = EguiWgpuRenderer::new(&device, output_format, None, 1);
-        let clipboard = unsafe { Clipboard::new(app.conn.display().id().as_ptr() as *mut _) };
-        let input_state = WaylandToEguiInput::new(clipboard);
+        let mut input_state = WaylandToEguiInput::new(clipboard_for_connection(&app.conn));
+        input_state.set_paste_limit(app.transfer_limits().text);
 
         Self {
             wl_surface,
             // instance,
-            surface,
-            // adapter,
+            surface: ManuallyDrop::new(surface),
+            adapter: gpu.adapter,
             device,
             queue,
-            renderer,
+            renderer: ManuallyDrop::new(renderer),
             egui_app,
             input_state,
             queue_handle: app.qh.clone(),
             width: 256,
             height: 256,
+            intrinsic_size: (256, 256),
+            content_fit: ContentFit::Resize,
+            constraints: SizeConstraints::default(),
+            fill: false,
             scale_factor: 1,
+            text_hinting: TextHinting::default(),
+            subpixel: None,
+            applied_buffer: (1, Transform::Normal),
             surface_config: None,
             output_format,
+            formats: caps.formats,
+            present_modes: caps.present_modes,
+            alpha_modes: caps.alpha_modes,
+            format_renegotiations: 0,
+            options,
+            background: egui::Color32::BLACK,
+            frame_budget: FrameBudget::default(),
+            gpu_timer,
+            configured_scale: 1,
+            configured: false,
+            frame_callback_pending: false,
+            frame_fallback: FrameFallback::default(),
+            frames_presented: 0,
+            frames_skipped: 0,
+            frames_deferred: 0,
+            frames_unchanged: 0,
+            swapchain_reconnects: 0,
+            input_latencies: VecDeque::with_capacity(INPUT_LATENCY_WINDOW),
+            debug_name: None,
+            content_hidden: false,
+            transparent_presented: false,
+            last_shapes: Vec::new(),
+            last_screen: None,
+            damaged: true,
+            native_textures: false,
+            custom_draw: None,
+            suspended: false,
+            frame_gated: false,
+            redraw_requested: false,
+            announced: None,
+            input_queued: false,
+            cursor_queued: false,
+            configure_queued: false,
+            cursor_override: None,
+            pointer_inside: false,
+            hidden: false,
+            map_fence: MapFence::default(),
+            display: app.conn.display(),
+            shown_at: None,
+            offset: SurfaceOffset::default(),
+            handle,
+            strict: StrictSurface::new(SurfaceRole::Toplevel),
+            decorations: None,
+            focus_policy: FocusPolicy::Remember,
+            arrange: None,
+            stats: None,
+            transform: Transform::Normal,
+            buffer_transform: None,
+            advice: SurfaceAdvice::default(),
+            thumbnails: app.thumbnail_service(),
+            presentation: app.presentation.manager(),
+            pacer: FramePacer::default(),
+            transactions: app.transactions.clone(),
+            input_batch: app.input_batch.clone(),
+            callback_commits: Vec::new(),
+            graveyard: app.graveyard(),
+            role: None,
         }
     }
 
+    /// Apply the configured size and render exactly one frame at it
+    ///
+    /// The configure is already acked by the toolkit, the swapchain is resized
+    /// before anything else can acquire a texture so no frame with the old
+    /// size is presented after this. The frame is rendered once the dispatch
+    /// is done, a configure superseded in the same dispatch is never shown.
+    ///
+    /// A zero size leaves it to the client, the intrinsic size within the
+    /// constraints is used.
     fn configure(&mut self, width: u32, height: u32) {
-        self.width = width.max(1);
-        self.height = height.max(1);
-        self.input_state.set_screen_size(self.width, self.height);
+        if !self.accepts_configure() {
+            debug!(
+                "Surface {} configure {}x{} of an earlier mapping ignored",
+                self.wl_surface.id(),
+                width,
+                height
+            );
+            return;
+        }
+        let fallback = self.constraints.clamp(self.intrinsic_size);
+        (self.width, self.height) = configured_size((width, height), fallback);
+        let max = self.max_buffer_size();
+        if self.width > max || self.height > max {
+            let error = SurfaceError::TooLarge {
+                width: self.width,
+                height: self.height,
+                max,
+            };
+            warn!("Surface {} {}, clamped", self.wl_surface.id(), error);
+            self.egui_app.surface_error(error);
+        }
+        self.apply_content_fit();
         self.reconfigure_surface();
-        self.render();
+        self.configured = true;
+        self.strict.configured();
+        self.apply_text_hinting();
+        self.configure_queued = true;
+        self.queue_render();
+        if let Some(shown_at) = self.shown_at.take() {
+            debug!(
+                "Surface {} mapped again in {:?}",
+                self.wl_surface.id(),
+                shown_at.elapsed()
+            );
+        }
+    }
+
+    /// Unmap the surface by committing a null buffer, WGPU surface and the
+    /// app state are kept
+    fn hide(&mut self) {
+        if self.hidden {
+            return;
+        }
+        self.hidden = true;
+        self.configured = false;
+        self.redraw_requested = false;
+        // Unmapped surfaces may never get the callback
+        self.frame_callback_pending = false;
+        self.frame_fallback.reset();
+        self.strict.unmapped();
+        self.shown_at = None;
+        // Leave events of the unmapped surface are dropped, hover and press
+        // state must not come back when it's shown again
+        self.input_state.feed(RawInputEvent::PointerLeave);
+        self.wl_surface.attach(None, 0, 0);
+        self.wl_surface.commit();
+        // Feedback of committed frames still arrives, frame callbacks may not
+        self.transactions.discard_pending(&self.wl_surface.id());
+        for commit in std::mem::take(&mut self.callback_commits) {
+            self.transactions.resolve(commit, FrameStatus::Discarded);
+        }
+    }
+
+    /// Map again, a commit without a buffer is an initial commit so the
+    /// compositor replies with a new configure, rendering resumes from that.
+    /// A configure still in flight from before hiding is ignored, see
+    /// `MapFence`.
+    fn show(&mut self) {
+        if !self.hidden {
+            return;
+        }
+        self.hidden = false;
+        self.shown_at = Some(Instant::now());
+        let sync = self.map_fence.show();
+        self.display.sync(&self.queue_handle, sync);
+        self.wl_surface.commit();
+    }
+
+    /// Configures sent while hidden or before the sync of the latest show
+    /// belong to an earlier mapping
+    fn accepts_configure(&self) -> bool {
+        !self.hidden && self.map_fence.is_open()
     }
 
     fn frame(&mut self, _time: u32) {
-        self.render();
+        self.frame_callback_pending = false;
+        if self.frame_fallback.callback() {
+            // Animations were stepped without rendering, catch up once
+            self.redraw_requested = true;
+        }
+        self.strict.frame_done();
+        for commit in std::mem::take(&mut self.callback_commits) {
+            let presented = Presented {
+                presented_at: Instant::now(),
+                refresh: None,
+                sequence: 0,
+                vsync: false,
+            };
+            self.transactions
+                .resolve(commit, FrameStatus::Presented(presented));
+        }
+        if !self.configured {
+            trace!(
+                "Dropping frame callback before configure on surface {}",
+                self.wl_surface.id()
+            );
+            return;
+        }
+        if self.pacer.hold(Instant::now()) {
+            // Too early for the target, wait for the next vblank
+            self.request_redraw();
+            return;
+        }
+        let idle = !self.input_state.has_pending_events() && !self.redraw_requested;
+        if self.frame_gated && idle {
+            // Nothing deferred, stop the callback chain
+            return;
+        }
+        if self.frame_budget.is_active(DegradationStep::SkipIdleFrames) && idle {
+            trace!("Skipping idle frame on surface {}", self.wl_surface.id());
+            self.frames_skipped += 1;
+            if let Some(stats) = &mut self.stats {
+                stats.record_skipped();
+            }
+            return;
+        }
+        self.render_frame();
+    }
+
+    /// Arm the fallback for the pending frame callback of a frame gated
+    /// surface, while an animation depends on the callback
+    fn arm_frame_fallback(&mut self) {
+        if !self.frame_gated || !self.frame_callback_pending {
+            return;
+        }
+        let interval = self.pacer.refresh().unwrap_or(NOMINAL_FRAME_INTERVAL);
+        if let Some(delay) = self.frame_fallback.arm(Instant::now(), interval) {
+            self.handle
+                .send(SurfaceRequestKind::FrameFallback { delay });
+        }
+    }
+
+    /// The frame callback is overdue, step the kinetic scroll without
+    /// rendering. `false` if the callback arrived in time.
+    fn frame_fallback(&mut self) -> bool {
+        let now = Instant::now();
+        if !self.frame_fallback.fire(now) {
+            return false;
+        }
+        trace!(
+            "Frame callback of surface {} withheld, stepping animations",
+            self.wl_surface.id()
+        );
+        let reduced_motion = self.renderer.context().style().animation_time <= 0.0;
+        if self.input_state.tick_kinetic(now, reduced_motion) {
+            self.redraw_requested = true;
+            self.arm_frame_fallback();
+        }
+        true
+    }
+
+    /// Drawn again so e.g. clocks don't show the time from before
+    fn system_resumed(&mut self, gap: Duration) {
+        self.egui_app.system_resumed(gap);
+        self.damaged = true;
+        self.request_redraw();
+    }
+
+    /// Suspended surfaces keep input but don't render, the configure that
+    /// clears the state renders once with everything accumulated
+    fn set_suspended(&mut self, suspended: bool) {
+        if self.suspended != suspended {
+            debug!(
+                "Surface {} {}",
+                self.wl_surface.id(),
+                if suspended { "suspended" } else { "resumed" }
+            );
+            self.suspended = suspended;
+        }
+    }
+
+    fn refresh_rate_changed(&mut self, refresh_rate: i32) {
+        let budget = FrameBudget::from_refresh_rate(refresh_rate).budget();
+        self.frame_budget.set_budget(budget);
+        self.pacer.set_output_refresh(refresh_rate);
+    }
+
+    fn present_at(&mut self, target: Instant) {
+        self.pacer.set_target(target);
+        self.request_redraw();
+    }
+
+    fn handle_pointer_events(&mut self, events: &[PointerEvent]) {
+        if self.hidden {
+            return;
+        }
+        self.input_state.handle_pointer_events(events);
+        for event in events {
+            match event.kind {
+                PointerEventKind::Enter { .. } => self.pointer_inside = true,
+                PointerEventKind::Leave { .. } => self.pointer_inside = false,
+                _ => {}
+            }
+        }
+        self.cursor_queued = true;
+        self.queue_render();
+    }
+
+    fn set_cursor_override(&mut self, shape: Option<Shape>) {
+        if shape == self.cursor_override {
+            return;
+        }
+        self.cursor_override = shape;
+        // Applied on the next enter otherwise, the cursor belongs to another
+        // surface until then
+        if self.pointer_inside {
+            self.cursor_queued = true;
+            self.queue_render();
+        }
+    }
+
+    fn handle_dnd_enter(&mut self, x: f64, y: f64, mime_types: &[String]) {
+        self.input_state.handle_dnd_enter(x, y, mime_types);
+        self.request_redraw();
+    }
+
+    fn handle_dnd_motion(&mut self, x: f64, y: f64) {
+        self.input_state.handle_dnd_motion(x, y);
+        self.request_redraw();
     }
 
-    fn handle_pointer_event(&mut self, event: &PointerEvent) {
-        self.input_state.handle_pointer_event(event);
-        let platform_output = self.render();
+    fn handle_dnd_leave(&mut self) {
+        self.input_state.handle_dnd_leave();
+        self.request_redraw();
+    }
 
-        // Handle cursor icon changes from EGUI
-        get_app().set_cursor(egui_to_cursor_shape(platform_output.cursor_icon));
+    fn handle_dnd_drop(&mut self, x: f64, y: f64, data: &DroppedData) {
+        self.input_state.handle_dnd_drop(x, y, data);
+        self.request_redraw();
     }
 
     fn handle_keyboard_enter(&mut self) {
         self.input_state.handle_keyboard_enter();
-        self.render();
+        self.queue_render();
     }
 
     fn handle_keyboard_leave(&mut self) {
         self.input_state.handle_keyboard_leave();
-        self.render();
+        if let Some(arrange) = &mut self.arrange {
+            arrange.exit();
+        }
+        if self.focus_policy == FocusPolicy::Exclusive {
+            self.renderer.context().memory_mut(|memory| {
+                if let Some(id) = memory.focused() {
+                    memory.surrender_focus(id);
+                }
+            });
+        }
+        self.queue_render();
     }
 
     fn handle_keyboard_event(&mut self, event: &KeyEvent, pressed: bool, repeat: bool) {
+        if self.hidden {
+            return;
+        }
         self.input_state
             .handle_keyboard_event(event, pressed, repeat);
-        self.render();
+        self.queue_render();
     }
 
     fn update_modifiers(&mut self, modifiers: &Modifiers) {
+        if let Some(arrange) = &mut self.arrange {
+            arrange.update_modifiers(modifiers);
+        }
         self.input_state.update_modifiers(modifiers);
-        self.render();
+        if self.input_state.take_bell() {
+            self.egui_app.bell();
+        }
+        self.queue_render();
+    }
+
+    /// Key press or repeat for the arrange mode, rendered again when the
+    /// mode is entered or left to show or hide the overlay
+    fn arrange_key(
+        &mut self,
+        event: &KeyEvent,
+        repeat: bool,
+        capabilities: ArrangeCapabilities,
+    ) -> ArrangeKey {
+        let Some(arrange) = self.arrange.as_mut().filter(|_| !self.hidden) else {
+            return ArrangeKey::Ignored;
+        };
+        let was_active = arrange.is_active();
+        let key = arrange.press(event.keysym, repeat, capabilities);
+        if arrange.is_active() != was_active {
+            debug!(
+                "[INPUT] Surface {} keyboard arrange mode {}",
+                self.wl_surface.id(),
+                if was_active { "left" } else { "entered" }
+            );
+            self.queue_render();
+        }
+        key
+    }
+
+    fn set_unicode_entry(&mut self, enabled: bool) {
+        self.input_state.set_unicode_entry(enabled);
+    }
+
+    fn handle_insert_text(&mut self, text: &str) {
+        if self.hidden {
+            return;
+        }
+        self.input_state.insert_text(text);
+        self.queue_render();
+    }
+
+    fn handle_preedit(&mut self, text: Option<&str>) {
+        if self.hidden {
+            return;
+        }
+        self.input_state.handle_preedit(text);
+        self.queue_render();
+    }
+
+    fn handle_delete_surrounding_text(&mut self, before_length: u32, after_length: u32) {
+        if self.hidden {
+            return;
+        }
+        self.input_state
+            .handle_delete_surrounding_text(before_length, after_length);
+        self.queue_render();
+    }
+
+    fn handle_commit_text(&mut self, text: &str) {
+        if self.hidden {
+            return;
+        }
+        self.input_state.handle_commit_text(text);
+        self.queue_render();
     }
 
+    /// The swapchain is resized right away, the buffer scale is set when the
+    /// first buffer of the new size is presented. Setting it earlier would
+    /// let any commit in between, e.g. for a frame callback, show the old
+    /// buffer at the new scale.
     fn scale_factor_changed(&mut self, new_factor: i32) {
-        self.wl_surface.set_buffer_scale(new_factor);
+        if !ProtocolFeature::BufferScale.is_supported(self.wl_surface.version()) {
+            debug!("Surface {} can't set buffer scale", self.wl_surface.id());
+            return;
+        }
         let factor = new_factor.max(1);
         if factor == self.scale_factor {
             return;
         }
         self.scale_factor = factor;
-        self.reconfigure_surface();
-        self.render();
+        self.apply_text_hinting();
+        if self.surface_config.is_some() {
+            self.reconfigure_surface();
+            self.damaged = true;
+            self.render();
+        }
+    }
+
+    /// Content is rotated by the client, the swapchain gets the dimensions
+    /// of the rotated buffer
+    fn transform_changed(&mut self, transform: Transform) {
+        if transform == self.transform
+            || !ProtocolFeature::BufferTransform.is_supported(self.wl_surface.version())
+        {
+            return;
+        }
+        debug!(
+            "Surface {} buffer transform {:?}",
+            self.wl_surface.id(),
+            transform
+        );
+        // Set with the next buffer, which has the new dimensions
+        self.transform = transform;
+        if self.configured {
+            self.reconfigure_surface();
+            self.render();
+        }
+    }
+
+    /// Mark the surface for `flush_input`, a burst of events in one
+    /// dispatch renders a single frame
+    fn queue_render(&mut self) {
+        if !self.input_queued {
+            self.input_queued = true;
+            self.input_batch.mark(self.wl_surface.id());
+        }
+    }
+
+    /// Render everything queued since the last dispatch at once
+    fn flush_input(&mut self) {
+        if !std::mem::take(&mut self.input_queued) {
+            return;
+        }
+        // Keys of the batch are converted with its final modifiers
+        self.input_state.flush_keys();
+        if self.input_state.take_bell() {
+            self.egui_app.bell();
+        }
+        if self.input_state.take_emoji_picker_request() {
+            self.egui_app.emoji_picker_requested();
+        }
+        let deferred = self.frames_deferred;
+        let platform_output = if std::mem::take(&mut self.configure_queued) {
+            self.render_frame()
+        } else {
+            self.render()
+        };
+        if self.frames_deferred != deferred || !std::mem::take(&mut self.cursor_queued) {
+            // Deferred, keep the cursor until EGUI has seen the input
+            return;
+        }
+
+        // Handle cursor icon changes from EGUI, the override wins
+        let shape = self
+            .cursor_override
+            .or_else(|| egui_to_cursor_shape(platform_output.cursor_icon));
+        match shape {
+            Some(shape) => self.handle.send(SurfaceRequestKind::Cursor(Some(shape))),
+            None => self.handle.send(SurfaceRequestKind::Cursor(None)),
+        }
     }
 
+    /// Render in response to input, deferred while a frame gated surface
+    /// waits for its frame callback
     fn render(&mut self) -> PlatformOutput {
+        if self.frame_gated && self.frame_callback_pending {
+            trace!(
+                "Surface {} waiting for frame callback, deferring render",
+                self.wl_surface.id()
+            );
+            self.frames_deferred += 1;
+            return PlatformOutput::default();
+        }
+        self.render_frame()
+    }
+
+    fn render_frame(&mut self) -> PlatformOutput {
+        if !self.configured {
+            trace!(
+                "Surface {} not configured yet, not rendering",
+                self.wl_surface.id()
+            );
+            return PlatformOutput::default();
+        }
+        if self.suspended {
+            trace!("Surface {} suspended, not rendering", self.wl_surface.id());
+            self.frames_deferred += 1;
+            return PlatformOutput::default();
+        }
         trace!("Rendering surface {}", self.wl_surface.id());
-        let surface_texture = self
-            .surface
-            .get_current_texture()
-            .expect("Failed to acquire next surface texture");
+        self.redraw_requested = false;
+        let frame_start = Instant::now();
+        if self.surface_config.is_some() && self.configured_scale != self.physical_scale() {
+            // Render scale degraded or restored
+            self.reconfigure_surface();
+        }
+        // Phase timestamps are taken only when stats are collected
+        let collect = self.stats.is_some();
+        let mark = || collect.then(Instant::now);
+
+        self.announce_size();
+        let reduced_motion = self.renderer.context().style().animation_time <= 0.0;
+        let coasting = self.input_state.tick_kinetic(frame_start, reduced_motion);
+        self.input_state
+            .set_frame_timing(self.pacer.next_present(frame_start), self.pacer.refresh());
+        let raw_input = self.input_state.take_raw_input();
+        let input_received = self.input_state.take_input_received();
+        for (latency, timed_out) in self.input_state.take_clipboard_reads() {
+            if let Some(stats) = &mut self.stats {
+                stats.record_clipboard_read(latency, timed_out);
+            }
+        }
+        self.renderer.begin_frame(raw_input);
+        if self.frames_presented == 0 {
+            // The first pass builds the font atlas
+            startup_timing::mark(Phase::FontsLoaded);
+        }
+        let ctx = self.renderer.context();
+        let decorations = self.decorations.as_ref().filter(|d| d.is_shown());
+        if apply_theme_transition(ctx) {
+            ctx.request_repaint();
+        }
+        if coasting {
+            // Next step of the kinetic scroll
+            ctx.request_repaint();
+        }
+        let mut decoration_action = decorations.and_then(|d| d.titlebar(ctx));
+        {
+            let _ui_call = UiCall::start(self.wl_surface.id().protocol_id());
+            self.egui_app.ui(ctx);
+        }
+        if let Some(arrange) = &self.arrange {
+            arrange.paint(ctx);
+        }
+        if let Some(decorations) = decorations {
+            decoration_action = decoration_action.or_else(|| decorations.resize_borders(ctx));
+        }
+        let ui_end = mark();
+        self.advice
+            .ui_time(&self.wl_surface.id(), frame_start.elapsed());
+
+        let screen_descriptor = egui_wgpu::ScreenDescriptor {
+            size_in_pixels: [
+                self.buffer_size().0.saturating_mul(self.physical_scale()),
+                self.buffer_size().1.saturating_mul(self.physical_scale()),
+            ],
+            pixels_per_point: self.physical_scale() as f32 * self.content_scale(),
+        };
+        let mut full_output = self.renderer.end_frame(screen_descriptor.pixels_per_point);
+        let end_frame_end = mark();
+        let platform_output = std::mem::take(&mut full_output.platform_output);
+        // Partial updates and the font atlas are expected every now and then
+        let allocated = full_output
+            .textures_delta
+            .set
+            .iter()
+            .any(|(id, delta)| *id != egui::TextureId::default() && delta.pos.is_none());
+        self.advice
+            .textures_allocated(&self.wl_surface.id(), allocated);
+
+        if let (Some(action), Some(decorations)) = (decoration_action, &self.decorations) {
+            decorations.apply(action, &self.handle);
+        }
+
+        for command in &platform_output.commands {
+            self.input_state.handle_output_command(command);
+        }
+        for event in &platform_output.events {
+            self.input_state.handle_output_event(event);
+        }
+        let text_focused = platform_output.ime.is_some();
+        if self.input_state.is_text_focused() && !text_focused {
+            self.handle.send(SurfaceRequestKind::ImeDisabled);
+        }
+        self.input_state.set_text_focused(text_focused);
+
+        if let Some(ime) = &platform_output.ime {
+            let rect = self.input_state.content_to_surface(ime.cursor_rect);
+            self.handle.send(SurfaceRequestKind::ImeCursorArea(
+                rect.min.x as i32,
+                rect.min.y as i32,
+                rect.width().ceil() as i32,
+                rect.height().ceil() as i32,
+            ));
+        }
+
+        // Input that changes nothing, e.g. pointer motion over empty space
+        let screen = (
+            screen_descriptor.size_in_pixels,
+            screen_descriptor.pixels_per_point,
+        );
+        if !self.damaged
+            && !self.native_textures
+            && self.custom_draw.is_none()
+            && full_output.textures_delta.is_empty()
+            && self.last_screen == Some(screen)
+            && full_output.shapes == self.last_shapes
+            && !self.transactions.has_pending(&self.wl_surface.id())
+        {
+            trace!("Surface {} unchanged, not presenting", self.wl_surface.id());
+            self.frames_unchanged += 1;
+            if self.input_state.has_pending_events() {
+                self.request_redraw();
+            }
+            return platform_output;
+        }
+
+        // Another fully transparent frame changes nothing on screen, nor
+        // does committing it. Layer surface state and frame tokens need the
+        // commit, a new size a buffer of that size.
+        let transparent = self.background.a() == 0
+            && !self.native_textures
+            && self.custom_draw.is_none()
+            && (self.content_hidden
+                || full_output
+                    .shapes
+                    .iter()
+                    .all(|clipped| is_invisible(&clipped.shape)));
+        if transparent
+            && self.transparent_presented
+            && !self.damaged
+            && full_output.textures_delta.is_empty()
+            && self.last_screen == Some(screen)
+            && !self.transactions.has_pending(&self.wl_surface.id())
+        {
+            trace!(
+                "Surface {} fully transparent, not presenting",
+                self.wl_surface.id()
+            );
+            if let Some(stats) = &mut self.stats {
+                stats.record_elided();
+            }
+            if self.input_state.has_pending_events() {
+                self.request_redraw();
+            }
+            return platform_output;
+        }
+        self.transparent_presented = transparent;
+        if self.content_hidden {
+            // Textures are still uploaded, nothing is drawn with them
+            full_output.shapes.clear();
+        }
+        self.damaged = false;
+        self.last_screen = Some(screen);
+        self.last_shapes = full_output.shapes.clone();
+
+        let draw_start = mark();
+        let surface_texture = match self.surface.get_current_texture() {
+            Ok(surface_texture) => surface_texture,
+            Err(wgpu::SurfaceError::OutOfMemory) => {
+                // Not retried, the next configure or input tries again
+                warn!(
+                    "Surface {} texture unavailable: out of memory",
+                    self.wl_surface.id()
+                );
+                self.frames_skipped += 1;
+                if let Some(stats) = &mut self.stats {
+                    stats.record_present_error(SurfaceError::OutOfMemory.to_string());
+                }
+                self.damaged = true;
+                self.egui_app.surface_error(SurfaceError::OutOfMemory);
+                return platform_output;
+            }
+            Err(err) => {
+                // Skip the frame and try again on the next frame callback
+                warn!(
+                    "Surface {} texture unavailable: {}",
+                    self.wl_surface.id(),
+                    err
+                );
+                self.frames_skipped += 1;
+                if let Some(stats) = &mut self.stats {
+                    stats.record_present_error(err.to_string());
+                }
+                if matches!(
+                    err,
+                    wgpu::SurfaceError::Outdated
+                        | wgpu::SurfaceError::Lost
+                        | wgpu::SurfaceError::Other
+                ) {
+                    self.swapchain_reconnects += 1;
+                    self.renegotiate_format();
+                    self.reconfigure_surface();
+                }
+                self.damaged = true;
+                self.request_redraw();
+                return platform_output;
+            }
+        };
 
         let texture_view = surface_texture
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
+        // Drawn upright offscreen when the buffer is transformed
+        let [width, height] = screen_descriptor.size_in_pixels;
+        let rotated = (self.transform != Transform::Normal).then(|| {
+            self.buffer_transform
+                .get_or_insert_with(|| BufferTransform::new(&self.device, self.output_format))
+                .target(&self.device, self.output_format, width, height)
+        });
+        let target_view = rotated.as_ref().unwrap_or(&texture_view);
         let mut encoder = self.device.create_command_encoder(&Default::default());
         {
             let _ = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("egui clear pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &texture_view,
+                    view: target_view,
                     resolve_target: None,
                     depth_slice: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        load: wgpu::LoadOp::Clear(self.clear_color()),
                         store: wgpu::StoreOp::Store,
                     },
                 })],
                 depth_stencil_attachment: None,
-                timestamp_writes: None,
+                timestamp_writes: self.gpu_timer.as_ref().and_then(GpuTimer::begin_writes),
                 occlusion_query_set: None,
             });
         }
+        if let Some(custom_draw) = &mut self.custom_draw {
+            custom_draw(&mut CustomDrawFrame {
+                device: &self.device,
+                queue: &self.queue,
+                encoder: &mut encoder,
+                view: target_view,
+                format: self.output_format,
+                size_in_pixels: screen_descriptor.size_in_pixels,
+                pixels_per_point: screen_descriptor.pixels_per_point,
+            });
+        }
 
-        let raw_input = self.input_state.take_raw_input();
-        self.renderer.begin_frame(raw_input);
-        self.egui_app.ui(self.renderer.context());
-
-        let screen_descriptor = egui_wgpu::ScreenDescriptor {
-            size_in_pixels: [
-                self.width.saturating_mul(self.physical_scale()),
-                self.height.saturating_mul(self.physical_scale()),
-            ],
-            pixels_per_point: self.physical_scale() as f32,
-        };
-
-        let platform_output = self.renderer.end_frame_and_draw(
+        self.renderer.draw_timed(
             &self.device,
             &self.queue,
             &mut encoder,
-            &texture_view,
+            target_view,
             screen_descriptor,
+            &mut full_output,
+            self.gpu_timer.as_ref().and_then(GpuTimer::end_writes),
         );
+        if let Some(gpu_timer) = &mut self.gpu_timer {
+            gpu_timer.resolve(&mut encoder);
+        }
+        if let (Some(buffer_transform), Some(_)) = (&self.buffer_transform, &rotated) {
+            buffer_transform.copy(&self.queue, &mut encoder, &texture_view, self.transform);
+        }
 
-        for command in &platform_output.commands {
-            self.input_state.handle_output_command(command);
+        // Only request next frame if there are events (similar to windowed.rs
+        // behavior), frame gated surfaces need it to know when to render again.
+        // The request is made before presenting so it's part of the same commit.
+        // Deferred input is delivered on the next frame, frame tokens are
+        // resolved by it without presentation time.
+        let commit = self.transactions.commit(&self.wl_surface.id());
+        if let (None, Some(commit)) = (&self.presentation, commit) {
+            self.callback_commits.push(commit);
+        }
+        let pending = self.frame_gated
+            || !platform_output.events.is_empty()
+            || self.input_state.has_pending_events()
+            || !self.callback_commits.is_empty();
+        if pending && !self.frame_callback_pending {
+            self.strict.request_frame();
+            self.wl_surface
+                .frame(&self.queue_handle, self.wl_surface.clone());
+            self.frame_callback_pending = true;
+        }
+        if coasting {
+            self.arm_frame_fallback();
         }
 
+        let present_start = mark();
+        // Part of the same commit as the presented buffer
+        self.offset.apply(&self.wl_surface);
+        self.apply_buffer_scale();
+        if let Some(presentation) = &self.presentation {
+            request_feedback(presentation, &self.wl_surface, &self.queue_handle, commit);
+        }
         self.queue.submit(Some(encoder.finish()));
+        if let Some(gpu_timer) = &mut self.gpu_timer {
+            gpu_timer.submitted();
+        }
+        self.strict.present();
         surface_texture.present();
-
-        // Only request next frame if there are events (similar to windowed.rs behavior)
-        if !platform_output.events.is_empty() {
-            self.wl_surface
-                .frame(&self.queue_handle, self.wl_surface.clone());
-            self.wl_surface.commit();
+        if self.frames_presented == 0 {
+            startup_timing::mark(Phase::FirstFrame);
+        }
+        self.frames_presented += 1;
+        if let Some(received) = input_received {
+            if self.input_latencies.len() == INPUT_LATENCY_WINDOW {
+                self.input_latencies.pop_front();
+            }
+            self.input_latencies.push_back(received.elapsed());
+        }
+        self.format_renegotiations = 0;
+        // GPU time of an earlier frame, the latest one isn't done yet
+        let gpu_time = self.gpu_timer.as_mut().and_then(GpuTimer::collect);
+        let cost = frame_start.elapsed() + gpu_time.unwrap_or_default();
+        if let Some(event) = self.frame_budget.record_frame(cost) {
+            self.degradation_changed(event);
+        }
+        if let Some(size) = self.thumbnails.due(&self.wl_surface.id(), width, height) {
+            self.capture_thumbnail(size, screen.1, width);
+        }
+        if let Some(stats) = &mut self.stats {
+            // Marks are all taken when stats are collected
+            let at = |mark: Option<Instant>| mark.unwrap_or(frame_start);
+            stats.record_gpu_time(gpu_time);
+            stats.record_frame(FramePhases {
+                ui: at(ui_end) - frame_start,
+                end_frame: at(end_frame_end) - at(ui_end),
+                draw: at(present_start) - at(draw_start),
+                present: at(present_start).elapsed(),
+            });
         }
         platform_output
     }
 
+    fn degradation_changed(&mut self, event: DegradationEvent) {
+        if let Some(stats) = &mut self.stats {
+            stats.record_degradation(event, self.frame_budget.active_steps());
+        }
+        if let DegradationEvent::Activated(DegradationStep::ReduceRenderScale)
+        | DegradationEvent::Restored(DegradationStep::ReduceRenderScale) = event
+        {
+            // Reconfigured at the new scale by the next frame
+            self.request_redraw();
+        }
+    }
+
     fn reconfigure_surface(&mut self) {
-        let width = self.width.saturating_mul(self.physical_scale()).max(1);
-        let height = self.height.saturating_mul(self.physical_scale()).max(1);
+        self.configured_scale = self.physical_scale();
+        let (width, height) = self.buffer_size();
+        let width = width.saturating_mul(self.physical_scale()).max(1);
+        let height = height.saturating_mul(self.physical_scale()).max(1);
+        let (width, height) = if swaps_axes(self.transform) {
+            (height, width)
+        } else {
+            (width, height)
+        };
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
             format: self.output_format,
             width,
             height,
-            present_mode: wgpu::PresentMode::Mailbox,
-            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+            present_mode: self.options.present_mode,
+            alpha_mode: self.alpha_mode(),
             view_formats: vec![self.output_format],
-            desired_maximum_frame_latency: 2,
+            desired_maximum_frame_latency: self.options.desired_latency,
         };
         self.surface.configure(&self.device, &config);
         self.surface_config = Some(config);
+        // New swapchain, its textures have nothing presented yet
+        self.damaged = true;
+    }
+
+    /// Query the capabilities again and select the format from them, the
+    /// renderer is rebuilt if the current format is no longer supported
+    ///
+    /// Formats may change under a running process, e.g. when a driver update
+    /// replaces the ICD, configuring the old one would then fail validation.
+    fn renegotiate_format(&mut self) {
+        if self.format_renegotiations >= MAX_FORMAT_RENEGOTIATIONS {
+            return;
+        }
+        self.format_renegotiations += 1;
+        let caps = self.surface.get_capabilities(&self.adapter);
+        if caps.formats.is_empty() {
+            warn!(
+                "[GPU] Surface {} reports no formats, keeping {:?}",
+                self.wl_surface.id(),
+                self.output_format
+            );
+            return;
+        }
+        let options = SurfaceOptions {
+            preferred_format: self.options.preferred_format.or(Some(self.output_format)),
+            ..self.options
+        };
+        let output_format = options.select_format(&caps.formats);
+        self.options.present_mode = self.options.select_present_mode(&caps.present_modes);
+        self.formats = caps.formats;
+        self.present_modes = caps.present_modes;
+        self.alpha_modes = caps.alpha_modes;
+        if output_format == self.output_format {
+            return;
+        }
+        warn!(
+            "[GPU] Format of surface {} no longer supported, {:?} -> {:?}",
+            self.wl_surface.id(),
+            self.output_format,
+            output_format
+        );
+        if let Some(stats) = &mut self.stats {
+            stats.record_format_change(self.output_format, output_format);
+        }
+        self.output_format = output_format;
+        if self.frames_presented > 0 {
+            self.renderer
+                .rebuild_for_format(&self.device, output_format);
+        } else {
+            self.renderer.set_output_format(&self.device, output_format);
+        }
+        // Its pipeline targets the old format
+        self.buffer_transform = None;
+    }
+
+    /// Translucent background needs an alpha mode that blends with whatever
+    /// is below the surface, premultiplied is preferred as EGUI colors are
+    fn alpha_mode(&self) -> wgpu::CompositeAlphaMode {
+        if let Some(alpha_mode) = self.options.select_alpha_mode(&self.alpha_modes) {
+            return alpha_mode;
+        }
+        if self.background.is_opaque() {
+            return wgpu::CompositeAlphaMode::Auto;
+        }
+        [
+            wgpu::CompositeAlphaMode::PreMultiplied,
+            wgpu::CompositeAlphaMode::PostMultiplied,
+        ]
+        .into_iter()
+        .find(|mode| self.alpha_modes.contains(mode))
+        .unwrap_or(wgpu::CompositeAlphaMode::Auto)
+    }
+
+    fn clear_color(&self) -> wgpu::Color {
+        let rgba = egui::Rgba::from(self.background);
+        wgpu::Color {
+            r: rgba.r() as f64,
+            g: rgba.g() as f64,
+            b: rgba.b() as f64,
+            a: rgba.a() as f64,
+        }
+    }
+
+    fn set_background(&mut self, background: egui::Color32) {
+        self.background = background;
+        self.damaged = true;
+        if self.surface_config.is_some() {
+            self.reconfigure_surface();
+        }
+    }
+
+    /// Options in effect, the present mode, format and alpha mode actually
+    /// selected from the surface capabilities
+    fn surface_options(&self) -> SurfaceOptions {
+        SurfaceOptions {
+            alpha_mode: Some(self.alpha_mode()),
+            preferred_format: Some(self.output_format),
+            ..self.options
+        }
+    }
+
+    fn set_surface_options(&mut self, options: SurfaceOptions) {
+        let present_mode = options.select_present_mode(&self.present_modes);
+        if present_mode != options.present_mode {
+            debug!(
+                "Present mode {:?} not supported on surface {}, using {:?}",
+                options.present_mode,
+                self.wl_surface.id(),
+                present_mode
+            );
+        }
+        self.options = SurfaceOptions {
+            present_mode,
+            ..options
+        };
+        let output_format = options.select_format(&self.formats);
+        if output_format != self.output_format && self.frames_presented > 0 {
+            warn!(
+                "Format of surface {} can't change after the first frame",
+                self.wl_surface.id()
+            );
+        } else if output_format != self.output_format {
+            self.output_format = output_format;
+            self.renderer.set_output_format(&self.device, output_format);
+            // Its pipeline targets the old format
+            self.buffer_transform = None;
+        }
+        if self.surface_config.is_some() {
+            self.reconfigure_surface();
+        }
+    }
+
+    fn set_theme(&mut self, theme: egui::Theme) {
+        self.renderer.context().set_theme(theme);
+    }
+
+    fn register_native_texture(
+        &mut self,
+        texture: &wgpu::TextureView,
+        texture_filter: wgpu::FilterMode,
+    ) -> egui::TextureId {
+        self.native_textures = true;
+        self.renderer
+            .register_native_texture(&self.device, texture, texture_filter)
+    }
+
+    fn request_redraw(&mut self) {
+        self.redraw_requested = true;
+        if !self.configured || self.frame_callback_pending {
+            return;
+        }
+        self.strict.request_frame();
+        self.wl_surface
+            .frame(&self.queue_handle, self.wl_surface.clone());
+        self.frame_callback_pending = true;
+        self.wl_surface.commit();
+    }
+
+    fn metrics(&self) -> SurfaceMetrics {
+        SurfaceMetrics {
+            frames_presented: self.frames_presented,
+            frames_skipped: self.frames_skipped,
+            frames_deferred: self.frames_deferred,
+            frames_unchanged: self.frames_unchanged,
+            degradation_events: self.frame_budget.transition_count(),
+            degradation_steps: self.frame_budget.active_steps().len() as u64,
+            frame_time_p95: self.frame_budget.p95(),
+            gpu_frame_time: self.gpu_timer.as_ref().and_then(GpuTimer::last),
+            debug_name: self.debug_name.clone(),
+            swapchain_reconnects: self.swapchain_reconnects,
+            swapchain_memory_bytes: self.swapchain_memory_bytes(),
+            input_latency_p95: self.input_latency_p95(),
+        }
+    }
+
+    /// Every texture the swapchain may hold, at the configured size and format
+    fn swapchain_memory_bytes(&self) -> u64 {
+        let Some(config) = &self.surface_config else {
+            return 0;
+        };
+        let texel = config.format.block_copy_size(None).unwrap_or(4) as u64;
+        let textures = config.desired_maximum_frame_latency as u64 + 1;
+        config.width as u64 * config.height as u64 * texel * textures
+    }
+
+    fn input_latency_p95(&self) -> Option<Duration> {
+        let mut sorted: Vec<Duration> = self.input_latencies.iter().copied().collect();
+        sorted.sort();
+        let index = ((sorted.len() as f32 * 0.95).ceil() as usize).saturating_sub(1);
+        sorted.get(index).copied()
+    }
+
+    /// Draw the frame again at the thumbnail size, after presenting so the
+    /// frame itself isn't delayed
+    fn capture_thumbnail(&mut self, size: (u32, u32), pixels_per_point: f32, width: u32) {
+        let view = self.thumbnails.target(
+            &self.device,
+            &self.wl_surface.id(),
+            size,
+            self.output_format,
+        );
+        let screen_descriptor = egui_wgpu::ScreenDescriptor {
+            size_in_pixels: [size.0, size.1],
+            pixels_per_point: pixels_per_point * size.0 as f32 / width as f32,
+        };
+        let clear = self.clear_color();
+        self.renderer
+            .redraw_into(&self.device, &self.queue, &view, &screen_descriptor, clear);
+    }
+
+    /// Draw the last frame again into a texture and copy it to the CPU, the
+    /// swapchain isn't touched so this works while unmapped or suspended
+    fn capture_frame(&mut self) -> Result<CapturedFrame, CaptureError> {
+        frame_capture::is_bgra(self.output_format)?;
+        let Some(([width, height], pixels_per_point)) = self.last_screen else {
+            return Err(CaptureError::NothingRendered);
+        };
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("frame capture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.output_format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let screen_descriptor = egui_wgpu::ScreenDescriptor {
+            size_in_pixels: [width, height],
+            pixels_per_point,
+        };
+        let clear = self.clear_color();
+        self.renderer
+            .redraw_into(&self.device, &self.queue, &view, &screen_descriptor, clear);
+        frame_capture::read_back(&self.device, &self.queue, &texture)
+    }
+
+    fn set_render_stats(&mut self, enabled: bool) {
+        if enabled != self.stats.is_some() {
+            self.stats = enabled.then(RenderStatsCollector::default);
+            self.apply_text_hinting();
+        }
+    }
+
+    /// Snap text to pixels as the hinting and the scale ask. The glyph atlas
+    /// doesn't depend on it, only the next frame is drawn again.
+    fn apply_text_hinting(&mut self) {
+        let hinted = match self.text_hinting {
+            TextHinting::Auto => self.scale_factor <= 1,
+            TextHinting::On => true,
+            TextHinting::Off => false,
+        };
+        let ctx = self.renderer.context();
+        if ctx.tessellation_options(|options| options.round_text_to_pixels) != hinted {
+            ctx.tessellation_options_mut(|options| options.round_text_to_pixels = hinted);
+            self.damaged = true;
+        }
+        if let Some(stats) = &mut self.stats {
+            stats.record_text_rendering(hinted, self.subpixel);
+        }
+    }
+
+    fn set_text_hinting(&mut self, text_hinting: TextHinting) {
+        self.text_hinting = text_hinting;
+        self.apply_text_hinting();
+    }
+
+    fn subpixel_changed(&mut self, subpixel: Subpixel) {
+        self.subpixel = Some(subpixel);
+        self.apply_text_hinting();
+    }
+
+    /// Buffer scale rendered at, one lower while the frame budget reduces
+    /// the render scale
+    fn physical_scale(&self) -> u32 {
+        let scale = self.scale_factor.max(1) as u32;
+        if self
+            .frame_budget
+            .is_active(DegradationStep::ReduceRenderScale)
+        {
+            (scale - 1).max(1)
+        } else {
+            scale
+        }
+    }
+
+    /// Set the scale and transform the next buffer is drawn with, part of
+    /// the commit presenting it
+    fn apply_buffer_scale(&mut self) {
+        let (scale, transform) = self.applied_buffer;
+        let buffer_scale = self.configured_scale as i32;
+        if scale != buffer_scale {
+            self.wl_surface.set_buffer_scale(buffer_scale);
+        }
+        if transform != self.transform {
+            self.wl_surface.set_buffer_transform(self.transform);
+        }
+        self.applied_buffer = (buffer_scale, self.transform);
+    }
+
+    /// Scale of the content relative to the surface, below 1.0 only when
+    /// scaling down
+    fn content_scale(&self) -> f32 {
+        self.content_layout().0
+    }
+
+    /// Scale, size in points and origin in points of the content, the
+    /// content is centered when the constraints keep it smaller than the
+    /// surface
+    fn content_layout(&self) -> (f32, egui::Vec2, egui::Pos2) {
+        let (intrinsic_width, intrinsic_height) = self.intrinsic_size;
+        let fit = match self.content_fit {
+            ContentFit::ScaleDown => (self.width as f32 / intrinsic_width.max(1) as f32)
+                .min(self.height as f32 / intrinsic_height.max(1) as f32)
+                .min(1.0),
+            ContentFit::Resize | ContentFit::Clamp => 1.0,
+        };
+        let (width, height) = self.buffer_size();
+        let surface = egui::vec2(width as f32, height as f32);
+        let available = surface / fit;
+        let (content_width, content_height) = self
+            .constraints
+            .content_size((available.x, available.y), self.fill);
+        let scale = fit
+            * (available.x / content_width)
+                .min(available.y / content_height)
+                .min(1.0);
+        let size = egui::vec2(content_width, content_height);
+        let origin = ((surface - size * scale) / 2.0 / scale).max(egui::Vec2::ZERO);
+        (scale, size, origin.to_pos2())
+    }
+
+    /// Logical size of the buffer, larger than the configured size only when
+    /// clamping
+    ///
+    /// Never larger than the device can allocate, the rest of the surface is
+    /// left transparent.
+    fn buffer_size(&self) -> (u32, u32) {
+        buffer_size(
+            (self.width, self.height),
+            self.intrinsic_size,
+            self.content_fit,
+            self.max_buffer_size(),
+        )
+    }
+
+    /// Largest side in points the swapchain can have at the current scale
+    fn max_buffer_size(&self) -> u32 {
+        let max_texture = self.device.limits().max_texture_dimension_2d;
+        (max_texture / self.physical_scale()).max(1)
+    }
+
+    /// Tell the app about size and scale changes before its next `ui`
+    fn announce_size(&mut self) {
+        let (scale, size, _) = self.content_layout();
+        let size = (size.x.round() as u32, size.y.round() as u32);
+        let pixels_per_point = self.physical_scale() as f32 * scale;
+        let (last_size, last_scale) = self.announced.replace((size, pixels_per_point)).unzip();
+        if last_size != Some(size) {
+            self.egui_app.resized(size.0, size.1);
+        }
+        if last_scale != Some(pixels_per_point) {
+            self.egui_app.scale_factor_changed(pixels_per_point);
+        }
+    }
+
+    fn apply_content_fit(&mut self) {
+        let (scale, size, origin) = self.content_layout();
+        self.input_state.set_input_scale(scale);
+        self.input_state
+            .set_screen_size(size.x.round() as u32, size.y.round() as u32);
+        self.input_state.set_screen_origin(origin);
+    }
+
+    fn set_content_fit(&mut self, content_fit: ContentFit) {
+        self.content_fit = content_fit;
+        self.refit();
+    }
+
+    fn set_size_constraints(&mut self, constraints: SizeConstraints) {
+        self.constraints = constraints;
+        self.refit();
+    }
+
+    fn refit(&mut self) {
+        self.apply_content_fit();
+        if self.surface_config.is_some() {
+            self.reconfigure_surface();
+            self.damaged = true;
+            self.render();
+        }
+    }
+}
+
+impl<A: EguiAppData> Drop for EguiSurfaceState<A> {
+    fn drop(&mut self) {
+        self.thumbnails.remove(&self.wl_surface.id());
+        self.transactions.discard_surface(&self.wl_surface.id());
+        // Lock surfaces can't be unmapped, they go away with the lock
+        let unmappable = self
+            .role
+            .as_ref()
+            .is_some_and(|role| !role.is::<SessionLockSurface>());
+        if unmappable && !self.hidden {
+            // Unmapped now, the role object is destroyed with the swapchain
+            self.wl_surface.attach(None, 0, 0);
+            self.wl_surface.commit();
+        }
+        // SAFETY: taken only here, nothing uses the fields after drop
+        let remains = unsafe {
+            (
+                ManuallyDrop::take(&mut self.surface),
+                ManuallyDrop::take(&mut self.renderer),
+            )
+        };
+        self.graveyard.bury(
+            &self.queue,
+            Box::new((remains, self.buffer_transform.take(), self.role.take())),
+        );
+    }
+}
+
+/// Settings and GPU access shared by every EGUI container
+pub trait EguiSurface: Sized {
+    type App: EguiAppData;
+
+    fn surface_state(&self) -> &EguiSurfaceState<Self::App>;
+
+    fn surface_state_mut(&mut self) -> &mut EguiSurfaceState<Self::App>;
+
+    /// Name reported with the metrics, e.g. to tell the panels of a kiosk apart
+    fn with_debug_name(mut self, name: impl Into<String>) -> Self {
+        self.surface_state_mut().debug_name = Some(name.into());
+        self
+    }
+
+    /// Frame budget controller, use it to pin degradation steps as not allowed
+    fn frame_budget_mut(&mut self) -> &mut FrameBudget {
+        &mut self.surface_state_mut().frame_budget
+    }
+
+    /// Offset of the content origin within the surface, applied to pointer
+    /// coordinates before they reach EGUI
+    fn set_input_offset(&mut self, dx: f32, dy: f32) {
+        self.surface_state_mut()
+            .input_state
+            .set_input_offset(dx, dy);
+    }
+
+    /// Register a texture view created on the shared device, the returned id
+    /// can be shown with `egui::Image` without copies
+    fn register_native_texture(
+        &mut self,
+        texture: &wgpu::TextureView,
+        texture_filter: wgpu::FilterMode,
+    ) -> egui::TextureId {
+        self.surface_state_mut()
+            .register_native_texture(texture, texture_filter)
+    }
+
+    /// EGUI theme of this surface, overrides the theme preference of the
+    /// context
+    fn with_theme(mut self, theme: egui::Theme) -> Self {
+        self.surface_state_mut().set_theme(theme);
+        self
+    }
+
+    /// Snapping of text to pixels, by default on outputs of scale 1
+    fn with_text_hinting(mut self, text_hinting: TextHinting) -> Self {
+        self.surface_state_mut().set_text_hinting(text_hinting);
+        self
+    }
+
+    /// Touchpad scrolling keeps going after the fingers lift
+    fn with_kinetic_scrolling(mut self, kinetic: KineticScrolling) -> Self {
+        self.surface_state_mut()
+            .input_state
+            .set_kinetic_scrolling(Some(kinetic));
+        self
+    }
+
+    /// Color the surface is cleared with before EGUI draws, defaults to black.
+    /// Translucent colors make the surface translucent where EGUI doesn't
+    /// paint, e.g. use `egui::Frame::NONE` for the panels.
+    fn with_background(mut self, background: egui::Color32) -> Self {
+        self.surface_state_mut().set_background(background);
+        self
+    }
+
+    /// Draw with WGPU under EGUI, e.g. an animated background. Every frame
+    /// is presented then, animate with `egui::Context::request_repaint`.
+    fn with_custom_draw(mut self, draw: impl FnMut(&mut CustomDrawFrame<'_>) + 'static) -> Self {
+        self.surface_state_mut().custom_draw = Some(Box::new(draw));
+        self
+    }
+
+    /// Device shared by the surfaces, e.g. for the pipelines of a custom draw
+    fn device(&self) -> &wgpu::Device {
+        &self.surface_state().device
+    }
+
+    fn queue(&self) -> &wgpu::Queue {
+        &self.surface_state().queue
+    }
+
+    /// Format of the swapchain, changes if the surface renegotiates it
+    fn texture_format(&self) -> wgpu::TextureFormat {
+        self.surface_state().output_format
+    }
+
+    /// Enable Unicode entry without an input method: Ctrl+Shift+U, hex digits
+    /// and Enter or Space as in GTK, or decimal digits on the numpad while
+    /// Alt is held. Escape cancels. Bypassed while an input method is active.
+    fn with_unicode_entry(mut self, enabled: bool) -> Self {
+        self.surface_state_mut().set_unicode_entry(enabled);
+        self
+    }
+
+    /// Shortcut calling `EguiAppData::emoji_picker_requested`, e.g.
+    /// `Ctrl+.` as in GTK. Off by default.
+    fn with_emoji_picker_shortcut(mut self, shortcut: egui::KeyboardShortcut) -> Self {
+        self.surface_state_mut()
+            .input_state
+            .set_emoji_picker_shortcut(Some(shortcut));
+        self
+    }
+
+    /// Present mode, frame latency, alpha mode and format of the swapchain,
+    /// unsupported choices fall back to what the surface supports
+    fn with_surface_options(mut self, options: SurfaceOptions) -> Self {
+        self.surface_state_mut().set_surface_options(options);
+        self
+    }
+
+    /// Options in effect after validation against the surface capabilities
+    fn surface_options(&self) -> SurfaceOptions {
+        self.surface_state().surface_options()
+    }
+
+    /// Whether the focused widget is kept when keyboard focus leaves
+    fn with_focus_policy(mut self, focus_policy: FocusPolicy) -> Self {
+        self.surface_state_mut().focus_policy = focus_policy;
+        self
+    }
+
+    /// Collect per-phase frame timing, off by default
+    fn with_render_stats(mut self, enabled: bool) -> Self {
+        self.surface_state_mut().set_render_stats(enabled);
+        self
+    }
+
+    /// `None` unless enabled with `with_render_stats`
+    fn render_stats(&self) -> Option<RenderStats> {
+        self.surface_state()
+            .stats
+            .as_ref()
+            .map(|stats| stats.stats())
+    }
+
+    /// Pixels of the last rendered frame at its physical size, blocks until
+    /// the GPU has copied them
+    fn capture_frame(&mut self) -> Result<CapturedFrame, CaptureError> {
+        self.surface_state_mut().capture_frame()
+    }
+}
+
+pub struct EguiWindow<A: EguiAppData> {
+    pub window: Window,
+    surface: EguiSurfaceState<A>,
+    window_state: WindowState,
+    decoration_mode: Option<DecorationMode>,
+    /// Answer of the app to the latest close request
+    close_allowed: bool,
+    /// Maximize or fullscreen change requested and not yet settled
+    size_request: SizeRequest,
+}
+
+impl<A: EguiAppData> EguiWindow<A> {
+    pub fn new(window: Window, egui_app: A, width: u32, height: u32) -> Self {
+        Self::new_with(get_app(), window, egui_app, width, height)
+    }
+
+    /// Create without the global application, e.g. when the process has
+    /// several applications or one that is not created with `get_init_app`
+    pub fn new_with(
+        app: &mut Application,
+        window: Window,
+        egui_app: A,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let mut surface = EguiSurfaceState::new(app, window.wl_surface().clone(), egui_app);
+        surface.width = width;
+        surface.height = height;
+        surface.intrinsic_size = (width, height);
+        let size_request = SizeRequest::default();
+        surface.decorations = Some(ClientDecorations::new(window.clone(), size_request.clone()));
+        surface.role = Some(Box::new(window.clone()));
+        Self {
+            window,
+            surface,
+            window_state: WindowState::empty(),
+            decoration_mode: None,
+            close_allowed: true,
+            size_request,
+        }
+    }
+
+    /// Latest toplevel state from the compositor
+    pub fn window_state(&self) -> WindowState {
+        self.window_state
+    }
+
+    /// Decoration mode negotiated in the latest configure
+    pub fn decoration_mode(&self) -> Option<DecorationMode> {
+        self.decoration_mode
+    }
+
+    pub fn set_maximized(&self, maximized: bool) {
+        self.size_request
+            .request(WindowState::MAXIMIZED, maximized, Instant::now());
+        if maximized {
+            self.window.set_maximized();
+        } else {
+            self.window.unset_maximized();
+        }
+    }
+
+    /// Fullscreen on the output the window is on, see `set_fullscreen_on`
+    pub fn set_fullscreen(&self, fullscreen: bool) {
+        if fullscreen {
+            self.set_fullscreen_on(OutputSelector::Current);
+        } else {
+            self.size_request
+                .request(WindowState::FULLSCREEN, false, Instant::now());
+            self.window.unset_fullscreen();
+        }
+    }
+
+    pub fn set_fullscreen_on(&self, selector: OutputSelector) {
+        self.size_request
+            .request(WindowState::FULLSCREEN, true, Instant::now());
+        let toplevel = self.window.xdg_toplevel().clone();
+        self.surface
+            .handle
+            .send(SurfaceRequestKind::Fullscreen(toplevel, selector));
+    }
+
+    pub fn set_minimized(&self) {
+        self.window.set_minimized();
+    }
+
+    /// Hide by unmapping the surface or show it again, nothing is destroyed
+    pub fn set_visible(&mut self, visible: bool) {
+        if visible {
+            self.surface.show();
+        } else {
+            self.surface.hide();
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        !self.surface.hidden
+    }
+
+    /// Content fit when configured to a size different from the intrinsic
+    /// size given to `new`, with `Resize` the minimum size of the window is
+    /// raised to the intrinsic size
+    pub fn set_content_fit(&mut self, content_fit: ContentFit) {
+        self.surface.set_content_fit(content_fit);
+        self.apply_min_size();
+    }
+
+    /// Keep the content within the sizes and the aspect ratio, the minimum
+    /// and maximum are also given to the compositor
+    ///
+    /// Sizes the compositor configures outside the constraints are kept and
+    /// the content is centered in them. While maximized or fullscreen only
+    /// the minimum applies.
+    pub fn set_size_constraints(
+        &mut self,
+        min: Option<(u32, u32)>,
+        max: Option<(u32, u32)>,
+        aspect: Option<f32>,
+    ) {
+        self.window.set_max_size(max);
+        self.surface
+            .set_size_constraints(SizeConstraints { min, max, aspect });
+        self.apply_min_size();
+    }
+
+    /// Minimum size given to the compositor, from both the content fit and
+    /// the constraints
+    fn apply_min_size(&self) {
+        self.window.set_min_size(min_window_size(
+            self.surface.constraints.min,
+            self.surface.intrinsic_size,
+            self.surface.content_fit,
+        ));
+    }
+
+    /// Toplevels can't be offset, only a zero offset is accepted
+    pub fn set_next_offset(&mut self, dx: i32, dy: i32) -> Result<(), OffsetNotAllowed> {
+        if (dx, dy) != (0, 0) {
+            return Err(OffsetNotAllowed);
+        }
+        Ok(())
+    }
+
+    /// Resize the window with the keyboard, e.g. Alt+F8 and Shift+arrows.
+    /// Toplevels can't be moved by the client, see `arrange_capabilities`.
+    pub fn with_keyboard_arrange(mut self, arrange: KeyboardArrange) -> Self {
+        self.surface.arrange = Some(arrange);
+        self
+    }
+
+    /// Only resizing, and only while the compositor leaves the size to the
+    /// client
+    pub fn arrange_capabilities(&self) -> ArrangeCapabilities {
+        let fixed = WindowState::MAXIMIZED | WindowState::FULLSCREEN | WindowState::TILED;
+        ArrangeCapabilities {
+            can_move: false,
+            can_resize: self.surface.configured && !self.window_state.intersects(fixed),
+        }
+    }
+
+    /// Commit a new size, floating windows keep the size of their buffer
+    fn arrange(&mut self, step: ArrangeStep) {
+        let ArrangeStep::Resize(dw, dh) = step else {
+            return;
+        };
+        let grow = |size: u32, delta: i32| (size as i32).saturating_add(delta).max(1) as u32;
+        let size = self
+            .surface
+            .constraints
+            .clamp((grow(self.surface.width, dw), grow(self.surface.height, dh)));
+        debug!("[INPUT] Window resized with the keyboard to {:?}", size);
+        self.surface.intrinsic_size = size;
+        self.surface.configure(size.0, size.1);
+    }
+
+    fn handle_key_press(&mut self, event: &KeyEvent, repeat: bool) {
+        match self
+            .surface
+            .arrange_key(event, repeat, self.arrange_capabilities())
+        {
+            ArrangeKey::Ignored => self.surface.handle_keyboard_event(event, true, repeat),
+            ArrangeKey::Consumed => {}
+            ArrangeKey::Step(step) => self.arrange(step),
+        }
+    }
+
+    /// Compositor has suspended the window, nothing is rendered until it
+    /// resumes, see `SurfaceMetrics::frames_deferred`
+    pub fn is_suspended(&self) -> bool {
+        self.surface.suspended
+    }
+
+    /// Ask the compositor to focus the window, or mark it urgent when
+    /// focus can't be taken
+    pub fn request_attention(&self) {
+        self.surface
+            .handle
+            .send(SurfaceRequestKind::RequestAttention);
+    }
+
+    /// Title of the window, also shown in the client-side titlebar
+    pub fn set_title(&mut self, title: impl Into<String>) {
+        let title = title.into();
+        self.window.set_title(title.clone());
+        if let Some(decorations) = &mut self.surface.decorations {
+            decorations.title = title;
+        }
+    }
+
+    /// Draw a titlebar with move, resize and close when the compositor
+    /// chooses client-side decorations, on by default. Turn off for apps that
+    /// draw their own chrome, `Application::window_handle` moves and resizes
+    /// the window from it.
+    pub fn with_client_decorations(mut self, enabled: bool) -> Self {
+        if let Some(decorations) = &mut self.surface.decorations {
+            decorations.enabled = enabled;
+        }
+        self
+    }
+}
+
+impl<A: EguiAppData> EguiSurface for EguiWindow<A> {
+    type App = A;
+
+    fn surface_state(&self) -> &EguiSurfaceState<A> {
+        &self.surface
+    }
+
+    fn surface_state_mut(&mut self) -> &mut EguiSurfaceState<A> {
+        &mut self.surface
+    }
+}
+
+impl<A: EguiAppData> CompositorHandlerContainer for EguiWindow<A> {
+    fn scale_factor_changed(&mut self, new_factor: i32) {
+        self.surface.scale_factor_changed(new_factor);
+    }
+
+    fn transform_changed(&mut self, new_transform: &Transform) {
+        self.surface.transform_changed(*new_transform);
+    }
+
+    fn frame(&mut self, time: u32) {
+        self.surface.frame(time);
+    }
+
+    fn refresh_rate_changed(&mut self, refresh_rate: i32) {
+        self.surface.refresh_rate_changed(refresh_rate);
+    }
+
+    fn subpixel_changed(&mut self, subpixel: Subpixel) {
+        self.surface.subpixel_changed(subpixel);
+    }
+
+    fn presented(&mut self, presented: &Presented) {
+        self.surface.pacer.presented(*presented);
+        self.surface.egui_app.presented(presented);
+    }
+
+    fn system_resumed(&mut self, gap: Duration) {
+        self.surface.system_resumed(gap);
+    }
+}
+
+impl<A: EguiAppData> KeyboardHandlerContainer for EguiWindow<A> {
+    fn enter(&mut self) {
+        self.surface.handle_keyboard_enter();
+    }
+
+    fn leave(&mut self) {
+        self.surface.handle_keyboard_leave();
+    }
+
+    fn press_key(&mut self, event: &KeyEvent) {
+        self.handle_key_press(event, false);
+    }
+
+    fn release_key(&mut self, event: &KeyEvent) {
+        self.surface.handle_keyboard_event(event, false, false);
+    }
+
+    fn update_modifiers(&mut self, modifiers: &Modifiers) {
+        self.surface.update_modifiers(modifiers);
+    }
+
+    fn repeat_key(&mut self, event: &KeyEvent) {
+        self.handle_key_press(event, true);
+    }
+
+    fn repeat_info_changed(&mut self, info: RepeatInfo) {
+        self.surface.egui_app.repeat_info_changed(info);
+        self.surface.request_redraw();
+    }
+
+    fn preedit(&mut self, text: Option<&str>, _cursor: Option<(i32, i32)>) {
+        self.surface.handle_preedit(text);
+    }
+
+    fn commit_text(&mut self, text: &str) {
+        self.surface.handle_commit_text(text);
+    }
+
+    fn delete_surrounding_text(&mut self, before_length: u32, after_length: u32) {
+        self.surface
+            .handle_delete_surrounding_text(before_length, after_length);
+    }
+
+    fn insert_text(&mut self, text: &str) {
+        self.surface.handle_insert_text(text);
+    }
+
+    fn text_input_focus(&mut self, focused: bool) {
+        self.surface.input_state.set_text_input_active(focused);
+    }
+
+    fn layout_changed(&mut self, layout_name: String) {
+        self.surface.egui_app.keyboard_layout_changed(&layout_name);
+        self.surface.request_redraw();
+    }
+}
+
+impl<A: EguiAppData> PointerHandlerContainer for EguiWindow<A> {
+    fn pointer_frame(&mut self, events: &[PointerEvent]) {
+        self.surface.handle_pointer_events(events);
+    }
+}
+
+impl<A: EguiAppData> DndHandlerContainer for EguiWindow<A> {
+    fn dnd_enter(&mut self, x: f64, y: f64, mime_types: &[String]) {
+        self.surface.handle_dnd_enter(x, y, mime_types);
+    }
+
+    fn dnd_motion(&mut self, x: f64, y: f64) {
+        self.surface.handle_dnd_motion(x, y);
+    }
+
+    fn dnd_leave(&mut self) {
+        self.surface.handle_dnd_leave();
+    }
+
+    fn dnd_drop(&mut self, x: f64, y: f64, data: &DroppedData) {
+        self.surface.handle_dnd_drop(x, y, data);
+    }
+}
+
+impl<A: EguiAppData> BaseTrait for EguiWindow<A> {
+    fn get_object_id(&self) -> wayland_backend::client::ObjectId {
+        self.window.wl_surface().id()
+    }
+
+    fn metrics(&self) -> SurfaceMetrics {
+        self.surface.metrics()
+    }
+
+    fn request_redraw(&mut self) {
+        self.surface.request_redraw();
+    }
+
+    fn present_at(&mut self, target: Instant) {
+        self.surface.present_at(target);
+    }
+
+    fn set_cursor_override(&mut self, shape: Option<Shape>) {
+        self.surface.set_cursor_override(shape);
+    }
+
+    fn flush_input(&mut self) {
+        self.surface.flush_input();
+    }
+}
+
+impl<A: EguiAppData> WindowContainer for EguiWindow<A> {
+    fn configure(&mut self, configure: &WindowConfigure) {
+        if !self.surface.accepts_configure() {
+            debug!(
+                "Surface {} configure {:?} of an earlier mapping ignored",
+                self.surface.wl_surface.id(),
+                configure.new_size
+            );
+            return;
+        }
+        if self
+            .size_request
+            .is_straggler(configure.state, Instant::now())
+        {
+            debug!(
+                "Surface {} resize configure {:?} after a requested state change, not rendered",
+                self.surface.wl_surface.id(),
+                configure.new_size
+            );
+            return;
+        }
+        // Zero leaves the size to the surface
+        let width = configure.new_size.0.map_or(0, |size| size.get());
+        let height = configure.new_size.1.map_or(0, |size| size.get());
+        self.surface.fill = configure
+            .state
+            .intersects(WindowState::MAXIMIZED | WindowState::FULLSCREEN);
+
+        self.decoration_mode = Some(configure.decoration_mode);
+        if let Some(decorations) = &mut self.surface.decorations {
+            decorations.client_side = configure.decoration_mode == DecorationMode::Client;
+            decorations.maximized = configure.state.contains(WindowState::MAXIMIZED);
+        }
+        if self.window_state != configure.state {
+            self.window_state = configure.state;
+            self.surface.input_state.set_window_state(
+                configure.state.contains(WindowState::MAXIMIZED),
+                configure.state.contains(WindowState::FULLSCREEN),
+                configure.state.contains(WindowState::ACTIVATED),
+            );
+            self.surface.egui_app.window_state_changed(configure.state);
+        }
+        self.surface
+            .set_suspended(configure.state.contains(WindowState::SUSPENDED));
+        self.surface.configure(width, height);
+    }
+
+    fn allowed_to_close(&self) -> bool {
+        self.close_allowed
+    }
+
+    /// Drawn again so the app can show why the window stays open
+    fn request_close(&mut self) {
+        self.close_allowed = self.surface.egui_app.close_requested();
+        if !self.close_allowed {
+            self.surface.request_redraw();
+        }
+    }
+}
+
+pub struct EguiLayerSurface<A: EguiAppData> {
+    pub layer_surface: LayerSurface,
+    surface: EguiSurfaceState<A>,
+    /// Last settings set on the layer surface, known when created from a
+    /// builder or set with `with_layer_settings`
+    settings: Option<LayerSurfaceSettings>,
+    /// Changed settings that break a rule fail instead of being corrected,
+    /// as set on the builder
+    strict: bool,
+    dock: Option<Dock>,
+}
+
+impl<A: EguiAppData> EguiLayerSurface<A> {
+    pub fn new(layer_surface: LayerSurface, egui_app: A, width: u32, height: u32) -> Self {
+        Self::new_with(get_app(), layer_surface, egui_app, width, height)
+    }
+
+    /// Create without the global application, e.g. when the process has
+    /// several applications or one that is not created with `get_init_app`
+    pub fn new_with(
+        app: &mut Application,
+        layer_surface: LayerSurface,
+        egui_app: A,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let mut surface = EguiSurfaceState::new(app, layer_surface.wl_surface().clone(), egui_app);
+        surface.width = width;
+        surface.height = height;
+        surface.intrinsic_size = (width, height);
+        surface.strict = StrictSurface::new(SurfaceRole::LayerSurface);
+        surface.frame_gated = true;
+        surface.role = Some(Box::new(layer_surface.clone()));
+        Self {
+            layer_surface,
+            surface,
+            settings: None,
+            strict: false,
+            dock: None,
+        }
+    }
+
+    /// Create the layer surface of the builder, zero sized axes get an
+    /// intrinsic size of 256 until configured
+    pub fn from_builder(
+        builder: &LayerSurfaceBuilder,
+        egui_app: A,
+    ) -> Result<Self, InvalidLayerSurface> {
+        Self::from_builder_with(get_app(), builder, egui_app)
+    }
+
+    /// `from_builder` without the global application
+    pub fn from_builder_with(
+        app: &mut Application,
+        builder: &LayerSurfaceBuilder,
+        egui_app: A,
+    ) -> Result<Self, InvalidLayerSurface> {
+        let layer_surface = builder.build(app)?;
+        let settings = builder.settings();
+        let or_default = |size: u32| if size == 0 { 256 } else { size };
+        let mut layer = Self::new_with(
+            app,
+            layer_surface,
+            egui_app,
+            or_default(settings.width),
+            or_default(settings.height),
+        )
+        .with_layer_settings(settings.corrected());
+        layer.strict = builder.is_strict();
+        Ok(layer)
+    }
+
+    /// Settings the layer surface was created with, needed for moving and
+    /// resizing with the keyboard when not created from a builder
+    pub fn with_layer_settings(mut self, settings: LayerSurfaceSettings) -> Self {
+        self.settings = Some(settings);
+        self
+    }
+
+    /// Change the reserved space, e.g. when a bar grows, committed right away
+    ///
+    /// With known settings the zone is validated like the builder does: in
+    /// strict mode a zone the anchors can't have fails and nothing changes,
+    /// otherwise it's corrected.
+    pub fn set_exclusive_zone(&mut self, exclusive_zone: i32) -> Result<(), InvalidLayerSurface> {
+        let Some(settings) = self.settings else {
+            self.layer_surface.set_exclusive_zone(exclusive_zone);
+            self.layer_surface.commit();
+            return Ok(());
+        };
+        let settings = LayerSurfaceSettings {
+            exclusive_zone,
+            ..settings
+        }
+        .validate(self.strict)?;
+        self.layer_surface
+            .set_exclusive_zone(settings.exclusive_zone);
+        self.layer_surface.commit();
+        self.settings = Some(settings);
+        Ok(())
+    }
+
+    /// Move by the margins and resize with the keyboard, e.g. Alt+F8, arrows
+    /// and Shift+arrows
+    pub fn with_keyboard_arrange(mut self, arrange: KeyboardArrange) -> Self {
+        self.surface.arrange = Some(arrange);
+        self
+    }
+
+    /// Auto-hide along the exclusive edge, expanding while the pointer is
+    /// over it. Needs known settings with a size across the edge, which is
+    /// the expanded size. Slide the content with `DockHandle::progress`.
+    pub fn with_dock(mut self, app: &Application, mode: DockMode, handle: DockHandle) -> Self {
+        let compositor = &app.compositor_state;
+        self.dock = self.settings.as_ref().and_then(|settings| {
+            Dock::new(compositor, &self.layer_surface, settings, mode, handle)
+        });
+        if self.dock.is_none() {
+            warn!("[LAYER] Dock needs known settings with an exclusive edge and its size");
+        }
+        self
+    }
+
+    /// Advance the dock before the frame, the changed layer surface state is
+    /// committed with the rendered buffer. `true` if the state changed.
+    fn advance_dock(&mut self) -> bool {
+        let Some(dock) = &mut self.dock else {
+            return false;
+        };
+        let step = dock.advance(&self.layer_surface, Instant::now());
+        self.surface.content_hidden = dock.is_hidden();
+        if step.render {
+            self.surface.damaged = true;
+            self.surface.request_redraw();
+        }
+        if let Some(delay) = step.wake_after {
+            self.surface
+                .handle
+                .send(SurfaceRequestKind::DockTimer { delay });
+        }
+        step.render
+    }
+
+    fn arm_dock_fallback(&mut self) {
+        if self.dock.as_ref().is_some_and(Dock::is_animating) {
+            self.surface.arm_frame_fallback();
+        }
+    }
+
+    /// Moving needs an anchor on one edge of an axis, resizing a size that
+    /// isn't stretched between two edges. Neither without known settings.
+    pub fn arrange_capabilities(&self) -> ArrangeCapabilities {
+        let Some(settings) = &self.settings else {
+            return ArrangeCapabilities::default();
+        };
+        ArrangeCapabilities {
+            can_move: settings.can_move(),
+            can_resize: settings.width != 0 || settings.height != 0,
+        }
+    }
+
+    /// Set and commit the new margins or size, the compositor configures
+    /// the new size
+    fn arrange(&mut self, step: ArrangeStep) {
+        let Some(settings) = self.settings else {
+            return;
+        };
+        let settings = match step {
+            ArrangeStep::Move(dx, dy) => settings.moved(dx, dy),
+            ArrangeStep::Resize(dw, dh) => settings.resized(dw, dh),
+        };
+        match settings.apply(&self.layer_surface, false) {
+            Ok(settings) => {
+                debug!(
+                    "[LAYER] Arranged with the keyboard, size {}x{}, margin {:?}",
+                    settings.width, settings.height, settings.margin
+                );
+                self.settings = Some(settings);
+                self.layer_surface.commit();
+            }
+            Err(err) => warn!("[LAYER] Can't arrange layer surface: {}", err),
+        }
+    }
+
+    fn handle_key_press(&mut self, event: &KeyEvent, repeat: bool) {
+        match self
+            .surface
+            .arrange_key(event, repeat, self.arrange_capabilities())
+        {
+            ArrangeKey::Ignored => self.surface.handle_keyboard_event(event, true, repeat),
+            ArrangeKey::Consumed => {}
+            ArrangeKey::Step(step) => self.arrange(step),
+        }
+    }
+
+    /// Hide by unmapping the surface or show it again, nothing is destroyed
+    pub fn set_visible(&mut self, visible: bool) {
+        if visible {
+            self.surface.show();
+        } else {
+            self.surface.hide();
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        !self.surface.hidden
+    }
+
+    /// Content fit when configured to a size different from the intrinsic
+    /// size given to `new`
+    pub fn set_content_fit(&mut self, content_fit: ContentFit) {
+        self.surface.set_content_fit(content_fit);
+    }
+
+    /// Move the content by the offset on the next presented frame, fails on
+    /// compositors without `wl_surface.offset`
+    pub fn set_next_offset(&mut self, dx: i32, dy: i32) -> Result<(), OffsetNotAllowed> {
+        self.surface
+            .offset
+            .set_next(&self.surface.wl_surface, dx, dy)?;
+        self.surface.damaged = true;
+        Ok(())
+    }
+}
+
+impl<A: EguiAppData> EguiSurface for EguiLayerSurface<A> {
+    type App = A;
+
+    fn surface_state(&self) -> &EguiSurfaceState<A> {
+        &self.surface
+    }
+
+    fn surface_state_mut(&mut self) -> &mut EguiSurfaceState<A> {
+        &mut self.surface
+    }
+}
+
+impl<A: EguiAppData> CompositorHandlerContainer for EguiLayerSurface<A> {
+    fn scale_factor_changed(&mut self, new_factor: i32) {
+        self.surface.scale_factor_changed(new_factor);
+    }
+
+    fn transform_changed(&mut self, new_transform: &Transform) {
+        self.surface.transform_changed(*new_transform);
+    }
+
+    fn frame(&mut self, time: u32) {
+        self.advance_dock();
+        self.surface.frame(time);
+        self.arm_dock_fallback();
+    }
+
+    /// E.g. covered on the bottom layer, the dock still reaches its end.
+    /// The zone and input region are committed without a buffer.
+    fn frame_fallback(&mut self) {
+        if !self.surface.frame_fallback() {
+            return;
+        }
+        if self.advance_dock() {
+            self.layer_surface.commit();
+        }
+        self.arm_dock_fallback();
+    }
+
+    fn refresh_rate_changed(&mut self, refresh_rate: i32) {
+        self.surface.refresh_rate_changed(refresh_rate);
+    }
+
+    fn subpixel_changed(&mut self, subpixel: Subpixel) {
+        self.surface.subpixel_changed(subpixel);
+    }
+
+    fn presented(&mut self, presented: &Presented) {
+        self.surface.pacer.presented(*presented);
+        self.surface.egui_app.presented(presented);
+    }
+
+    fn system_resumed(&mut self, gap: Duration) {
+        self.surface.system_resumed(gap);
+    }
+}
+
+impl<A: EguiAppData> KeyboardHandlerContainer for EguiLayerSurface<A> {
+    fn enter(&mut self) {
+        self.surface.handle_keyboard_enter();
+    }
+
+    fn leave(&mut self) {
+        self.surface.handle_keyboard_leave();
+    }
+
+    fn press_key(&mut self, event: &KeyEvent) {
+        self.handle_key_press(event, false);
+    }
+
+    fn release_key(&mut self, event: &KeyEvent) {
+        self.surface.handle_keyboard_event(event, false, false);
+    }
+
+    fn update_modifiers(&mut self, modifiers: &Modifiers) {
+        self.surface.update_modifiers(modifiers);
+    }
+
+    fn repeat_key(&mut self, event: &KeyEvent) {
+        self.handle_key_press(event, true);
+    }
+
+    fn repeat_info_changed(&mut self, info: RepeatInfo) {
+        self.surface.egui_app.repeat_info_changed(info);
+        self.surface.request_redraw();
+    }
+
+    fn preedit(&mut self, text: Option<&str>, _cursor: Option<(i32, i32)>) {
+        self.surface.handle_preedit(text);
+    }
+
+    fn commit_text(&mut self, text: &str) {
+        self.surface.handle_commit_text(text);
+    }
+
+    fn delete_surrounding_text(&mut self, before_length: u32, after_length: u32) {
+        self.surface
+            .handle_delete_surrounding_text(before_length, after_length);
+    }
+
+    fn insert_text(&mut self, text: &str) {
+        self.surface.handle_insert_text(text);
+    }
+
+    fn text_input_focus(&mut self, focused: bool) {
+        self.surface.input_state.set_text_input_active(focused);
+    }
+
+    fn layout_changed(&mut self, layout_name: String) {
+        self.surface.egui_app.keyboard_layout_changed(&layout_name);
+        self.surface.request_redraw();
+    }
+}
+
+impl<A: EguiAppData> PointerHandlerContainer for EguiLayerSurface<A> {
+    fn pointer_frame(&mut self, events: &[PointerEvent]) {
+        if let Some(dock) = &mut self.dock {
+            for event in events {
+                match event.kind {
+                    PointerEventKind::Enter { .. } => dock.pointer_entered(),
+                    PointerEventKind::Leave { .. } => dock.pointer_left(Instant::now()),
+                    _ => continue,
+                }
+                self.surface.request_redraw();
+            }
+        }
+        self.surface.handle_pointer_events(events);
+    }
+}
+
+impl<A: EguiAppData> DndHandlerContainer for EguiLayerSurface<A> {
+    fn dnd_enter(&mut self, x: f64, y: f64, mime_types: &[String]) {
+        self.surface.handle_dnd_enter(x, y, mime_types);
+    }
+
+    fn dnd_motion(&mut self, x: f64, y: f64) {
+        self.surface.handle_dnd_motion(x, y);
+    }
+
+    fn dnd_leave(&mut self) {
+        self.surface.handle_dnd_leave();
+    }
+
+    fn dnd_drop(&mut self, x: f64, y: f64, data: &DroppedData) {
+        self.surface.handle_dnd_drop(x, y, data);
+    }
+}
+
+impl<A: EguiAppData> BaseTrait for EguiLayerSurface<A> {
+    fn get_object_id(&self) -> wayland_backend::client::ObjectId {
+        self.layer_surface.wl_surface().id()
+    }
+
+    fn metrics(&self) -> SurfaceMetrics {
+        self.surface.metrics()
+    }
+
+    fn request_redraw(&mut self) {
+        self.surface.request_redraw();
+    }
+
+    fn present_at(&mut self, target: Instant) {
+        self.surface.present_at(target);
+    }
+
+    fn set_cursor_override(&mut self, shape: Option<Shape>) {
+        self.surface.set_cursor_override(shape);
+    }
+
+    fn flush_input(&mut self) {
+        self.surface.flush_input();
+    }
+}
+
+impl<A: EguiAppData> LayerSurfaceContainer for EguiLayerSurface<A> {
+    fn configure(&mut self, config: &LayerSurfaceConfigure) {
+        self.surface.configure(config.new_size.0, config.new_size.1);
+    }
+
+    /// Advanced right away, waiting for a frame callback would keep a
+    /// covered dock from collapsing or pinning
+    fn dock_timer(&mut self) {
+        if self.advance_dock() && self.surface.frame_callback_pending {
+            // The redraw waits for the pending callback, the zone and input
+            // region don't
+            self.layer_surface.commit();
+        }
+        self.arm_dock_fallback();
+    }
+}
+
+pub struct EguiPopup<A: EguiAppData> {
+    pub popup: Popup,
+    surface: EguiSurfaceState<A>,
+}
+
+impl<A: EguiAppData> EguiPopup<A> {
+    pub fn new(popup: Popup, egui_app: A, width: u32, height: u32) -> Self {
+        Self::new_with(get_app(), popup, egui_app, width, height)
+    }
+
+    /// Create without the global application, e.g. when the process has
+    /// several applications or one that is not created with `get_init_app`
+    pub fn new_with(
+        app: &mut Application,
+        popup: Popup,
+        egui_app: A,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let mut surface = EguiSurfaceState::new(app, popup.wl_surface().clone(), egui_app);
+        surface.width = width;
+        surface.height = height;
+        surface.intrinsic_size = (width, height);
+        surface.strict = StrictSurface::new(SurfaceRole::Popup);
+        surface.role = Some(Box::new(popup.clone()));
+        Self { popup, surface }
+    }
+
+    /// Move the content by the offset on the next presented frame, fails on
+    /// compositors without `wl_surface.offset`
+    pub fn set_next_offset(&mut self, dx: i32, dy: i32) -> Result<(), OffsetNotAllowed> {
+        self.surface
+            .offset
+            .set_next(&self.surface.wl_surface, dx, dy)?;
+        self.surface.damaged = true;
+        Ok(())
+    }
+}
+
+impl<A: EguiAppData> EguiSurface for EguiPopup<A> {
+    type App = A;
+
+    fn surface_state(&self) -> &EguiSurfaceState<A> {
+        &self.surface
+    }
+
+    fn surface_state_mut(&mut self) -> &mut EguiSurfaceState<A> {
+        &mut self.surface
+    }
+}
+
+impl<A: EguiAppData> CompositorHandlerContainer for EguiPopup<A> {
+    fn scale_factor_changed(&mut self, new_factor: i32) {
+        self.surface.scale_factor_changed(new_factor);
+    }
+
+    fn transform_changed(&mut self, new_transform: &Transform) {
+        self.surface.transform_changed(*new_transform);
+    }
+
+    fn frame(&mut self, time: u32) {
+        self.surface.frame(time);
+    }
+
+    fn refresh_rate_changed(&mut self, refresh_rate: i32) {
+        self.surface.refresh_rate_changed(refresh_rate);
+    }
+
+    fn subpixel_changed(&mut self, subpixel: Subpixel) {
+        self.surface.subpixel_changed(subpixel);
+    }
+
+    fn presented(&mut self, presented: &Presented) {
+        self.surface.pacer.presented(*presented);
+        self.surface.egui_app.presented(presented);
+    }
+
+    fn system_resumed(&mut self, gap: Duration) {
+        self.surface.system_resumed(gap);
+    }
+}
+
+impl<A: EguiAppData> KeyboardHandlerContainer for EguiPopup<A> {
+    fn enter(&mut self) {
+        self.surface.handle_keyboard_enter();
+    }
+
+    fn leave(&mut self) {
+        self.surface.handle_keyboard_leave();
+    }
+
+    fn press_key(&mut self, event: &KeyEvent) {
+        self.surface.handle_keyboard_event(event, true, false);
+    }
+
+    fn release_key(&mut self, event: &KeyEvent) {
+        self.surface.handle_keyboard_event(event, false, false);
+    }
+
+    fn update_modifiers(&mut self, modifiers: &Modifiers) {
+        self.surface.update_modifiers(modifiers);
+    }
+
+    fn repeat_key(&mut self, event: &KeyEvent) {
+        self.surface.handle_keyboard_event(event, true, true);
+    }
+
+    fn repeat_info_changed(&mut self, info: RepeatInfo) {
+        self.surface.egui_app.repeat_info_changed(info);
+        self.surface.request_redraw();
+    }
+
+    fn preedit(&mut self, text: Option<&str>, _cursor: Option<(i32, i32)>) {
+        self.surface.handle_preedit(text);
+    }
+
+    fn commit_text(&mut self, text: &str) {
+        self.surface.handle_commit_text(text);
+    }
+
+    fn delete_surrounding_text(&mut self, before_length: u32, after_length: u32) {
+        self.surface
+            .handle_delete_surrounding_text(before_length, after_length);
+    }
+
+    fn insert_text(&mut self, text: &str) {
+        self.surface.handle_insert_text(text);
+    }
+
+    fn text_input_focus(&mut self, focused: bool) {
+        self.surface.input_state.set_text_input_active(focused);
+    }
+
+    fn layout_changed(&mut self, layout_name: String) {
+        self.surface.egui_app.keyboard_layout_changed(&layout_name);
+        self.surface.request_redraw();
+    }
+}
+
+impl<A: EguiAppData> PointerHandlerContainer for EguiPopup<A> {
+    fn pointer_frame(&mut self, events: &[PointerEvent]) {
+        self.surface.handle_pointer_events(events);
+    }
+}
+
+impl<A: EguiAppData> DndHandlerContainer for EguiPopup<A> {
+    fn dnd_enter(&mut self, x: f64, y: f64, mime_types: &[String]) {
+        self.surface.handle_dnd_enter(x, y, mime_types);
+    }
+
+    fn dnd_motion(&mut self, x: f64, y: f64) {
+        self.surface.handle_dnd_motion(x, y);
+    }
+
+    fn dnd_leave(&mut self) {
+        self.surface.handle_dnd_leave();
+    }
+
+    fn dnd_drop(&mut self, x: f64, y: f64, data: &DroppedData) {
+        self.surface.handle_dnd_drop(x, y, data);
+    }
+}
+
+impl<A: EguiAppData> BaseTrait for EguiPopup<A> {
+    fn get_object_id(&self) -> wayland_backend::client::ObjectId {
+        self.popup.wl_surface().id()
+    }
+
+    fn metrics(&self) -> SurfaceMetrics {
+        self.surface.metrics()
+    }
+
+    fn request_redraw(&mut self) {
+        self.surface.request_redraw();
+    }
+
+    fn present_at(&mut self, target: Instant) {
+        self.surface.present_at(target);
+    }
+
+    fn set_cursor_override(&mut self, shape: Option<Shape>) {
+        self.surface.set_cursor_override(shape);
+    }
+
+    fn flush_input(&mut self) {
+        self.surface.flush_input();
+    }
+}
+
+impl<A: EguiAppData> PopupContainer for EguiPopup<A> {
+    fn configure(&mut self, config: &PopupConfigure) {
+        self.surface
+            .configure(config.width as u32, config.height as u32);
+    }
+
+    fn done(&mut self) {}
+}
+
+pub struct EguiSubsurface<A: EguiAppData> {
+    pub wl_surface: WlSurface,
+    surface: EguiSurfaceState<A>,
+    /// Role object and parent, when created with `create`
+    subsurface: Option<(WlSubsurface, WlSurface)>,
+}
+
+impl<A: EguiAppData> EguiSubsurface<A> {
+    /// The wl_surface is kept by the subsurface, don't destroy it
+    pub fn new(wl_surface: WlSurface, egui_app: A, width: u32, height: u32) -> Self {
+        Self::new_with(get_app(), wl_surface, egui_app, width, height)
+    }
+
+    /// Create without the global application, e.g. when the process has
+    /// several applications or one that is not created with `get_init_app`
+    pub fn new_with(
+        app: &mut Application,
+        wl_surface: WlSurface,
+        egui_app: A,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let mut surface = EguiSurfaceState::new(app, wl_surface.clone(), egui_app);
+        surface.width = width;
+        surface.height = height;
+        surface.intrinsic_size = (width, height);
+        surface.strict = StrictSurface::new(SurfaceRole::Subsurface);
+        Self {
+            wl_surface,
+            surface,
+            subsurface: None,
+        }
+    }
+
+    /// Create a subsurface of the parent at the position in parent surface
+    /// coordinates, subsurfaces have no configure so rendering starts
+    /// immediately
+    pub fn create(
+        parent: &WlSurface,
+        x: i32,
+        y: i32,
+        egui_app: A,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        Self::create_with(get_app(), parent, x, y, egui_app, width, height)
     }
 
-    fn physical_scale(&self) -> u32 {
-        self.scale_factor.max(1) as u32
+    /// `create` without the global application
+    pub fn create_with(
+        app: &mut Application,
+        parent: &WlSurface,
+        x: i32,
+        y: i32,
+        egui_app: A,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let (subsurface, wl_surface) = app.create_subsurface(parent, x, y);
+        let mut this = Self::new_with(app, wl_surface, egui_app, width, height);
+        this.subsurface = Some((subsurface, parent.clone()));
+        this.configure(width, height);
+        this
     }
-}
 
-pub struct EguiWindow<A: EguiAppData> {
-    pub window: Window,
-    surface: EguiSurfaceState<A>,
+    /// Move the subsurface, position is applied on the next commit of the
+    /// parent so the parent is committed too
+    pub fn set_position(&self, x: i32, y: i32) {
+        if let Some((subsurface, parent)) = &self.subsurface {
+            subsurface.set_position(x, y);
+            parent.commit();
+        }
+    }
+
+    /// Move the content by the offset on the next presented frame, fails on
+    /// compositors without `wl_surface.offset`
+    pub fn set_next_offset(&mut self, dx: i32, dy: i32) -> Result<(), OffsetNotAllowed> {
+        self.surface
+            .offset
+            .set_next(&self.surface.wl_surface, dx, dy)?;
+        self.surface.damaged = true;
+        Ok(())
+    }
 }
 
-impl<A: EguiAppData> EguiWindow<A> {
-    pub fn new(window: Window, egui_app: A, width: u32, height: u32) -> Self {
-        let mut surface = EguiSurfaceState::new(window.wl_surface().clone(), egui_app);
-        surface.width = width;
-        surface.height = height;
-        Self { window, surface }
+impl<A: EguiAppData> EguiSurface for EguiSubsurface<A> {
+    type App = A;
+
+    fn surface_state(&self) -> &EguiSurfaceState<A> {
+        &self.surface
+    }
+
+    fn surface_state_mut(&mut self) -> &mut EguiSurfaceState<A> {
+        &mut self.surface
     }
 }
 
-impl<A: EguiAppData> CompositorHandlerContainer for EguiWindow<A> {
+impl<A: EguiAppData> CompositorHandlerContainer for EguiSubsurface<A> {
     fn scale_factor_changed(&mut self, new_factor: i32) {
         self.surface.scale_factor_changed(new_factor);
     }
 
+    fn transform_changed(&mut self, new_transform: &Transform) {
+        self.surface.transform_changed(*new_transform);
+    }
+
     fn frame(&mut self, time: u32) {
         self.surface.frame(time);
     }
+
+    fn refresh_rate_changed(&mut self, refresh_rate: i32) {
+        self.surface.refresh_rate_changed(refresh_rate);
+    }
+
+    fn subpixel_changed(&mut self, subpixel: Subpixel) {
+        self.surface.subpixel_changed(subpixel);
+    }
+
+    fn presented(&mut self, presented: &Presented) {
+        self.surface.pacer.presented(*presented);
+        self.surface.egui_app.presented(presented);
+    }
+
+    fn system_resumed(&mut self, gap: Duration) {
+        self.surface.system_resumed(gap);
+    }
 }
 
-impl<A: EguiAppData> KeyboardHandlerContainer for EguiWindow<A> {
+impl<A: EguiAppData> KeyboardHandlerContainer for EguiSubsurface<A> {
     fn enter(&mut self) {
         self.surface.handle_keyboard_enter();
     }
@@ -309,130 +3138,190 @@ impl<A: EguiAppData> KeyboardHandlerContainer for EguiWindow<A> {
     fn repeat_key(&mut self, event: &KeyEvent) {
         self.surface.handle_keyboard_event(event, true, true);
     }
-}
 
-impl<A: EguiAppData> PointerHandlerContainer for EguiWindow<A> {
-    fn pointer_frame(&mut self, event: &PointerEvent) {
-        self.surface.handle_pointer_event(event);
+    fn repeat_info_changed(&mut self, info: RepeatInfo) {
+        self.surface.egui_app.repeat_info_changed(info);
+        self.surface.request_redraw();
     }
-}
 
-impl<A: EguiAppData> BaseTrait for EguiWindow<A> {
-    fn get_object_id(&self) -> wayland_backend::client::ObjectId {
-        self.window.wl_surface().id()
+    fn preedit(&mut self, text: Option<&str>, _cursor: Option<(i32, i32)>) {
+        self.surface.handle_preedit(text);
     }
-}
 
-impl<A: EguiAppData> WindowContainer for EguiWindow<A> {
-    fn configure(&mut self, configure: &WindowConfigure) {
-        let width = configure.new_size.0.map_or(256, |size| size.get());
-        let height = configure.new_size.1.map_or(256, |size| size.get());
-        self.window
-            .wl_surface()
-            .set_buffer_scale(self.surface.scale_factor);
-        self.surface.configure(width, height);
+    fn commit_text(&mut self, text: &str) {
+        self.surface.handle_commit_text(text);
     }
-}
 
-pub struct EguiLayerSurface<A: EguiAppData> {
-    pub layer_surface: LayerSurface,
-    surface: EguiSurfaceState<A>,
+    fn delete_surrounding_text(&mut self, before_length: u32, after_length: u32) {
+        self.surface
+            .handle_delete_surrounding_text(before_length, after_length);
+    }
+
+    fn insert_text(&mut self, text: &str) {
+        self.surface.handle_insert_text(text);
+    }
+
+    fn text_input_focus(&mut self, focused: bool) {
+        self.surface.input_state.set_text_input_active(focused);
+    }
+
+    fn layout_changed(&mut self, layout_name: String) {
+        self.surface.egui_app.keyboard_layout_changed(&layout_name);
+        self.surface.request_redraw();
+    }
 }
 
-impl<A: EguiAppData> EguiLayerSurface<A> {
-    pub fn new(layer_surface: LayerSurface, egui_app: A, width: u32, height: u32) -> Self {
-        let mut surface = EguiSurfaceState::new(layer_surface.wl_surface().clone(), egui_app);
-        surface.width = width;
-        surface.height = height;
-        Self {
-            layer_surface,
-            surface,
-        }
+impl<A: EguiAppData> PointerHandlerContainer for EguiSubsurface<A> {
+    fn pointer_frame(&mut self, events: &[PointerEvent]) {
+        self.surface.handle_pointer_events(events);
     }
 }
 
-impl<A: EguiAppData> CompositorHandlerContainer for EguiLayerSurface<A> {
-    fn scale_factor_changed(&mut self, new_factor: i32) {
-        self.surface.scale_factor_changed(new_factor);
+impl<A: EguiAppData> DndHandlerContainer for EguiSubsurface<A> {
+    fn dnd_enter(&mut self, x: f64, y: f64, mime_types: &[String]) {
+        self.surface.handle_dnd_enter(x, y, mime_types);
     }
 
-    fn frame(&mut self, time: u32) {
-        self.surface.frame(time);
+    fn dnd_motion(&mut self, x: f64, y: f64) {
+        self.surface.handle_dnd_motion(x, y);
     }
-}
 
-impl<A: EguiAppData> KeyboardHandlerContainer for EguiLayerSurface<A> {
-    fn enter(&mut self) {
-        self.surface.handle_keyboard_enter();
+    fn dnd_leave(&mut self) {
+        self.surface.handle_dnd_leave();
     }
 
-    fn leave(&mut self) {
-        self.surface.handle_keyboard_leave();
+    fn dnd_drop(&mut self, x: f64, y: f64, data: &DroppedData) {
+        self.surface.handle_dnd_drop(x, y, data);
     }
+}
 
-    fn press_key(&mut self, event: &KeyEvent) {
-        self.surface.handle_keyboard_event(event, true, false);
+impl<A: EguiAppData> BaseTrait for EguiSubsurface<A> {
+    fn get_object_id(&self) -> wayland_backend::client::ObjectId {
+        self.wl_surface.id()
     }
 
-    fn release_key(&mut self, event: &KeyEvent) {
-        self.surface.handle_keyboard_event(event, false, false);
+    fn metrics(&self) -> SurfaceMetrics {
+        self.surface.metrics()
     }
 
-    fn update_modifiers(&mut self, modifiers: &Modifiers) {
-        self.surface.update_modifiers(modifiers);
+    fn request_redraw(&mut self) {
+        self.surface.request_redraw();
     }
 
-    fn repeat_key(&mut self, event: &KeyEvent) {
-        self.surface.handle_keyboard_event(event, true, true);
+    fn present_at(&mut self, target: Instant) {
+        self.surface.present_at(target);
     }
-}
 
-impl<A: EguiAppData> PointerHandlerContainer for EguiLayerSurface<A> {
-    fn pointer_frame(&mut self, event: &PointerEvent) {
-        self.surface.handle_pointer_event(event);
+    fn set_cursor_override(&mut self, shape: Option<Shape>) {
+        self.surface.set_cursor_override(shape);
+    }
+
+    fn flush_input(&mut self) {
+        self.surface.flush_input();
     }
 }
 
-impl<A: EguiAppData> BaseTrait for EguiLayerSurface<A> {
-    fn get_object_id(&self) -> wayland_backend::client::ObjectId {
-        self.layer_surface.wl_surface().id()
+impl<A: EguiAppData> SubsurfaceContainer for EguiSubsurface<A> {
+    fn configure(&mut self, width: u32, height: u32) {
+        self.surface.configure(width, height);
     }
 }
 
-impl<A: EguiAppData> LayerSurfaceContainer for EguiLayerSurface<A> {
-    fn configure(&mut self, config: &LayerSurfaceConfigure) {
-        self.layer_surface
-            .wl_surface()
-            .set_buffer_scale(self.surface.scale_factor);
-        self.surface.configure(config.new_size.0, config.new_size.1);
+impl<A: EguiAppData> Drop for EguiSubsurface<A> {
+    fn drop(&mut self) {
+        if let Some((subsurface, _)) = &self.subsurface {
+            subsurface.destroy();
+        }
     }
 }
 
-pub struct EguiPopup<A: EguiAppData> {
-    pub popup: Popup,
+/// Lock surface of one output, created in the factory given to
+/// `Application::lock_session`
+pub struct EguiLockSurface<A: EguiAppData> {
+    pub lock_surface: SessionLockSurface,
+    pub output: WlOutput,
     surface: EguiSurfaceState<A>,
 }
 
-impl<A: EguiAppData> EguiPopup<A> {
-    pub fn new(popup: Popup, egui_app: A, width: u32, height: u32) -> Self {
-        let mut surface = EguiSurfaceState::new(popup.wl_surface().clone(), egui_app);
-        surface.width = width;
-        surface.height = height;
-        Self { popup, surface }
+impl<A: EguiAppData> EguiLockSurface<A> {
+    pub fn new(lock: &SessionLockHandle, output: &WlOutput, egui_app: A) -> Self {
+        Self::new_with(get_app(), lock, output, egui_app)
+    }
+
+    /// Create without the global application, e.g. when the process has
+    /// several applications or one that is not created with `get_init_app`
+    pub fn new_with(
+        app: &mut Application,
+        lock: &SessionLockHandle,
+        output: &WlOutput,
+        egui_app: A,
+    ) -> Self {
+        let wl_surface = app.compositor_state.create_surface(&app.qh);
+        let lock_surface =
+            lock.session_lock()
+                .create_lock_surface(wl_surface.clone(), output, &app.qh);
+        let mut surface = EguiSurfaceState::new(app, wl_surface, egui_app);
+        surface.role = Some(Box::new(lock_surface.clone()));
+        surface.strict = StrictSurface::new(SurfaceRole::LockSurface);
+        surface.frame_gated = true;
+        // Initial commit without a buffer, the configure has the output size
+        surface.wl_surface.commit();
+        Self {
+            lock_surface,
+            output: output.clone(),
+            surface,
+        }
     }
 }
 
-impl<A: EguiAppData> CompositorHandlerContainer for EguiPopup<A> {
+impl<A: EguiAppData> EguiSurface for EguiLockSurface<A> {
+    type App = A;
+
+    fn surface_state(&self) -> &EguiSurfaceState<A> {
+        &self.surface
+    }
+
+    fn surface_state_mut(&mut self) -> &mut EguiSurfaceState<A> {
+        &mut self.surface
+    }
+}
+
+impl<A: EguiAppData> CompositorHandlerContainer for EguiLockSurface<A> {
     fn scale_factor_changed(&mut self, new_factor: i32) {
         self.surface.scale_factor_changed(new_factor);
     }
 
+    fn transform_changed(&mut self, new_transform: &Transform) {
+        self.surface.transform_changed(*new_transform);
+    }
+
     fn frame(&mut self, time: u32) {
         self.surface.frame(time);
     }
+
+    fn frame_fallback(&mut self) {
+        self.surface.frame_fallback();
+    }
+
+    fn refresh_rate_changed(&mut self, refresh_rate: i32) {
+        self.surface.refresh_rate_changed(refresh_rate);
+    }
+
+    fn subpixel_changed(&mut self, subpixel: Subpixel) {
+        self.surface.subpixel_changed(subpixel);
+    }
+
+    fn presented(&mut self, presented: &Presented) {
+        self.surface.pacer.presented(*presented);
+        self.surface.egui_app.presented(presented);
+    }
+
+    fn system_resumed(&mut self, gap: Duration) {
+        self.surface.system_resumed(gap);
+    }
 }
 
-impl<A: EguiAppData> KeyboardHandlerContainer for EguiPopup<A> {
+impl<A: EguiAppData> KeyboardHandlerContainer for EguiLockSurface<A> {
     fn enter(&mut self) {
         self.surface.handle_keyboard_enter();
     }
@@ -456,112 +3345,119 @@ impl<A: EguiAppData> KeyboardHandlerContainer for EguiPopup<A> {
     fn repeat_key(&mut self, event: &KeyEvent) {
         self.surface.handle_keyboard_event(event, true, true);
     }
-}
 
-impl<A: EguiAppData> PointerHandlerContainer for EguiPopup<A> {
-    fn pointer_frame(&mut self, event: &PointerEvent) {
-        self.surface.handle_pointer_event(event);
+    fn repeat_info_changed(&mut self, info: RepeatInfo) {
+        self.surface.egui_app.repeat_info_changed(info);
+        self.surface.request_redraw();
     }
-}
 
-impl<A: EguiAppData> BaseTrait for EguiPopup<A> {
-    fn get_object_id(&self) -> wayland_backend::client::ObjectId {
-        self.popup.wl_surface().id()
+    fn preedit(&mut self, text: Option<&str>, _cursor: Option<(i32, i32)>) {
+        self.surface.handle_preedit(text);
     }
-}
 
-impl<A: EguiAppData> PopupContainer for EguiPopup<A> {
-    fn configure(&mut self, config: &PopupConfigure) {
-        self.popup
-            .wl_surface()
-            .set_buffer_scale(self.surface.scale_factor);
+    fn commit_text(&mut self, text: &str) {
+        self.surface.handle_commit_text(text);
+    }
+
+    fn delete_surrounding_text(&mut self, before_length: u32, after_length: u32) {
         self.surface
-            .configure(config.width as u32, config.height as u32);
+            .handle_delete_surrounding_text(before_length, after_length);
     }
 
-    fn done(&mut self) {}
-}
+    fn insert_text(&mut self, text: &str) {
+        self.surface.handle_insert_text(text);
+    }
 
-pub struct EguiSubsurface<A: EguiAppData> {
-    pub wl_surface: WlSurface,
-    surface: EguiSurfaceState<A>,
+    fn text_input_focus(&mut self, focused: bool) {
+        self.surface.input_state.set_text_input_active(focused);
+    }
+
+    fn layout_changed(&mut self, layout_name: String) {
+        self.surface.egui_app.keyboard_layout_changed(&layout_name);
+        self.surface.request_redraw();
+    }
 }
 
-impl<A: EguiAppData> EguiSubsurface<A> {
-    pub fn new(wl_surface: WlSurface, egui_app: A, width: u32, height: u32) -> Self {
-        let mut surface = EguiSurfaceState::new(wl_surface.clone(), egui_app);
-        surface.width = width;
-        surface.height = height;
-        Self {
-            wl_surface,
-            surface,
-        }
+impl<A: EguiAppData> PointerHandlerContainer for EguiLockSurface<A> {
+    fn pointer_frame(&mut self, events: &[PointerEvent]) {
+        self.surface.handle_pointer_events(events);
     }
 }
 
-impl<A: EguiAppData> CompositorHandlerContainer for EguiSubsurface<A> {
-    fn scale_factor_changed(&mut self, new_factor: i32) {
-        self.surface.scale_factor_changed(new_factor);
+impl<A: EguiAppData> DndHandlerContainer for EguiLockSurface<A> {
+    fn dnd_enter(&mut self, x: f64, y: f64, mime_types: &[String]) {
+        self.surface.handle_dnd_enter(x, y, mime_types);
     }
 
-    fn frame(&mut self, time: u32) {
-        self.surface.frame(time);
+    fn dnd_motion(&mut self, x: f64, y: f64) {
+        self.surface.handle_dnd_motion(x, y);
     }
-}
 
-impl<A: EguiAppData> KeyboardHandlerContainer for EguiSubsurface<A> {
-    fn enter(&mut self) {
-        self.surface.handle_keyboard_enter();
+    fn dnd_leave(&mut self) {
+        self.surface.handle_dnd_leave();
     }
 
-    fn leave(&mut self) {
-        self.surface.handle_keyboard_leave();
+    fn dnd_drop(&mut self, x: f64, y: f64, data: &DroppedData) {
+        self.surface.handle_dnd_drop(x, y, data);
     }
+}
 
-    fn press_key(&mut self, event: &KeyEvent) {
-        self.surface.handle_keyboard_event(event, true, false);
+impl<A: EguiAppData> BaseTrait for EguiLockSurface<A> {
+    fn get_object_id(&self) -> wayland_backend::client::ObjectId {
+        self.surface.wl_surface.id()
     }
 
-    fn release_key(&mut self, event: &KeyEvent) {
-        self.surface.handle_keyboard_event(event, false, false);
+    fn metrics(&self) -> SurfaceMetrics {
+        self.surface.metrics()
     }
 
-    fn update_modifiers(&mut self, modifiers: &Modifiers) {
-        self.surface.update_modifiers(modifiers);
+    fn request_redraw(&mut self) {
+        self.surface.request_redraw();
     }
 
-    fn repeat_key(&mut self, event: &KeyEvent) {
-        self.surface.handle_keyboard_event(event, true, true);
+    fn present_at(&mut self, target: Instant) {
+        self.surface.present_at(target);
     }
-}
 
-impl<A: EguiAppData> PointerHandlerContainer for EguiSubsurface<A> {
-    fn pointer_frame(&mut self, event: &PointerEvent) {
-        self.surface.handle_pointer_event(event);
+    fn set_cursor_override(&mut self, shape: Option<Shape>) {
+        self.surface.set_cursor_override(shape);
+    }
+
+    fn flush_input(&mut self) {
+        self.surface.flush_input();
     }
 }
 
-impl<A: EguiAppData> BaseTrait for EguiSubsurface<A> {
-    fn get_object_id(&self) -> wayland_backend::client::ObjectId {
-        self.wl_surface.id()
+impl<A: EguiAppData> LockSurfaceContainer for EguiLockSurface<A> {
+    fn configure(&mut self, config: &SessionLockSurfaceConfigure) {
+        // Exactly the output size, never fit
+        self.surface.intrinsic_size = config.new_size;
+        self.surface.configure(config.new_size.0, config.new_size.1);
     }
 }
 
-impl<A: EguiAppData> SubsurfaceContainer for EguiSubsurface<A> {
-    fn configure(&mut self, width: u32, height: u32) {
-        self.wl_surface.set_buffer_scale(self.surface.scale_factor);
-        self.surface.configure(width, height);
+/// Shape draws no pixels, e.g. a transparent fill without a stroke
+///
+/// Conservative, shapes not known to draw nothing count as visible.
+fn is_invisible(shape: &egui::Shape) -> bool {
+    match shape {
+        egui::Shape::Noop => true,
+        egui::Shape::Vec(shapes) => shapes.iter().all(is_invisible),
+        egui::Shape::Rect(rect) => rect.fill.a() == 0 && rect.stroke.is_empty(),
+        egui::Shape::Circle(circle) => circle.fill.a() == 0 && circle.stroke.is_empty(),
+        egui::Shape::Mesh(mesh) => mesh.is_empty(),
+        _ => false,
     }
 }
 
-/// Convert EGUI cursor icon to Wayland cursor shape
-fn egui_to_cursor_shape(cursor: egui::CursorIcon) -> Shape {
+/// Convert EGUI cursor icon to Wayland cursor shape, `None` hides the cursor
+fn egui_to_cursor_shape(cursor: egui::CursorIcon) -> Option<Shape> {
     use egui::CursorIcon::*;
     use wayland_protocols::wp::cursor_shape::v1::client::wp_cursor_shape_device_v1::Shape as CursorShape;
 
-    match cursor {
+    let shape = match cursor {
+        None => return Option::None,
         Default => CursorShape::Default,
-        None => CursorShape::Default,
         ContextMenu => CursorShape::ContextMenu,
         Help => CursorShape::Help,
         PointingHand => CursorShape::Pointer,
@@ -595,5 +3491,218 @@ fn egui_to_cursor_shape(cursor: egui::CursorIcon) -> Shape {
         ResizeRow => CursorShape::RowResize,
         ZoomIn => CursorShape::ZoomIn,
         ZoomOut => CursorShape::ZoomOut,
+    };
+    Some(shape)
+}
+
+/// Minimum size of a window, the minimum of the constraints raised to the
+/// intrinsic size when the content is laid out at the configured size
+fn min_window_size(
+    min: Option<(u32, u32)>,
+    (intrinsic_width, intrinsic_height): (u32, u32),
+    content_fit: ContentFit,
+) -> Option<(u32, u32)> {
+    match content_fit {
+        ContentFit::Resize => {
+            let (min_width, min_height) = min.unwrap_or_default();
+            Some((
+                min_width.max(intrinsic_width),
+                min_height.max(intrinsic_height),
+            ))
+        }
+        ContentFit::ScaleDown | ContentFit::Clamp => min,
+    }
+}
+
+/// Size to lay out for a configure, a zero side is left to the surface and
+/// the fallback is used for it
+fn configured_size((width, height): (u32, u32), fallback: (u32, u32)) -> (u32, u32) {
+    let width = if width == 0 { fallback.0 } else { width };
+    let height = if height == 0 { fallback.1 } else { height };
+    (width.max(1), height.max(1))
+}
+
+/// Logical size of the buffer for the configured size, see
+/// `EguiSurfaceState::buffer_size`
+fn buffer_size(
+    (width, height): (u32, u32),
+    (intrinsic_width, intrinsic_height): (u32, u32),
+    content_fit: ContentFit,
+    max: u32,
+) -> (u32, u32) {
+    let (width, height) = match content_fit {
+        ContentFit::Clamp => (width.max(intrinsic_width), height.max(intrinsic_height)),
+        ContentFit::Resize | ContentFit::ScaleDown => (width, height),
+    };
+    (width.min(max), height.min(max))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `max_texture_dimension_2d` of most desktop GPUs
+    const MAX: u32 = 16384;
+
+    #[test]
+    fn min_window_size_keeps_constraints_with_every_fit() {
+        let min = Some((500, 100));
+        assert_eq!(
+            min_window_size(min, (300, 200), ContentFit::Resize),
+            Some((500, 200))
+        );
+        assert_eq!(min_window_size(min, (300, 200), ContentFit::ScaleDown), min);
+        assert_eq!(min_window_size(min, (300, 200), ContentFit::Clamp), min);
+        assert_eq!(
+            min_window_size(None, (300, 200), ContentFit::Resize),
+            Some((300, 200))
+        );
+        assert_eq!(min_window_size(None, (300, 200), ContentFit::Clamp), None);
+    }
+
+    #[test]
+    fn zero_configure_uses_the_fallback() {
+        assert_eq!(configured_size((0, 0), (300, 200)), (300, 200));
+        assert_eq!(
+            buffer_size((300, 200), (300, 200), ContentFit::Resize, MAX),
+            (300, 200)
+        );
+    }
+
+    #[test]
+    fn zero_side_uses_the_fallback_for_that_side() {
+        assert_eq!(configured_size((0, 256), (300, 200)), (300, 256));
+        assert_eq!(configured_size((640, 0), (300, 200)), (640, 200));
+        // Never a zero sized swapchain, even with an empty fallback
+        assert_eq!(configured_size((0, 256), (0, 0)), (1, 256));
+    }
+
+    #[test]
+    fn oversized_configure_is_clamped_to_the_device() {
+        let size = configured_size((20000, 256), (300, 200));
+        assert_eq!(size, (20000, 256));
+        assert_eq!(
+            buffer_size(size, (300, 200), ContentFit::Resize, MAX),
+            (MAX, 256)
+        );
+        // At scale 2 the limit in points is halved
+        assert_eq!(
+            buffer_size(size, (300, 200), ContentFit::Resize, MAX / 2),
+            (MAX / 2, 256)
+        );
+    }
+
+    #[test]
+    fn clamp_fit_keeps_the_intrinsic_size_within_the_device() {
+        assert_eq!(
+            buffer_size((100, 100), (300, 200), ContentFit::Clamp, MAX),
+            (300, 200)
+        );
+        assert_eq!(
+            buffer_size((100, 100), (30000, 200), ContentFit::Clamp, MAX),
+            (MAX, 200)
+        );
+    }
+
+    #[test]
+    fn unconstrained_content_fills_available() {
+        let constraints = SizeConstraints::default();
+        assert_eq!(
+            constraints.content_size((640.0, 480.0), false),
+            (640.0, 480.0)
+        );
+        assert_eq!(constraints.content_size((0.0, 0.0), false), (1.0, 1.0));
+    }
+
+    #[test]
+    fn max_limits_content_unless_filling() {
+        let constraints = SizeConstraints {
+            max: Some((400, 300)),
+            ..Default::default()
+        };
+        assert_eq!(
+            constraints.content_size((640.0, 200.0), false),
+            (400.0, 200.0)
+        );
+        assert_eq!(
+            constraints.content_size((640.0, 480.0), true),
+            (640.0, 480.0)
+        );
+    }
+
+    #[test]
+    fn min_wins_over_max_and_fill() {
+        let constraints = SizeConstraints {
+            min: Some((300, 200)),
+            max: Some((100, 100)),
+            aspect: None,
+        };
+        assert_eq!(
+            constraints.content_size((640.0, 480.0), false),
+            (300.0, 200.0)
+        );
+        assert_eq!(
+            constraints.content_size((100.0, 50.0), true),
+            (300.0, 200.0)
+        );
+    }
+
+    #[test]
+    fn aspect_shrinks_the_longer_side() {
+        let constraints = SizeConstraints {
+            aspect: Some(2.0),
+            ..Default::default()
+        };
+        // Too wide, the width follows the height
+        assert_eq!(
+            constraints.content_size((800.0, 200.0), false),
+            (400.0, 200.0)
+        );
+        // Too tall, the height follows the width
+        assert_eq!(
+            constraints.content_size((400.0, 600.0), false),
+            (400.0, 200.0)
+        );
+        assert_eq!(
+            constraints.content_size((800.0, 600.0), true),
+            (800.0, 600.0)
+        );
+    }
+
+    #[test]
+    fn aspect_applies_within_max() {
+        let constraints = SizeConstraints {
+            max: Some((300, 300)),
+            aspect: Some(1.5),
+            ..Default::default()
+        };
+        assert_eq!(
+            constraints.content_size((1000.0, 1000.0), false),
+            (300.0, 200.0)
+        );
+    }
+
+    #[test]
+    fn non_positive_aspect_is_ignored() {
+        let constraints = SizeConstraints {
+            aspect: Some(0.0),
+            ..Default::default()
+        };
+        assert_eq!(
+            constraints.content_size((800.0, 200.0), false),
+            (800.0, 200.0)
+        );
+    }
+
+    #[test]
+    fn clamp_rounds_to_whole_pixels() {
+        let constraints = SizeConstraints {
+            min: Some((100, 100)),
+            aspect: Some(4.0 / 3.0),
+            ..Default::default()
+        };
+        assert_eq!(constraints.clamp((500, 500)), (500, 375));
+        assert_eq!(constraints.clamp((10, 10)), (100, 100));
+        assert_eq!(constraints.clamp((301, 1000)), (301, 226));
     }
 }
@@ -1,111 +1,426 @@
+use crate::DroppedData;
+use crate::TransferLimits;
+use crate::dnd::truncate_text;
+use crate::egui::clipboard::TimedClipboard;
+use crate::egui::kinetic_scroll::KineticScroll;
+use crate::egui::kinetic_scroll::KineticScrolling;
+use crate::egui::unicode_entry::EntryResult;
+use crate::egui::unicode_entry::UnicodeEntry;
 use egui::Event;
+use egui::ImeEvent;
 use egui::Key;
 use egui::Modifiers;
 use egui::PointerButton;
 use egui::Pos2;
 use egui::RawInput;
+use egui::ViewportId;
+use egui::ViewportInfo;
 use log::trace;
+use log::warn;
 use smithay_client_toolkit::seat::keyboard::KeyEvent;
 use smithay_client_toolkit::seat::keyboard::Keysym;
 use smithay_client_toolkit::seat::keyboard::Modifiers as WaylandModifiers;
+use smithay_client_toolkit::seat::pointer::AxisScroll;
 use smithay_client_toolkit::seat::pointer::PointerEvent;
 use smithay_client_toolkit::seat::pointer::PointerEventKind;
 use smithay_clipboard::Clipboard;
+use std::time::Duration;
 use std::time::Instant;
+use wayland_client::protocol::wl_pointer::AxisSource;
 
 /// Handles input events from Wayland and converts them to EGUI RawInput
 pub struct WaylandToEguiInput {
     modifiers: Modifiers,
     pointer_pos: Pos2,
     events: Vec<Event>,
+    /// Key events converted at the end of the batch, see `flush_keys`
+    pending_keys: Vec<RawInputEvent>,
+    /// Keyboard events held back from the previous batch, see
+    /// `take_raw_input`
+    deferred: Vec<Event>,
     screen_width: u32,
     screen_height: u32,
+    screen_origin: Pos2,
     start_time: Instant,
+    /// Predicted presentation of the next frame, see `set_frame_timing`
+    present_at: Option<Instant>,
+    refresh: Option<Duration>,
+    /// EGUI time of the previous frame, time never goes backwards
+    last_time: f64,
     // pressed_keys: std::collections::HashSet<u32>,
-    clipboard: Clipboard,
+    /// `None` when fed without a compositor, copy and paste do nothing
+    clipboard: Option<TimedClipboard>,
+    /// Latency of the reads since the last `take_clipboard_reads` and
+    /// whether they timed out
+    clipboard_reads: Vec<(Duration, bool)>,
+    /// Pasted text is truncated to this many bytes
+    paste_limit: usize,
     last_key_utf8: Option<String>,
+    viewport_info: ViewportInfo,
+    /// Origin of the EGUI content in surface-local coordinates
+    input_offset: egui::Vec2,
+    /// Scale of the EGUI content relative to the surface
+    input_scale: f32,
+    /// Input method is composing, EGUI expects enabled before preedit
+    ime_enabled: bool,
+    /// Unicode codepoint entry, `None` when not enabled
+    unicode_entry: Option<UnicodeEntry>,
+    /// Text-input-v3 has focus on the surface, the input method handles
+    /// composing and Unicode entry is bypassed
+    text_input_active: bool,
+    /// Input was rejected since the last `take_bell`
+    bell: bool,
+    /// EGUI draws the caret only when focused
+    keyboard_focus: bool,
+    /// A text field has the EGUI focus, middle click pastes into it
+    text_focused: bool,
+    /// Text of the focused text field and the cursor in chars, from its
+    /// latest selection change
+    surrounding: Option<(String, usize)>,
+    /// Alt as reported by the compositor
+    wayland_alt: bool,
+    /// Shortcut for opening the app's emoji picker, swallowed when pressed
+    emoji_picker: Option<egui::KeyboardShortcut>,
+    emoji_picker_requested: bool,
+    /// Drag over the surface, kept until it leaves or drops
+    hovered_files: Vec<egui::HoveredFile>,
+    dropped_files: Vec<egui::DroppedFile>,
+    /// AltGr (ISO_Level3_Shift) is held, some keymaps also report it as Alt
+    /// but it selects characters and is never a shortcut modifier
+    level3: bool,
+    /// `None` unless enabled with `set_kinetic_scrolling`
+    kinetic: Option<KineticScroll>,
+    /// Arrival of the oldest input not yet taken with the raw input
+    input_received: Option<Instant>,
 }
 
 impl WaylandToEguiInput {
     pub fn new(clipboard: Clipboard) -> Self {
+        Self::with_clipboard(Some(TimedClipboard::new(clipboard)))
+    }
+
+    /// Translator without a Wayland connection, for driving it with `feed`
+    pub fn without_clipboard() -> Self {
+        Self::with_clipboard(None)
+    }
+
+    fn with_clipboard(clipboard: Option<TimedClipboard>) -> Self {
         Self {
             modifiers: Modifiers::default(),
             pointer_pos: Pos2::ZERO,
             events: Vec::new(),
+            pending_keys: Vec::new(),
+            deferred: Vec::new(),
             screen_width: 256,
             screen_height: 256,
+            screen_origin: Pos2::ZERO,
             start_time: Instant::now(),
+            present_at: None,
+            refresh: None,
+            last_time: 0.0,
             // pressed_keys: std::collections::HashSet::new(),
             clipboard,
+            clipboard_reads: Vec::new(),
+            paste_limit: TransferLimits::default().text,
             last_key_utf8: None,
+            viewport_info: ViewportInfo::default(),
+            input_offset: egui::Vec2::ZERO,
+            input_scale: 1.0,
+            ime_enabled: false,
+            unicode_entry: None,
+            text_input_active: false,
+            bell: false,
+            text_focused: false,
+            surrounding: None,
+            wayland_alt: false,
+            emoji_picker: None,
+            emoji_picker_requested: false,
+            level3: false,
+            hovered_files: Vec::new(),
+            dropped_files: Vec::new(),
+            keyboard_focus: false,
+            kinetic: None,
+            input_received: None,
         }
     }
 
+    /// Whether there are input events waiting for the next frame
+    pub fn has_pending_events(&self) -> bool {
+        !self.events.is_empty()
+            || !self.pending_keys.is_empty()
+            || !self.deferred.is_empty()
+            || !self.dropped_files.is_empty()
+    }
+
     pub fn set_screen_size(&mut self, width: u32, height: u32) {
         self.screen_width = width;
         self.screen_height = height;
     }
 
+    /// Top left of the screen rect in points, non-zero when the content is
+    /// centered in a larger surface
+    pub fn set_screen_origin(&mut self, origin: Pos2) {
+        self.screen_origin = origin;
+    }
+
+    /// Set the origin of the content within the surface, e.g. CSD shadow insets
+    ///
+    /// Pointer positions are surface-local, this offset is subtracted before
+    /// they are given to EGUI.
+    pub fn set_input_offset(&mut self, dx: f32, dy: f32) {
+        self.input_offset = egui::vec2(dx, dy);
+    }
+
+    pub fn input_offset(&self) -> egui::Vec2 {
+        self.input_offset
+    }
+
+    /// Set the scale the content is drawn with, pointer positions are divided
+    /// by it after the offset is subtracted
+    pub fn set_input_scale(&mut self, scale: f32) {
+        self.input_scale = scale;
+    }
+
+    /// Map surface-local coordinates to EGUI coordinates
+    pub fn surface_to_content(&self, pos: Pos2) -> Pos2 {
+        ((pos - self.input_offset).to_vec2() / self.input_scale).to_pos2()
+    }
+
+    /// Map a rectangle in EGUI coordinates back to surface-local coordinates
+    pub fn content_to_surface(&self, rect: egui::Rect) -> egui::Rect {
+        egui::Rect::from_min_max(
+            (rect.min.to_vec2() * self.input_scale).to_pos2(),
+            (rect.max.to_vec2() * self.input_scale).to_pos2(),
+        )
+        .translate(self.input_offset)
+    }
+
+    /// Set the toplevel state reported to EGUI via the root viewport info
+    pub fn set_window_state(&mut self, maximized: bool, fullscreen: bool, activated: bool) {
+        self.viewport_info.maximized = Some(maximized);
+        self.viewport_info.fullscreen = Some(fullscreen);
+        self.viewport_info.focused = Some(activated);
+    }
+
     pub fn handle_pointer_event(&mut self, event: &PointerEvent) {
         trace!("[INPUT] Pointer event: {:?}", event.kind);
-        match &event.kind {
-            PointerEventKind::Enter { .. } => {
+        self.feed(RawInputEvent::from(event));
+        if let Some(stop) = axis_stop(event) {
+            self.feed(stop);
+        }
+    }
+
+    /// Handle events of one pointer frame, axis events are summed into a
+    /// single scroll event
+    pub fn handle_pointer_events(&mut self, events: &[PointerEvent]) {
+        self.feed_frame(
+            events.iter().flat_map(|event| {
+                std::iter::once(RawInputEvent::from(event)).chain(axis_stop(event))
+            }),
+        );
+    }
+
+    /// Touchpad scrolling keeps going after the fingers lift, `None` turns
+    /// it off
+    pub fn set_kinetic_scrolling(&mut self, kinetic: Option<KineticScrolling>) {
+        self.kinetic = kinetic.map(KineticScroll::new);
+    }
+
+    /// Scroll the next step of a kinetic scroll, whether it continues on
+    /// the next frame. Steps aren't tracked as finger scrolling.
+    pub fn tick_kinetic(&mut self, now: Instant, reduced_motion: bool) -> bool {
+        let Some(kinetic) = &mut self.kinetic else {
+            return false;
+        };
+        if reduced_motion {
+            kinetic.cancel();
+            return false;
+        }
+        if let Some(delta) = kinetic.step(now)
+            && delta != egui::Vec2::ZERO
+        {
+            self.events.push(Event::MouseWheel {
+                unit: egui::MouseWheelUnit::Point,
+                delta,
+                modifiers: self.modifiers,
+            });
+        }
+        kinetic.is_coasting()
+    }
+
+    fn cancel_kinetic(&mut self) {
+        if let Some(kinetic) = &mut self.kinetic {
+            kinetic.cancel();
+        }
+    }
+
+    /// Translate one event to EGUI events, the seat handlers convert their
+    /// events to these so the translation can be driven without a compositor
+    pub fn feed(&mut self, event: RawInputEvent) {
+        self.input_received.get_or_insert_with(Instant::now);
+        if !matches!(
+            event,
+            RawInputEvent::Key { .. } | RawInputEvent::Modifiers(_)
+        ) {
+            // Keys typed before a click stay ahead of it
+            self.flush_keys();
+        }
+        match event {
+            RawInputEvent::PointerEnter { .. } => {
                 trace!("[INPUT] Pointer entered surface");
                 // Pointer entered the surface
             }
-            PointerEventKind::Leave { .. } => {
+            RawInputEvent::PointerLeave => {
                 trace!("[INPUT] Pointer left surface");
                 // Pointer left the surface
+                self.cancel_kinetic();
                 self.events.push(Event::PointerGone);
             }
-            PointerEventKind::Motion { .. } => {
-                let (x, y) = event.position;
-                self.pointer_pos = Pos2::new(x as f32, y as f32);
+            RawInputEvent::PointerMotion { x, y } => {
+                self.pointer_pos = self.surface_to_content(Pos2::new(x as f32, y as f32));
                 trace!("[INPUT] Pointer moved to: ({}, {})", x, y);
                 self.events.push(Event::PointerMoved(self.pointer_pos));
             }
-            PointerEventKind::Press { button, .. } => {
-                trace!("[INPUT] Pointer button pressed: {}", button);
-                if let Some(egui_button) = wayland_button_to_egui(*button) {
+            RawInputEvent::Button { code, pressed } => {
+                trace!("[INPUT] Pointer button {}: {}", code, pressed);
+                if pressed {
+                    self.cancel_kinetic();
+                }
+                if let Some(egui_button) = wayland_button_to_egui(code) {
                     trace!("[INPUT] Mapped to EGUI button: {:?}", egui_button);
                     self.events.push(Event::PointerButton {
                         pos: self.pointer_pos,
                         button: egui_button,
-                        pressed: true,
+                        pressed,
                         modifiers: self.modifiers,
                     });
+                    if egui_button == PointerButton::Middle && pressed {
+                        self.paste_primary();
+                    }
                 }
             }
-            PointerEventKind::Release { button, .. } => {
-                trace!("[INPUT] Pointer button released: {}", button);
-                if let Some(egui_button) = wayland_button_to_egui(*button) {
-                    self.events.push(Event::PointerButton {
-                        pos: self.pointer_pos,
-                        button: egui_button,
-                        pressed: false,
-                        modifiers: self.modifiers,
-                    });
+            RawInputEvent::Axis {
+                lines,
+                points,
+                finger,
+            } => {
+                self.push_scroll(FrameScroll {
+                    lines,
+                    points,
+                    finger,
+                });
+            }
+            RawInputEvent::AxisStop => {
+                if let Some(kinetic) = &mut self.kinetic {
+                    kinetic.release(Instant::now());
                 }
             }
-            PointerEventKind::Axis {
-                horizontal,
-                vertical,
-                ..
-            } => {
-                // Handle scroll events
-                let scroll_delta = egui::vec2(
-                    horizontal.discrete as f32 * 10.0,
-                    vertical.discrete as f32 * 10.0,
-                );
+            RawInputEvent::Key { .. } => self.pending_keys.push(event),
+            RawInputEvent::Modifiers(modifiers) => self.update_modifiers(&modifiers),
+        }
+    }
 
-                if scroll_delta != egui::Vec2::ZERO {
-                    self.events.push(Event::MouseWheel {
-                        unit: egui::MouseWheelUnit::Line,
-                        delta: scroll_delta,
-                        modifiers: self.modifiers,
-                    });
+    /// Feed the events of one pointer frame, scrolling is summed into a
+    /// single event
+    pub fn feed_frame(&mut self, events: impl IntoIterator<Item = RawInputEvent>) {
+        self.flush_keys();
+        let mut scroll = FrameScroll::default();
+        let mut stop = false;
+        for event in events {
+            match event {
+                RawInputEvent::Axis {
+                    lines,
+                    points,
+                    finger,
+                } => {
+                    scroll.lines += lines;
+                    scroll.points += points;
+                    scroll.finger |= finger;
                 }
+                RawInputEvent::AxisStop => stop = true,
+                event => self.feed(event),
+            }
+        }
+        self.push_scroll(scroll);
+        if stop {
+            // After the last distance of the frame is tracked
+            self.feed(RawInputEvent::AxisStop);
+        }
+    }
+
+    fn push_scroll(&mut self, scroll: FrameScroll) {
+        if (scroll.lines != egui::Vec2::ZERO || scroll.points != egui::Vec2::ZERO)
+            && let Some(kinetic) = &mut self.kinetic
+        {
+            // Wheels scroll on their own
+            if scroll.finger {
+                kinetic.track(Instant::now(), scroll.points);
+            } else {
+                kinetic.cancel();
+            }
+        }
+        // Wheels report discrete steps, touchpads only continuous values
+        if scroll.lines != egui::Vec2::ZERO {
+            self.events.push(Event::MouseWheel {
+                unit: egui::MouseWheelUnit::Line,
+                delta: scroll.lines * 10.0,
+                modifiers: self.modifiers,
+            });
+        } else if scroll.points != egui::Vec2::ZERO {
+            self.events.push(Event::MouseWheel {
+                unit: egui::MouseWheelUnit::Point,
+                delta: scroll.points,
+                modifiers: self.modifiers,
+            });
+        }
+    }
+
+    /// Drag entered, EGUI shows it as one hovered file per mime type since
+    /// the paths are only known after the drop
+    pub fn handle_dnd_enter(&mut self, x: f64, y: f64, mime_types: &[String]) {
+        self.hovered_files = mime_types
+            .iter()
+            .map(|mime| egui::HoveredFile {
+                path: None,
+                mime: mime.clone(),
+            })
+            .collect();
+        self.handle_dnd_motion(x, y);
+    }
+
+    pub fn handle_dnd_motion(&mut self, x: f64, y: f64) {
+        self.pointer_pos = self.surface_to_content(Pos2::new(x as f32, y as f32));
+        self.events.push(Event::PointerMoved(self.pointer_pos));
+    }
+
+    pub fn handle_dnd_leave(&mut self) {
+        self.hovered_files.clear();
+        self.events.push(Event::PointerGone);
+    }
+
+    /// Files are dropped with their paths, text as a single file with the
+    /// text as its bytes
+    pub fn handle_dnd_drop(&mut self, x: f64, y: f64, data: &DroppedData) {
+        self.hovered_files.clear();
+        self.handle_dnd_motion(x, y);
+        match data {
+            DroppedData::Files(paths) => {
+                self.dropped_files.extend(paths.iter().map(|path| {
+                    egui::DroppedFile {
+                        path: Some(path.clone()),
+                        name: path
+                            .file_name()
+                            .map(|name| name.to_string_lossy().into_owned())
+                            .unwrap_or_default(),
+                        ..Default::default()
+                    }
+                }));
             }
+            DroppedData::Text(text) => self.dropped_files.push(egui::DroppedFile {
+                mime: "text/plain".into(),
+                bytes: Some(text.as_bytes().into()),
+                ..Default::default()
+            }),
         }
     }
 
@@ -114,37 +429,260 @@ impl WaylandToEguiInput {
         // This is strictly not the same thing, but Wayland can't know for instance if
         // layer surface has focus or not, but it knows keyboard focus is on the surface
         self.events.push(Event::WindowFocused(true));
+        self.keyboard_focus = true;
+        // Text of a key pressed before the focus must not leak into repeats
+        self.last_key_utf8 = None;
     }
 
     pub fn handle_keyboard_leave(&mut self) {
         trace!("[INPUT] Keyboard focus left surface");
+        self.flush_keys();
+        self.cancel_unicode_entry();
         self.events.push(Event::WindowFocused(false));
+        self.keyboard_focus = false;
+        self.level3 = false;
+    }
+
+    /// Input method preedit, `None` ends the composition
+    pub fn handle_preedit(&mut self, text: Option<&str>) {
+        trace!("[INPUT] Preedit: {:?}", text);
+        self.flush_keys();
+        match text {
+            Some(text) => {
+                self.enable_ime();
+                self.events
+                    .push(Event::Ime(ImeEvent::Preedit(text.to_string())));
+            }
+            None if self.ime_enabled => {
+                self.events
+                    .push(Event::Ime(ImeEvent::Preedit(String::new())));
+                self.events.push(Event::Ime(ImeEvent::Disabled));
+                self.ime_enabled = false;
+            }
+            None => {}
+        }
+    }
+
+    /// Input method deletes around the cursor before its commit, as
+    /// Backspace and Delete presses. The lengths are in bytes.
+    pub fn handle_delete_surrounding_text(&mut self, before_length: u32, after_length: u32) {
+        trace!(
+            "[INPUT] Input method delete: {} before, {} after",
+            before_length, after_length
+        );
+        self.flush_keys();
+        let surrounding = self
+            .surrounding
+            .as_ref()
+            .map(|(text, cursor)| (text.as_str(), *cursor));
+        let (before, after) = surrounding_chars(surrounding, before_length, after_length);
+        let keys = std::iter::repeat_n(Key::Backspace, before)
+            .chain(std::iter::repeat_n(Key::Delete, after));
+        for key in keys {
+            for pressed in [true, false] {
+                self.events.push(Event::Key {
+                    key,
+                    physical_key: None,
+                    pressed,
+                    repeat: false,
+                    modifiers: Modifiers::default(),
+                });
+            }
+        }
+    }
+
+    pub fn handle_commit_text(&mut self, text: &str) {
+        trace!("[INPUT] Input method commit: {:?}", text);
+        self.flush_keys();
+        self.enable_ime();
+        self.events
+            .push(Event::Ime(ImeEvent::Commit(text.to_string())));
+    }
+
+    /// Enable Ctrl+Shift+U hex and Alt+numpad decimal codepoint entry, the
+    /// typed digits are shown as IME preedit
+    pub fn set_unicode_entry(&mut self, enabled: bool) {
+        self.cancel_unicode_entry();
+        self.unicode_entry = enabled.then(UnicodeEntry::default);
+    }
+
+    pub fn set_text_input_active(&mut self, active: bool) {
+        if active {
+            self.cancel_unicode_entry();
+        }
+        self.text_input_active = active;
+    }
+
+    /// Whether input was rejected since the last call
+    pub fn take_bell(&mut self) -> bool {
+        std::mem::take(&mut self.bell)
+    }
+
+    /// `None` disables it, e.g. `Ctrl+.` as in GTK
+    pub fn set_emoji_picker_shortcut(&mut self, shortcut: Option<egui::KeyboardShortcut>) {
+        self.emoji_picker = shortcut;
+    }
+
+    /// Whether the emoji picker shortcut was pressed since the last call
+    pub fn take_emoji_picker_request(&mut self) -> bool {
+        std::mem::take(&mut self.emoji_picker_requested)
+    }
+
+    /// Text inserted by the application as if typed, control characters
+    /// are dropped
+    pub fn insert_text(&mut self, text: &str) {
+        let text: String = text.chars().filter(|c| !c.is_control()).collect();
+        if !text.is_empty() {
+            trace!("[INPUT] Inserted text: '{}'", text);
+            self.flush_keys();
+            self.events.push(Event::Text(text));
+        }
+    }
+
+    fn cancel_unicode_entry(&mut self) {
+        if let Some(entry) = &mut self.unicode_entry
+            && entry.is_active()
+        {
+            entry.cancel();
+            self.handle_preedit(None);
+        }
+    }
+
+    fn apply_unicode_entry(&mut self, result: EntryResult) -> bool {
+        match result {
+            EntryResult::Ignored => return false,
+            EntryResult::Composing(text) => self.handle_preedit(Some(&text)),
+            EntryResult::Commit(c) => {
+                trace!("[INPUT] Unicode entry: U+{:04X}", c as u32);
+                self.handle_commit_text(&c.to_string());
+                self.handle_preedit(None);
+            }
+            EntryResult::Cancel => self.handle_preedit(None),
+            EntryResult::Invalid(text) => {
+                warn!("[INPUT] Not a valid codepoint: {}", text);
+                self.bell = true;
+                if !self
+                    .unicode_entry
+                    .as_ref()
+                    .is_some_and(UnicodeEntry::is_active)
+                {
+                    self.handle_preedit(None);
+                }
+            }
+        }
+        true
+    }
+
+    fn enable_ime(&mut self) {
+        if !self.ime_enabled {
+            self.events.push(Event::Ime(ImeEvent::Enabled));
+            self.ime_enabled = true;
+        }
     }
 
     pub fn handle_keyboard_event(&mut self, event: &KeyEvent, pressed: bool, is_repeat: bool) {
+        self.feed(RawInputEvent::Key {
+            keysym: event.keysym,
+            utf8: event.utf8.clone(),
+            raw_code: event.raw_code,
+            pressed,
+            repeat: is_repeat,
+        });
+    }
+
+    /// Convert the key events of the batch
+    ///
+    /// Compositors may send wl_keyboard.modifiers after the key press it
+    /// belongs to in the same batch, e.g. Ctrl pressed and A pressed before
+    /// the modifiers with Ctrl. Keys wait for the end of the batch so such a
+    /// press still gets Ctrl, modifiers being released convert the keys
+    /// before them with the modifiers they were typed with. Pointer events
+    /// convert them too, keeping them in order with e.g. a click.
+    pub fn flush_keys(&mut self) {
+        for event in std::mem::take(&mut self.pending_keys) {
+            if let RawInputEvent::Key {
+                keysym,
+                utf8,
+                raw_code,
+                pressed,
+                repeat,
+            } = event
+            {
+                self.handle_key(keysym, raw_code, utf8, pressed, repeat);
+            }
+        }
+    }
+
+    fn handle_key(
+        &mut self,
+        keysym: Keysym,
+        raw_code: u32,
+        utf8: Option<String>,
+        pressed: bool,
+        is_repeat: bool,
+    ) {
         trace!(
             "[INPUT] Keyboard event - keysym: {:?}, raw_code: {}, pressed: {}, repeat: {}, utf8: \
              {:?}",
-            event.keysym.raw(),
-            event.raw_code,
+            keysym.raw(),
+            raw_code,
             pressed,
             is_repeat,
-            event.utf8
+            utf8
         );
 
+        if matches!(keysym, Keysym::ISO_Level3_Shift | Keysym::Mode_switch) {
+            // Alt_R is AltGr on many layouts, it must not toggle Alt
+            self.level3 = pressed || is_repeat;
+            self.modifiers.alt = self.wayland_alt && !self.level3;
+        }
+
+        if let Some(entry) = self
+            .unicode_entry
+            .as_mut()
+            .filter(|_| !self.text_input_active)
+        {
+            if pressed || is_repeat {
+                let result = entry.press(
+                    keysym,
+                    self.modifiers.ctrl,
+                    self.modifiers.shift,
+                    self.modifiers.alt,
+                );
+                if self.apply_unicode_entry(result) {
+                    return;
+                }
+            } else if entry.is_active() {
+                // Releases of keys swallowed by the entry
+                return;
+            }
+        }
+
         // Check for clipboard operations BEFORE general key handling
         if pressed && !is_repeat && self.modifiers.ctrl {
-            match event.keysym {
+            match keysym {
                 Keysym::c => self.events.push(Event::Copy),
                 Keysym::x => self.events.push(Event::Cut),
-                Keysym::v => self
-                    .events
-                    .push(Event::Paste(self.clipboard.load().unwrap_or_default())),
+                Keysym::v => {
+                    let text = self.load_clipboard(false);
+                    self.events.push(Event::Paste(text.unwrap_or_default()));
+                }
                 _ => (),
             }
         }
 
-        if let Some(key) = keysym_to_egui_key(event.keysym) {
+        let picker = self.emoji_picker.filter(|shortcut| {
+            Some(shortcut.logical_key) == keysym_to_egui_key(keysym)
+                && self.modifiers.matches_exact(shortcut.modifiers)
+        });
+        if picker.is_some() {
+            if pressed && !is_repeat {
+                self.emoji_picker_requested = true;
+            }
+            return;
+        }
+
+        if let Some(key) = keysym_to_egui_key(keysym) {
             trace!(
                 "[INPUT] Mapped to EGUI key: {:?}, repeat: {}",
                 key, is_repeat
@@ -158,27 +696,27 @@ impl WaylandToEguiInput {
                 modifiers: self.modifiers,
             });
         } else {
-            trace!(
-                "[INPUT] No EGUI key mapping for keysym: {:?}",
-                event.keysym.raw()
-            );
+            trace!("[INPUT] No EGUI key mapping for keysym: {:?}", keysym.raw());
         }
 
         if pressed || is_repeat {
-            let mut text = event.utf8.clone();
+            let mut text = utf8.clone();
             if is_repeat && text.is_none() {
                 text = self.last_key_utf8.clone();
             }
-            if let Some(text) = text {
-                if !text.chars().any(|c| c.is_control()) {
-                    trace!("[INPUT] Text input: '{}'", text);
-                    self.events.push(Event::Text(text.clone()));
-                }
+            // Alt and Ctrl combinations are shortcuts, characters typed with
+            // AltGr are not affected as it doesn't set Alt
+            let shortcut = self.modifiers.alt || self.modifiers.command;
+            if let Some(text) = text.filter(|_| !shortcut)
+                && !text.chars().any(|c| c.is_control())
+            {
+                trace!("[INPUT] Text input: '{}'", text);
+                self.events.push(Event::Text(text.clone()));
             }
         }
 
-        if event.utf8.is_some() {
-            self.last_key_utf8 = event.utf8.clone();
+        if utf8.is_some() {
+            self.last_key_utf8 = utf8;
         }
     }
 
@@ -187,13 +725,25 @@ impl WaylandToEguiInput {
             "[INPUT] Modifiers updated - ctrl: {}, shift: {}, alt: {}",
             wayland_mods.ctrl, wayland_mods.shift, wayland_mods.alt
         );
+        let released = (self.modifiers.ctrl && !wayland_mods.ctrl)
+            || (self.modifiers.shift && !wayland_mods.shift)
+            || (self.wayland_alt && !wayland_mods.alt);
+        if released {
+            self.flush_keys();
+        }
+        self.wayland_alt = wayland_mods.alt;
+        let alt = wayland_mods.alt && !self.level3;
         self.modifiers = Modifiers {
-            alt: wayland_mods.alt,
+            alt,
             ctrl: wayland_mods.ctrl,
             shift: wayland_mods.shift,
             mac_cmd: false,             // Not applicable on Linux/Wayland
             command: wayland_mods.ctrl, // On non-Mac, command is ctrl
         };
+        if let Some(entry) = &mut self.unicode_entry {
+            let result = entry.alt_changed(alt);
+            self.apply_unicode_entry(result);
+        }
     }
 
     /// Get current modifiers state
@@ -201,33 +751,149 @@ impl WaylandToEguiInput {
     //     &self.modifiers
     // }
 
+    /// Vblank the next frame is predicted to be shown at and the refresh
+    /// duration, from presentation feedback. Animations then advance by the
+    /// time between shown frames instead of render times that jitter.
+    /// Without them the time is when the input is taken.
+    pub fn set_frame_timing(&mut self, present_at: Option<Instant>, refresh: Option<Duration>) {
+        self.present_at = present_at;
+        self.refresh = refresh;
+    }
+
+    /// Events since the previous call as one batch
+    ///
+    /// Activation discipline: when the batch has a pointer button press, the
+    /// keyboard events from the first Space or Enter key onward are delivered
+    /// in the next batch, ahead of its own events. A widget pressed with the
+    /// pointer is thus always pressed before a keyboard activation typed in
+    /// the same frame, never the other way around, and keyboard events keep
+    /// their order. Deferred events are delayed by one batch at most.
     pub fn take_raw_input(&mut self) -> RawInput {
-        let events = std::mem::take(&mut self.events);
+        self.flush_keys();
+        let mut events = std::mem::take(&mut self.deferred);
+        let (current, deferred) = defer_activations(std::mem::take(&mut self.events));
+        events.extend(current);
+        self.deferred = deferred;
         trace!("[INPUT] Taking raw input with {} events", events.len());
         if !events.is_empty() {
             trace!("[INPUT] Events: {:?}", events);
         }
 
+        let at = self.present_at.take().unwrap_or_else(Instant::now);
+        let time = at
+            .saturating_duration_since(self.start_time)
+            .as_secs_f64()
+            .max(self.last_time);
+        self.last_time = time;
+
         RawInput {
             screen_rect: Some(egui::Rect::from_min_size(
-                Pos2::ZERO,
+                self.screen_origin,
                 egui::vec2(self.screen_width as f32, self.screen_height as f32),
             )),
-            time: Some(self.start_time.elapsed().as_secs_f64()),
-            predicted_dt: 1.0 / 60.0, // Assume 60 FPS
+            time: Some(time),
+            // Assume 60 FPS without a known refresh
+            predicted_dt: self
+                .refresh
+                .map_or(1.0 / 60.0, |refresh| refresh.as_secs_f32()),
             modifiers: self.modifiers,
             events,
-            hovered_files: Vec::new(),
-            dropped_files: Vec::new(),
-            focused: true, // Assume focused when we have the input
+            hovered_files: self.hovered_files.clone(),
+            dropped_files: std::mem::take(&mut self.dropped_files),
+            focused: self.keyboard_focus,
+            viewports: std::iter::once((ViewportId::ROOT, self.viewport_info.clone())).collect(),
             ..Default::default()
         }
     }
 
+    /// Middle click pastes the primary selection into the focused text field,
+    /// EGUI doesn't move the focus on middle click so it's not the one under
+    /// the pointer. Nothing is pasted without a focused text field.
+    fn paste_primary(&mut self) {
+        if !self.text_focused {
+            return;
+        }
+        let Some(text) = self.load_clipboard(true) else {
+            return;
+        };
+        trace!("[INPUT] Pasting primary selection: {:?}", text);
+        if !text.is_empty() {
+            self.events.push(Event::Paste(text));
+        }
+    }
+
+    /// Clipboard or primary selection text within the paste limit
+    fn load_clipboard(&mut self, primary: bool) -> Option<String> {
+        let read = self.clipboard.as_ref()?.load(primary);
+        self.clipboard_reads.push((read.latency, read.timed_out));
+        let mut text = read.text?;
+        if truncate_text(&mut text, self.paste_limit) {
+            warn!(
+                "[INPUT] Pasted text is over {} bytes, truncated",
+                self.paste_limit
+            );
+        }
+        Some(text)
+    }
+
+    /// Arrival of the oldest input in the last taken raw input, for measuring
+    /// the input latency
+    pub fn take_input_received(&mut self) -> Option<Instant> {
+        self.input_received.take()
+    }
+
+    /// Latency of the clipboard reads since the last call and whether they
+    /// timed out
+    pub fn take_clipboard_reads(&mut self) -> Vec<(Duration, bool)> {
+        std::mem::take(&mut self.clipboard_reads)
+    }
+
+    /// Pasted text longer than this in bytes is truncated, see
+    /// `TransferLimits::text`
+    pub fn set_paste_limit(&mut self, bytes: usize) {
+        self.paste_limit = bytes;
+    }
+
+    pub fn is_text_focused(&self) -> bool {
+        self.text_focused
+    }
+
+    /// Whether a text field has the focus, from the IME output of the frame
+    pub fn set_text_focused(&mut self, focused: bool) {
+        self.text_focused = focused;
+        if !focused {
+            self.surrounding = None;
+        }
+    }
+
+    /// Selected text becomes the primary selection, as on other Linux apps
+    pub fn handle_output_event(&mut self, event: &egui::output::OutputEvent) {
+        let egui::output::OutputEvent::TextSelectionChanged(info) = event else {
+            return;
+        };
+        let (Some(text), Some(selection)) = (&info.current_text_value, &info.text_selection) else {
+            return;
+        };
+        // Cursor indices in chars, either end may come first
+        let start = *selection.start().min(selection.end());
+        let end = *selection.start().max(selection.end());
+        self.surrounding = Some((text.clone(), end));
+        if start == end {
+            return;
+        }
+        let selected: String = text.chars().skip(start).take(end - start).collect();
+        trace!("[INPUT] Primary selection: {:?}", selected);
+        if let Some(clipboard) = &self.clipboard {
+            clipboard.store(selected, true);
+        }
+    }
+
     pub fn handle_output_command(&mut self, output: &egui::OutputCommand) {
         match output {
             egui::OutputCommand::CopyText(text) => {
-                self.clipboard.store(text.clone());
+                if let Some(clipboard) = &self.clipboard {
+                    clipboard.store(text.clone(), false);
+                }
                 trace!("[INPUT] Copied text to clipboard: {:?}", text);
             }
             egui::OutputCommand::CopyImage(_image) => {
@@ -242,6 +908,159 @@ impl WaylandToEguiInput {
     }
 }
 
+/// Input in a form that can be constructed without live Wayland objects
+#[derive(Debug, Clone)]
+pub enum RawInputEvent {
+    /// Positions are in surface-local coordinates
+    PointerEnter {
+        x: f64,
+        y: f64,
+    },
+    PointerLeave,
+    PointerMotion {
+        x: f64,
+        y: f64,
+    },
+    /// Linux button code, e.g. `BTN_LEFT`
+    Button {
+        code: u32,
+        pressed: bool,
+    },
+    /// Wheel steps, fractions from high resolution wheels, and continuous
+    /// distance from touchpads. Finger scrolling drives kinetic scrolling.
+    Axis {
+        lines: egui::Vec2,
+        points: egui::Vec2,
+        finger: bool,
+    },
+    /// Fingers lifted from the touchpad, a kinetic scroll coasts from here
+    AxisStop,
+    Key {
+        keysym: Keysym,
+        utf8: Option<String>,
+        raw_code: u32,
+        pressed: bool,
+        repeat: bool,
+    },
+    Modifiers(WaylandModifiers),
+}
+
+impl From<&PointerEvent> for RawInputEvent {
+    fn from(event: &PointerEvent) -> Self {
+        let (x, y) = event.position;
+        match &event.kind {
+            PointerEventKind::Enter { .. } => RawInputEvent::PointerEnter { x, y },
+            PointerEventKind::Leave { .. } => RawInputEvent::PointerLeave,
+            PointerEventKind::Motion { .. } => RawInputEvent::PointerMotion { x, y },
+            PointerEventKind::Press { button, .. } => RawInputEvent::Button {
+                code: *button,
+                pressed: true,
+            },
+            PointerEventKind::Release { button, .. } => RawInputEvent::Button {
+                code: *button,
+                pressed: false,
+            },
+            PointerEventKind::Axis {
+                horizontal,
+                vertical,
+                source,
+                ..
+            } => axis_event(horizontal, vertical, *source),
+        }
+    }
+}
+
+fn axis_event(
+    horizontal: &AxisScroll,
+    vertical: &AxisScroll,
+    source: Option<AxisSource>,
+) -> RawInputEvent {
+    let mut scroll = FrameScroll::default();
+    scroll.add(horizontal, vertical);
+    RawInputEvent::Axis {
+        lines: scroll.lines,
+        points: scroll.points,
+        finger: source == Some(AxisSource::Finger),
+    }
+}
+
+/// Fingers lifted, sent with the last axis event of the scroll
+fn axis_stop(event: &PointerEvent) -> Option<RawInputEvent> {
+    match &event.kind {
+        PointerEventKind::Axis {
+            horizontal,
+            vertical,
+            ..
+        } if horizontal.stop || vertical.stop => Some(RawInputEvent::AxisStop),
+        _ => None,
+    }
+}
+
+/// Axis values summed over a pointer frame
+#[derive(Default)]
+struct FrameScroll {
+    /// High-resolution wheels send fractions of a step, 120 per step
+    lines: egui::Vec2,
+    points: egui::Vec2,
+    finger: bool,
+}
+
+impl FrameScroll {
+    fn add(&mut self, horizontal: &AxisScroll, vertical: &AxisScroll) {
+        self.lines += egui::vec2(axis_lines(horizontal), axis_lines(vertical));
+        self.points += egui::vec2(horizontal.absolute as f32, vertical.absolute as f32);
+    }
+}
+
+fn axis_lines(axis: &AxisScroll) -> f32 {
+    if axis.value120 != 0 {
+        axis.value120 as f32 / 120.0
+    } else {
+        axis.discrete as f32
+    }
+}
+
+fn is_keyboard_event(event: &Event) -> bool {
+    matches!(
+        event,
+        Event::Key { .. } | Event::Text(_) | Event::Copy | Event::Cut | Event::Paste(_)
+    )
+}
+
+/// Split a batch into the events delivered now and the keyboard events
+/// deferred to the next batch, see `WaylandToEguiInput::take_raw_input`
+fn defer_activations(events: Vec<Event>) -> (Vec<Event>, Vec<Event>) {
+    let pointer_press = events
+        .iter()
+        .any(|event| matches!(event, Event::PointerButton { pressed: true, .. }));
+    let first_activation = events.iter().position(|event| {
+        matches!(
+            event,
+            Event::Key {
+                key: Key::Space | Key::Enter,
+                ..
+            }
+        )
+    });
+    let (true, Some(first_activation)) = (pointer_press, first_activation) else {
+        return (events, Vec::new());
+    };
+    let mut current = Vec::with_capacity(events.len());
+    let mut deferred = Vec::new();
+    for (index, event) in events.into_iter().enumerate() {
+        if index >= first_activation && is_keyboard_event(&event) {
+            deferred.push(event);
+        } else {
+            current.push(event);
+        }
+    }
+    trace!(
+        "[INPUT] Deferring {} keyboard events after a pointer press",
+        deferred.len()
+    );
+    (current, deferred)
+}
+
 fn wayland_button_to_egui(button: u32) -> Option<PointerButton> {
     // Linux button codes (from linux/input-event-codes.h)
     match button {
@@ -252,6 +1071,38 @@ fn wayland_button_to_egui(button: u32) -> Option<PointerButton> {
     }
 }
 
+/// Chars to delete before and after the cursor for the byte lengths of an
+/// input method, counted in the text around the cursor when it's known
+fn surrounding_chars(
+    surrounding: Option<(&str, usize)>,
+    before: u32,
+    after: u32,
+) -> (usize, usize) {
+    let Some((text, cursor)) = surrounding else {
+        return (before as usize, after as usize);
+    };
+    let offset = text
+        .char_indices()
+        .nth(cursor)
+        .map_or(text.len(), |(offset, _)| offset);
+    (
+        chars_in_bytes(text[..offset].chars().rev(), before),
+        chars_in_bytes(text[offset..].chars(), after),
+    )
+}
+
+/// Leading chars that fit in the bytes
+fn chars_in_bytes(chars: impl Iterator<Item = char>, bytes: u32) -> usize {
+    let mut remaining = bytes as usize;
+    chars
+        .take_while(|c| {
+            let fits = c.len_utf8() <= remaining;
+            remaining = remaining.saturating_sub(c.len_utf8());
+            fits
+        })
+        .count()
+}
+
 fn keysym_to_egui_key(keysym: Keysym) -> Option<Key> {
     Some(match keysym {
         // Commands:
@@ -368,3 +1219,425 @@ fn keysym_to_egui_key(keysym: Keysym) -> Option<Key> {
         _ => return None,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(dx: f32, dy: f32, scale: f32) -> WaylandToEguiInput {
+        let mut input = WaylandToEguiInput::without_clipboard();
+        input.set_input_offset(dx, dy);
+        input.set_input_scale(scale);
+        input
+    }
+
+    #[test]
+    fn surface_to_content_without_offset_is_identity() {
+        let input = input(0.0, 0.0, 1.0);
+        assert_eq!(
+            input.surface_to_content(egui::pos2(12.5, 40.0)),
+            egui::pos2(12.5, 40.0)
+        );
+    }
+
+    #[test]
+    fn surface_to_content_subtracts_offset_before_scale() {
+        let input = input(10.0, 20.0, 2.0);
+        assert_eq!(
+            input.surface_to_content(egui::pos2(30.0, 20.0)),
+            egui::pos2(10.0, 0.0)
+        );
+        // Pointer in the inset, e.g. on the CSD shadow
+        assert_eq!(
+            input.surface_to_content(egui::pos2(4.0, 8.0)),
+            egui::pos2(-3.0, -6.0)
+        );
+    }
+
+    #[test]
+    fn content_to_surface_inverts_surface_to_content() {
+        let input = input(10.0, 20.0, 1.5);
+        let rect = egui::Rect::from_min_max(egui::pos2(2.0, 4.0), egui::pos2(8.0, 10.0));
+        let surface = input.content_to_surface(rect);
+        assert_eq!(surface.min, egui::pos2(13.0, 26.0));
+        assert_eq!(surface.max, egui::pos2(22.0, 35.0));
+        assert_eq!(input.surface_to_content(surface.min), rect.min);
+        assert_eq!(input.surface_to_content(surface.max), rect.max);
+    }
+
+    const BTN_LEFT: u32 = 0x110;
+
+    fn key(keysym: Keysym, utf8: Option<&str>, pressed: bool) -> RawInputEvent {
+        RawInputEvent::Key {
+            keysym,
+            utf8: utf8.map(str::to_string),
+            raw_code: 0,
+            pressed,
+            repeat: false,
+        }
+    }
+
+    fn egui_key(key: Key, pressed: bool, modifiers: Modifiers) -> Event {
+        Event::Key {
+            key,
+            physical_key: None,
+            pressed,
+            repeat: false,
+            modifiers,
+        }
+    }
+
+    fn button(pos: Pos2, pressed: bool) -> Event {
+        Event::PointerButton {
+            pos,
+            button: PointerButton::Primary,
+            pressed,
+            modifiers: Modifiers::default(),
+        }
+    }
+
+    #[test]
+    fn click_drag_release() {
+        let mut input = WaylandToEguiInput::without_clipboard();
+        input.feed(RawInputEvent::PointerEnter { x: 10.0, y: 10.0 });
+        input.feed(RawInputEvent::PointerMotion { x: 10.0, y: 10.0 });
+        input.feed(RawInputEvent::Button {
+            code: BTN_LEFT,
+            pressed: true,
+        });
+        input.feed(RawInputEvent::PointerMotion { x: 40.0, y: 25.0 });
+        input.feed(RawInputEvent::Button {
+            code: BTN_LEFT,
+            pressed: false,
+        });
+        assert!(input.has_pending_events());
+        assert_eq!(
+            input.take_raw_input().events,
+            vec![
+                Event::PointerMoved(egui::pos2(10.0, 10.0)),
+                button(egui::pos2(10.0, 10.0), true),
+                Event::PointerMoved(egui::pos2(40.0, 25.0)),
+                button(egui::pos2(40.0, 25.0), false),
+            ]
+        );
+        assert!(!input.has_pending_events());
+    }
+
+    #[test]
+    fn shift_arrow() {
+        let shift = WaylandModifiers {
+            shift: true,
+            ..Default::default()
+        };
+        let egui_shift = Modifiers {
+            shift: true,
+            ..Default::default()
+        };
+        let mut input = WaylandToEguiInput::without_clipboard();
+        input.feed(key(Keysym::Shift_L, None, true));
+        input.feed(RawInputEvent::Modifiers(shift));
+        input.feed(key(Keysym::Right, None, true));
+        input.feed(key(Keysym::Right, None, false));
+        input.feed(key(Keysym::Shift_L, None, false));
+        input.feed(RawInputEvent::Modifiers(WaylandModifiers::default()));
+
+        let raw = input.take_raw_input();
+        assert_eq!(
+            raw.events,
+            vec![
+                egui_key(Key::ArrowRight, true, egui_shift),
+                egui_key(Key::ArrowRight, false, egui_shift),
+            ]
+        );
+        assert_eq!(raw.modifiers, Modifiers::default());
+    }
+
+    #[test]
+    fn activation_keys_after_pointer_press_are_deferred() {
+        let none = Modifiers::default();
+        let press = button(egui::pos2(5.0, 5.0), true);
+        let events = vec![
+            egui_key(Key::A, true, none),
+            Event::Text("a".to_string()),
+            egui_key(Key::Space, true, none),
+            Event::PointerMoved(egui::pos2(5.0, 5.0)),
+            press.clone(),
+            egui_key(Key::B, true, none),
+        ];
+        let (current, deferred) = defer_activations(events);
+        assert_eq!(
+            current,
+            vec![
+                egui_key(Key::A, true, none),
+                Event::Text("a".to_string()),
+                Event::PointerMoved(egui::pos2(5.0, 5.0)),
+                press,
+            ]
+        );
+        assert_eq!(
+            deferred,
+            vec![
+                egui_key(Key::Space, true, none),
+                egui_key(Key::B, true, none),
+            ]
+        );
+    }
+
+    #[test]
+    fn activation_keys_without_pointer_press_are_not_deferred() {
+        let none = Modifiers::default();
+        let events = vec![
+            Event::PointerMoved(egui::pos2(5.0, 5.0)),
+            egui_key(Key::Enter, true, none),
+            button(egui::pos2(5.0, 5.0), false),
+        ];
+        let (current, deferred) = defer_activations(events.clone());
+        assert_eq!(current, events);
+        assert!(deferred.is_empty());
+    }
+
+    #[test]
+    fn deferred_events_lead_the_next_batch() {
+        let mut input = WaylandToEguiInput::without_clipboard();
+        input.feed(RawInputEvent::PointerMotion { x: 5.0, y: 5.0 });
+        input.feed(key(Keysym::space, Some(" "), true));
+        input.feed(RawInputEvent::Button {
+            code: BTN_LEFT,
+            pressed: true,
+        });
+        let first = input.take_raw_input().events;
+        assert!(!first.iter().any(is_keyboard_event));
+
+        input.feed(RawInputEvent::Button {
+            code: BTN_LEFT,
+            pressed: false,
+        });
+        let second = input.take_raw_input().events;
+        assert_eq!(
+            second,
+            vec![
+                egui_key(Key::Space, true, Modifiers::default()),
+                Event::Text(" ".to_string()),
+                button(egui::pos2(5.0, 5.0), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn altgr_types_characters_even_when_reported_as_alt() {
+        // Some keymaps set Mod1 together with level 3
+        let alt = WaylandModifiers {
+            alt: true,
+            ..Default::default()
+        };
+        let mut input = WaylandToEguiInput::without_clipboard();
+        input.feed(key(Keysym::ISO_Level3_Shift, None, true));
+        input.feed(RawInputEvent::Modifiers(alt));
+        input.feed(key(Keysym::q, Some("@"), true));
+        let raw = input.take_raw_input();
+        assert_eq!(
+            raw.events,
+            vec![
+                egui_key(Key::Q, true, Modifiers::default()),
+                Event::Text("@".to_string()),
+            ]
+        );
+        assert!(!raw.modifiers.alt);
+
+        input.feed(key(Keysym::ISO_Level3_Shift, None, false));
+        input.feed(RawInputEvent::Modifiers(WaylandModifiers::default()));
+        input.take_raw_input();
+
+        // Real Alt makes a shortcut without text
+        input.feed(key(Keysym::Alt_L, None, true));
+        input.feed(RawInputEvent::Modifiers(alt));
+        input.feed(key(Keysym::q, Some("q"), true));
+        assert_eq!(
+            input.take_raw_input().events,
+            vec![egui_key(
+                Key::Q,
+                true,
+                Modifiers {
+                    alt: true,
+                    ..Default::default()
+                }
+            )]
+        );
+    }
+
+    fn ctrl_a(delivery: [RawInputEvent; 3]) -> Vec<Event> {
+        let mut input = WaylandToEguiInput::without_clipboard();
+        for event in delivery {
+            input.feed(event);
+        }
+        input.take_raw_input().events
+    }
+
+    fn ctrl() -> (WaylandModifiers, Modifiers) {
+        (
+            WaylandModifiers {
+                ctrl: true,
+                ..Default::default()
+            },
+            Modifiers {
+                ctrl: true,
+                command: true,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn ctrl_a_with_modifiers_before_the_key() {
+        let (wayland_ctrl, egui_ctrl) = ctrl();
+        let events = ctrl_a([
+            key(Keysym::Control_L, None, true),
+            RawInputEvent::Modifiers(wayland_ctrl),
+            key(Keysym::a, Some("a"), true),
+        ]);
+        assert_eq!(events, vec![egui_key(Key::A, true, egui_ctrl)]);
+    }
+
+    #[test]
+    fn ctrl_a_with_modifiers_after_the_key() {
+        let (wayland_ctrl, egui_ctrl) = ctrl();
+        let events = ctrl_a([
+            key(Keysym::Control_L, None, true),
+            key(Keysym::a, Some("a"), true),
+            RawInputEvent::Modifiers(wayland_ctrl),
+        ]);
+        assert_eq!(events, vec![egui_key(Key::A, true, egui_ctrl)]);
+    }
+
+    #[test]
+    fn keys_stay_ahead_of_later_pointer_events() {
+        let mut input = WaylandToEguiInput::without_clipboard();
+        input.feed(RawInputEvent::PointerMotion { x: 5.0, y: 5.0 });
+        input.feed(key(Keysym::a, Some("a"), true));
+        input.feed(RawInputEvent::Button {
+            code: BTN_LEFT,
+            pressed: true,
+        });
+        assert_eq!(
+            input.take_raw_input().events,
+            vec![
+                Event::PointerMoved(egui::pos2(5.0, 5.0)),
+                egui_key(Key::A, true, Modifiers::default()),
+                Event::Text("a".to_string()),
+                button(egui::pos2(5.0, 5.0), true),
+            ]
+        );
+    }
+
+    fn vertical(absolute: f64, discrete: i32, value120: i32) -> RawInputEvent {
+        let vertical = AxisScroll {
+            absolute,
+            discrete,
+            value120,
+            ..Default::default()
+        };
+        axis_event(&AxisScroll::default(), &vertical, Some(AxisSource::Wheel))
+    }
+
+    fn wheel_events(events: &[Event]) -> Vec<&Event> {
+        events
+            .iter()
+            .filter(|event| matches!(event, Event::MouseWheel { .. }))
+            .collect()
+    }
+
+    /// High resolution wheel: two half steps in one wl_pointer.frame, the
+    /// second also with the legacy discrete step
+    #[test]
+    fn axis_events_of_a_frame_scroll_once() {
+        let mut input = WaylandToEguiInput::without_clipboard();
+        input.feed_frame([
+            RawInputEvent::PointerMotion { x: 5.0, y: 5.0 },
+            vertical(7.5, 0, 60),
+            vertical(7.5, 1, 60),
+        ]);
+        let events = input.take_raw_input().events;
+        assert_eq!(
+            wheel_events(&events),
+            vec![&Event::MouseWheel {
+                unit: egui::MouseWheelUnit::Line,
+                delta: egui::vec2(0.0, 10.0),
+                modifiers: Modifiers::default(),
+            }]
+        );
+    }
+
+    #[test]
+    fn discrete_steps_count_without_value120() {
+        let mut input = WaylandToEguiInput::without_clipboard();
+        input.feed_frame([vertical(10.0, 1, 0), vertical(10.0, 1, 0)]);
+        let events = input.take_raw_input().events;
+        assert_eq!(
+            wheel_events(&events),
+            vec![&Event::MouseWheel {
+                unit: egui::MouseWheelUnit::Line,
+                delta: egui::vec2(0.0, 20.0),
+                modifiers: Modifiers::default(),
+            }]
+        );
+    }
+
+    #[test]
+    fn touchpad_distance_is_summed_in_points() {
+        let mut input = WaylandToEguiInput::without_clipboard();
+        let finger = |absolute| {
+            let vertical = AxisScroll {
+                absolute,
+                ..Default::default()
+            };
+            axis_event(&AxisScroll::default(), &vertical, Some(AxisSource::Finger))
+        };
+        input.feed_frame([finger(3.0), finger(4.5)]);
+        let events = input.take_raw_input().events;
+        assert_eq!(
+            wheel_events(&events),
+            vec![&Event::MouseWheel {
+                unit: egui::MouseWheelUnit::Point,
+                delta: egui::vec2(0.0, 7.5),
+                modifiers: Modifiers::default(),
+            }]
+        );
+    }
+
+    #[test]
+    fn input_method_deletes_then_commits_then_preedits() {
+        let mut input = WaylandToEguiInput::without_clipboard();
+        // Done of one input method transaction, in protocol order
+        input.handle_delete_surrounding_text(2, 1);
+        input.handle_commit_text("ü");
+        input.handle_preedit(Some("n"));
+        let key = |key, pressed| egui_key(key, pressed, Modifiers::default());
+        assert_eq!(
+            input.take_raw_input().events,
+            vec![
+                key(Key::Backspace, true),
+                key(Key::Backspace, false),
+                key(Key::Backspace, true),
+                key(Key::Backspace, false),
+                key(Key::Delete, true),
+                key(Key::Delete, false),
+                Event::Ime(ImeEvent::Enabled),
+                Event::Ime(ImeEvent::Commit("ü".to_string())),
+                Event::Ime(ImeEvent::Preedit("n".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn deleted_bytes_are_counted_in_the_surrounding_text() {
+        // Cursor after "ü", two bytes
+        assert_eq!(surrounding_chars(Some(("aüb", 2)), 2, 1), (1, 1));
+        assert_eq!(surrounding_chars(Some(("aüb", 2)), 3, 0), (2, 0));
+        // Half a char isn't deleted
+        assert_eq!(surrounding_chars(Some(("aüb", 2)), 1, 0), (0, 0));
+        // Not more than there is
+        assert_eq!(surrounding_chars(Some(("ab", 1)), 5, 5), (1, 1));
+        // Unknown text, taken as chars
+        assert_eq!(surrounding_chars(None, 2, 1), (2, 1));
+    }
+}
@@ -16,10 +16,17 @@ use egui_wgpu::wgpu::StoreOp;
 use egui_wgpu::wgpu::TextureFormat;
 use egui_wgpu::wgpu::TextureView;
 
+/// Toggled in the font definitions to make EGUI rebuild the font atlas
+const REBUILD_FONT_FAMILY: &str = "wayapp-rebuild";
+
 pub struct EguiWgpuRenderer {
     context: Context,
     renderer: Renderer,
     frame_started: bool,
+    output_depth_format: Option<TextureFormat>,
+    msaa_samples: u32,
+    /// Primitives of the last drawn frame, for drawing it again elsewhere
+    last_primitives: Vec<egui::ClippedPrimitive>,
 }
 
 impl EguiWgpuRenderer {
@@ -54,9 +61,63 @@ impl EguiWgpuRenderer {
             context: egui_context,
             renderer: egui_renderer,
             frame_started: false,
+            output_depth_format,
+            msaa_samples,
+            last_primitives: Vec::new(),
         }
     }
 
+    /// Recreate the WGPU renderer for another output format, only before the
+    /// first frame since textures uploaded so far are lost
+    pub fn set_output_format(&mut self, device: &Device, output_color_format: TextureFormat) {
+        self.renderer = Renderer::new(
+            device,
+            output_color_format,
+            RendererOptions {
+                msaa_samples: self.msaa_samples,
+                depth_stencil_format: self.output_depth_format,
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Recreate the WGPU renderer for another output format after frames have
+    /// been drawn. The font atlas is sent again in full on the next frame and
+    /// images of the loaders are reloaded, textures allocated by the app
+    /// itself with `Context::load_texture` are lost.
+    pub fn rebuild_for_format(&mut self, device: &Device, output_color_format: TextureFormat) {
+        self.set_output_format(device, output_color_format);
+        self.last_primitives.clear();
+        // EGUI rebuilds the fonts only when the definitions change
+        let mut fonts = self.context.fonts(|fonts| fonts.definitions().clone());
+        let family = egui::FontFamily::Name(REBUILD_FONT_FAMILY.into());
+        if fonts.families.remove(&family).is_none() {
+            let proportional = fonts
+                .families
+                .get(&egui::FontFamily::Proportional)
+                .cloned()
+                .unwrap_or_default();
+            fonts.families.insert(family, proportional);
+        }
+        self.context.set_fonts(fonts);
+        self.context.forget_all_images();
+    }
+
+    /// Show a texture rendered elsewhere on the same device with `egui::Image`
+    pub fn register_native_texture(
+        &mut self,
+        device: &Device,
+        texture: &TextureView,
+        texture_filter: wgpu::FilterMode,
+    ) -> egui::TextureId {
+        self.renderer
+            .register_native_texture(device, texture, texture_filter)
+    }
+
+    pub fn free_texture(&mut self, id: &egui::TextureId) {
+        self.renderer.free_texture(id);
+    }
+
     pub fn ppp(&mut self, v: f32) {
         self.context.set_pixels_per_point(v);
     }
@@ -74,18 +135,68 @@ impl EguiWgpuRenderer {
         window_surface_view: &TextureView,
         screen_descriptor: ScreenDescriptor,
     ) -> egui::PlatformOutput {
+        let mut full_output = self.end_frame(screen_descriptor.pixels_per_point);
+        self.draw(
+            device,
+            queue,
+            encoder,
+            window_surface_view,
+            screen_descriptor,
+            &mut full_output,
+        );
+        full_output.platform_output
+    }
+
+    /// End the pass without drawing, the output can be compared to the
+    /// previous frame before drawing it with `draw`
+    pub fn end_frame(&mut self, pixels_per_point: f32) -> egui::FullOutput {
         if !self.frame_started {
-            panic!("begin_frame must be called before end_frame_and_draw can be called!");
+            panic!("begin_frame must be called before end_frame can be called!");
         }
+        self.frame_started = false;
+        self.ppp(pixels_per_point);
+        self.context.end_pass()
+    }
 
-        self.ppp(screen_descriptor.pixels_per_point);
-
-        let full_output = self.context.end_pass();
+    /// Draw the shapes and apply the texture changes, the shapes are taken
+    pub fn draw(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        encoder: &mut CommandEncoder,
+        window_surface_view: &TextureView,
+        screen_descriptor: ScreenDescriptor,
+        full_output: &mut egui::FullOutput,
+    ) {
+        self.draw_timed(
+            device,
+            queue,
+            encoder,
+            window_surface_view,
+            screen_descriptor,
+            full_output,
+            None,
+        );
+    }
 
+    /// `draw` with timestamps written by the render pass
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_timed(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        encoder: &mut CommandEncoder,
+        window_surface_view: &TextureView,
+        screen_descriptor: ScreenDescriptor,
+        full_output: &mut egui::FullOutput,
+        timestamp_writes: Option<wgpu::RenderPassTimestampWrites<'_>>,
+    ) {
+        let shapes = std::mem::take(&mut full_output.shapes);
+        let textures_delta = &full_output.textures_delta;
         let tris = self
             .context
-            .tessellate(full_output.shapes, self.context.pixels_per_point());
-        for (id, image_delta) in &full_output.textures_delta.set {
+            .tessellate(shapes, self.context.pixels_per_point());
+        for (id, image_delta) in &textures_delta.set {
             self.renderer
                 .update_texture(device, queue, *id, image_delta);
         }
@@ -102,19 +213,58 @@ impl EguiWgpuRenderer {
                 },
             })],
             depth_stencil_attachment: None,
-            timestamp_writes: None,
+            timestamp_writes,
             label: Some("egui main render pass"),
             occlusion_query_set: None,
         });
 
         self.renderer
             .render(&mut rpass.forget_lifetime(), &tris, &screen_descriptor);
-        for x in &full_output.textures_delta.free {
+        for x in &textures_delta.free {
             self.renderer.free_texture(x)
         }
+        self.last_primitives = tris;
+    }
 
-        self.frame_started = false;
-
-        full_output.platform_output
+    /// Draw the last frame again into another target of the same format,
+    /// e.g. a downscaled thumbnail. The renderer's buffers are rewritten so
+    /// this is submitted separately, after the frame itself.
+    pub fn redraw_into(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        view: &TextureView,
+        screen_descriptor: &ScreenDescriptor,
+        clear: wgpu::Color,
+    ) {
+        let mut encoder = device.create_command_encoder(&Default::default());
+        self.renderer.update_buffers(
+            device,
+            queue,
+            &mut encoder,
+            &self.last_primitives,
+            screen_descriptor,
+        );
+        let rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                depth_slice: None,
+                ops: egui_wgpu::wgpu::Operations {
+                    load: egui_wgpu::wgpu::LoadOp::Clear(clear),
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            label: Some("egui redraw pass"),
+            occlusion_query_set: None,
+        });
+        self.renderer.render(
+            &mut rpass.forget_lifetime(),
+            &self.last_primitives,
+            screen_descriptor,
+        );
+        queue.submit(Some(encoder.finish()));
     }
 }
@@ -0,0 +1,119 @@
+use std::fmt;
+
+/// Pixels of a captured frame
+#[derive(Debug, Clone)]
+pub struct CapturedFrame {
+    pub width: u32,
+    pub height: u32,
+    /// RGBA8 in the surface's color space, rows are `width * 4` bytes
+    pub pixels: Vec<u8>,
+}
+
+impl CapturedFrame {
+    pub fn pixel(&self, x: u32, y: u32) -> Option<[u8; 4]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let start = ((y * self.width + x) * 4) as usize;
+        self.pixels[start..start + 4].try_into().ok()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CaptureError {
+    /// Surface hasn't rendered a frame yet, so its size is unknown
+    NothingRendered,
+    /// Only 8-bit RGBA and BGRA surfaces are converted
+    UnsupportedFormat(wgpu::TextureFormat),
+    Failed(String),
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureError::NothingRendered => write!(f, "Surface has not rendered a frame"),
+            CaptureError::UnsupportedFormat(format) => {
+                write!(f, "Capturing {:?} surfaces is not supported", format)
+            }
+            CaptureError::Failed(err) => write!(f, "Capturing the frame failed: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
+
+/// Whether the channels of the format are swapped relative to RGBA
+pub(crate) fn is_bgra(format: wgpu::TextureFormat) -> Result<bool, CaptureError> {
+    match format {
+        wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => Ok(false),
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => Ok(true),
+        format => Err(CaptureError::UnsupportedFormat(format)),
+    }
+}
+
+/// Copy the texture to the CPU as RGBA8, waits for the GPU to finish
+///
+/// The texture needs `COPY_SRC` and one of the formats of `is_bgra`.
+pub(crate) fn read_back(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+) -> Result<CapturedFrame, CaptureError> {
+    let bgra = is_bgra(texture.format())?;
+    let (width, height) = (texture.width(), texture.height());
+    let row = width * 4;
+    // Rows of a buffer copy are aligned to 256 bytes
+    let padded_row =
+        row.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("frame capture readback"),
+        size: (padded_row * height) as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device.create_command_encoder(&Default::default());
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::TexelCopyBufferInfo {
+            buffer: &buffer,
+            layout: wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row),
+                rows_per_image: Some(height),
+            },
+        },
+        texture.size(),
+    );
+    queue.submit(Some(encoder.finish()));
+
+    let slice = buffer.slice(..);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    device
+        .poll(wgpu::PollType::wait_indefinitely())
+        .map_err(|err| CaptureError::Failed(err.to_string()))?;
+    receiver
+        .recv()
+        .map_err(|err| CaptureError::Failed(err.to_string()))?
+        .map_err(|err| CaptureError::Failed(err.to_string()))?;
+
+    let mapped = slice.get_mapped_range();
+    let mut pixels = Vec::with_capacity((row * height) as usize);
+    for padded in mapped.chunks(padded_row as usize) {
+        pixels.extend_from_slice(&padded[..row as usize]);
+    }
+    drop(mapped);
+    buffer.unmap();
+    if bgra {
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+        }
+    }
+    Ok(CapturedFrame {
+        width,
+        height,
+        pixels,
+    })
+}
@@ -0,0 +1,150 @@
+use std::time::Duration;
+use std::time::Instant;
+
+/// Frame interval of the fallback before the refresh of the output is known
+pub(crate) const NOMINAL_FRAME_INTERVAL: Duration = Duration::from_micros(16_667);
+
+/// Stands in for frame callbacks the compositor withholds while an animation
+/// runs, e.g. from a covered layer surface on the bottom layer
+///
+/// Armed when a frame callback is requested mid-animation. If the timer
+/// fires before the callback arrives, the animation is stepped without
+/// rendering so it still ends, and the callback renders once to catch up.
+#[derive(Default)]
+pub(crate) struct FrameFallback {
+    /// Step is due at this time unless the callback arrives first
+    due: Option<Instant>,
+    /// Steps ran since the last callback, the content is behind
+    stepped: bool,
+}
+
+impl FrameFallback {
+    /// Delay of the timer to schedule, `None` while already armed
+    pub fn arm(&mut self, now: Instant, interval: Duration) -> Option<Duration> {
+        if self.due.is_some() {
+            return None;
+        }
+        self.due = Some(now + interval);
+        Some(interval)
+    }
+
+    /// Timer fired, `true` when the callback is still missing and the
+    /// animation is stepped. Timers armed before a callback that did arrive
+    /// are stale and do nothing.
+    pub fn fire(&mut self, now: Instant) -> bool {
+        match self.due {
+            Some(due) if now >= due => {
+                self.due = None;
+                self.stepped = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Frame callback arrived, `true` once after fallback steps so the
+    /// frame renders even if nothing else changed
+    pub fn callback(&mut self) -> bool {
+        self.due = None;
+        std::mem::take(&mut self.stepped)
+    }
+
+    /// Unmapped, no callback is waited for
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERVAL: Duration = Duration::from_millis(16);
+
+    #[test]
+    fn arms_once_until_fired_or_called_back() {
+        let mut fallback = FrameFallback::default();
+        let now = Instant::now();
+        assert_eq!(fallback.arm(now, INTERVAL), Some(INTERVAL));
+        assert_eq!(fallback.arm(now, INTERVAL), None);
+        assert!(fallback.fire(now + INTERVAL));
+        assert_eq!(fallback.arm(now + INTERVAL, INTERVAL), Some(INTERVAL));
+        fallback.callback();
+        assert_eq!(fallback.arm(now + INTERVAL, INTERVAL), Some(INTERVAL));
+    }
+
+    #[test]
+    fn callback_in_time_makes_the_timer_stale() {
+        let mut fallback = FrameFallback::default();
+        let now = Instant::now();
+        fallback.arm(now, INTERVAL);
+        assert!(!fallback.callback());
+        assert!(!fallback.fire(now + INTERVAL));
+    }
+
+    #[test]
+    fn timer_of_an_earlier_arm_is_stale() {
+        let mut fallback = FrameFallback::default();
+        let now = Instant::now();
+        fallback.arm(now, INTERVAL);
+        fallback.callback();
+        let later = now + INTERVAL / 2;
+        fallback.arm(later, INTERVAL);
+        assert!(!fallback.fire(now + INTERVAL));
+        assert!(fallback.fire(later + INTERVAL));
+    }
+
+    #[test]
+    fn reset_drops_pending_steps() {
+        let mut fallback = FrameFallback::default();
+        let now = Instant::now();
+        fallback.arm(now, INTERVAL);
+        assert!(fallback.fire(now + INTERVAL));
+        fallback.reset();
+        assert!(!fallback.callback());
+    }
+
+    /// A panel sliding out over 10 frames while covered: callbacks stop
+    /// after the third frame and resume once it's uncovered
+    #[test]
+    fn withheld_callbacks_complete_the_animation_with_one_catch_up_render() {
+        const TARGET: u32 = 10;
+        let mut fallback = FrameFallback::default();
+        let mut now = Instant::now();
+        let mut position = 0;
+        let mut renders = 0;
+        let mut timer = None;
+
+        // Frame callbacks arrive, each renders a step and requests the next
+        for _ in 0..3 {
+            fallback.callback();
+            position += 1;
+            renders += 1;
+            timer = fallback
+                .arm(now, INTERVAL)
+                .map(|delay| now + delay)
+                .or(timer);
+            now += INTERVAL / 2;
+        }
+
+        // Covered, only the fallback timer fires
+        while let Some(at) = timer.take() {
+            now = at;
+            if fallback.fire(now) {
+                position += 1;
+                if position < TARGET {
+                    timer = fallback.arm(now, INTERVAL).map(|delay| now + delay);
+                }
+            }
+        }
+        assert_eq!(position, TARGET);
+        assert_eq!(renders, 3);
+
+        // Uncovered, the callback renders the final state once
+        if fallback.callback() {
+            renders += 1;
+        }
+        assert_eq!(renders, 4);
+        assert!(!fallback.callback());
+    }
+}
@@ -0,0 +1,439 @@
+use log::debug;
+use log::trace;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::Cursor;
+use std::path::Path;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Key of the per-context textures in the EGUI temporary data
+const TEXTURES_ID: &str = "wayapp-icons";
+
+/// Icon loaded into `Icons`, cheap to copy into the UI state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Icon(u64);
+
+#[derive(Debug)]
+pub enum IconError {
+    Io(std::io::Error),
+    Svg(String),
+    Png(String),
+}
+
+impl fmt::Display for IconError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IconError::Io(err) => write!(f, "Failed to read icon: {}", err),
+            IconError::Svg(err) => write!(f, "Invalid SVG icon: {}", err),
+            IconError::Png(err) => write!(f, "Invalid PNG icon: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for IconError {}
+
+impl From<std::io::Error> for IconError {
+    fn from(err: std::io::Error) -> Self {
+        IconError::Io(err)
+    }
+}
+
+enum Source {
+    /// Rasterized again for every pixel size
+    Svg(Box<resvg::usvg::Tree>),
+    /// Decoded once, EGUI scales the texture
+    Raster(Arc<egui::ColorImage>),
+}
+
+/// Icon and its size in physical pixels
+type RasterKey = (Icon, [usize; 2]);
+
+struct IconsState {
+    sources: HashMap<Icon, Source>,
+    next_id: u64,
+    /// Themed icons already looked up, by name and size
+    themed: HashMap<(String, u32), Option<Icon>>,
+    theme: String,
+    rasters: HashMap<RasterKey, Arc<egui::ColorImage>>,
+    /// Least recently used first
+    recent: VecDeque<RasterKey>,
+    capacity: usize,
+}
+
+/// SVG and PNG icons rasterized at the exact pixel size of the surface
+/// drawing them, clones share the same icons
+///
+/// Rasterizations are cached per icon and pixel size in a bounded LRU shared
+/// by all surfaces, moving a surface to an output of another scale
+/// rasterizes the icon once for the new scale. Each surface has its own EGUI
+/// context, so the textures are uploaded per surface.
+#[derive(Clone)]
+pub struct Icons(Rc<RefCell<IconsState>>);
+
+impl Default for Icons {
+    fn default() -> Self {
+        Self(Rc::new(RefCell::new(IconsState {
+            sources: HashMap::new(),
+            next_id: 0,
+            themed: HashMap::new(),
+            theme: "hicolor".to_string(),
+            rasters: HashMap::new(),
+            recent: VecDeque::new(),
+            capacity: 64,
+        })))
+    }
+}
+
+impl Icons {
+    /// Amount of rasterizations kept, shared by all surfaces
+    pub fn set_cache_size(&self, capacity: usize) {
+        let mut state = self.0.borrow_mut();
+        state.capacity = capacity.max(1);
+        state.evict();
+    }
+
+    /// Icon theme used by `load_themed`, e.g. "Adwaita"
+    ///
+    /// There's no settings portal client in this crate, pass the theme of
+    /// the desktop here. Icons missing from it are looked up in the themes
+    /// it inherits and then in hicolor.
+    pub fn set_theme(&self, theme: &str) {
+        let mut state = self.0.borrow_mut();
+        if state.theme != theme {
+            state.theme = theme.to_string();
+            state.themed.clear();
+        }
+    }
+
+    pub fn load_svg(&self, bytes: &[u8]) -> Result<Icon, IconError> {
+        let tree = resvg::usvg::Tree::from_data(bytes, &resvg::usvg::Options::default())
+            .map_err(|err| IconError::Svg(err.to_string()))?;
+        Ok(self.insert(Source::Svg(Box::new(tree))))
+    }
+
+    pub fn load_png(&self, bytes: &[u8]) -> Result<Icon, IconError> {
+        let image = decode_png(bytes)?;
+        Ok(self.insert(Source::Raster(Arc::new(image))))
+    }
+
+    /// Load an SVG or PNG file, picked by the extension
+    pub fn load_file(&self, path: impl AsRef<Path>) -> Result<Icon, IconError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)?;
+        if path.extension().is_some_and(|ext| ext == "png") {
+            self.load_png(&bytes)
+        } else {
+            self.load_svg(&bytes)
+        }
+    }
+
+    /// Icon of the XDG icon theme closest to the size in logical pixels,
+    /// `None` if no theme has it
+    pub fn load_themed(&self, name: &str, size: u32) -> Option<Icon> {
+        let key = (name.to_string(), size);
+        if let Some(icon) = self.0.borrow().themed.get(&key) {
+            return *icon;
+        }
+        let theme = self.0.borrow().theme.clone();
+        let icon = match lookup_icon(&theme, name, size) {
+            Some(path) => {
+                trace!("[ICONS] Themed icon {} found at {}", name, path.display());
+                self.load_file(&path)
+                    .inspect_err(|err| debug!("[ICONS] {}: {}", path.display(), err))
+                    .ok()
+            }
+            None => {
+                debug!("[ICONS] Themed icon {} not found in {}", name, theme);
+                None
+            }
+        };
+        self.0.borrow_mut().themed.insert(key, icon);
+        icon
+    }
+
+    /// Image of the icon for the context, `size` is in points
+    ///
+    /// Call this every frame, the pixel size follows the scale of the
+    /// surface.
+    pub fn image(&self, ctx: &egui::Context, icon: Icon, size: egui::Vec2) -> egui::Image<'static> {
+        let pixels = size * ctx.pixels_per_point();
+        let pixels = [
+            (pixels.x.round() as usize).max(1),
+            (pixels.y.round() as usize).max(1),
+        ];
+        let Some(image) = self.raster(icon, pixels) else {
+            return egui::Image::new((egui::TextureId::default(), egui::Vec2::ZERO));
+        };
+        let key = (icon, pixels);
+        let id = egui::Id::new(TEXTURES_ID);
+        let cached = ctx.data(|data| {
+            data.get_temp::<Arc<HashMap<RasterKey, egui::TextureHandle>>>(id)
+                .and_then(|textures| textures.get(&key).cloned())
+        });
+        let texture = cached.unwrap_or_else(|| {
+            let texture = ctx.load_texture(
+                format!("icon-{}-{}x{}", icon.0, pixels[0], pixels[1]),
+                image,
+                egui::TextureOptions::LINEAR,
+            );
+            // Textures of evicted rasterizations are freed with the handles
+            let state = self.0.borrow();
+            ctx.data_mut(|data| {
+                let textures = data
+                    .get_temp_mut_or_default::<Arc<HashMap<RasterKey, egui::TextureHandle>>>(id);
+                let textures = Arc::make_mut(textures);
+                textures.retain(|key, _| state.rasters.contains_key(key));
+                textures.insert(key, texture.clone());
+            });
+            texture
+        });
+        egui::Image::new((texture.id(), size))
+    }
+
+    fn insert(&self, source: Source) -> Icon {
+        let mut state = self.0.borrow_mut();
+        let icon = Icon(state.next_id);
+        state.next_id += 1;
+        state.sources.insert(icon, source);
+        icon
+    }
+
+    /// Rasterization of the icon at the pixel size, from the cache if
+    /// any surface drew it at that size recently
+    fn raster(&self, icon: Icon, pixels: [usize; 2]) -> Option<Arc<egui::ColorImage>> {
+        let mut state = self.0.borrow_mut();
+        let key = (icon, pixels);
+        if let Some(image) = state.rasters.get(&key).cloned() {
+            state.touch(key);
+            return Some(image);
+        }
+        let image = match state.sources.get(&icon)? {
+            Source::Svg(tree) => Arc::new(rasterize_svg(tree, pixels)?),
+            Source::Raster(image) => image.clone(),
+        };
+        trace!("[ICONS] Rasterized icon {} at {:?}", icon.0, pixels);
+        state.rasters.insert(key, image.clone());
+        state.touch(key);
+        state.evict();
+        Some(image)
+    }
+}
+
+impl IconsState {
+    fn touch(&mut self, key: RasterKey) {
+        self.recent.retain(|recent| *recent != key);
+        self.recent.push_back(key);
+    }
+
+    fn evict(&mut self) {
+        while self.recent.len() > self.capacity {
+            if let Some(key) = self.recent.pop_front() {
+                self.rasters.remove(&key);
+            }
+        }
+    }
+}
+
+fn rasterize_svg(tree: &resvg::usvg::Tree, pixels: [usize; 2]) -> Option<egui::ColorImage> {
+    let mut pixmap = resvg::tiny_skia::Pixmap::new(pixels[0] as u32, pixels[1] as u32)?;
+    let size = tree.size();
+    let transform = resvg::tiny_skia::Transform::from_scale(
+        pixels[0] as f32 / size.width(),
+        pixels[1] as f32 / size.height(),
+    );
+    resvg::render(tree, transform, &mut pixmap.as_mut());
+    Some(egui::ColorImage::from_rgba_premultiplied(
+        pixels,
+        pixmap.data(),
+    ))
+}
+
+fn decode_png(bytes: &[u8]) -> Result<egui::ColorImage, IconError> {
+    let mut decoder = png::Decoder::new(Cursor::new(bytes));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder
+        .read_info()
+        .map_err(|err| IconError::Png(err.to_string()))?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut buffer)
+        .map_err(|err| IconError::Png(err.to_string()))?;
+    let pixels = &buffer[..info.buffer_size()];
+    let rgba: Vec<u8> = match info.color_type {
+        png::ColorType::Rgba => pixels.to_vec(),
+        png::ColorType::Rgb => pixels
+            .chunks_exact(3)
+            .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => pixels
+            .chunks_exact(2)
+            .flat_map(|ga| [ga[0], ga[0], ga[0], ga[1]])
+            .collect(),
+        png::ColorType::Grayscale => pixels.iter().flat_map(|g| [*g, *g, *g, 255]).collect(),
+        png::ColorType::Indexed => {
+            return Err(IconError::Png("indexed colors not expanded".to_string()));
+        }
+    };
+    let size = [info.width as usize, info.height as usize];
+    Ok(egui::ColorImage::from_rgba_unmultiplied(size, &rgba))
+}
+
+/// Directory of an icon theme from its index.theme
+struct ThemeDirectory {
+    path: String,
+    size: u32,
+    min_size: u32,
+    max_size: u32,
+    threshold: u32,
+    kind: String,
+}
+
+impl ThemeDirectory {
+    /// Distance of the size from the directory as in the icon theme spec
+    fn distance(&self, size: u32) -> u32 {
+        match self.kind.as_str() {
+            "Fixed" => self.size.abs_diff(size),
+            "Scalable" if size < self.min_size => self.min_size - size,
+            "Scalable" if size > self.max_size => size - self.max_size,
+            "Scalable" => 0,
+            _ if size + self.threshold < self.size => self.size - self.threshold - size,
+            _ if size > self.size + self.threshold => size - self.size - self.threshold,
+            _ => 0,
+        }
+    }
+}
+
+/// Base directories of the icon themes in the order of the spec
+fn icon_base_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    if let Some(home) = &home {
+        dirs.push(home.join(".icons"));
+    }
+    match std::env::var_os("XDG_DATA_HOME") {
+        Some(data_home) => dirs.push(PathBuf::from(data_home).join("icons")),
+        None => dirs.extend(home.map(|home| home.join(".local/share/icons"))),
+    }
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+    dirs.extend(
+        data_dirs
+            .split(':')
+            .map(|dir| PathBuf::from(dir).join("icons")),
+    );
+    dirs
+}
+
+/// Directories and inherited themes of the theme, `None` if not installed
+fn read_theme(base_dirs: &[PathBuf], theme: &str) -> Option<(Vec<ThemeDirectory>, Vec<String>)> {
+    let index = base_dirs
+        .iter()
+        .find_map(|dir| std::fs::read_to_string(dir.join(theme).join("index.theme")).ok())?;
+    let mut directories = Vec::new();
+    let mut inherits = Vec::new();
+    let mut section = String::new();
+    let mut current: Option<ThemeDirectory> = None;
+    for line in index.lines().map(str::trim) {
+        if let Some(name) = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            directories.extend(current.take());
+            section = name.to_string();
+            if section != "Icon Theme" {
+                current = Some(ThemeDirectory {
+                    path: section.clone(),
+                    size: 0,
+                    min_size: 0,
+                    max_size: 0,
+                    threshold: 2,
+                    kind: "Threshold".to_string(),
+                });
+            }
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let (key, value) = (key.trim(), value.trim());
+        if section == "Icon Theme" && key == "Inherits" {
+            inherits = value
+                .split(',')
+                .map(|theme| theme.trim().to_string())
+                .collect();
+        }
+        if let Some(directory) = &mut current {
+            match key {
+                "Size" => directory.size = value.parse().unwrap_or(0),
+                "MinSize" => directory.min_size = value.parse().unwrap_or(0),
+                "MaxSize" => directory.max_size = value.parse().unwrap_or(0),
+                "Threshold" => directory.threshold = value.parse().unwrap_or(2),
+                "Type" => directory.kind = value.to_string(),
+                _ => {}
+            }
+        }
+    }
+    directories.extend(current);
+    for directory in &mut directories {
+        if directory.min_size == 0 {
+            directory.min_size = directory.size;
+        }
+        if directory.max_size == 0 {
+            directory.max_size = directory.size;
+        }
+    }
+    Some((directories, inherits))
+}
+
+/// Closest icon of the theme, its inherited themes, hicolor and the pixmaps
+fn lookup_icon(theme: &str, name: &str, size: u32) -> Option<PathBuf> {
+    let base_dirs = icon_base_dirs();
+    let mut themes = vec![theme.to_string()];
+    let mut visited = Vec::new();
+    while let Some(theme) = themes.pop() {
+        if visited.contains(&theme) {
+            continue;
+        }
+        visited.push(theme.clone());
+        let Some((directories, inherits)) = read_theme(&base_dirs, &theme) else {
+            continue;
+        };
+        let mut best: Option<(u32, PathBuf)> = None;
+        for directory in &directories {
+            let distance = directory.distance(size);
+            if best.as_ref().is_some_and(|(best, _)| *best <= distance) {
+                continue;
+            }
+            let found = base_dirs.iter().find_map(|base| {
+                ["svg", "png"].iter().find_map(|ext| {
+                    let path = base
+                        .join(&theme)
+                        .join(&directory.path)
+                        .join(format!("{}.{}", name, ext));
+                    path.is_file().then_some(path)
+                })
+            });
+            if let Some(path) = found {
+                best = Some((distance, path));
+            }
+        }
+        if let Some((_, path)) = best {
+            return Some(path);
+        }
+        // Depth first as in the spec, hicolor comes last
+        themes.extend(inherits.into_iter().rev());
+        if themes.is_empty() && !visited.iter().any(|theme| theme == "hicolor") {
+            themes.push("hicolor".to_string());
+        }
+    }
+    ["svg", "png"].iter().find_map(|ext| {
+        let path = PathBuf::from("/usr/share/pixmaps").join(format!("{}.{}", name, ext));
+        path.is_file().then_some(path)
+    })
+}
@@ -0,0 +1,348 @@
+use smithay_client_toolkit::seat::keyboard::Keysym;
+use smithay_client_toolkit::seat::keyboard::Modifiers;
+
+/// Key combination that enters and leaves the arrange mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArrangeShortcut {
+    pub keysym: Keysym,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub logo: bool,
+}
+
+impl Default for ArrangeShortcut {
+    /// Alt+F8, the resize binding of most desktops
+    fn default() -> Self {
+        Self {
+            keysym: Keysym::F8,
+            ctrl: false,
+            alt: true,
+            shift: false,
+            logo: false,
+        }
+    }
+}
+
+impl ArrangeShortcut {
+    fn matches(&self, keysym: Keysym, modifiers: &Modifiers) -> bool {
+        keysym == self.keysym
+            && modifiers.ctrl == self.ctrl
+            && modifiers.alt == self.alt
+            && modifiers.shift == self.shift
+            && modifiers.logo == self.logo
+    }
+}
+
+/// What the arrange mode can do with a surface
+///
+/// Toplevels can't be moved by the client, only layer surfaces anchored to
+/// one edge of an axis move by their margin. Sizes are fixed while a
+/// toplevel is maximized, fullscreen or tiled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ArrangeCapabilities {
+    pub can_move: bool,
+    pub can_resize: bool,
+}
+
+/// Move or resize requested with an arrow key, in logical pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ArrangeStep {
+    Move(i32, i32),
+    Resize(i32, i32),
+}
+
+/// Result of a key press or repeat
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ArrangeKey {
+    /// Not in the arrange mode, handle the key normally
+    Ignored,
+    /// Swallowed, e.g. entering or leaving the mode or an unused key
+    Consumed,
+    Step(ArrangeStep),
+}
+
+/// Moving and resizing the surface with the keyboard, for when there's no
+/// pointer and the compositor has no bindings of its own
+///
+/// The shortcut enters the mode, arrows move and Shift+arrows resize, and
+/// Enter, Escape or the shortcut again leave it. All keys go to the mode
+/// while it's active.
+#[derive(Debug, Clone)]
+pub struct KeyboardArrange {
+    shortcut: ArrangeShortcut,
+    step: i32,
+    modifiers: Modifiers,
+    /// Capabilities when the mode was entered, `None` when not active
+    active: Option<ArrangeCapabilities>,
+}
+
+impl Default for KeyboardArrange {
+    fn default() -> Self {
+        Self {
+            shortcut: ArrangeShortcut::default(),
+            step: 10,
+            modifiers: Modifiers::default(),
+            active: None,
+        }
+    }
+}
+
+impl KeyboardArrange {
+    /// Alt+F8 and steps of 10 logical pixels
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_shortcut(mut self, shortcut: ArrangeShortcut) -> Self {
+        self.shortcut = shortcut;
+        self
+    }
+
+    /// Pixels moved or resized per arrow key press
+    pub fn with_step(mut self, step: u32) -> Self {
+        self.step = step.max(1) as i32;
+        self
+    }
+
+    pub(crate) fn is_active(&self) -> bool {
+        self.active.is_some()
+    }
+
+    pub(crate) fn exit(&mut self) {
+        self.active = None;
+    }
+
+    pub(crate) fn update_modifiers(&mut self, modifiers: &Modifiers) {
+        self.modifiers = *modifiers;
+    }
+
+    /// Handle a key press, arrows also repeat. The mode isn't entered when
+    /// the surface can neither move nor resize.
+    pub(crate) fn press(
+        &mut self,
+        keysym: Keysym,
+        repeat: bool,
+        capabilities: ArrangeCapabilities,
+    ) -> ArrangeKey {
+        let Some(active) = self.active else {
+            if repeat
+                || !self.shortcut.matches(keysym, &self.modifiers)
+                || !(capabilities.can_move || capabilities.can_resize)
+            {
+                return ArrangeKey::Ignored;
+            }
+            self.active = Some(capabilities);
+            return ArrangeKey::Consumed;
+        };
+        let exits = matches!(keysym, Keysym::Escape | Keysym::Return | Keysym::KP_Enter)
+            || self.shortcut.matches(keysym, &self.modifiers);
+        if exits {
+            if !repeat {
+                self.active = None;
+            }
+            return ArrangeKey::Consumed;
+        }
+        let (dx, dy) = match keysym {
+            Keysym::Left => (-self.step, 0),
+            Keysym::Right => (self.step, 0),
+            Keysym::Up => (0, -self.step),
+            Keysym::Down => (0, self.step),
+            _ => return ArrangeKey::Consumed,
+        };
+        match self.modifiers.shift {
+            true if active.can_resize => ArrangeKey::Step(ArrangeStep::Resize(dx, dy)),
+            false if active.can_move => ArrangeKey::Step(ArrangeStep::Move(dx, dy)),
+            _ => ArrangeKey::Consumed,
+        }
+    }
+
+    /// Border around the surface and a hint of the keys, drawn over the app
+    pub(crate) fn paint(&self, ctx: &egui::Context) {
+        let Some(active) = self.active else {
+            return;
+        };
+        let hint = match (active.can_move, active.can_resize) {
+            (true, true) => "Arrows move, Shift+arrows resize, Enter to finish",
+            (true, false) => "Arrows move, Enter to finish",
+            _ => "Shift+arrows resize, Enter to finish",
+        };
+        let visuals = ctx.style().visuals.clone();
+        let painter = ctx.layer_painter(egui::LayerId::new(
+            egui::Order::Foreground,
+            egui::Id::new("wayapp_keyboard_arrange"),
+        ));
+        let rect = painter.clip_rect();
+        painter.rect_stroke(
+            rect,
+            0.0,
+            egui::Stroke::new(3.0, visuals.selection.bg_fill),
+            egui::StrokeKind::Inside,
+        );
+        let galley = painter.layout_no_wrap(
+            hint.to_string(),
+            egui::FontId::proportional(14.0),
+            visuals.strong_text_color(),
+        );
+        let text_rect = egui::Align2::CENTER_CENTER.anchor_size(rect.center(), galley.size());
+        painter.rect_filled(text_rect.expand(6.0), 4.0, visuals.extreme_bg_color);
+        painter.galley(text_rect.min, galley, visuals.strong_text_color());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOTH: ArrangeCapabilities = ArrangeCapabilities {
+        can_move: true,
+        can_resize: true,
+    };
+
+    fn alt() -> Modifiers {
+        Modifiers {
+            alt: true,
+            ..Default::default()
+        }
+    }
+
+    fn shift() -> Modifiers {
+        Modifiers {
+            shift: true,
+            ..Default::default()
+        }
+    }
+
+    /// Arrange mode entered with the default shortcut
+    fn entered(capabilities: ArrangeCapabilities) -> KeyboardArrange {
+        let mut arrange = KeyboardArrange::new();
+        arrange.update_modifiers(&alt());
+        assert_eq!(
+            arrange.press(Keysym::F8, false, capabilities),
+            ArrangeKey::Consumed
+        );
+        arrange.update_modifiers(&Modifiers::default());
+        assert!(arrange.is_active());
+        arrange
+    }
+
+    #[test]
+    fn keys_are_ignored_until_the_shortcut() {
+        let mut arrange = KeyboardArrange::new();
+        assert_eq!(arrange.press(Keysym::F8, false, BOTH), ArrangeKey::Ignored);
+        assert_eq!(
+            arrange.press(Keysym::Left, false, BOTH),
+            ArrangeKey::Ignored
+        );
+        arrange.update_modifiers(&alt());
+        // A repeat of the shortcut doesn't enter the mode
+        assert_eq!(arrange.press(Keysym::F8, true, BOTH), ArrangeKey::Ignored);
+        assert!(!arrange.is_active());
+    }
+
+    #[test]
+    fn not_entered_without_capabilities() {
+        let mut arrange = KeyboardArrange::new();
+        arrange.update_modifiers(&alt());
+        assert_eq!(
+            arrange.press(Keysym::F8, false, ArrangeCapabilities::default()),
+            ArrangeKey::Ignored
+        );
+        assert!(!arrange.is_active());
+    }
+
+    #[test]
+    fn arrows_move_and_shift_arrows_resize() {
+        let mut arrange = entered(BOTH).with_step(5);
+        assert_eq!(
+            arrange.press(Keysym::Left, false, BOTH),
+            ArrangeKey::Step(ArrangeStep::Move(-5, 0))
+        );
+        assert_eq!(
+            arrange.press(Keysym::Down, true, BOTH),
+            ArrangeKey::Step(ArrangeStep::Move(0, 5))
+        );
+        arrange.update_modifiers(&shift());
+        assert_eq!(
+            arrange.press(Keysym::Right, false, BOTH),
+            ArrangeKey::Step(ArrangeStep::Resize(5, 0))
+        );
+        assert_eq!(
+            arrange.press(Keysym::Up, false, BOTH),
+            ArrangeKey::Step(ArrangeStep::Resize(0, -5))
+        );
+        // Other keys are swallowed while active
+        assert_eq!(arrange.press(Keysym::a, false, BOTH), ArrangeKey::Consumed);
+    }
+
+    #[test]
+    fn capabilities_of_entering_apply() {
+        let move_only = ArrangeCapabilities {
+            can_move: true,
+            can_resize: false,
+        };
+        let mut arrange = entered(move_only);
+        arrange.update_modifiers(&shift());
+        // Capabilities given later don't widen the mode
+        assert_eq!(
+            arrange.press(Keysym::Right, false, BOTH),
+            ArrangeKey::Consumed
+        );
+        arrange.update_modifiers(&Modifiers::default());
+        assert_eq!(
+            arrange.press(Keysym::Right, false, BOTH),
+            ArrangeKey::Step(ArrangeStep::Move(10, 0))
+        );
+    }
+
+    #[test]
+    fn enter_escape_and_shortcut_leave() {
+        for keysym in [Keysym::Return, Keysym::KP_Enter, Keysym::Escape] {
+            let mut arrange = entered(BOTH);
+            // A held key repeating doesn't leave
+            assert_eq!(arrange.press(keysym, true, BOTH), ArrangeKey::Consumed);
+            assert!(arrange.is_active());
+            assert_eq!(arrange.press(keysym, false, BOTH), ArrangeKey::Consumed);
+            assert!(!arrange.is_active());
+            assert_eq!(
+                arrange.press(Keysym::Left, false, BOTH),
+                ArrangeKey::Ignored
+            );
+        }
+
+        let mut arrange = entered(BOTH);
+        arrange.update_modifiers(&alt());
+        assert_eq!(arrange.press(Keysym::F8, false, BOTH), ArrangeKey::Consumed);
+        assert!(!arrange.is_active());
+    }
+
+    #[test]
+    fn exit_leaves_the_mode() {
+        let mut arrange = entered(BOTH);
+        arrange.exit();
+        assert!(!arrange.is_active());
+    }
+
+    #[test]
+    fn custom_shortcut_needs_exact_modifiers() {
+        let mut arrange = KeyboardArrange::new().with_shortcut(ArrangeShortcut {
+            keysym: Keysym::r,
+            ctrl: false,
+            alt: false,
+            shift: false,
+            logo: true,
+        });
+        arrange.update_modifiers(&Modifiers {
+            logo: true,
+            shift: true,
+            ..Default::default()
+        });
+        assert_eq!(arrange.press(Keysym::r, false, BOTH), ArrangeKey::Ignored);
+        arrange.update_modifiers(&Modifiers {
+            logo: true,
+            ..Default::default()
+        });
+        assert_eq!(arrange.press(Keysym::r, false, BOTH), ArrangeKey::Consumed);
+        assert!(arrange.is_active());
+    }
+}
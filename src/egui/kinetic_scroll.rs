@@ -0,0 +1,203 @@
+use egui::Vec2;
+use std::collections::VecDeque;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Scrolling of the last moment before the fingers lift sets the velocity
+const SAMPLE_WINDOW: Duration = Duration::from_millis(100);
+
+/// Coasting stops below this in points per second, about half a point per
+/// frame
+const STOP_VELOCITY: f32 = 30.0;
+
+/// Slower flicks than this in points per second don't coast
+const MIN_VELOCITY: f32 = 100.0;
+
+/// Touchpad scrolling that keeps going after the fingers lift, see
+/// `EguiWindow::with_kinetic_scrolling`
+///
+/// Off when the EGUI style has no animation time, apps honoring a reduced
+/// motion preference set it to zero.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KineticScrolling {
+    /// Exponential decay of the velocity per second, positive, higher stops
+    /// sooner
+    pub friction: f32,
+}
+
+impl Default for KineticScrolling {
+    fn default() -> Self {
+        Self { friction: 4.0 }
+    }
+}
+
+/// Velocity of finger scrolling and the coast after it
+pub(crate) struct KineticScroll {
+    settings: KineticScrolling,
+    samples: VecDeque<(Instant, Vec2)>,
+    /// Velocity in points per second and the time of the last step
+    coast: Option<(Vec2, Instant)>,
+}
+
+impl KineticScroll {
+    pub fn new(settings: KineticScrolling) -> Self {
+        Self {
+            settings,
+            samples: VecDeque::new(),
+            coast: None,
+        }
+    }
+
+    /// Finger scrolling, stops a coast
+    pub fn track(&mut self, now: Instant, delta: Vec2) {
+        self.coast = None;
+        self.samples.push_back((now, delta));
+        while self
+            .samples
+            .front()
+            .is_some_and(|(time, _)| now.duration_since(*time) > SAMPLE_WINDOW)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Fingers lifted, coasts if they were moving fast enough
+    pub fn release(&mut self, now: Instant) {
+        let samples = std::mem::take(&mut self.samples);
+        let Some((first, _)) = samples.front() else {
+            return;
+        };
+        if now.duration_since(*first) > SAMPLE_WINDOW {
+            // Fingers rested before lifting
+            return;
+        }
+        let distance = samples
+            .iter()
+            .map(|(_, delta)| *delta)
+            .fold(Vec2::ZERO, |a, d| a + d);
+        // A single sample has no duration, it's spread over a typical frame
+        let span = now
+            .duration_since(*first)
+            .max(Duration::from_millis(16))
+            .as_secs_f32();
+        let velocity = distance / span;
+        if velocity.length() >= MIN_VELOCITY {
+            self.coast = Some((velocity, now));
+        }
+    }
+
+    /// Any other input stops the coast
+    pub fn cancel(&mut self) {
+        self.samples.clear();
+        self.coast = None;
+    }
+
+    /// Distance to scroll this frame, `None` once the coast has ended
+    pub fn step(&mut self, now: Instant) -> Option<Vec2> {
+        let (velocity, last) = self.coast?;
+        let dt = now.duration_since(last).as_secs_f32();
+        let decayed = velocity * (-self.settings.friction * dt).exp();
+        if decayed.length() < STOP_VELOCITY {
+            self.coast = None;
+            return None;
+        }
+        self.coast = Some((decayed, now));
+        // Distance covered is the integral of the decaying velocity
+        Some((velocity - decayed) / self.settings.friction)
+    }
+
+    pub fn is_coasting(&self) -> bool {
+        self.coast.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(start: Instant, millis: u64) -> Instant {
+        start + Duration::from_millis(millis)
+    }
+
+    fn flick(scroll: &mut KineticScroll, start: Instant) {
+        // 10 points every 10 ms, 1000 points per second
+        for i in 0..5 {
+            scroll.track(ms(start, i * 10), Vec2::new(0.0, 10.0));
+        }
+        scroll.release(ms(start, 50));
+    }
+
+    #[test]
+    fn velocity_from_samples_in_window() {
+        let start = Instant::now();
+        let mut scroll = KineticScroll::new(KineticScrolling::default());
+        // Slow scrolling long before the flick is outside the window
+        scroll.track(start, Vec2::new(0.0, 1.0));
+        flick(&mut scroll, ms(start, 500));
+        assert!(scroll.is_coasting());
+        let (velocity, _) = scroll.coast.unwrap();
+        assert_eq!(velocity, Vec2::new(0.0, 1000.0));
+    }
+
+    #[test]
+    fn single_sample_spans_a_frame() {
+        let start = Instant::now();
+        let mut scroll = KineticScroll::new(KineticScrolling::default());
+        scroll.track(start, Vec2::new(16.0, 0.0));
+        scroll.release(start);
+        let (velocity, _) = scroll.coast.unwrap();
+        assert!((velocity.x - 1000.0).abs() < 1e-2);
+        assert_eq!(velocity.y, 0.0);
+    }
+
+    #[test]
+    fn no_coast_after_resting_or_slow_flick() {
+        let start = Instant::now();
+        let mut scroll = KineticScroll::new(KineticScrolling::default());
+        scroll.track(start, Vec2::new(0.0, 50.0));
+        scroll.release(ms(start, 150));
+        assert!(!scroll.is_coasting());
+
+        // 1 point per 16 ms frame is below the minimum velocity
+        scroll.track(start, Vec2::new(0.0, 1.0));
+        scroll.release(start);
+        assert!(!scroll.is_coasting());
+    }
+
+    #[test]
+    fn step_covers_decayed_distance() {
+        let start = Instant::now();
+        let settings = KineticScrolling { friction: 4.0 };
+        let mut scroll = KineticScroll::new(settings);
+        flick(&mut scroll, start);
+        let step = scroll.step(ms(start, 150)).unwrap();
+        let decayed = 1000.0 * (-settings.friction * 0.1f32).exp();
+        assert!((step.y - (1000.0 - decayed) / settings.friction).abs() < 1e-3);
+        assert_eq!(step.x, 0.0);
+    }
+
+    #[test]
+    fn coast_stops_below_cutoff() {
+        let start = Instant::now();
+        let mut scroll = KineticScroll::new(KineticScrolling::default());
+        flick(&mut scroll, start);
+        // 1000 * e^(-4t) drops below 30 after about 0.88 s
+        assert!(scroll.step(ms(start, 850)).is_some());
+        assert!(scroll.is_coasting());
+        assert_eq!(scroll.step(ms(start, 1000)), None);
+        assert!(!scroll.is_coasting());
+    }
+
+    #[test]
+    fn tracking_or_cancel_stops_coast() {
+        let start = Instant::now();
+        let mut scroll = KineticScroll::new(KineticScrolling::default());
+        flick(&mut scroll, start);
+        scroll.track(ms(start, 60), Vec2::new(0.0, 1.0));
+        assert!(!scroll.is_coasting());
+
+        flick(&mut scroll, ms(start, 1000));
+        scroll.cancel();
+        assert_eq!(scroll.step(ms(start, 1100)), None);
+    }
+}
@@ -0,0 +1,103 @@
+use crate::Application;
+use std::sync::Arc;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use wayland_client::Connection;
+use wayland_client::Dispatch;
+use wayland_client::QueueHandle;
+use wayland_client::protocol::wl_callback;
+use wayland_client::protocol::wl_callback::WlCallback;
+
+/// Tells configures answering the initial commit of a surface shown again
+/// from ones the compositor sent for the mapping before it was hidden
+///
+/// Showing sends a `wl_display.sync` right before the initial commit. The
+/// compositor handles requests in order, so every configure sent before the
+/// sync is done belongs to the earlier mapping. Each show is a new
+/// generation and only the sync of the latest one opens the fence, rapid
+/// hide and show toggles wait for the last show.
+#[derive(Default)]
+pub(crate) struct MapFence {
+    /// Generation of the latest show
+    generation: u64,
+    /// Latest generation whose sync is done
+    passed: Arc<AtomicU64>,
+}
+
+impl MapFence {
+    /// New generation, send the sync with the returned data before the
+    /// initial commit
+    pub fn show(&mut self) -> MapFenceSync {
+        self.generation += 1;
+        MapFenceSync {
+            generation: self.generation,
+            passed: self.passed.clone(),
+        }
+    }
+
+    /// Sync of the latest show is done, configures are current
+    pub fn is_open(&self) -> bool {
+        self.passed.load(Ordering::Acquire) >= self.generation
+    }
+}
+
+/// User data of the `wl_display.sync` callback of a show
+pub(crate) struct MapFenceSync {
+    generation: u64,
+    passed: Arc<AtomicU64>,
+}
+
+impl MapFenceSync {
+    fn done(&self) {
+        self.passed.fetch_max(self.generation, Ordering::AcqRel);
+    }
+}
+
+impl Dispatch<WlCallback, MapFenceSync> for Application {
+    fn event(
+        _: &mut Self,
+        _: &WlCallback,
+        event: wl_callback::Event,
+        sync: &MapFenceSync,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let wl_callback::Event::Done { .. } = event {
+            sync.done();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn open_until_shown() {
+        assert!(MapFence::default().is_open());
+    }
+
+    #[test]
+    fn configure_before_the_sync_is_stale() {
+        let mut fence = MapFence::default();
+        // Hidden and shown again, the configure of the old mapping arrives
+        let sync = fence.show();
+        assert!(!fence.is_open());
+        sync.done();
+        assert!(fence.is_open());
+    }
+
+    #[test]
+    fn rapid_toggles_wait_for_the_last_show() {
+        let mut fence = MapFence::default();
+        let first = fence.show();
+        let second = fence.show();
+        first.done();
+        assert!(!fence.is_open());
+        second.done();
+        assert!(fence.is_open());
+        // Done events can't close it again
+        first.done();
+        assert!(fence.is_open());
+    }
+}
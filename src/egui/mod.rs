@@ -1,6 +1,38 @@
+mod buffer_transform;
+mod client_decorations;
+mod clipboard;
+mod custom_draw;
+mod dock;
 mod egui_containers;
 mod egui_input_handler;
 mod egui_wgpu_renderer;
+mod frame_capture;
+mod frame_fallback;
+#[cfg(feature = "icons")]
+mod icons;
+mod keyboard_arrange;
+mod kinetic_scroll;
+mod map_fence;
+mod size_request;
+mod theme_transition;
+mod thumbnails;
+mod unicode_entry;
+pub use custom_draw::CustomDrawFrame;
+pub use dock::DockHandle;
+pub use dock::DockMode;
 pub use egui_containers::*;
+pub use egui_input_handler::RawInputEvent;
 pub use egui_input_handler::WaylandToEguiInput;
 pub use egui_wgpu_renderer::EguiWgpuRenderer;
+pub use frame_capture::CaptureError;
+pub use frame_capture::CapturedFrame;
+#[cfg(feature = "icons")]
+pub use icons::*;
+pub use keyboard_arrange::ArrangeCapabilities;
+pub use keyboard_arrange::ArrangeShortcut;
+pub use keyboard_arrange::KeyboardArrange;
+pub use kinetic_scroll::KineticScrolling;
+pub use theme_transition::mix_visuals;
+pub use theme_transition::set_theme_animated;
+pub use thumbnails::Thumbnail;
+pub use thumbnails::Thumbnails;
@@ -0,0 +1,180 @@
+use smithay_client_toolkit::reexports::csd_frame::WindowState;
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Interactive resize configures arriving this long after the requested
+/// state are still stragglers of the ended drag
+const STRAGGLER_WINDOW: Duration = Duration::from_millis(500);
+
+/// Requests without a configure of the requested state this long after
+/// they were made count as declined, e.g. by a compositor that doesn't
+/// maximize during an interactive resize
+const REQUEST_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Configures that may have been in flight when the request was made, later
+/// ones were sent after the compositor saw it. The toolkit doesn't expose
+/// configure serials, so configures are counted instead.
+const IN_FLIGHT_CONFIGURES: u32 = 4;
+
+#[derive(Debug, Clone, Copy)]
+struct Pending {
+    state: WindowState,
+    wanted: bool,
+    requested_at: Instant,
+    /// Configures received since the request
+    configures: u32,
+    /// Configure with the requested state arrived at
+    settled_at: Option<Instant>,
+}
+
+/// Maximize or fullscreen change requested by this client, shared by the
+/// window and its client-side decorations
+///
+/// A resize drag and e.g. a titlebar double-click can race, configures of
+/// the drag then arrive around the one of the maximize. Their size is of
+/// the drag the request ended, rendering it would show the maximized
+/// window at the old drag size. Sizes of unmaximized configures are only
+/// hints, so the stragglers are acked without being rendered.
+#[derive(Clone, Default)]
+pub(crate) struct SizeRequest(Rc<Cell<Option<Pending>>>);
+
+impl SizeRequest {
+    /// Expect the state set or unset, replaces an earlier request
+    pub fn request(&self, state: WindowState, wanted: bool, now: Instant) {
+        self.0.set(Some(Pending {
+            state,
+            wanted,
+            requested_at: now,
+            configures: 0,
+            settled_at: None,
+        }));
+    }
+
+    /// Whether the configured state is an interactive resize straggler of
+    /// the request. Configures without resizing settle or clear it, e.g.
+    /// when the compositor declines the request. A request still unanswered
+    /// after the timeout or the configures in flight is declined too, the
+    /// drag it raced with resizes again.
+    pub fn is_straggler(&self, state: WindowState, now: Instant) -> bool {
+        let Some(mut pending) = self.0.get() else {
+            return false;
+        };
+        pending.configures += 1;
+        let expired = match pending.settled_at {
+            Some(at) => now.saturating_duration_since(at) > STRAGGLER_WINDOW,
+            None => {
+                now.saturating_duration_since(pending.requested_at) > REQUEST_TIMEOUT
+                    || pending.configures > IN_FLIGHT_CONFIGURES
+            }
+        };
+        if expired {
+            self.0.set(None);
+            return false;
+        }
+        if state.contains(pending.state) == pending.wanted {
+            pending.settled_at.get_or_insert(now);
+            self.0.set(Some(pending));
+            return false;
+        }
+        if !state.contains(WindowState::RESIZING) {
+            self.0.set(None);
+            return false;
+        }
+        self.0.set(Some(pending));
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESIZING: WindowState = WindowState::RESIZING;
+    const MAXIMIZED: WindowState = WindowState::MAXIMIZED;
+    const MS: Duration = Duration::from_millis(1);
+
+    fn maximize_requested(now: Instant) -> SizeRequest {
+        let request = SizeRequest::default();
+        request.request(MAXIMIZED, true, now);
+        request
+    }
+
+    #[test]
+    fn nothing_is_a_straggler_without_a_request() {
+        let request = SizeRequest::default();
+        assert!(!request.is_straggler(RESIZING, Instant::now()));
+    }
+
+    #[test]
+    fn resize_configures_around_the_requested_state_are_stragglers() {
+        let now = Instant::now();
+        let request = maximize_requested(now);
+        assert!(request.is_straggler(RESIZING, now + MS));
+        assert!(!request.is_straggler(MAXIMIZED, now + 2 * MS));
+        assert!(request.is_straggler(RESIZING, now + 3 * MS));
+        // The requested state itself is always rendered
+        assert!(!request.is_straggler(MAXIMIZED | RESIZING, now + 4 * MS));
+    }
+
+    #[test]
+    fn settled_request_expires_after_the_window() {
+        let now = Instant::now();
+        let request = maximize_requested(now);
+        assert!(!request.is_straggler(MAXIMIZED, now));
+        let later = now + STRAGGLER_WINDOW + MS;
+        assert!(!request.is_straggler(RESIZING, later));
+        // Cleared, a new drag isn't held back
+        assert!(!request.is_straggler(RESIZING, later + MS));
+    }
+
+    #[test]
+    fn configure_without_resizing_declines_the_request() {
+        let now = Instant::now();
+        let request = maximize_requested(now);
+        assert!(!request.is_straggler(WindowState::empty(), now + MS));
+        assert!(!request.is_straggler(RESIZING, now + 2 * MS));
+    }
+
+    #[test]
+    fn ignored_request_expires_after_the_configures_in_flight() {
+        let now = Instant::now();
+        let request = maximize_requested(now);
+        for configure in 1..=IN_FLIGHT_CONFIGURES {
+            assert!(request.is_straggler(RESIZING, now + configure * MS));
+        }
+        assert!(!request.is_straggler(RESIZING, now + 10 * MS));
+        assert!(!request.is_straggler(RESIZING, now + 11 * MS));
+    }
+
+    #[test]
+    fn ignored_request_expires_after_the_timeout() {
+        let now = Instant::now();
+        let request = maximize_requested(now);
+        assert!(request.is_straggler(RESIZING, now + MS));
+        let later = now + REQUEST_TIMEOUT + MS;
+        assert!(!request.is_straggler(RESIZING, later));
+        assert!(!request.is_straggler(RESIZING, later + MS));
+    }
+
+    #[test]
+    fn unmaximize_settles_on_a_configure_without_the_state() {
+        let now = Instant::now();
+        let request = SizeRequest::default();
+        request.request(MAXIMIZED, false, now);
+        assert!(request.is_straggler(MAXIMIZED | RESIZING, now + MS));
+        assert!(!request.is_straggler(RESIZING, now + 2 * MS));
+        assert!(request.is_straggler(MAXIMIZED | RESIZING, now + 3 * MS));
+    }
+
+    #[test]
+    fn newer_request_replaces_the_pending_one() {
+        let now = Instant::now();
+        let request = maximize_requested(now);
+        assert!(!request.is_straggler(MAXIMIZED, now + MS));
+        request.request(WindowState::FULLSCREEN, true, now + 2 * MS);
+        assert!(request.is_straggler(MAXIMIZED | RESIZING, now + 3 * MS));
+        assert!(!request.is_straggler(WindowState::FULLSCREEN, now + 4 * MS));
+    }
+}
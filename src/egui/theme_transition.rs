@@ -0,0 +1,200 @@
+use egui::Color32;
+use egui::Context;
+use egui::Stroke;
+use egui::Theme;
+use egui::Visuals;
+use egui::style::WidgetVisuals;
+use std::time::Duration;
+
+/// Key of the transition in the EGUI temporary data
+const TRANSITION_ID: &str = "wayapp-theme-transition";
+
+#[derive(Clone)]
+struct ThemeTransition {
+    /// Visuals when the transition started, the interpolated ones if it
+    /// replaced another transition
+    from: Visuals,
+    target: Theme,
+    /// Theme active before the first transition, its visuals are
+    /// overwritten while transitioning and restored at the end
+    base_theme: Theme,
+    base_visuals: Visuals,
+    /// Input time of the context
+    started: f64,
+    duration: f64,
+    easing: fn(f32) -> f32,
+}
+
+/// Switch the theme of the context with a fade of the colors, e.g. with
+/// `egui::emath::easing::cubic_in_out`
+///
+/// The interpolated colors are in `ctx.style().visuals` while it runs, for
+/// widgets painting with their own colors. Another transition started
+/// meanwhile continues from the colors shown at that moment. The surfaces
+/// redraw every frame until the target theme is reached exactly.
+pub fn set_theme_animated(ctx: &Context, theme: Theme, duration: Duration, easing: fn(f32) -> f32) {
+    let id = egui::Id::new(TRANSITION_ID);
+    let running = ctx.data(|data| data.get_temp::<ThemeTransition>(id));
+    let (base_theme, base_visuals) = match &running {
+        Some(transition) => (transition.base_theme, transition.base_visuals.clone()),
+        None => (ctx.theme(), ctx.style().visuals.clone()),
+    };
+    let transition = ThemeTransition {
+        from: ctx.style().visuals.clone(),
+        target: theme,
+        base_theme,
+        base_visuals,
+        started: ctx.input(|input| input.time),
+        duration: duration.as_secs_f64(),
+        easing,
+    };
+    ctx.data_mut(|data| data.insert_temp(id, transition));
+    ctx.request_repaint();
+}
+
+/// Apply the colors of the running transition for this frame, false once
+/// there is none
+pub(crate) fn apply_theme_transition(ctx: &Context) -> bool {
+    let id = egui::Id::new(TRANSITION_ID);
+    let Some(transition) = ctx.data(|data| data.get_temp::<ThemeTransition>(id)) else {
+        return false;
+    };
+    let elapsed = ctx.input(|input| input.time) - transition.started;
+    let progress = if transition.duration > 0.0 {
+        (elapsed / transition.duration).clamp(0.0, 1.0) as f32
+    } else {
+        1.0
+    };
+    if progress >= 1.0 {
+        ctx.data_mut(|data| data.remove::<ThemeTransition>(id));
+        ctx.set_visuals_of(transition.base_theme, transition.base_visuals);
+        ctx.set_theme(transition.target);
+        return false;
+    }
+    // Visuals of the base theme are the interpolated ones meanwhile
+    let target = if transition.target == transition.base_theme {
+        transition.base_visuals.clone()
+    } else {
+        ctx.style_of(transition.target).visuals.clone()
+    };
+    let visuals = mix_visuals(&transition.from, &target, (transition.easing)(progress));
+    ctx.set_visuals_of(transition.base_theme, visuals);
+    true
+}
+
+/// Colors of `from` moved toward `to` by `t`, everything else is from `to`
+pub fn mix_visuals(from: &Visuals, to: &Visuals, t: f32) -> Visuals {
+    let mix = |a: Color32, b: Color32| a.lerp_to_gamma(b, t);
+    let mix_stroke =
+        |a: Stroke, b: Stroke| Stroke::new(egui::lerp(a.width..=b.width, t), mix(a.color, b.color));
+    let mix_option = |a: Option<Color32>, b: Option<Color32>| match (a, b) {
+        (Some(a), Some(b)) => Some(mix(a, b)),
+        (_, b) => b,
+    };
+    let mix_widget = |a: &WidgetVisuals, b: &WidgetVisuals| WidgetVisuals {
+        bg_fill: mix(a.bg_fill, b.bg_fill),
+        weak_bg_fill: mix(a.weak_bg_fill, b.weak_bg_fill),
+        bg_stroke: mix_stroke(a.bg_stroke, b.bg_stroke),
+        fg_stroke: mix_stroke(a.fg_stroke, b.fg_stroke),
+        ..*b
+    };
+    let mut visuals = to.clone();
+    visuals.override_text_color = mix_option(from.override_text_color, to.override_text_color);
+    let (a, b) = (&from.widgets, &to.widgets);
+    visuals.widgets.noninteractive = mix_widget(&a.noninteractive, &b.noninteractive);
+    visuals.widgets.inactive = mix_widget(&a.inactive, &b.inactive);
+    visuals.widgets.hovered = mix_widget(&a.hovered, &b.hovered);
+    visuals.widgets.active = mix_widget(&a.active, &b.active);
+    visuals.widgets.open = mix_widget(&a.open, &b.open);
+    visuals.selection.bg_fill = mix(from.selection.bg_fill, to.selection.bg_fill);
+    visuals.selection.stroke = mix_stroke(from.selection.stroke, to.selection.stroke);
+    visuals.hyperlink_color = mix(from.hyperlink_color, to.hyperlink_color);
+    visuals.faint_bg_color = mix(from.faint_bg_color, to.faint_bg_color);
+    visuals.extreme_bg_color = mix(from.extreme_bg_color, to.extreme_bg_color);
+    visuals.code_bg_color = mix(from.code_bg_color, to.code_bg_color);
+    visuals.warn_fg_color = mix(from.warn_fg_color, to.warn_fg_color);
+    visuals.error_fg_color = mix(from.error_fg_color, to.error_fg_color);
+    visuals.window_fill = mix(from.window_fill, to.window_fill);
+    visuals.window_stroke = mix_stroke(from.window_stroke, to.window_stroke);
+    visuals.panel_fill = mix(from.panel_fill, to.panel_fill);
+    visuals.window_shadow.color = mix(from.window_shadow.color, to.window_shadow.color);
+    visuals.popup_shadow.color = mix(from.popup_shadow.color, to.popup_shadow.color);
+    visuals.text_cursor.stroke = mix_stroke(from.text_cursor.stroke, to.text_cursor.stroke);
+    visuals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mix_at_end_is_target() {
+        let (dark, light) = (Visuals::dark(), Visuals::light());
+        assert_eq!(mix_visuals(&dark, &light, 1.0), light);
+        assert_eq!(mix_visuals(&light, &dark, 1.0), dark);
+    }
+
+    #[test]
+    fn mix_at_start_has_source_colors() {
+        let (dark, light) = (Visuals::dark(), Visuals::light());
+        let mixed = mix_visuals(&dark, &light, 0.0);
+        assert_eq!(mixed.window_fill, dark.window_fill);
+        assert_eq!(mixed.panel_fill, dark.panel_fill);
+        assert_eq!(mixed.selection, dark.selection);
+        assert_eq!(
+            mixed.widgets.inactive.bg_fill,
+            dark.widgets.inactive.bg_fill
+        );
+        assert_eq!(
+            mixed.widgets.hovered.fg_stroke,
+            dark.widgets.hovered.fg_stroke
+        );
+        assert_eq!(mixed.text_cursor.stroke, dark.text_cursor.stroke);
+        // Everything that isn't a color is from the target right away
+        assert_eq!(mixed.dark_mode, light.dark_mode);
+        assert_eq!(
+            mixed.widgets.inactive.corner_radius,
+            light.widgets.inactive.corner_radius
+        );
+    }
+
+    #[test]
+    fn mix_at_midpoint_is_between() {
+        let from = Visuals {
+            window_fill: Color32::from_rgb(0, 100, 200),
+            window_stroke: Stroke::new(1.0, Color32::BLACK),
+            ..Visuals::dark()
+        };
+        let to = Visuals {
+            window_fill: Color32::from_rgb(200, 100, 0),
+            window_stroke: Stroke::new(3.0, Color32::WHITE),
+            ..Visuals::light()
+        };
+        let mixed = mix_visuals(&from, &to, 0.5);
+        assert_eq!(mixed.window_fill, Color32::from_rgb(100, 100, 100));
+        assert_eq!(mixed.window_stroke.width, 2.0);
+        assert_eq!(mixed.window_stroke.color, Color32::from_gray(128));
+    }
+
+    #[test]
+    fn override_text_color_is_mixed_only_when_both_have_one() {
+        let with = |color| Visuals {
+            override_text_color: color,
+            ..Visuals::dark()
+        };
+        let black = Some(Color32::BLACK);
+        let white = Some(Color32::WHITE);
+        assert_eq!(
+            mix_visuals(&with(black), &with(white), 0.5).override_text_color,
+            Some(Color32::from_gray(128))
+        );
+        assert_eq!(
+            mix_visuals(&with(None), &with(white), 0.5).override_text_color,
+            white
+        );
+        assert_eq!(
+            mix_visuals(&with(black), &with(None), 0.5).override_text_color,
+            None
+        );
+    }
+}
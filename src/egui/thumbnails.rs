@@ -0,0 +1,156 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Duration;
+use std::time::Instant;
+use wayland_backend::client::ObjectId;
+
+/// Downscaled copy of a surface, the texture is updated in place
+#[derive(Clone)]
+pub struct Thumbnail {
+    /// Same format as the surface, usable as a native texture in EGUI
+    pub view: wgpu::TextureView,
+    pub width: u32,
+    pub height: u32,
+    pub captured_at: Instant,
+    /// Changes when the texture is recreated, e.g. after a resize, the view
+    /// must then be registered again
+    pub generation: u64,
+}
+
+struct ThumbnailsState {
+    /// Disabled until the application asks for thumbnails
+    enabled: bool,
+    max_size: u32,
+    interval: Duration,
+    /// Surfaces beyond this aren't captured, bounds the memory to about
+    /// `max_surfaces * max_size^2 * 4` bytes
+    max_surfaces: usize,
+    thumbnails: HashMap<ObjectId, (wgpu::Texture, Thumbnail)>,
+    generation: u64,
+}
+
+/// Periodically refreshed thumbnails of the application's surfaces, clones
+/// share the same thumbnails
+///
+/// Surfaces capture after a presented frame when the interval has passed,
+/// suspended and unchanged surfaces keep their last thumbnail. The capture
+/// draws the frame again at the thumbnail size on the GPU, there is no CPU
+/// copy.
+#[derive(Clone)]
+pub struct Thumbnails(Rc<RefCell<ThumbnailsState>>);
+
+impl Default for Thumbnails {
+    fn default() -> Self {
+        Self(Rc::new(RefCell::new(ThumbnailsState {
+            enabled: false,
+            max_size: 256,
+            interval: Duration::from_secs(1),
+            max_surfaces: 16,
+            thumbnails: HashMap::new(),
+            generation: 0,
+        })))
+    }
+}
+
+impl Thumbnails {
+    pub(crate) fn enable(&self, max_size: u32, interval: Duration) {
+        let mut state = self.0.borrow_mut();
+        state.enabled = true;
+        state.max_size = max_size.max(1);
+        state.interval = interval;
+    }
+
+    pub fn set_max_surfaces(&self, max_surfaces: usize) {
+        self.0.borrow_mut().max_surfaces = max_surfaces;
+    }
+
+    pub fn get(&self, surface_id: &ObjectId) -> Option<Thumbnail> {
+        let state = self.0.borrow();
+        state
+            .thumbnails
+            .get(surface_id)
+            .map(|(_, thumbnail)| thumbnail.clone())
+    }
+
+    /// Surfaces with a thumbnail
+    pub fn surfaces(&self) -> Vec<ObjectId> {
+        self.0.borrow().thumbnails.keys().cloned().collect()
+    }
+
+    /// Size of the thumbnail if the surface should be captured now
+    pub(crate) fn due(&self, surface_id: &ObjectId, width: u32, height: u32) -> Option<(u32, u32)> {
+        let state = self.0.borrow();
+        if !state.enabled || width == 0 || height == 0 {
+            return None;
+        }
+        match state.thumbnails.get(surface_id) {
+            Some((_, thumbnail)) if thumbnail.captured_at.elapsed() < state.interval => {
+                return None;
+            }
+            None if state.thumbnails.len() >= state.max_surfaces => return None,
+            _ => {}
+        }
+        let scale = (state.max_size as f32 / width.max(height) as f32).min(1.0);
+        Some((
+            ((width as f32 * scale).round() as u32).max(1),
+            ((height as f32 * scale).round() as u32).max(1),
+        ))
+    }
+
+    /// Persistent texture for the surface, recreated when the size or
+    /// format changes
+    pub(crate) fn target(
+        &self,
+        device: &wgpu::Device,
+        surface_id: &ObjectId,
+        (width, height): (u32, u32),
+        format: wgpu::TextureFormat,
+    ) -> wgpu::TextureView {
+        let mut state = self.0.borrow_mut();
+        let reusable = state
+            .thumbnails
+            .get(surface_id)
+            .is_some_and(|(texture, _)| {
+                texture.width() == width && texture.height() == height && texture.format() == format
+            });
+        if !reusable {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("thumbnail"),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+            state.generation += 1;
+            let thumbnail = Thumbnail {
+                view: texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                width,
+                height,
+                captured_at: Instant::now(),
+                generation: state.generation,
+            };
+            state
+                .thumbnails
+                .insert(surface_id.clone(), (texture, thumbnail));
+        }
+        let (_, thumbnail) = state
+            .thumbnails
+            .get_mut(surface_id)
+            .expect("Thumbnail was just created");
+        thumbnail.captured_at = Instant::now();
+        thumbnail.view.clone()
+    }
+
+    pub(crate) fn remove(&self, surface_id: &ObjectId) {
+        self.0.borrow_mut().thumbnails.remove(surface_id);
+    }
+}
@@ -0,0 +1,289 @@
+use smithay_client_toolkit::seat::keyboard::Keysym;
+
+/// Codepoints above this can't be typed, 8 hex or decimal digits is plenty
+const MAX_DIGITS: usize = 8;
+
+enum EntryMode {
+    /// Ctrl+Shift+U followed by hex digits, as in GTK
+    Hex,
+    /// Decimal digits on the numpad while Alt is held
+    AltNumpad,
+}
+
+/// Result of a key press or modifier change during Unicode entry
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum EntryResult {
+    /// Not part of an entry, handle the key normally
+    Ignored,
+    /// Consumed, the text typed so far is shown as preedit
+    Composing(String),
+    Commit(char),
+    Cancel,
+    /// Digits are not a valid codepoint, e.g. a surrogate or above U+10FFFF,
+    /// the entry continues so it can be corrected with backspace
+    Invalid(String),
+}
+
+/// Unicode codepoint entry for when there's no input method
+#[derive(Default)]
+pub(crate) struct UnicodeEntry {
+    mode: Option<EntryMode>,
+    digits: String,
+}
+
+impl UnicodeEntry {
+    pub fn is_active(&self) -> bool {
+        self.mode.is_some()
+    }
+
+    pub fn cancel(&mut self) {
+        self.mode = None;
+        self.digits.clear();
+    }
+
+    /// Handle a key press, `ctrl`, `shift` and `alt` are the held modifiers
+    pub fn press(&mut self, keysym: Keysym, ctrl: bool, shift: bool, alt: bool) -> EntryResult {
+        match self.mode {
+            None => {
+                if ctrl && shift && matches!(keysym, Keysym::u | Keysym::U) {
+                    self.mode = Some(EntryMode::Hex);
+                    return EntryResult::Composing(self.preedit());
+                }
+                match keypad_digit(keysym) {
+                    Some(digit) if alt => {
+                        self.mode = Some(EntryMode::AltNumpad);
+                        self.digits.push(digit);
+                        EntryResult::Composing(self.preedit())
+                    }
+                    _ => EntryResult::Ignored,
+                }
+            }
+            Some(EntryMode::Hex) => match keysym {
+                Keysym::Return | Keysym::KP_Enter | Keysym::space => self.finish(16),
+                Keysym::Escape => {
+                    self.cancel();
+                    EntryResult::Cancel
+                }
+                Keysym::BackSpace => {
+                    self.digits.pop();
+                    EntryResult::Composing(self.preedit())
+                }
+                _ => {
+                    let digit = keysym
+                        .key_char()
+                        .or_else(|| keypad_digit(keysym))
+                        .filter(char::is_ascii_hexdigit);
+                    if let Some(digit) = digit
+                        && self.digits.len() < MAX_DIGITS
+                    {
+                        self.digits.push(digit.to_ascii_lowercase());
+                    }
+                    // Other keys are swallowed while the entry is open
+                    EntryResult::Composing(self.preedit())
+                }
+            },
+            Some(EntryMode::AltNumpad) => match keypad_digit(keysym) {
+                Some(digit) => {
+                    if self.digits.len() < MAX_DIGITS {
+                        self.digits.push(digit);
+                    }
+                    EntryResult::Composing(self.preedit())
+                }
+                None => {
+                    self.cancel();
+                    EntryResult::Cancel
+                }
+            },
+        }
+    }
+
+    /// Releasing Alt ends the numpad entry
+    pub fn alt_changed(&mut self, alt: bool) -> EntryResult {
+        match self.mode {
+            Some(EntryMode::AltNumpad) if !alt => {
+                let result = self.finish(10);
+                // Alt is gone, there's nothing more to correct
+                if matches!(result, EntryResult::Invalid(_)) {
+                    self.cancel();
+                }
+                result
+            }
+            _ => EntryResult::Ignored,
+        }
+    }
+
+    fn finish(&mut self, radix: u32) -> EntryResult {
+        if self.digits.is_empty() {
+            self.cancel();
+            return EntryResult::Cancel;
+        }
+        match u32::from_str_radix(&self.digits, radix)
+            .ok()
+            .and_then(char::from_u32)
+        {
+            Some(c) => {
+                self.cancel();
+                EntryResult::Commit(c)
+            }
+            None => EntryResult::Invalid(self.preedit()),
+        }
+    }
+
+    fn preedit(&self) -> String {
+        match self.mode {
+            Some(EntryMode::Hex) => format!("u{}", self.digits),
+            _ => self.digits.clone(),
+        }
+    }
+}
+
+fn keypad_digit(keysym: Keysym) -> Option<char> {
+    let offset = keysym.raw().checked_sub(Keysym::KP_0.raw())?;
+    char::from_digit(offset, 10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(entry: &mut UnicodeEntry, keys: &[Keysym]) -> EntryResult {
+        assert_eq!(
+            entry.press(Keysym::U, true, true, false),
+            EntryResult::Composing("u".into())
+        );
+        let mut result = EntryResult::Ignored;
+        for key in keys {
+            result = entry.press(*key, false, false, false);
+        }
+        result
+    }
+
+    #[test]
+    fn hex_commits_codepoint() {
+        let mut entry = UnicodeEntry::default();
+        let result = hex(&mut entry, &[Keysym::_1, Keysym::F, Keysym::_6, Keysym::_0]);
+        assert_eq!(result, EntryResult::Composing("u1f60".into()));
+        assert_eq!(
+            entry.press(Keysym::_0, false, false, false),
+            EntryResult::Composing("u1f600".into())
+        );
+        assert_eq!(
+            entry.press(Keysym::Return, false, false, false),
+            EntryResult::Commit('😀')
+        );
+        assert!(!entry.is_active());
+    }
+
+    #[test]
+    fn hex_ignores_non_hex_keys() {
+        let mut entry = UnicodeEntry::default();
+        let result = hex(&mut entry, &[Keysym::e, Keysym::g, Keysym::KP_9]);
+        assert_eq!(result, EntryResult::Composing("ue9".into()));
+        assert_eq!(
+            entry.press(Keysym::space, false, false, false),
+            EntryResult::Commit('é')
+        );
+    }
+
+    #[test]
+    fn surrogate_is_invalid_until_corrected() {
+        let mut entry = UnicodeEntry::default();
+        hex(&mut entry, &[Keysym::d, Keysym::_8, Keysym::_0, Keysym::_0]);
+        assert_eq!(
+            entry.press(Keysym::Return, false, false, false),
+            EntryResult::Invalid("ud800".into())
+        );
+        assert!(entry.is_active());
+        entry.press(Keysym::BackSpace, false, false, false);
+        entry.press(Keysym::BackSpace, false, false, false);
+        entry.press(Keysym::BackSpace, false, false, false);
+        assert_eq!(
+            entry.press(Keysym::Return, false, false, false),
+            EntryResult::Commit('\u{d}')
+        );
+    }
+
+    #[test]
+    fn overflowing_input_is_capped_and_invalid() {
+        let mut entry = UnicodeEntry::default();
+        let result = hex(&mut entry, &[Keysym::f; MAX_DIGITS + 2]);
+        assert_eq!(result, EntryResult::Composing("uffffffff".into()));
+        assert_eq!(
+            entry.press(Keysym::Return, false, false, false),
+            EntryResult::Invalid("uffffffff".into())
+        );
+        // Above U+10FFFF but within u32
+        let mut entry = UnicodeEntry::default();
+        hex(
+            &mut entry,
+            &[
+                Keysym::_1,
+                Keysym::_1,
+                Keysym::_0,
+                Keysym::_0,
+                Keysym::_0,
+                Keysym::_0,
+            ],
+        );
+        assert_eq!(
+            entry.press(Keysym::Return, false, false, false),
+            EntryResult::Invalid("u110000".into())
+        );
+    }
+
+    #[test]
+    fn empty_or_escaped_entry_cancels() {
+        let mut entry = UnicodeEntry::default();
+        hex(&mut entry, &[]);
+        assert_eq!(
+            entry.press(Keysym::Return, false, false, false),
+            EntryResult::Cancel
+        );
+        hex(&mut entry, &[Keysym::a]);
+        assert_eq!(
+            entry.press(Keysym::Escape, false, false, false),
+            EntryResult::Cancel
+        );
+        assert!(!entry.is_active());
+    }
+
+    #[test]
+    fn alt_numpad_commits_decimal_on_release() {
+        let mut entry = UnicodeEntry::default();
+        assert_eq!(
+            entry.press(Keysym::KP_2, false, false, true),
+            EntryResult::Composing("2".into())
+        );
+        entry.press(Keysym::KP_3, false, false, true);
+        entry.press(Keysym::KP_3, false, false, true);
+        assert_eq!(entry.alt_changed(true), EntryResult::Ignored);
+        assert_eq!(entry.alt_changed(false), EntryResult::Commit('é'));
+    }
+
+    #[test]
+    fn alt_numpad_invalid_cancels_on_release() {
+        let mut entry = UnicodeEntry::default();
+        for _ in 0..MAX_DIGITS + 1 {
+            entry.press(Keysym::KP_9, false, false, true);
+        }
+        assert_eq!(
+            entry.alt_changed(false),
+            EntryResult::Invalid("99999999".into())
+        );
+        assert!(!entry.is_active());
+    }
+
+    #[test]
+    fn keys_without_entry_are_ignored() {
+        let mut entry = UnicodeEntry::default();
+        assert_eq!(
+            entry.press(Keysym::_1, false, false, true),
+            EntryResult::Ignored
+        );
+        assert_eq!(
+            entry.press(Keysym::KP_1, false, false, false),
+            EntryResult::Ignored
+        );
+        assert!(!entry.is_active());
+    }
+}
@@ -0,0 +1,418 @@
+use log::debug;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Amount of frame samples used for the percentile calculation
+const SAMPLE_WINDOW: usize = 60;
+
+/// Frames that must pass between two transitions, avoids oscillation
+const TRANSITION_COOLDOWN: u32 = 30;
+
+/// Steps are restored only when p95 is below this fraction of the budget
+const RESTORE_HEADROOM: f32 = 0.6;
+
+/// Untaken events beyond this are dropped, oldest first
+const MAX_PENDING_EVENTS: usize = 32;
+
+/// Degradation steps, activated in this order and restored in reverse
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DegradationStep {
+    /// Buffers are rendered one buffer scale lower and scaled up by the
+    /// compositor, no effect at scale 1
+    ReduceRenderScale,
+    /// Frame callbacks without pending input or a requested repaint are not
+    /// rendered
+    SkipIdleFrames,
+}
+
+impl DegradationStep {
+    pub const ALL: [DegradationStep; 2] = [
+        DegradationStep::ReduceRenderScale,
+        DegradationStep::SkipIdleFrames,
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DegradationEvent {
+    Activated(DegradationStep),
+    Restored(DegradationStep),
+}
+
+/// Cooperative frame budget controller
+///
+/// Feed it the measured CPU and GPU cost of each frame with `record_frame`,
+/// when the p95 exceeds the budget the next allowed degradation step is
+/// activated. Steps are restored in reverse order once there is enough
+/// headroom again.
+pub struct FrameBudget {
+    budget: Duration,
+    samples: VecDeque<Duration>,
+    active: Vec<DegradationStep>,
+    not_allowed: HashSet<DegradationStep>,
+    frames_since_transition: u32,
+    events: Vec<DegradationEvent>,
+    transition_count: u64,
+}
+
+impl FrameBudget {
+    pub fn new(budget: Duration) -> Self {
+        Self {
+            budget,
+            samples: VecDeque::with_capacity(SAMPLE_WINDOW),
+            active: Vec::new(),
+            not_allowed: HashSet::new(),
+            frames_since_transition: 0,
+            events: Vec::new(),
+            transition_count: 0,
+        }
+    }
+
+    /// Budget derived from the refresh rate in mHz (as reported by wl_output)
+    pub fn from_refresh_rate(refresh_mhz: i32) -> Self {
+        let refresh_mhz = if refresh_mhz > 0 { refresh_mhz } else { 60_000 };
+        Self::new(Duration::from_secs_f64(1000.0 / refresh_mhz as f64))
+    }
+
+    pub fn budget(&self) -> Duration {
+        self.budget
+    }
+
+    pub fn set_budget(&mut self, budget: Duration) {
+        self.budget = budget;
+    }
+
+    /// Pin step as not allowed, if it's currently active it's restored
+    pub fn set_allowed(&mut self, step: DegradationStep, allowed: bool) {
+        if allowed {
+            self.not_allowed.remove(&step);
+        } else {
+            self.not_allowed.insert(step);
+            if let Some(index) = self.active.iter().position(|s| *s == step) {
+                self.active.remove(index);
+                self.push_event(DegradationEvent::Restored(step));
+            }
+        }
+    }
+
+    pub fn is_active(&self, step: DegradationStep) -> bool {
+        self.active.contains(&step)
+    }
+
+    pub fn active_steps(&self) -> &[DegradationStep] {
+        &self.active
+    }
+
+    /// 95th percentile of the recent frame times
+    pub fn p95(&self) -> Option<Duration> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<Duration> = self.samples.iter().copied().collect();
+        sorted.sort();
+        let index = ((sorted.len() as f32 * 0.95).ceil() as usize).saturating_sub(1);
+        Some(sorted[index])
+    }
+
+    /// Total amount of degradation transitions
+    pub fn transition_count(&self) -> u64 {
+        self.transition_count
+    }
+
+    /// Take degradation events emitted since the last call
+    pub fn take_events(&mut self) -> Vec<DegradationEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Record the cost of a frame and step degradation up or down if needed,
+    /// the transition made is returned
+    pub fn record_frame(&mut self, cost: Duration) -> Option<DegradationEvent> {
+        if self.samples.len() == SAMPLE_WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(cost);
+        self.frames_since_transition = self.frames_since_transition.saturating_add(1);
+
+        if self.samples.len() < SAMPLE_WINDOW / 2
+            || self.frames_since_transition < TRANSITION_COOLDOWN
+        {
+            return None;
+        }
+
+        let p95 = self.p95()?;
+        let event = if p95 > self.budget {
+            let step = DegradationStep::ALL
+                .into_iter()
+                .find(|step| !self.active.contains(step) && !self.not_allowed.contains(step))?;
+            self.active.push(step);
+            DegradationEvent::Activated(step)
+        } else if p95.as_secs_f32() < self.budget.as_secs_f32() * RESTORE_HEADROOM {
+            DegradationEvent::Restored(self.active.pop()?)
+        } else {
+            return None;
+        };
+        self.transition(event);
+        Some(event)
+    }
+
+    fn transition(&mut self, event: DegradationEvent) {
+        self.frames_since_transition = 0;
+        // Old samples were measured with different settings
+        self.samples.clear();
+        self.push_event(event);
+    }
+
+    fn push_event(&mut self, event: DegradationEvent) {
+        debug!("[BUDGET] {:?}", event);
+        self.transition_count += 1;
+        if self.events.len() == MAX_PENDING_EVENTS {
+            self.events.remove(0);
+        }
+        self.events.push(event);
+    }
+}
+
+impl Default for FrameBudget {
+    fn default() -> Self {
+        Self::from_refresh_rate(60_000)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gpu_context::GpuContext;
+    use crate::gpu_timer::GpuTimer;
+    use std::time::Instant;
+
+    const BUDGET: Duration = Duration::from_millis(10);
+
+    fn record(budget: &mut FrameBudget, frames: usize, cost_ms: u64) {
+        for _ in 0..frames {
+            budget.record_frame(Duration::from_millis(cost_ms));
+        }
+    }
+
+    #[test]
+    fn p95_of_samples() {
+        let mut budget = FrameBudget::new(BUDGET);
+        assert_eq!(budget.p95(), None);
+        for ms in 1..=20 {
+            budget.record_frame(Duration::from_millis(ms));
+        }
+        assert_eq!(budget.p95(), Some(Duration::from_millis(19)));
+    }
+
+    #[test]
+    fn p95_ignores_samples_outside_window() {
+        let mut budget = FrameBudget::new(BUDGET);
+        for step in DegradationStep::ALL {
+            budget.set_allowed(step, false);
+        }
+        record(&mut budget, SAMPLE_WINDOW, 50);
+        record(&mut budget, SAMPLE_WINDOW, 1);
+        assert_eq!(budget.p95(), Some(Duration::from_millis(1)));
+    }
+
+    #[test]
+    fn activates_after_cooldown() {
+        let mut budget = FrameBudget::new(BUDGET);
+        record(&mut budget, TRANSITION_COOLDOWN as usize - 1, 20);
+        assert!(budget.active_steps().is_empty());
+        assert_eq!(
+            budget.record_frame(Duration::from_millis(20)),
+            Some(DegradationEvent::Activated(
+                DegradationStep::ReduceRenderScale
+            ))
+        );
+        assert_eq!(
+            budget.take_events(),
+            vec![DegradationEvent::Activated(
+                DegradationStep::ReduceRenderScale
+            )]
+        );
+        assert!(budget.take_events().is_empty());
+    }
+
+    #[test]
+    fn steps_in_order_and_restores_in_reverse() {
+        let mut budget = FrameBudget::new(BUDGET);
+        record(&mut budget, TRANSITION_COOLDOWN as usize * 2, 20);
+        assert_eq!(budget.active_steps(), DegradationStep::ALL);
+
+        record(&mut budget, TRANSITION_COOLDOWN as usize, 1);
+        assert_eq!(budget.active_steps(), [DegradationStep::ReduceRenderScale]);
+        record(&mut budget, TRANSITION_COOLDOWN as usize, 1);
+        assert!(budget.active_steps().is_empty());
+        assert_eq!(
+            budget.take_events(),
+            vec![
+                DegradationEvent::Activated(DegradationStep::ReduceRenderScale),
+                DegradationEvent::Activated(DegradationStep::SkipIdleFrames),
+                DegradationEvent::Restored(DegradationStep::SkipIdleFrames),
+                DegradationEvent::Restored(DegradationStep::ReduceRenderScale),
+            ]
+        );
+    }
+
+    #[test]
+    fn restores_only_with_headroom() {
+        let mut budget = FrameBudget::new(BUDGET);
+        record(&mut budget, TRANSITION_COOLDOWN as usize, 20);
+        assert!(budget.is_active(DegradationStep::ReduceRenderScale));
+
+        // Within the budget but above the headroom
+        record(&mut budget, SAMPLE_WINDOW, 8);
+        assert!(budget.is_active(DegradationStep::ReduceRenderScale));
+
+        record(&mut budget, SAMPLE_WINDOW, 5);
+        assert!(!budget.is_active(DegradationStep::ReduceRenderScale));
+        assert_eq!(budget.transition_count(), 2);
+    }
+
+    #[test]
+    fn not_allowed_step_is_restored_and_skipped() {
+        let mut budget = FrameBudget::new(BUDGET);
+        record(&mut budget, TRANSITION_COOLDOWN as usize, 20);
+        budget.set_allowed(DegradationStep::ReduceRenderScale, false);
+        assert!(budget.active_steps().is_empty());
+
+        record(&mut budget, SAMPLE_WINDOW, 20);
+        assert_eq!(budget.active_steps(), [DegradationStep::SkipIdleFrames]);
+        assert_eq!(
+            budget.take_events(),
+            vec![
+                DegradationEvent::Activated(DegradationStep::ReduceRenderScale),
+                DegradationEvent::Restored(DegradationStep::ReduceRenderScale),
+                DegradationEvent::Activated(DegradationStep::SkipIdleFrames),
+            ]
+        );
+    }
+
+    /// Frame cost of a surface at scale 2: a fixed cost plus an effect that
+    /// scales with the rendered pixels
+    fn frame_cost(budget: &FrameBudget, effect_ms: f64) -> Duration {
+        let pixels = if budget.is_active(DegradationStep::ReduceRenderScale) {
+            0.25
+        } else {
+            1.0
+        };
+        Duration::from_secs_f64((3.0 + effect_ms * pixels) / 1000.0)
+    }
+
+    #[test]
+    fn steps_down_under_load_and_back_up() {
+        let mut budget = FrameBudget::new(BUDGET);
+        for _ in 0..SAMPLE_WINDOW * 10 {
+            let cost = frame_cost(&budget, 20.0);
+            budget.record_frame(cost);
+        }
+        // Reduced scale fits, nothing more is given up and nothing oscillates
+        assert_eq!(budget.active_steps(), [DegradationStep::ReduceRenderScale]);
+        assert!(budget.p95().unwrap() <= budget.budget());
+        assert_eq!(budget.transition_count(), 1);
+
+        // Expensive effect removed
+        for _ in 0..SAMPLE_WINDOW * 10 {
+            let cost = frame_cost(&budget, 0.0);
+            budget.record_frame(cost);
+        }
+        assert!(budget.active_steps().is_empty());
+        assert_eq!(budget.transition_count(), 2);
+    }
+
+    /// Clear a texture in two passes and wait for it, the cost is measured
+    /// like in the renderer: CPU time plus the GPU time of an earlier frame
+    fn render_frame(
+        context: &GpuContext,
+        target: &wgpu::TextureView,
+        gpu_timer: &mut Option<GpuTimer>,
+    ) -> Duration {
+        let frame_start = Instant::now();
+        let mut encoder = context
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        let writes = [
+            (
+                wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                gpu_timer.as_ref().and_then(GpuTimer::begin_writes),
+            ),
+            (
+                wgpu::LoadOp::Load,
+                gpu_timer.as_ref().and_then(GpuTimer::end_writes),
+            ),
+        ];
+        for (load, timestamp_writes) in writes {
+            encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                timestamp_writes,
+                ..Default::default()
+            });
+        }
+        if let Some(gpu_timer) = gpu_timer.as_mut() {
+            gpu_timer.resolve(&mut encoder);
+        }
+        context.queue.submit(Some(encoder.finish()));
+        if let Some(gpu_timer) = gpu_timer.as_mut() {
+            gpu_timer.submitted();
+        }
+        context
+            .device
+            .poll(wgpu::PollType::wait_indefinitely())
+            .expect("Failed to wait for the frame");
+        let gpu_time = gpu_timer.as_mut().and_then(GpuTimer::collect);
+        frame_start.elapsed() + gpu_time.unwrap_or_default()
+    }
+
+    #[test]
+    fn real_frames_step_down_only_over_the_budget() {
+        let Some(context) = GpuContext::software() else {
+            eprintln!("No software adapter, skipped");
+            return;
+        };
+        let texture = context.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("frame budget target"),
+            size: wgpu::Extent3d {
+                width: 512,
+                height: 512,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let target = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut gpu_timer = GpuTimer::new(&context.device, &context.queue);
+
+        let mut generous = FrameBudget::new(Duration::from_secs(1));
+        let mut exceeded = FrameBudget::new(Duration::from_nanos(1));
+        for _ in 0..TRANSITION_COOLDOWN {
+            let cost = render_frame(&context, &target, &mut gpu_timer);
+            generous.record_frame(cost);
+            exceeded.record_frame(cost);
+        }
+        assert!(generous.active_steps().is_empty());
+        assert!(generous.p95().unwrap() > Duration::ZERO);
+        assert_eq!(
+            exceeded.active_steps(),
+            [DegradationStep::ReduceRenderScale]
+        );
+    }
+
+    #[test]
+    fn zero_refresh_rate_falls_back_to_60hz() {
+        let budget = FrameBudget::from_refresh_rate(0);
+        assert_eq!(budget.budget(), Duration::from_secs_f64(1.0 / 60.0));
+    }
+}
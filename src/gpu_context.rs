@@ -0,0 +1,177 @@
+use crate::startup_timing;
+use crate::startup_timing::Phase;
+use log::debug;
+use log::warn;
+use pollster::block_on;
+use std::fmt;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+static DEVICES_CREATED: AtomicUsize = AtomicUsize::new(0);
+
+/// Amount of WGPU devices created by this crate during the process lifetime
+pub fn devices_created() -> usize {
+    DEVICES_CREATED.load(Ordering::Relaxed)
+}
+
+/// Device features required by the EGUI renderer
+pub fn required_features() -> wgpu::Features {
+    wgpu::Features::empty()
+}
+
+/// Device limits required by the EGUI renderer
+pub fn required_limits() -> wgpu::Limits {
+    wgpu::Limits::downlevel_webgl2_defaults()
+}
+
+/// Returned when an external device doesn't satisfy the renderer requirements
+#[derive(Debug, Clone)]
+pub struct MissingGpuCapabilities {
+    pub features: wgpu::Features,
+    /// Names of the limits that are not satisfied
+    pub limits: Vec<&'static str>,
+}
+
+impl fmt::Display for MissingGpuCapabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Device is missing")?;
+        if !self.features.is_empty() {
+            write!(f, " features {:?}", self.features)?;
+        }
+        if !self.limits.is_empty() {
+            write!(f, " limits {}", self.limits.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for MissingGpuCapabilities {}
+
+/// GPU device shared by all surfaces of the application
+///
+/// Only the `wgpu::Surface` and its configuration are per-surface, texture
+/// format is still chosen per-surface from the surface capabilities.
+///
+/// When the device is handed in by the host with `with_existing` the host
+/// owns it: this crate never calls `Device::poll`, doesn't push error scopes
+/// and doesn't set the uncaptured error handler, so device errors are routed
+/// wherever the host routes them.
+#[derive(Clone)]
+pub struct GpuContext {
+    pub instance: wgpu::Instance,
+    pub adapter: wgpu::Adapter,
+    pub device: wgpu::Device,
+    pub queue: wgpu::Queue,
+}
+
+impl GpuContext {
+    /// Create a device on an adapter compatible with the given surface
+    ///
+    /// Without a hardware adapter, or with `WAYAPP_SOFTWARE=1`, a software
+    /// adapter such as llvmpipe is used. It still renders through WGPU,
+    /// there's no renderer drawing into shm buffers.
+    pub fn new(instance: wgpu::Instance, compatible_surface: &wgpu::Surface<'_>) -> Self {
+        Self::create(instance, Some(compatible_surface))
+    }
+
+    /// Create a device for rendering into textures only, e.g. screenshots
+    /// without a compositor. Adapter selection is the same as in `new`.
+    pub fn headless(instance: wgpu::Instance) -> Self {
+        Self::create(instance, None)
+    }
+
+    /// Headless device on a software adapter, `None` without one so tests
+    /// skip themselves
+    #[cfg(test)]
+    pub(crate) fn software() -> Option<Self> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let adapter = Self::request_adapter(&instance, None, true).ok()?;
+        Some(Self::with_adapter(instance, adapter))
+    }
+
+    fn create(instance: wgpu::Instance, compatible_surface: Option<&wgpu::Surface<'_>>) -> Self {
+        let force_software = std::env::var("WAYAPP_SOFTWARE").is_ok_and(|value| value == "1");
+        let adapter = Self::request_adapter(&instance, compatible_surface, force_software)
+            .expect("Failed to find a suitable adapter");
+        Self::with_adapter(instance, adapter)
+    }
+
+    fn request_adapter(
+        instance: &wgpu::Instance,
+        compatible_surface: Option<&wgpu::Surface<'_>>,
+        force_software: bool,
+    ) -> Result<wgpu::Adapter, wgpu::RequestAdapterError> {
+        let request = |force_fallback_adapter| {
+            block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+                compatible_surface,
+                force_fallback_adapter,
+                ..Default::default()
+            }))
+        };
+        if force_software {
+            return request(true);
+        }
+        request(false).or_else(|err| {
+            warn!(
+                "[GPU] No hardware adapter ({}), trying a software adapter",
+                err
+            );
+            request(true)
+        })
+    }
+
+    fn with_adapter(instance: wgpu::Instance, adapter: wgpu::Adapter) -> Self {
+        startup_timing::mark(Phase::AdapterReady);
+
+        // Optional, frame budgets measure GPU time with it when available
+        let timestamps = adapter.features() & wgpu::Features::TIMESTAMP_QUERY;
+        let (device, queue) = block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+            required_features: required_features() | timestamps,
+            memory_hints: wgpu::MemoryHints::MemoryUsage,
+            ..Default::default()
+        }))
+        .expect("Failed to request WGPU device");
+
+        DEVICES_CREATED.fetch_add(1, Ordering::Relaxed);
+        debug!(
+            "[GPU] Created device on adapter {:?}",
+            adapter.get_info().name
+        );
+
+        Self {
+            instance,
+            adapter,
+            device,
+            queue,
+        }
+    }
+
+    /// Use a device created by the host application instead of creating one
+    ///
+    /// Pass it to `Application::set_gpu_context` before creating surfaces.
+    pub fn with_existing(
+        instance: wgpu::Instance,
+        adapter: wgpu::Adapter,
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+    ) -> Result<Self, MissingGpuCapabilities> {
+        let features = required_features() - device.features();
+        let mut limits = Vec::new();
+        required_limits()
+            .check_limits_with_fail_fn(&device.limits(), false, |name, _, _| limits.push(name));
+        if !features.is_empty() || !limits.is_empty() {
+            return Err(MissingGpuCapabilities { features, limits });
+        }
+
+        debug!(
+            "[GPU] Using existing device on adapter {:?}",
+            adapter.get_info().name
+        );
+        Ok(Self {
+            instance,
+            adapter,
+            device,
+            queue,
+        })
+    }
+}
@@ -0,0 +1,143 @@
+use std::sync::Arc;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Both timestamps as resolved into the buffers
+const TIMESTAMPS_SIZE: u64 = 2 * wgpu::QUERY_SIZE as u64;
+
+enum Readback {
+    Idle,
+    /// Resolve and copy are recorded, waiting for the encoder to be submitted
+    Recorded,
+    /// Set to whether the mapping succeeded once it completes
+    Mapping(Arc<OnceLock<bool>>),
+}
+
+/// GPU time of a frame, measured with timestamp queries written at the
+/// start of the first pass and the end of the last one
+///
+/// Results are read back without blocking, so they lag a frame or two
+/// behind. Frames recorded while the previous result is still mapping are
+/// not measured.
+pub(crate) struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve: wgpu::Buffer,
+    readback: wgpu::Buffer,
+    /// Nanoseconds per timestamp tick
+    period: f32,
+    state: Readback,
+    last: Option<Duration>,
+}
+
+impl GpuTimer {
+    /// `None` when the device doesn't have timestamp queries enabled
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("frame timestamps"),
+            ty: wgpu::QueryType::Timestamp,
+            count: 2,
+        });
+        let resolve = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("frame timestamps resolve"),
+            size: TIMESTAMPS_SIZE,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("frame timestamps readback"),
+            size: TIMESTAMPS_SIZE,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Some(Self {
+            query_set,
+            resolve,
+            readback,
+            period: queue.get_timestamp_period(),
+            state: Readback::Idle,
+            last: None,
+        })
+    }
+
+    fn measuring(&self) -> bool {
+        !matches!(self.state, Readback::Mapping(_))
+    }
+
+    /// Timestamp writes of the first pass of the frame
+    pub fn begin_writes(&self) -> Option<wgpu::RenderPassTimestampWrites<'_>> {
+        self.measuring().then_some(wgpu::RenderPassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(0),
+            end_of_pass_write_index: None,
+        })
+    }
+
+    /// Timestamp writes of the last pass of the frame
+    pub fn end_writes(&self) -> Option<wgpu::RenderPassTimestampWrites<'_>> {
+        self.measuring().then_some(wgpu::RenderPassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: None,
+            end_of_pass_write_index: Some(1),
+        })
+    }
+
+    /// Record copying the timestamps out, after the last pass has ended
+    pub fn resolve(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if !self.measuring() {
+            return;
+        }
+        encoder.resolve_query_set(&self.query_set, 0..2, &self.resolve, 0);
+        encoder.copy_buffer_to_buffer(&self.resolve, 0, &self.readback, 0, TIMESTAMPS_SIZE);
+        self.state = Readback::Recorded;
+    }
+
+    /// Map the timestamps once the encoder has been submitted, the mapping
+    /// completes on a later submit
+    pub fn submitted(&mut self) {
+        if !matches!(self.state, Readback::Recorded) {
+            return;
+        }
+        let done = Arc::new(OnceLock::new());
+        let set = done.clone();
+        self.readback
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let _ = set.set(result.is_ok());
+            });
+        self.state = Readback::Mapping(done);
+    }
+
+    /// Latest measured GPU time
+    pub fn last(&self) -> Option<Duration> {
+        self.last
+    }
+
+    /// Collect the readback when it's ready, returns the latest GPU time
+    pub fn collect(&mut self) -> Option<Duration> {
+        let Readback::Mapping(done) = &self.state else {
+            return self.last;
+        };
+        match done.get() {
+            None => return self.last,
+            Some(true) => {
+                let ticks = {
+                    let view = self.readback.slice(..).get_mapped_range();
+                    let timestamp = |at: usize| {
+                        u64::from_le_bytes(view[at..at + 8].try_into().expect("8 bytes"))
+                    };
+                    timestamp(8).saturating_sub(timestamp(0))
+                };
+                self.readback.unmap();
+                self.last = Some(Duration::from_nanos(
+                    (ticks as f64 * self.period as f64) as u64,
+                ));
+            }
+            Some(false) => {}
+        }
+        self.state = Readback::Idle;
+        self.last
+    }
+}
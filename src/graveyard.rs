@@ -0,0 +1,104 @@
+use crate::Application;
+use log::debug;
+use log::warn;
+use smithay_client_toolkit::reexports::calloop::LoopHandle;
+use smithay_client_toolkit::reexports::calloop::channel::Sender;
+use smithay_client_toolkit::reexports::calloop::timer::TimeoutAction;
+use smithay_client_toolkit::reexports::calloop::timer::Timer;
+use std::any::Any;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+/// Resources are dropped after this even if the queue never reports the
+/// work done, e.g. when the device stalls, so they can't pile up
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+struct Grave {
+    id: u64,
+    remains: Box<dyn Any>,
+}
+
+struct GraveyardState {
+    graves: Vec<Grave>,
+    next_id: u64,
+    /// Ids of the graves whose submitted work is done, sent from the queue
+    sender: Sender<u64>,
+    loop_handle: LoopHandle<'static, Application>,
+}
+
+/// GPU resources of closed surfaces kept until the frames already submitted
+/// with them are done, clones share the same graves
+///
+/// Dropping a swapchain or textures while other surfaces have frames in
+/// flight can make the driver wait for the whole device, which shows up as a
+/// hitch in the surfaces that keep animating.
+#[derive(Clone)]
+pub(crate) struct Graveyard(Rc<RefCell<GraveyardState>>);
+
+impl Graveyard {
+    pub fn new(sender: Sender<u64>, loop_handle: LoopHandle<'static, Application>) -> Self {
+        Self(Rc::new(RefCell::new(GraveyardState {
+            graves: Vec::new(),
+            next_id: 0,
+            sender,
+            loop_handle,
+        })))
+    }
+
+    /// Keep the remains until the work submitted to the queue so far is done,
+    /// or until the timeout
+    pub fn bury(&self, queue: &wgpu::Queue, remains: Box<dyn Any>) {
+        let mut state = self.0.borrow_mut();
+        let id = state.next_id;
+        state.next_id += 1;
+        state.graves.push(Grave { id, remains });
+        debug!(
+            "[GPU] Deferred destroy {}, {} pending",
+            id,
+            state.graves.len()
+        );
+
+        // Called while the queue is maintained, e.g. on the next submit of
+        // another surface, the remains are dropped on the loop afterwards
+        let sender = state.sender.clone();
+        queue.on_submitted_work_done(move || {
+            // Fails only if the application is gone, the graves went with it
+            let _ = sender.send(id);
+        });
+        let graveyard = self.clone();
+        let timer =
+            state
+                .loop_handle
+                .insert_source(Timer::from_duration(TIMEOUT), move |_, _, _| {
+                    if graveyard.release(id) {
+                        warn!("[GPU] Deferred destroy {} timed out, dropped anyway", id);
+                    }
+                    TimeoutAction::Drop
+                });
+        if timer.is_err() {
+            warn!("[GPU] Failed to insert deferred destroy timer");
+        }
+    }
+
+    /// Drop everything right away, e.g. on exit
+    pub fn release_all(&self) {
+        let graves = std::mem::take(&mut self.0.borrow_mut().graves);
+        debug!("[GPU] Dropping {} deferred destroys", graves.len());
+        drop(graves);
+    }
+
+    /// Drop the remains of the grave, false if already dropped
+    pub fn release(&self, id: u64) -> bool {
+        let grave = {
+            let mut state = self.0.borrow_mut();
+            let Some(index) = state.graves.iter().position(|grave| grave.id == id) else {
+                return false;
+            };
+            state.graves.swap_remove(index)
+        };
+        // Dropped outside the borrow, the driver may take a while
+        drop(grave.remains);
+        true
+    }
+}
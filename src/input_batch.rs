@@ -0,0 +1,26 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use wayland_backend::client::ObjectId;
+
+/// Surfaces with input queued since the last dispatch of the event loop,
+/// shared by the application and its surfaces
+///
+/// A single Wayland dispatch often carries a burst of events, e.g. motion,
+/// a button and the modifiers. The surfaces only queue them, the application
+/// renders each marked surface once after the dispatch.
+#[derive(Clone, Default)]
+pub(crate) struct InputBatch(Rc<RefCell<Vec<ObjectId>>>);
+
+impl InputBatch {
+    pub fn mark(&self, surface_id: ObjectId) {
+        let mut marked = self.0.borrow_mut();
+        if !marked.contains(&surface_id) {
+            marked.push(surface_id);
+        }
+    }
+
+    /// Marked surfaces in the order the input arrived
+    pub fn take(&self) -> Vec<ObjectId> {
+        std::mem::take(&mut *self.0.borrow_mut())
+    }
+}
@@ -0,0 +1,240 @@
+use log::debug;
+use smithay_client_toolkit::seat::keyboard::KeyEvent;
+use smithay_client_toolkit::seat::keyboard::RepeatInfo;
+use std::ffi::OsString;
+use std::time::Duration;
+use xkbcommon::xkb;
+use xkbcommon::xkb::compose;
+
+/// Locale of the compose table, as chosen by libX11 and GTK
+fn compose_locale() -> OsString {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .into_iter()
+        .filter_map(std::env::var_os)
+        .find(|value| !value.is_empty())
+        .unwrap_or_else(|| "C".into())
+}
+
+/// Dead keys and compose sequences of the locale, e.g. ´ followed by e gives
+/// é. The text of the key finishing a sequence is replaced with the composed
+/// text, keys within the sequence get no text.
+pub(crate) struct Compose {
+    /// `None` when the locale has no compose table, keys keep their text
+    state: Option<compose::State>,
+}
+
+impl Compose {
+    pub fn new() -> Self {
+        let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+        let locale = compose_locale();
+        let state =
+            match compose::Table::new_from_locale(&context, &locale, compose::COMPILE_NO_FLAGS) {
+                Ok(table) => Some(compose::State::new(&table, compose::STATE_NO_FLAGS)),
+                Err(()) => {
+                    debug!("[INPUT] No compose table for locale {:?}", locale);
+                    None
+                }
+            };
+        Self { state }
+    }
+
+    /// Feed a pressed key, its text is replaced by the composition result
+    pub fn feed(&mut self, event: &mut KeyEvent) {
+        let Some(state) = &mut self.state else {
+            return;
+        };
+        if matches!(state.feed(event.keysym), compose::FeedResult::Ignored) {
+            // Modifier keys don't affect the sequence
+            return;
+        }
+        match state.status() {
+            compose::Status::Composing => event.utf8 = None,
+            compose::Status::Composed => {
+                event.utf8 = state.utf8();
+                state.reset();
+            }
+            compose::Status::Cancelled => {
+                event.utf8 = None;
+                state.reset();
+            }
+            compose::Status::Nothing => {}
+        }
+    }
+
+    /// Forget a sequence in progress, e.g. when the keyboard focus leaves
+    pub fn reset(&mut self) {
+        if let Some(state) = &mut self.state {
+            state.reset();
+        }
+    }
+}
+
+/// Time until the next repeat of a key held for `held`, and the interval
+/// after it. Keys already repeating continue at the rate from now, e.g.
+/// after the compositor changed it. `None` with repeat disabled.
+pub(crate) fn repeat_schedule(info: RepeatInfo, held: Duration) -> Option<(Duration, Duration)> {
+    let RepeatInfo::Repeat { rate, delay } = info else {
+        return None;
+    };
+    let interval = Duration::from_micros(1_000_000 / rate.get() as u64);
+    let first = Duration::from_millis(delay as u64)
+        .checked_sub(held)
+        .unwrap_or(interval);
+    Some((first, interval))
+}
+
+/// Layout names of the keymap and the active layout
+#[derive(Default)]
+pub(crate) struct KeyboardLayouts {
+    names: Vec<String>,
+    active: u32,
+}
+
+impl KeyboardLayouts {
+    /// Layout names from a keymap in the text format of wl_keyboard.keymap
+    pub fn set_keymap(&mut self, keymap: String) {
+        let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+        self.names = xkb::Keymap::new_from_string(
+            &context,
+            keymap,
+            xkb::KEYMAP_FORMAT_TEXT_V1,
+            xkb::KEYMAP_COMPILE_NO_FLAGS,
+        )
+        .map(|keymap| {
+            (0..keymap.num_layouts())
+                .map(|layout| keymap.layout_get_name(layout).to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    }
+
+    /// Returns true if the active layout index changed
+    pub fn set_active(&mut self, layout: u32) -> bool {
+        std::mem::replace(&mut self.active, layout) != layout
+    }
+
+    pub fn active_name(&self) -> Option<&str> {
+        self.names.get(self.active as usize).map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smithay_client_toolkit::seat::keyboard::Keysym;
+
+    fn compose() -> Compose {
+        let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+        let table = compose::Table::new_from_buffer(
+            &context,
+            "<dead_acute> <e> : \"é\" eacute\n",
+            "C",
+            compose::FORMAT_TEXT_V1,
+            compose::COMPILE_NO_FLAGS,
+        )
+        .expect("valid compose rules");
+        Compose {
+            state: Some(compose::State::new(&table, compose::STATE_NO_FLAGS)),
+        }
+    }
+
+    /// Text of the pressed key after composing
+    fn press(compose: &mut Compose, keysym: Keysym, utf8: Option<&str>) -> Option<String> {
+        let mut event = KeyEvent {
+            time: 0,
+            raw_code: 0,
+            keysym,
+            utf8: utf8.map(str::to_string),
+        };
+        compose.feed(&mut event);
+        event.utf8
+    }
+
+    #[test]
+    fn dead_key_composes_with_next_key() {
+        let mut compose = compose();
+        assert_eq!(press(&mut compose, Keysym::dead_acute, None), None);
+        // Modifiers pressed within the sequence don't cancel it
+        assert_eq!(press(&mut compose, Keysym::Shift_L, None), None);
+        assert_eq!(
+            press(&mut compose, Keysym::e, Some("e")),
+            Some("é".to_string())
+        );
+        assert_eq!(
+            press(&mut compose, Keysym::e, Some("e")),
+            Some("e".to_string())
+        );
+    }
+
+    #[test]
+    fn reset_forgets_sequence() {
+        let mut compose = compose();
+        assert_eq!(press(&mut compose, Keysym::dead_acute, None), None);
+        compose.reset();
+        assert_eq!(
+            press(&mut compose, Keysym::e, Some("e")),
+            Some("e".to_string())
+        );
+    }
+
+    #[test]
+    fn keys_outside_sequences_keep_text() {
+        let mut compose = compose();
+        assert_eq!(
+            press(&mut compose, Keysym::a, Some("a")),
+            Some("a".to_string())
+        );
+    }
+
+    fn repeat(rate: u32, delay: u32) -> RepeatInfo {
+        RepeatInfo::Repeat {
+            rate: rate.try_into().expect("non-zero rate"),
+            delay,
+        }
+    }
+
+    #[test]
+    fn repeat_starts_after_the_delay() {
+        assert_eq!(
+            repeat_schedule(repeat(25, 400), Duration::ZERO),
+            Some((Duration::from_millis(400), Duration::from_millis(40)))
+        );
+    }
+
+    #[test]
+    fn repeat_waits_out_the_rest_of_the_delay() {
+        assert_eq!(
+            repeat_schedule(repeat(25, 400), Duration::from_millis(150)),
+            Some((Duration::from_millis(250), Duration::from_millis(40)))
+        );
+    }
+
+    #[test]
+    fn repeating_key_continues_at_the_new_rate() {
+        let held = Duration::from_secs(2);
+        assert_eq!(
+            repeat_schedule(repeat(25, 400), held),
+            Some((Duration::from_millis(40), Duration::from_millis(40)))
+        );
+        assert_eq!(
+            repeat_schedule(repeat(50, 400), held),
+            Some((Duration::from_millis(20), Duration::from_millis(20)))
+        );
+    }
+
+    /// A key held over a suspend repeats once, then at the rate from then
+    /// on, not once for every interval that passed
+    #[test]
+    fn repeat_after_a_gap_fires_once() {
+        let held = Duration::from_secs(3600);
+        assert_eq!(
+            repeat_schedule(repeat(25, 400), held),
+            Some((Duration::from_millis(40), Duration::from_millis(40)))
+        );
+    }
+
+    #[test]
+    fn disabled_repeat_has_no_schedule() {
+        assert_eq!(repeat_schedule(RepeatInfo::Disable, Duration::ZERO), None);
+    }
+}
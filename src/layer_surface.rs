@@ -0,0 +1,454 @@
+use crate::Application;
+use log::warn;
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shell::wlr_layer::Anchor;
+use smithay_client_toolkit::shell::wlr_layer::KeyboardInteractivity;
+use smithay_client_toolkit::shell::wlr_layer::Layer;
+use smithay_client_toolkit::shell::wlr_layer::LayerSurface;
+use std::fmt;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
+use wayland_client::protocol::wl_output::WlOutput;
+
+/// Rules already warned about, one bit per rule
+static WARNED: AtomicU32 = AtomicU32::new(0);
+
+/// Layer shell configurations compositors disagree on, some kill the client
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LayerSurfaceRule {
+    /// Zero width requires both left and right anchors
+    ZeroWidthWithoutHorizontalAnchors,
+    /// Zero height requires both top and bottom anchors
+    ZeroHeightWithoutVerticalAnchors,
+    /// Exclusive zone requires anchoring to one edge, optionally with both
+    /// perpendicular edges
+    ExclusiveZoneWithoutEdge,
+    /// Margin of an edge the surface is not anchored to has no effect
+    MarginOnUnanchoredEdge,
+}
+
+impl LayerSurfaceRule {
+    fn bit(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+impl fmt::Display for LayerSurfaceRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            Self::ZeroWidthWithoutHorizontalAnchors => {
+                "zero width requires both left and right anchors"
+            }
+            Self::ZeroHeightWithoutVerticalAnchors => {
+                "zero height requires both top and bottom anchors"
+            }
+            Self::ExclusiveZoneWithoutEdge => "exclusive zone requires anchoring to an edge",
+            Self::MarginOnUnanchoredEdge => "margin is set on an edge that is not anchored",
+        };
+        f.write_str(text)
+    }
+}
+
+/// Returned in strict mode instead of correcting the settings
+#[derive(Debug, Clone)]
+pub struct InvalidLayerSurface {
+    pub violations: Vec<LayerSurfaceRule>,
+}
+
+impl fmt::Display for InvalidLayerSurface {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid layer surface settings: ")?;
+        for (i, rule) in self.violations.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", rule)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for InvalidLayerSurface {}
+
+/// Anchor, size, exclusive zone and margin of a layer surface
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayerSurfaceSettings {
+    pub anchor: Anchor,
+    pub width: u32,
+    pub height: u32,
+    pub exclusive_zone: i32,
+    /// Top, right, bottom, left
+    pub margin: (i32, i32, i32, i32),
+}
+
+impl Default for LayerSurfaceSettings {
+    fn default() -> Self {
+        Self {
+            anchor: Anchor::empty(),
+            width: 256,
+            height: 256,
+            exclusive_zone: 0,
+            margin: (0, 0, 0, 0),
+        }
+    }
+}
+
+impl LayerSurfaceSettings {
+    /// Rules the settings break
+    pub fn violations(&self) -> Vec<LayerSurfaceRule> {
+        let mut violations = Vec::new();
+        if self.width == 0 && !self.anchor.contains(Anchor::LEFT | Anchor::RIGHT) {
+            violations.push(LayerSurfaceRule::ZeroWidthWithoutHorizontalAnchors);
+        }
+        if self.height == 0 && !self.anchor.contains(Anchor::TOP | Anchor::BOTTOM) {
+            violations.push(LayerSurfaceRule::ZeroHeightWithoutVerticalAnchors);
+        }
+        if self.exclusive_zone > 0 && exclusive_edge(self.anchor).is_none() {
+            violations.push(LayerSurfaceRule::ExclusiveZoneWithoutEdge);
+        }
+        if self.margin != self.effective_margin() {
+            violations.push(LayerSurfaceRule::MarginOnUnanchoredEdge);
+        }
+        violations
+    }
+
+    /// Nearest valid settings: zero sized axes are stretched between both
+    /// edges, exclusive zone and margins that can't apply are zeroed
+    pub fn corrected(&self) -> Self {
+        let mut settings = *self;
+        if settings.width == 0 {
+            settings.anchor |= Anchor::LEFT | Anchor::RIGHT;
+        }
+        if settings.height == 0 {
+            settings.anchor |= Anchor::TOP | Anchor::BOTTOM;
+        }
+        if settings.exclusive_zone > 0 && exclusive_edge(settings.anchor).is_none() {
+            settings.exclusive_zone = 0;
+        }
+        settings.margin = settings.effective_margin();
+        settings
+    }
+
+    /// Correct the settings and warn once per broken rule, or fail in strict
+    /// mode
+    pub fn validate(&self, strict: bool) -> Result<Self, InvalidLayerSurface> {
+        let violations = self.violations();
+        if violations.is_empty() {
+            return Ok(*self);
+        }
+        if strict {
+            return Err(InvalidLayerSurface { violations });
+        }
+        for rule in violations {
+            if WARNED.fetch_or(rule.bit(), Ordering::Relaxed) & rule.bit() == 0 {
+                warn!("[LAYER] Correcting layer surface settings: {}", rule);
+            }
+        }
+        Ok(self.corrected())
+    }
+
+    /// Validate and set everything on the layer surface, commit is left to
+    /// the caller
+    pub fn apply(
+        &self,
+        layer_surface: &LayerSurface,
+        strict: bool,
+    ) -> Result<Self, InvalidLayerSurface> {
+        let settings = self.validate(strict)?;
+        let (top, right, bottom, left) = settings.margin;
+        layer_surface.set_anchor(settings.anchor);
+        layer_surface.set_size(settings.width, settings.height);
+        layer_surface.set_exclusive_zone(settings.exclusive_zone);
+        layer_surface.set_margin(top, right, bottom, left);
+        Ok(settings)
+    }
+
+    /// Whether margins can move the surface on either axis, which needs an
+    /// anchor on exactly one edge of the axis
+    pub fn can_move(&self) -> bool {
+        self.anchor.contains(Anchor::LEFT) != self.anchor.contains(Anchor::RIGHT)
+            || self.anchor.contains(Anchor::TOP) != self.anchor.contains(Anchor::BOTTOM)
+    }
+
+    /// Moved right and down by growing the margin of the anchored edge, or
+    /// shrinking the opposite one. Centered and stretched axes stay.
+    pub fn moved(&self, dx: i32, dy: i32) -> Self {
+        let mut settings = *self;
+        let (top, right, bottom, left) = &mut settings.margin;
+        match (
+            self.anchor.contains(Anchor::LEFT),
+            self.anchor.contains(Anchor::RIGHT),
+        ) {
+            (true, false) => *left += dx,
+            (false, true) => *right -= dx,
+            _ => {}
+        }
+        match (
+            self.anchor.contains(Anchor::TOP),
+            self.anchor.contains(Anchor::BOTTOM),
+        ) {
+            (true, false) => *top += dy,
+            (false, true) => *bottom -= dy,
+            _ => {}
+        }
+        settings
+    }
+
+    /// Grown by the deltas, at least one pixel. Stretched axes stay.
+    pub fn resized(&self, dw: i32, dh: i32) -> Self {
+        let mut settings = *self;
+        let grow = |size: u32, delta: i32| (size as i32).saturating_add(delta).max(1) as u32;
+        if settings.width != 0 {
+            settings.width = grow(settings.width, dw);
+        }
+        if settings.height != 0 {
+            settings.height = grow(settings.height, dh);
+        }
+        settings
+    }
+
+    fn effective_margin(&self) -> (i32, i32, i32, i32) {
+        let (top, right, bottom, left) = self.margin;
+        let keep = |edge: Anchor, value: i32| {
+            if self.anchor.contains(edge) { value } else { 0 }
+        };
+        (
+            keep(Anchor::TOP, top),
+            keep(Anchor::RIGHT, right),
+            keep(Anchor::BOTTOM, bottom),
+            keep(Anchor::LEFT, left),
+        )
+    }
+}
+
+/// Layer surface with its settings, created and committed by `build`
+///
+/// Defaults to a 256x256 surface on the top layer, not anchored and with
+/// on-demand keyboard interactivity.
+#[derive(Debug, Clone)]
+pub struct LayerSurfaceBuilder {
+    layer: Layer,
+    settings: LayerSurfaceSettings,
+    keyboard_interactivity: KeyboardInteractivity,
+    output: Option<WlOutput>,
+    namespace: Option<String>,
+    strict: bool,
+}
+
+impl Default for LayerSurfaceBuilder {
+    fn default() -> Self {
+        Self {
+            layer: Layer::Top,
+            settings: LayerSurfaceSettings::default(),
+            keyboard_interactivity: KeyboardInteractivity::OnDemand,
+            output: None,
+            namespace: None,
+            strict: false,
+        }
+    }
+}
+
+impl LayerSurfaceBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn layer(mut self, layer: Layer) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    pub fn anchor(mut self, anchor: Anchor) -> Self {
+        self.settings.anchor = anchor;
+        self
+    }
+
+    pub fn exclusive_zone(mut self, exclusive_zone: i32) -> Self {
+        self.settings.exclusive_zone = exclusive_zone;
+        self
+    }
+
+    pub fn margins(mut self, top: i32, right: i32, bottom: i32, left: i32) -> Self {
+        self.settings.margin = (top, right, bottom, left);
+        self
+    }
+
+    pub fn keyboard_interactivity(mut self, keyboard_interactivity: KeyboardInteractivity) -> Self {
+        self.keyboard_interactivity = keyboard_interactivity;
+        self
+    }
+
+    /// Zero stretches the axis between the anchored edges
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.settings.width = width;
+        self.settings.height = height;
+        self
+    }
+
+    /// `None` lets the compositor pick the output
+    pub fn output(mut self, output: Option<&WlOutput>) -> Self {
+        self.output = output.cloned();
+        self
+    }
+
+    pub fn namespace(mut self, namespace: &str) -> Self {
+        self.namespace = Some(namespace.to_string());
+        self
+    }
+
+    /// Fail on settings compositors disagree on instead of correcting them
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    pub fn settings(&self) -> &LayerSurfaceSettings {
+        &self.settings
+    }
+
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Create the layer surface and commit it, the container pushed for it
+    /// gets the first configure
+    pub fn build(&self, app: &mut Application) -> Result<LayerSurface, InvalidLayerSurface> {
+        let settings = self.settings.validate(self.strict)?;
+        let layer_surface = app.layer_shell.create_layer_surface(
+            &app.qh,
+            app.compositor_state.create_surface(&app.qh),
+            self.layer,
+            self.namespace.clone(),
+            self.output.as_ref(),
+        );
+        settings.apply(&layer_surface, self.strict)?;
+        layer_surface.set_keyboard_interactivity(self.keyboard_interactivity);
+        layer_surface.commit();
+        Ok(layer_surface)
+    }
+}
+
+/// Edge the exclusive zone applies to: the only anchored edge, or the edge
+/// anchored together with both of its perpendicular edges
+pub(crate) fn exclusive_edge(anchor: Anchor) -> Option<Anchor> {
+    let horizontal = Anchor::LEFT | Anchor::RIGHT;
+    let vertical = Anchor::TOP | Anchor::BOTTOM;
+    [Anchor::TOP, Anchor::BOTTOM, Anchor::LEFT, Anchor::RIGHT]
+        .into_iter()
+        .find(|edge| {
+            let perpendicular = if vertical.contains(*edge) {
+                horizontal
+            } else {
+                vertical
+            };
+            anchor == *edge || anchor == *edge | perpendicular
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(anchor: Anchor, width: u32, height: u32) -> LayerSurfaceSettings {
+        LayerSurfaceSettings {
+            anchor,
+            width,
+            height,
+            ..Default::default()
+        }
+    }
+
+    /// Strict validation fails with only `rule`, the corrected settings
+    /// are valid and equal to `expected`
+    fn assert_rule(
+        invalid: LayerSurfaceSettings,
+        rule: LayerSurfaceRule,
+        expected: LayerSurfaceSettings,
+    ) {
+        assert_eq!(invalid.violations(), vec![rule]);
+        let err = invalid.validate(true).unwrap_err();
+        assert_eq!(err.violations, vec![rule]);
+
+        let corrected = invalid.validate(false).unwrap();
+        assert_eq!(corrected, expected);
+        assert_eq!(corrected, invalid.corrected());
+        assert!(corrected.violations().is_empty());
+    }
+
+    #[test]
+    fn valid_settings_are_kept() {
+        let valid = LayerSurfaceSettings {
+            exclusive_zone: 32,
+            margin: (4, 0, 0, 0),
+            ..settings(Anchor::TOP | Anchor::LEFT | Anchor::RIGHT, 0, 32)
+        };
+        assert!(valid.violations().is_empty());
+        assert_eq!(valid.validate(true).unwrap(), valid);
+        assert_eq!(valid.corrected(), valid);
+    }
+
+    #[test]
+    fn zero_width_needs_horizontal_anchors() {
+        assert_rule(
+            settings(Anchor::TOP | Anchor::LEFT, 0, 32),
+            LayerSurfaceRule::ZeroWidthWithoutHorizontalAnchors,
+            settings(Anchor::TOP | Anchor::LEFT | Anchor::RIGHT, 0, 32),
+        );
+    }
+
+    #[test]
+    fn zero_height_needs_vertical_anchors() {
+        assert_rule(
+            settings(Anchor::LEFT | Anchor::BOTTOM, 32, 0),
+            LayerSurfaceRule::ZeroHeightWithoutVerticalAnchors,
+            settings(Anchor::LEFT | Anchor::TOP | Anchor::BOTTOM, 32, 0),
+        );
+    }
+
+    #[test]
+    fn exclusive_zone_needs_an_edge() {
+        let corner = Anchor::TOP | Anchor::LEFT;
+        assert_rule(
+            LayerSurfaceSettings {
+                exclusive_zone: 32,
+                ..settings(corner, 32, 32)
+            },
+            LayerSurfaceRule::ExclusiveZoneWithoutEdge,
+            settings(corner, 32, 32),
+        );
+        // Negative zones only ask not to be moved, they need no edge
+        let negative = LayerSurfaceSettings {
+            exclusive_zone: -1,
+            ..settings(corner, 32, 32)
+        };
+        assert!(negative.violations().is_empty());
+    }
+
+    #[test]
+    fn margin_needs_an_anchored_edge() {
+        assert_rule(
+            LayerSurfaceSettings {
+                margin: (8, 8, 8, 8),
+                ..settings(Anchor::TOP | Anchor::LEFT, 32, 32)
+            },
+            LayerSurfaceRule::MarginOnUnanchoredEdge,
+            LayerSurfaceSettings {
+                margin: (8, 0, 0, 8),
+                ..settings(Anchor::TOP | Anchor::LEFT, 32, 32)
+            },
+        );
+    }
+
+    #[test]
+    fn exclusive_edge_of_anchors() {
+        let horizontal = Anchor::LEFT | Anchor::RIGHT;
+        assert_eq!(exclusive_edge(Anchor::TOP), Some(Anchor::TOP));
+        assert_eq!(
+            exclusive_edge(Anchor::BOTTOM | horizontal),
+            Some(Anchor::BOTTOM)
+        );
+        assert_eq!(exclusive_edge(Anchor::TOP | Anchor::LEFT), None);
+        assert_eq!(exclusive_edge(Anchor::all()), None);
+        assert_eq!(exclusive_edge(Anchor::empty()), None);
+    }
+}
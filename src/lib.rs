@@ -1,9 +1,76 @@
+mod advice;
 mod application;
+#[cfg(feature = "automation")]
+mod automation;
 mod containers;
+mod dnd;
+#[cfg(feature = "docs-render")]
+mod docs_render;
 mod egui;
+mod frame_budget;
+mod gpu_context;
+mod gpu_timer;
+mod graveyard;
+mod input_batch;
+mod keyboard;
+mod layer_surface;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod outputs;
+mod popup;
+#[cfg(feature = "portal")]
+mod portal;
+mod presentation;
+mod protocol_versions;
+mod raw_handles;
+mod readiness;
+mod redraw;
+mod render_stats;
+mod resume;
+mod seats;
+mod session_lock;
+mod shared_state;
 mod single_color;
+mod stall_watch;
+mod startup_timing;
+mod strict;
+mod stuck_grab;
+mod surface_offset;
+mod surface_options;
+mod tasks;
+mod text_input;
+mod viewporter;
 
 pub use application::*;
+#[cfg(feature = "automation")]
+pub use automation::*;
 pub use containers::*;
+pub use dnd::DroppedData;
+pub use dnd::TransferLimits;
+#[cfg(feature = "docs-render")]
+pub use docs_render::*;
 pub use egui::*;
+pub use frame_budget::*;
+pub use gpu_context::*;
+pub use layer_surface::*;
+#[cfg(feature = "metrics")]
+pub use metrics::*;
+pub use outputs::OutputSelector;
+pub use popup::*;
+#[cfg(feature = "portal")]
+pub use portal::*;
+pub use presentation::FrameStatus;
+pub use presentation::FrameToken;
+pub use presentation::Presented;
+pub use protocol_versions::*;
+pub use redraw::RedrawHandle;
+pub use redraw::WindowHandle;
+pub use render_stats::FramePhases;
+pub use render_stats::RenderStats;
+pub use session_lock::SessionLockHandle;
+pub use shared_state::SharedGuard;
+pub use shared_state::SharedState;
 pub use single_color::*;
+pub use surface_offset::*;
+pub use surface_options::*;
+pub use tasks::Tasks;
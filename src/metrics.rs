@@ -0,0 +1,235 @@
+//! Prometheus textfile exporter, enabled with the `metrics` feature.
+//!
+//! Metrics are snapshotted from the containers on an interval timer and written
+//! atomically to a file for node_exporter's textfile collector.
+//!
+//! Exported series, all labeled with `role` (window, layer_surface, popup,
+//! subsurface, lock_surface), `surface` (the wl_surface object id) and `name`
+//! (set with `EguiSurface::with_debug_name`, empty otherwise):
+//!
+//! - `wayapp_frames_presented_total` counter
+//! - `wayapp_frames_skipped_total` counter
+//! - `wayapp_frames_deferred_total` counter
+//! - `wayapp_frames_unchanged_total` counter
+//! - `wayapp_degradation_events_total` counter
+//! - `wayapp_degradation_steps` gauge
+//! - `wayapp_frame_time_p95_seconds` gauge
+//! - `wayapp_gpu_frame_time_seconds` gauge
+//! - `wayapp_swapchain_reconnects_total` counter
+//! - `wayapp_swapchain_memory_bytes` gauge
+//! - `wayapp_input_latency_p95_seconds` gauge
+use crate::Application;
+use crate::SurfaceMetrics;
+use log::warn;
+use smithay_client_toolkit::reexports::calloop::RegistrationToken;
+use smithay_client_toolkit::reexports::calloop::timer::TimeoutAction;
+use smithay_client_toolkit::reexports::calloop::timer::Timer;
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Snapshot of a single surface's metrics with its labels
+pub struct LabeledSurfaceMetrics {
+    pub role: &'static str,
+    pub surface: String,
+    pub metrics: SurfaceMetrics,
+}
+
+/// Name, type, help and value of an exported series
+type Series = (
+    &'static str,
+    &'static str,
+    &'static str,
+    fn(&SurfaceMetrics) -> f64,
+);
+
+/// Render snapshots in the Prometheus exposition format
+pub fn render_exposition(snapshots: &[LabeledSurfaceMetrics]) -> String {
+    let mut out = String::new();
+    let series: [Series; 11] = [
+        (
+            "wayapp_frames_presented_total",
+            "counter",
+            "Frames presented by the surface",
+            |m| m.frames_presented as f64,
+        ),
+        (
+            "wayapp_frames_skipped_total",
+            "counter",
+            "Frames skipped by the surface",
+            |m| m.frames_skipped as f64,
+        ),
+        (
+            "wayapp_frames_deferred_total",
+            "counter",
+            "Renders deferred while suspended or throttled",
+            |m| m.frames_deferred as f64,
+        ),
+        (
+            "wayapp_frames_unchanged_total",
+            "counter",
+            "Frames not presented since nothing changed",
+            |m| m.frames_unchanged as f64,
+        ),
+        (
+            "wayapp_degradation_events_total",
+            "counter",
+            "Frame budget degradation transitions",
+            |m| m.degradation_events as f64,
+        ),
+        (
+            "wayapp_degradation_steps",
+            "gauge",
+            "Frame budget degradation steps active",
+            |m| m.degradation_steps as f64,
+        ),
+        (
+            "wayapp_frame_time_p95_seconds",
+            "gauge",
+            "95th percentile of recent frame times",
+            |m| m.frame_time_p95.map_or(0.0, |d| d.as_secs_f64()),
+        ),
+        (
+            "wayapp_gpu_frame_time_seconds",
+            "gauge",
+            "GPU time of the latest measured frame",
+            |m| m.gpu_frame_time.map_or(0.0, |d| d.as_secs_f64()),
+        ),
+        (
+            "wayapp_swapchain_reconnects_total",
+            "counter",
+            "Swapchains reconfigured after the surface was lost or outdated",
+            |m| m.swapchain_reconnects as f64,
+        ),
+        (
+            "wayapp_swapchain_memory_bytes",
+            "gauge",
+            "Estimated size of the swapchain textures",
+            |m| m.swapchain_memory_bytes as f64,
+        ),
+        (
+            "wayapp_input_latency_p95_seconds",
+            "gauge",
+            "95th percentile of the time from input to its presented frame",
+            |m| m.input_latency_p95.map_or(0.0, |d| d.as_secs_f64()),
+        ),
+    ];
+    for (name, kind, help, value) in series {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} {kind}");
+        for snapshot in snapshots {
+            let _ = writeln!(
+                out,
+                "{name}{{role=\"{}\",surface=\"{}\",name=\"{}\"}} {}",
+                escape_label(snapshot.role),
+                escape_label(&snapshot.surface),
+                escape_label(snapshot.metrics.debug_name.as_deref().unwrap_or_default()),
+                value(&snapshot.metrics)
+            );
+        }
+    }
+    out
+}
+
+/// Label value escaped as the exposition format requires
+fn escape_label(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn write_atomically(path: &PathBuf, contents: &str) -> std::io::Result<()> {
+    let mut tmp = path.clone().into_os_string();
+    tmp.push(".tmp");
+    std::fs::write(&tmp, contents)?;
+    std::fs::rename(&tmp, path)
+}
+
+impl Application {
+    /// Write metrics to `path` every `interval` for node_exporter's textfile
+    /// collector
+    pub fn export_metrics_textfile(
+        &mut self,
+        path: impl Into<PathBuf>,
+        interval: Duration,
+    ) -> RegistrationToken {
+        let path = path.into();
+        self.loop_handle
+            .insert_source(Timer::from_duration(interval), move |_, _, app| {
+                let exposition = render_exposition(&app.surface_metrics());
+                if let Err(err) = write_atomically(&path, &exposition) {
+                    warn!("[METRICS] Failed to write {}: {}", path.display(), err);
+                }
+                TimeoutAction::ToDuration(interval)
+            })
+            .expect("Failed to insert metrics timer")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(name: Option<&str>) -> LabeledSurfaceMetrics {
+        LabeledSurfaceMetrics {
+            role: "window",
+            surface: "wl_surface@3".to_string(),
+            metrics: SurfaceMetrics {
+                frames_presented: 42,
+                swapchain_memory_bytes: 1024,
+                frame_time_p95: Some(Duration::from_millis(5)),
+                debug_name: name.map(str::to_string),
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn escapes_label_values() {
+        assert_eq!(escape_label("plain"), "plain");
+        assert_eq!(escape_label("a\\b"), "a\\\\b");
+        assert_eq!(escape_label("say \"hi\""), "say \\\"hi\\\"");
+        assert_eq!(escape_label("two\nlines"), "two\\nlines");
+    }
+
+    #[test]
+    fn exposition_format() {
+        let out = render_exposition(&[snapshot(Some("panel \"top\"")), snapshot(None)]);
+        let lines: Vec<&str> = out.lines().collect();
+        // HELP, TYPE and one sample per surface for each series
+        assert_eq!(lines.len(), 11 * 4);
+        for chunk in lines.chunks(4) {
+            let name = chunk[0]
+                .strip_prefix("# HELP ")
+                .and_then(|rest| rest.split(' ').next())
+                .expect("HELP line first");
+            assert!(chunk[1].starts_with(&format!("# TYPE {name} ")));
+            for sample in &chunk[2..] {
+                let (series, value) = sample.rsplit_once(' ').unwrap();
+                assert!(series.starts_with(&format!("{name}{{role=\"window\"")));
+                assert!(series.ends_with("\"}"));
+                value.parse::<f64>().expect("sample value is a number");
+            }
+        }
+        assert!(out.contains(
+            "wayapp_frames_presented_total{role=\"window\",surface=\"wl_surface@3\",name=\"panel \
+             \\\"top\\\"\"} 42\n"
+        ));
+        assert!(out.contains(
+            "wayapp_swapchain_memory_bytes{role=\"window\",surface=\"wl_surface@3\",name=\"\"} \
+             1024\n"
+        ));
+        assert!(out.contains("# TYPE wayapp_input_latency_p95_seconds gauge\n"));
+        assert!(out.contains(
+            "wayapp_frame_time_p95_seconds{role=\"window\",surface=\"wl_surface@3\",name=\"\"} \
+             0.005\n"
+        ));
+    }
+}
@@ -0,0 +1,112 @@
+use smithay_client_toolkit::output::OutputState;
+use std::collections::HashMap;
+use wayland_backend::client::ObjectId;
+use wayland_client::protocol::wl_output::WlOutput;
+
+/// Output a window is made fullscreen on
+///
+/// Clients don't know their global position, so the overlap with each
+/// output can't be measured. The outputs the surface has entered are the
+/// only hint; when it has entered several the choice is left to the
+/// compositor.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum OutputSelector {
+    /// The output the surface is on, compositor's choice if it straddles
+    /// several or hasn't entered any yet
+    #[default]
+    Current,
+    /// Output with this name, e.g. `DP-1`
+    Name(String),
+    /// Output by the order the compositor advertised them
+    Index(usize),
+    /// Largest of the entered outputs, or of all outputs if none entered
+    Largest,
+    /// Let the compositor pick
+    Compositor,
+}
+
+/// Outputs each window surface has entered, in the order of entering
+#[derive(Default)]
+pub(crate) struct SurfaceOutputs {
+    entered: HashMap<ObjectId, Vec<WlOutput>>,
+    /// Enter or leave arrived in the current dispatch, more may follow
+    changing: bool,
+}
+
+impl SurfaceOutputs {
+    /// Returns true if this is the first change of the dispatch
+    pub fn enter(&mut self, surface_id: &ObjectId, output: &WlOutput) -> bool {
+        let outputs = self.entered.entry(surface_id.clone()).or_default();
+        if !outputs.contains(output) {
+            outputs.push(output.clone());
+        }
+        !std::mem::replace(&mut self.changing, true)
+    }
+
+    /// Returns true if this is the first change of the dispatch
+    pub fn leave(&mut self, surface_id: &ObjectId, output: &WlOutput) -> bool {
+        if let Some(outputs) = self.entered.get_mut(surface_id) {
+            outputs.retain(|o| o != output);
+        }
+        !std::mem::replace(&mut self.changing, true)
+    }
+
+    pub fn settled(&mut self) {
+        self.changing = false;
+    }
+
+    pub fn is_changing(&self) -> bool {
+        self.changing
+    }
+
+    pub fn remove_surface(&mut self, surface_id: &ObjectId) {
+        self.entered.remove(surface_id);
+    }
+
+    pub fn remove_output(&mut self, output: &WlOutput) {
+        for outputs in self.entered.values_mut() {
+            outputs.retain(|o| o != output);
+        }
+    }
+
+    /// `None` leaves the choice to the compositor
+    pub fn select(
+        &self,
+        output_state: &OutputState,
+        surface_id: &ObjectId,
+        selector: &OutputSelector,
+    ) -> Option<WlOutput> {
+        let entered = self
+            .entered
+            .get(surface_id)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        match selector {
+            OutputSelector::Current => match entered {
+                [output] => Some(output.clone()),
+                _ => None,
+            },
+            OutputSelector::Name(name) => output_state.outputs().find(|output| {
+                output_state
+                    .info(output)
+                    .is_some_and(|info| info.name.as_deref() == Some(name.as_str()))
+            }),
+            OutputSelector::Index(index) => output_state.outputs().nth(*index),
+            OutputSelector::Largest => {
+                let candidates: Vec<WlOutput> = if entered.is_empty() {
+                    output_state.outputs().collect()
+                } else {
+                    entered.to_vec()
+                };
+                candidates.into_iter().max_by_key(|output| {
+                    output_state
+                        .info(output)
+                        .and_then(|info| info.logical_size)
+                        .map(|(w, h)| w as i64 * h as i64)
+                        .unwrap_or(0)
+                })
+            }
+            OutputSelector::Compositor => None,
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use crate::Application;
+use smithay_client_toolkit::reexports::protocols::xdg::shell::client::xdg_positioner::Anchor;
+use smithay_client_toolkit::reexports::protocols::xdg::shell::client::xdg_positioner::ConstraintAdjustment;
+use smithay_client_toolkit::reexports::protocols::xdg::shell::client::xdg_positioner::Gravity;
+use smithay_client_toolkit::shell::wlr_layer::LayerSurface;
+use smithay_client_toolkit::shell::xdg::XdgPositioner;
+use smithay_client_toolkit::shell::xdg::XdgSurface;
+use smithay_client_toolkit::shell::xdg::popup::Popup;
+use smithay_client_toolkit::shell::xdg::window::Window;
+
+/// Surface a popup is created for
+pub enum PopupParent<'a> {
+    Window(&'a Window),
+    LayerSurface(&'a LayerSurface),
+}
+
+/// Placement of a popup relative to its parent, see xdg_positioner
+#[derive(Debug, Clone, Copy)]
+pub struct PopupPlacement {
+    /// Rectangle in parent surface coordinates the popup is anchored to:
+    /// x, y, width, height
+    pub anchor_rect: (i32, i32, i32, i32),
+    pub size: (u32, u32),
+    pub anchor: Anchor,
+    pub gravity: Gravity,
+}
+
+impl PopupPlacement {
+    /// Context menu opening down and right from the point
+    pub fn at_point(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self {
+            anchor_rect: (x, y, 1, 1),
+            size: (width, height),
+            anchor: Anchor::TopLeft,
+            gravity: Gravity::BottomRight,
+        }
+    }
+}
+
+impl Application {
+    /// Create an xdg popup, with `grab` it takes the keyboard and pointer
+    /// using the serial of the latest button or key press and is dismissed
+    /// by clicking elsewhere
+    ///
+    /// Push it in a `PopupContainer` to render, the container is removed when
+    /// the compositor dismisses the popup.
+    pub fn create_popup(
+        &mut self,
+        parent: PopupParent<'_>,
+        placement: &PopupPlacement,
+        grab: bool,
+    ) -> Popup {
+        let positioner = XdgPositioner::new(&self.xdg_shell).expect("Failed to create positioner");
+        let (x, y, width, height) = placement.anchor_rect;
+        positioner.set_anchor_rect(x, y, width.max(1), height.max(1));
+        positioner.set_size(
+            placement.size.0.max(1) as i32,
+            placement.size.1.max(1) as i32,
+        );
+        positioner.set_anchor(placement.anchor);
+        positioner.set_gravity(placement.gravity);
+        positioner.set_constraint_adjustment(
+            ConstraintAdjustment::FlipX
+                | ConstraintAdjustment::FlipY
+                | ConstraintAdjustment::SlideX
+                | ConstraintAdjustment::SlideY,
+        );
+
+        let popup = match parent {
+            PopupParent::Window(window) => Popup::new(
+                window.xdg_surface(),
+                &positioner,
+                &self.qh,
+                &self.compositor_state,
+                &self.xdg_shell,
+            ),
+            PopupParent::LayerSurface(layer_surface) => Popup::from_surface(
+                None,
+                &positioner,
+                &self.qh,
+                self.compositor_state.create_surface(&self.qh),
+                &self.xdg_shell,
+            )
+            .inspect(|popup| layer_surface.get_popup(popup.xdg_popup())),
+        }
+        .expect("Failed to create popup");
+
+        if grab && let Some((seat, serial)) = self.grab_serial() {
+            popup.xdg_popup().grab(&seat, serial);
+        }
+        popup.wl_surface().commit();
+        popup
+    }
+}
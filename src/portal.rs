@@ -0,0 +1,112 @@
+use log::debug;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use zbus::blocking::Connection;
+use zbus::blocking::Proxy;
+use zbus::zvariant::OwnedObjectPath;
+use zbus::zvariant::OwnedValue;
+use zbus::zvariant::Value;
+
+const PORTAL_DESTINATION: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+
+static NEXT_TOKEN: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PickColorError {
+    /// User dismissed the picker
+    Cancelled,
+    /// No session bus or the portal has no Screenshot interface
+    Unsupported,
+    Failed(String),
+}
+
+impl fmt::Display for PickColorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PickColorError::Cancelled => write!(f, "Color picking was cancelled"),
+            PickColorError::Unsupported => write!(f, "Color picking is not supported"),
+            PickColorError::Failed(err) => write!(f, "Color picking failed: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for PickColorError {}
+
+/// Let the user pick a color anywhere on the screen with the picker of the
+/// compositor, through the Screenshot portal
+///
+/// Blocks until the user has picked, run it with `Tasks::spawn_blocking`.
+/// `parent_window` is the portal identifier of the requesting window, e.g.
+/// "wayland:<exported handle>", or empty. This crate doesn't export
+/// toplevels with xdg-foreign, so the picker is not modal to the window.
+pub fn pick_color(parent_window: &str) -> Result<egui::Color32, PickColorError> {
+    let unsupported = |err: zbus::Error| {
+        debug!("[PORTAL] Screenshot portal not available: {}", err);
+        PickColorError::Unsupported
+    };
+    let failed = |err: zbus::Error| PickColorError::Failed(err.to_string());
+
+    let connection = Connection::session().map_err(unsupported)?;
+    let screenshot = Proxy::new(
+        &connection,
+        PORTAL_DESTINATION,
+        PORTAL_PATH,
+        "org.freedesktop.portal.Screenshot",
+    )
+    .map_err(unsupported)?;
+
+    // The request object path is known from the token, the response is
+    // subscribed to before the call so it can't be missed
+    let token = format!("wayapp{}", NEXT_TOKEN.fetch_add(1, Ordering::Relaxed));
+    let sender = connection
+        .unique_name()
+        .map(|name| name.trim_start_matches(':').replace('.', "_"))
+        .unwrap_or_default();
+    let request_path = format!("{}/request/{}/{}", PORTAL_PATH, sender, token);
+    let request = Proxy::new(
+        &connection,
+        PORTAL_DESTINATION,
+        request_path.as_str(),
+        "org.freedesktop.portal.Request",
+    )
+    .map_err(failed)?;
+    let mut responses = request.receive_signal("Response").map_err(failed)?;
+
+    let mut options: HashMap<&str, Value> = HashMap::new();
+    options.insert("handle_token", token.as_str().into());
+    let _: OwnedObjectPath = screenshot
+        .call("PickColor", &(parent_window, options))
+        .map_err(|err| match err {
+            zbus::Error::MethodError(..) => unsupported(err),
+            err => failed(err),
+        })?;
+
+    let message = responses
+        .next()
+        .ok_or_else(|| PickColorError::Failed("portal closed the request".to_string()))?;
+    let (response, results): (u32, HashMap<String, OwnedValue>) =
+        message.body().deserialize().map_err(failed)?;
+    match response {
+        0 => {}
+        1 => return Err(PickColorError::Cancelled),
+        _ => {
+            return Err(PickColorError::Failed(
+                "portal ended the request".to_string(),
+            ));
+        }
+    }
+    let color = results
+        .get("color")
+        .and_then(|color| color.try_clone().ok())
+        .and_then(|color| <(f64, f64, f64)>::try_from(Value::from(color)).ok())
+        .ok_or_else(|| PickColorError::Failed("response has no color".to_string()))?;
+    let channel = |value: f64| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    Ok(egui::Color32::from_rgb(
+        channel(color.0),
+        channel(color.1),
+        channel(color.2),
+    ))
+}
@@ -0,0 +1,380 @@
+use crate::Application;
+use log::trace;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::mem::MaybeUninit;
+use std::rc::Rc;
+use std::time::Duration;
+use std::time::Instant;
+use wayland_backend::client::ObjectId;
+use wayland_client::Connection;
+use wayland_client::Dispatch;
+use wayland_client::Proxy;
+use wayland_client::QueueHandle;
+use wayland_client::globals::GlobalList;
+use wayland_client::protocol::wl_surface::WlSurface;
+use wayland_protocols::wp::presentation_time::client::wp_presentation;
+use wayland_protocols::wp::presentation_time::client::wp_presentation::WpPresentation;
+use wayland_protocols::wp::presentation_time::client::wp_presentation_feedback;
+use wayland_protocols::wp::presentation_time::client::wp_presentation_feedback::WpPresentationFeedback;
+
+/// CLOCK_MONOTONIC, used until the compositor announces its clock
+const CLOCK_MONOTONIC: u32 = 1;
+
+fn clock_now(clock_id: u32) -> Option<Duration> {
+    let mut now = MaybeUninit::<libc::timespec>::uninit();
+    // SAFETY: clock_gettime only writes the timespec
+    let result = unsafe { libc::clock_gettime(clock_id as libc::clockid_t, now.as_mut_ptr()) };
+    if result != 0 {
+        return None;
+    }
+    // SAFETY: written by the successful call
+    let now = unsafe { now.assume_init() };
+    Some(Duration::new(now.tv_sec as u64, now.tv_nsec as u32))
+}
+
+/// Time a timestamp of the feedback clock corresponds to
+fn to_instant(clock_id: u32, timestamp: Duration) -> Option<Instant> {
+    let (now, clock) = (Instant::now(), clock_now(clock_id)?);
+    match clock.checked_sub(timestamp) {
+        Some(ago) => now.checked_sub(ago),
+        None => now.checked_add(timestamp - clock),
+    }
+}
+
+/// When a frame of the surface was shown, from wp_presentation feedback
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Presented {
+    pub presented_at: Instant,
+    /// Duration of a refresh of the output, `None` if it's variable or
+    /// unknown
+    pub refresh: Option<Duration>,
+    /// Vblank counter of the output, increases by one per refresh
+    pub sequence: u64,
+    /// Presented in sync with the vblank
+    pub vsync: bool,
+}
+
+/// wp_presentation global and the latest presentation of each surface
+#[derive(Default)]
+pub(crate) struct PresentationTime {
+    presentation: Option<WpPresentation>,
+    clock_id: Option<u32>,
+    presented: HashMap<ObjectId, Presented>,
+}
+
+impl PresentationTime {
+    pub fn bind(globals: &GlobalList, qh: &QueueHandle<Application>) -> Self {
+        let presentation = globals
+            .bind::<WpPresentation, _, _>(qh, 1..=1, ())
+            .inspect_err(|_| trace!("[MAIN] Presentation time not available"))
+            .ok();
+        Self {
+            presentation,
+            ..Default::default()
+        }
+    }
+
+    /// `None` without presentation time, surfaces then pace by frame
+    /// callbacks
+    pub fn manager(&self) -> Option<WpPresentation> {
+        self.presentation.clone()
+    }
+
+    pub fn last_presented(&self, surface_id: &ObjectId) -> Option<Presented> {
+        self.presented.get(surface_id).copied()
+    }
+
+    pub fn remove_surface(&mut self, surface_id: &ObjectId) {
+        self.presented.remove(surface_id);
+    }
+}
+
+/// Surface and transaction commit a feedback object belongs to
+pub(crate) struct FeedbackData {
+    surface: ObjectId,
+    commit: Option<u64>,
+}
+
+/// Ask for the presentation time of the next commit of the surface
+pub(crate) fn request_feedback(
+    presentation: &WpPresentation,
+    surface: &WlSurface,
+    qh: &QueueHandle<Application>,
+    commit: Option<u64>,
+) {
+    let data = FeedbackData {
+        surface: surface.id(),
+        commit,
+    };
+    presentation.feedback(surface, qh, data);
+}
+
+/// Target time a frame should be shown at, held until the vblank nearest to
+/// it is the next one a commit can make
+#[derive(Default)]
+pub(crate) struct FramePacer {
+    target: Option<Instant>,
+    last: Option<Presented>,
+    /// Refresh of the entered output, used without feedback
+    output_refresh: Option<Duration>,
+}
+
+impl FramePacer {
+    pub fn set_target(&mut self, target: Instant) {
+        self.target = Some(target);
+    }
+
+    pub fn presented(&mut self, presented: Presented) {
+        self.last = Some(presented);
+    }
+
+    /// Refresh rate in mHz of the entered output
+    pub fn set_output_refresh(&mut self, refresh_rate: i32) {
+        self.output_refresh = (refresh_rate > 0)
+            .then(|| Duration::from_nanos(1_000_000_000_000 / refresh_rate as u64));
+    }
+
+    /// Duration of a refresh from the feedback, else of the entered output
+    pub fn refresh(&self) -> Option<Duration> {
+        self.last
+            .and_then(|last| last.refresh)
+            .or(self.output_refresh)
+    }
+
+    /// Vblank a frame committed now is shown at, predicted from the feedback
+    /// timing. `None` without feedback or a known refresh.
+    pub fn next_present(&self, now: Instant) -> Option<Instant> {
+        let (last, refresh) = (self.last?, self.refresh()?);
+        let since = now.saturating_duration_since(last.presented_at);
+        let refreshes = since.as_nanos() / refresh.as_nanos().max(1) + 1;
+        Some(last.presented_at + refresh * refreshes as u32)
+    }
+
+    /// Whether a frame committed now would miss the vblank nearest the
+    /// target by showing too early, the target is then kept
+    pub fn hold(&mut self, now: Instant) -> bool {
+        let Some(target) = self.target else {
+            return false;
+        };
+        let hold = match (self.next_present(now), self.refresh()) {
+            // Next vblank from the feedback timing
+            (Some(next), Some(refresh)) => next + refresh / 2 <= target,
+            // Frame callbacks arrive at the vblank, a commit now is shown on
+            // the following one
+            (_, Some(refresh)) => now + refresh + refresh / 2 <= target,
+            (_, None) => now < target,
+        };
+        if !hold {
+            self.target = None;
+        }
+        hold
+    }
+}
+
+/// State change queued with `Application::frame_token`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FrameToken(u64);
+
+/// Outcome of the frame a `FrameToken` was presented in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameStatus {
+    /// Without the presentation time protocol the time is when the frame
+    /// callback arrived, refresh and sequence are unknown
+    Presented(Presented),
+    /// Never shown, e.g. the surface was hidden or destroyed first
+    Discarded,
+}
+
+type FrameCallback = Box<dyn FnOnce(FrameStatus)>;
+
+#[derive(Default)]
+struct TransactionsState {
+    next_token: u64,
+    next_commit: u64,
+    /// Tokens waiting for the next presented frame of each surface
+    pending: HashMap<ObjectId, Vec<FrameToken>>,
+    /// Tokens of each commit awaiting presentation
+    committed: HashMap<u64, (ObjectId, Vec<FrameToken>)>,
+    callbacks: HashMap<FrameToken, FrameCallback>,
+}
+
+/// Tokens correlated to the commits that include them, shared by the
+/// application and its surfaces
+#[derive(Clone, Default)]
+pub(crate) struct FrameTransactions(Rc<RefCell<TransactionsState>>);
+
+impl FrameTransactions {
+    pub fn token(&self, surface_id: &ObjectId) -> FrameToken {
+        let mut state = self.0.borrow_mut();
+        state.next_token += 1;
+        let token = FrameToken(state.next_token);
+        state
+            .pending
+            .entry(surface_id.clone())
+            .or_default()
+            .push(token);
+        token
+    }
+
+    /// Dropped if the token is already resolved
+    pub fn on_presented(&self, token: FrameToken, callback: FrameCallback) {
+        let mut state = self.0.borrow_mut();
+        let known = state.pending.values().flatten().any(|t| *t == token)
+            || state
+                .committed
+                .values()
+                .any(|(_, tokens)| tokens.contains(&token));
+        if known {
+            state.callbacks.insert(token, callback);
+        }
+    }
+
+    pub fn has_pending(&self, surface_id: &ObjectId) -> bool {
+        self.0.borrow().pending.contains_key(surface_id)
+    }
+
+    /// Bind the pending tokens of the surface to the commit being made,
+    /// `None` if there are none
+    pub fn commit(&self, surface_id: &ObjectId) -> Option<u64> {
+        let mut state = self.0.borrow_mut();
+        let tokens = state.pending.remove(surface_id)?;
+        state.next_commit += 1;
+        let commit = state.next_commit;
+        state.committed.insert(commit, (surface_id.clone(), tokens));
+        Some(commit)
+    }
+
+    pub fn resolve(&self, commit: u64, status: FrameStatus) {
+        let callbacks = {
+            let mut state = self.0.borrow_mut();
+            let Some((_, tokens)) = state.committed.remove(&commit) else {
+                return;
+            };
+            tokens
+                .iter()
+                .filter_map(|token| state.callbacks.remove(token))
+                .collect::<Vec<_>>()
+        };
+        // Callbacks may queue new tokens
+        for callback in callbacks {
+            callback(status);
+        }
+    }
+
+    /// Discard the tokens not committed yet, e.g. when the surface is hidden
+    pub fn discard_pending(&self, surface_id: &ObjectId) {
+        let callbacks = {
+            let mut state = self.0.borrow_mut();
+            let tokens = state.pending.remove(surface_id).unwrap_or_default();
+            tokens
+                .iter()
+                .filter_map(|token| state.callbacks.remove(token))
+                .collect::<Vec<_>>()
+        };
+        for callback in callbacks {
+            callback(FrameStatus::Discarded);
+        }
+    }
+
+    /// Discard everything of a destroyed surface, its feedback never arrives
+    pub fn discard_surface(&self, surface_id: &ObjectId) {
+        self.discard_pending(surface_id);
+        let commits: Vec<u64> = self
+            .0
+            .borrow()
+            .committed
+            .iter()
+            .filter(|(_, (surface, _))| surface == surface_id)
+            .map(|(commit, _)| *commit)
+            .collect();
+        for commit in commits {
+            self.resolve(commit, FrameStatus::Discarded);
+        }
+    }
+}
+
+impl Dispatch<WpPresentation, ()> for Application {
+    fn event(
+        app: &mut Self,
+        _: &WpPresentation,
+        event: wp_presentation::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let wp_presentation::Event::ClockId { clk_id } = event {
+            trace!("[MAIN] Presentation clock {}", clk_id);
+            app.presentation.clock_id = Some(clk_id);
+        }
+    }
+}
+
+impl Dispatch<WpPresentationFeedback, FeedbackData> for Application {
+    fn event(
+        app: &mut Self,
+        _: &WpPresentationFeedback,
+        event: wp_presentation_feedback::Event,
+        data: &FeedbackData,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let (tv_sec_hi, tv_sec_lo, tv_nsec, refresh, seq_hi, seq_lo, flags) = match event {
+            wp_presentation_feedback::Event::Presented {
+                tv_sec_hi,
+                tv_sec_lo,
+                tv_nsec,
+                refresh,
+                seq_hi,
+                seq_lo,
+                flags,
+            } => (
+                tv_sec_hi, tv_sec_lo, tv_nsec, refresh, seq_hi, seq_lo, flags,
+            ),
+            wp_presentation_feedback::Event::Discarded => {
+                if let Some(commit) = data.commit {
+                    app.transactions.resolve(commit, FrameStatus::Discarded);
+                }
+                return;
+            }
+            // Sync outputs are not tracked
+            _ => return,
+        };
+        let clock_id = app.presentation.clock_id.unwrap_or(CLOCK_MONOTONIC);
+        let timestamp = Duration::new(((tv_sec_hi as u64) << 32) | tv_sec_lo as u64, tv_nsec);
+        let presented_at = to_instant(clock_id, timestamp).unwrap_or_else(Instant::now);
+        let presented = Presented {
+            presented_at,
+            refresh: (refresh > 0).then(|| Duration::from_nanos(refresh as u64)),
+            sequence: ((seq_hi as u64) << 32) | seq_lo as u64,
+            vsync: flags
+                .into_result()
+                .is_ok_and(|flags| flags.contains(wp_presentation_feedback::Kind::Vsync)),
+        };
+        if let Some(commit) = data.commit {
+            app.transactions
+                .resolve(commit, FrameStatus::Presented(presented));
+        }
+        app.presentation
+            .presented
+            .insert(data.surface.clone(), presented);
+        app.dispatch_presented(&data.surface, &presented);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn monotonic_clock_maps_to_instants() {
+        let before = Instant::now();
+        let clock = clock_now(CLOCK_MONOTONIC).unwrap();
+        let at = to_instant(CLOCK_MONOTONIC, clock).unwrap();
+        // Instant is CLOCK_MONOTONIC on Linux, only the time between the
+        // two reads separates them
+        assert!(at + Duration::from_millis(100) >= before);
+        assert!(at <= Instant::now());
+        assert!(clock_now(u32::MAX).is_none());
+    }
+}
@@ -0,0 +1,178 @@
+use log::debug;
+use std::collections::HashMap;
+use wayland_client::globals::GlobalList;
+
+/// Globals whose version gates features
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Global {
+    Compositor,
+    XdgWmBase,
+    LayerShell,
+    Seat,
+    Output,
+}
+
+impl Global {
+    pub const ALL: [Global; 5] = [
+        Global::Compositor,
+        Global::XdgWmBase,
+        Global::LayerShell,
+        Global::Seat,
+        Global::Output,
+    ];
+
+    pub fn interface(self) -> &'static str {
+        match self {
+            Global::Compositor => "wl_compositor",
+            Global::XdgWmBase => "xdg_wm_base",
+            Global::LayerShell => "zwlr_layer_shell_v1",
+            Global::Seat => "wl_seat",
+            Global::Output => "wl_output",
+        }
+    }
+
+    /// Binding fails below this
+    pub fn minimum(self) -> u32 {
+        1
+    }
+
+    /// Highest version bound, newer versions advertised by the compositor
+    /// are bound at this
+    pub fn preferred(self) -> u32 {
+        match self {
+            Global::Compositor => 6,
+            Global::XdgWmBase => 6,
+            Global::LayerShell => 4,
+            Global::Seat => 9,
+            Global::Output => 4,
+        }
+    }
+}
+
+/// Features added in a later version of a global, objects created from the
+/// global have its version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProtocolFeature {
+    /// wl_surface.set_buffer_transform
+    BufferTransform,
+    /// wl_surface.set_buffer_scale, surfaces are drawn at scale 1 before it
+    BufferScale,
+    /// wl_surface.offset, given in wl_surface.attach before it
+    SurfaceOffset,
+    /// wl_surface.preferred_buffer_scale and preferred_buffer_transform,
+    /// the scale of the entered output is used before it
+    PreferredBufferScale,
+    /// xdg_toplevel.wm_capabilities, all window management is assumed
+    /// supported before it
+    WmCapabilities,
+    /// Suspended toplevel state, hidden windows keep rendering before it
+    ToplevelSuspended,
+    /// wl_pointer.axis_value120, discrete steps are used before it
+    AxisValue120,
+}
+
+impl ProtocolFeature {
+    pub const ALL: [ProtocolFeature; 7] = [
+        ProtocolFeature::BufferTransform,
+        ProtocolFeature::BufferScale,
+        ProtocolFeature::SurfaceOffset,
+        ProtocolFeature::PreferredBufferScale,
+        ProtocolFeature::WmCapabilities,
+        ProtocolFeature::ToplevelSuspended,
+        ProtocolFeature::AxisValue120,
+    ];
+
+    pub fn global(self) -> Global {
+        match self {
+            ProtocolFeature::BufferTransform
+            | ProtocolFeature::BufferScale
+            | ProtocolFeature::SurfaceOffset
+            | ProtocolFeature::PreferredBufferScale => Global::Compositor,
+            ProtocolFeature::WmCapabilities | ProtocolFeature::ToplevelSuspended => {
+                Global::XdgWmBase
+            }
+            ProtocolFeature::AxisValue120 => Global::Seat,
+        }
+    }
+
+    /// Version of the global the feature was added in
+    pub fn since(self) -> u32 {
+        match self {
+            ProtocolFeature::BufferTransform => 2,
+            ProtocolFeature::BufferScale => 3,
+            ProtocolFeature::SurfaceOffset => 5,
+            ProtocolFeature::PreferredBufferScale => 6,
+            ProtocolFeature::WmCapabilities => 5,
+            ProtocolFeature::ToplevelSuspended => 6,
+            ProtocolFeature::AxisValue120 => 8,
+        }
+    }
+
+    /// Whether an object of this version supports the feature, e.g.
+    /// `BufferScale.is_supported(wl_surface.version())`
+    pub fn is_supported(self, version: u32) -> bool {
+        version >= self.since()
+    }
+}
+
+/// Versions the globals were bound at
+#[derive(Debug, Clone, Default)]
+pub struct ProtocolVersions {
+    negotiated: HashMap<Global, u32>,
+}
+
+impl ProtocolVersions {
+    /// Versions as bound, the advertised version capped at the preferred one
+    pub(crate) fn from_globals(globals: &GlobalList) -> Self {
+        let mut versions = Self::default();
+        globals.contents().with_list(|list| {
+            for global in Global::ALL {
+                let advertised = list
+                    .iter()
+                    .filter(|g| g.interface == global.interface())
+                    .map(|g| g.version)
+                    .max();
+                if let Some(advertised) = advertised {
+                    versions.record(global, advertised.min(global.preferred()));
+                }
+            }
+        });
+        for (feature, available) in versions.report() {
+            debug!(
+                "[MAIN] Protocol feature {:?} available: {}",
+                feature, available
+            );
+        }
+        versions
+    }
+
+    pub(crate) fn record(&mut self, global: Global, version: u32) {
+        if version < global.minimum() {
+            debug!(
+                "[MAIN] {} version {} is below the minimum {}",
+                global.interface(),
+                version,
+                global.minimum()
+            );
+        }
+        self.negotiated.insert(global, version);
+    }
+
+    /// `None` if the compositor doesn't advertise the global
+    pub fn version(&self, global: Global) -> Option<u32> {
+        self.negotiated.get(&global).copied()
+    }
+
+    pub fn supports(&self, feature: ProtocolFeature) -> bool {
+        self.version(feature.global())
+            .is_some_and(|version| feature.is_supported(version))
+    }
+
+    /// Availability of every feature
+    pub fn report(&self) -> Vec<(ProtocolFeature, bool)> {
+        ProtocolFeature::ALL
+            .into_iter()
+            .map(|feature| (feature, self.supports(feature)))
+            .collect()
+    }
+}
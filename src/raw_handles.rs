@@ -0,0 +1,84 @@
+//! Construction of the raw Wayland handles handed to WGPU and the clipboard.
+//!
+//! All unsafe raw pointer handling lives here, the invariants each function
+//! relies on are documented next to the unsafe block.
+use raw_window_handle::RawDisplayHandle;
+use raw_window_handle::RawWindowHandle;
+use raw_window_handle::WaylandDisplayHandle;
+use raw_window_handle::WaylandWindowHandle;
+use smithay_clipboard::Clipboard;
+use std::ops::Deref;
+use std::ptr::NonNull;
+use wayland_client::Connection;
+use wayland_client::Proxy;
+use wayland_client::protocol::wl_surface::WlSurface;
+
+/// Clipboard bound to the display of the given connection
+pub(crate) fn clipboard_for_connection(conn: &Connection) -> Clipboard {
+    let display = display_ptr(conn);
+    // SAFETY: The display pointer is valid as long as the connection backend is
+    // alive. smithay-clipboard creates its own event queue on the display and
+    // doesn't outlive the process' connection, which `Application` keeps for
+    // the whole lifetime of the program.
+    unsafe { Clipboard::new(display.as_ptr()) }
+}
+
+/// WGPU surface that keeps the Wayland connection it was created from alive
+///
+/// The raw handles given to WGPU point into the connection backend and the
+/// wl_surface. The clone of the connection keeps the display alive, fields
+/// are dropped in declaration order so the WGPU surface goes first. A proxy
+/// of the wl_surface can't keep it alive, it's destroyed with the role object
+/// owning it, see `WaylandWgpuSurface::new`.
+pub(crate) struct WaylandWgpuSurface {
+    surface: wgpu::Surface<'static>,
+    _connection: Connection,
+}
+
+impl WaylandWgpuSurface {
+    /// # Safety
+    ///
+    /// The wl_surface must not be destroyed before the returned surface is
+    /// dropped, e.g. the role object owning it has to outlive the swapchain.
+    pub(crate) unsafe fn new(
+        instance: &wgpu::Instance,
+        conn: &Connection,
+        wl_surface: &WlSurface,
+    ) -> Result<Self, wgpu::CreateSurfaceError> {
+        let raw_display_handle =
+            RawDisplayHandle::Wayland(WaylandDisplayHandle::new(display_ptr(conn)));
+        let raw_window_handle =
+            RawWindowHandle::Wayland(WaylandWindowHandle::new(surface_ptr(wl_surface)));
+
+        // SAFETY: Both handles are non-null. The display stays valid because we
+        // keep the connection next to the surface, the wl_surface is kept by the
+        // caller.
+        let surface = unsafe {
+            instance.create_surface_unsafe(wgpu::SurfaceTargetUnsafe::RawHandle {
+                raw_display_handle,
+                raw_window_handle,
+            })?
+        };
+
+        Ok(Self {
+            surface,
+            _connection: conn.clone(),
+        })
+    }
+}
+
+impl Deref for WaylandWgpuSurface {
+    type Target = wgpu::Surface<'static>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.surface
+    }
+}
+
+fn display_ptr(conn: &Connection) -> NonNull<std::ffi::c_void> {
+    NonNull::new(conn.backend().display_ptr() as *mut _).expect("Wayland display pointer was null")
+}
+
+fn surface_ptr(wl_surface: &WlSurface) -> NonNull<std::ffi::c_void> {
+    NonNull::new(wl_surface.id().as_ptr() as *mut _).expect("Wayland surface handle was null")
+}
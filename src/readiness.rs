@@ -0,0 +1,187 @@
+use smithay_client_toolkit::shell::wlr_layer::LayerSurfaceConfigure;
+use smithay_client_toolkit::shell::xdg::window::WindowConfigure;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::time::Duration;
+use wayland_backend::client::ObjectId;
+
+/// Groups not configured completely by then are released as they are
+pub(crate) const READINESS_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Configure of a member, delivered when its group is released
+pub(crate) enum HeldConfigure {
+    Window(WindowConfigure),
+    LayerSurface(LayerSurfaceConfigure),
+}
+
+struct Group<C> {
+    members: HashSet<ObjectId>,
+    held: HashMap<ObjectId, C>,
+}
+
+impl<C> Default for Group<C> {
+    fn default() -> Self {
+        Self {
+            members: HashSet::new(),
+            held: HashMap::new(),
+        }
+    }
+}
+
+/// Surfaces that map together, see `Application::reveal_together`
+///
+/// The configures of the members are held until every member has one, then
+/// they are all delivered in the same dispatch. Each container renders its
+/// first frame on its configure, so the first buffers are committed one
+/// right after another. Holding the configure rather than a rendered frame
+/// works with any swapchain: WGPU commits the buffer when presenting, a
+/// frame can't be rendered without committing it.
+pub(crate) struct Readiness<C = HeldConfigure> {
+    groups: HashMap<String, Group<C>>,
+}
+
+impl<C> Default for Readiness<C> {
+    fn default() -> Self {
+        Self {
+            groups: HashMap::new(),
+        }
+    }
+}
+
+impl<C> Readiness<C> {
+    /// Whether the group is new and needs its timeout
+    pub fn join(&mut self, group: &str, surface_id: &ObjectId) -> bool {
+        let new = !self.groups.contains_key(group);
+        self.groups
+            .entry(group.to_string())
+            .or_default()
+            .members
+            .insert(surface_id.clone());
+        new
+    }
+
+    /// Configures to deliver now: this one if the surface isn't held, all
+    /// of the group once it was the last one missing
+    pub fn configure(&mut self, surface_id: &ObjectId, configure: C) -> Vec<(ObjectId, C)> {
+        let Some((name, group)) = self
+            .groups
+            .iter_mut()
+            .find(|(_, group)| group.members.contains(surface_id))
+        else {
+            return vec![(surface_id.clone(), configure)];
+        };
+        // A later configure replaces the held one
+        group.held.insert(surface_id.clone(), configure);
+        if group.held.len() < group.members.len() {
+            return Vec::new();
+        }
+        let name = name.clone();
+        self.release(&name)
+    }
+
+    /// Held configures of the group, its members aren't held anymore
+    pub fn release(&mut self, group: &str) -> Vec<(ObjectId, C)> {
+        self.groups
+            .remove(group)
+            .map(|group| group.held.into_iter().collect())
+            .unwrap_or_default()
+    }
+
+    /// Surface was removed, the rest of its group may be complete now
+    pub fn remove(&mut self, surface_id: &ObjectId) -> Vec<(ObjectId, C)> {
+        let Some((name, group)) = self
+            .groups
+            .iter_mut()
+            .find(|(_, group)| group.members.contains(surface_id))
+        else {
+            return Vec::new();
+        };
+        group.members.remove(surface_id);
+        group.held.remove(surface_id);
+        if group.held.len() < group.members.len() {
+            return Vec::new();
+        }
+        let name = name.clone();
+        self.release(&name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixStream;
+    use wayland_client::Connection;
+    use wayland_client::Dispatch;
+    use wayland_client::Proxy;
+    use wayland_client::QueueHandle;
+    use wayland_client::protocol::wl_callback;
+    use wayland_client::protocol::wl_callback::WlCallback;
+
+    struct Ids;
+
+    impl Dispatch<WlCallback, ()> for Ids {
+        fn event(
+            _: &mut Self,
+            _: &WlCallback,
+            _: wl_callback::Event,
+            _: &(),
+            _: &Connection,
+            _: &QueueHandle<Self>,
+        ) {
+        }
+    }
+
+    /// Distinct object ids without a compositor, from syncs that are never
+    /// sent
+    fn object_ids<const N: usize>() -> [ObjectId; N] {
+        let (client, _server) = UnixStream::pair().unwrap();
+        let conn = Connection::from_socket(client).unwrap();
+        let queue = conn.new_event_queue::<Ids>();
+        std::array::from_fn(|_| conn.display().sync(&queue.handle(), ()).id())
+    }
+
+    /// Configures are widths here
+    fn sorted(mut configures: Vec<(ObjectId, u32)>) -> Vec<(ObjectId, u32)> {
+        configures.sort_by_key(|(_, width)| *width);
+        configures
+    }
+
+    #[test]
+    fn group_is_held_until_every_member_is_configured() {
+        let [a, b] = object_ids();
+        let mut readiness = Readiness::<u32>::default();
+        assert!(readiness.join("panels", &a));
+        assert!(!readiness.join("panels", &b));
+        assert!(readiness.configure(&a, 1).is_empty());
+        // A later configure replaces the held one
+        assert!(readiness.configure(&a, 2).is_empty());
+        assert_eq!(sorted(readiness.configure(&b, 3)), [(a, 2), (b, 3)]);
+    }
+
+    #[test]
+    fn removing_the_missing_member_releases_the_group() {
+        let [a, b, c] = object_ids();
+        let mut readiness = Readiness::<u32>::default();
+        readiness.join("panels", &a);
+        readiness.join("panels", &b);
+        readiness.join("panels", &c);
+        assert!(readiness.configure(&a, 1).is_empty());
+        assert!(readiness.configure(&b, 2).is_empty());
+        // Closed before its configure, the others don't wait for the timeout
+        assert_eq!(sorted(readiness.remove(&c)), [(a.clone(), 1), (b, 2)]);
+        // Not held anymore
+        assert_eq!(readiness.configure(&a, 4), [(a, 4)]);
+    }
+
+    #[test]
+    fn removing_a_configured_member_keeps_waiting_for_the_rest() {
+        let [a, b, c] = object_ids();
+        let mut readiness = Readiness::<u32>::default();
+        readiness.join("panels", &a);
+        readiness.join("panels", &b);
+        assert!(readiness.configure(&a, 1).is_empty());
+        assert!(readiness.remove(&a).is_empty());
+        assert!(readiness.remove(&c).is_empty());
+        assert_eq!(readiness.configure(&b, 2), [(b, 2)]);
+    }
+}
@@ -0,0 +1,149 @@
+use crate::OutputSelector;
+use smithay_client_toolkit::reexports::calloop::channel::Sender;
+use smithay_client_toolkit::reexports::protocols::xdg::shell::client::xdg_toplevel::ResizeEdge;
+use smithay_client_toolkit::reexports::protocols::xdg::shell::client::xdg_toplevel::XdgToplevel;
+use std::time::Duration;
+use std::time::Instant;
+use wayland_client::backend::ObjectId;
+use wayland_protocols::wp::cursor_shape::v1::client::wp_cursor_shape_device_v1::Shape;
+
+/// Requests from a surface to the application it belongs to, containers send
+/// these instead of reaching the application through the global
+pub(crate) enum SurfaceRequestKind {
+    Redraw {
+        delay: Duration,
+    },
+    /// Step animations of a frame gated surface after the delay if its
+    /// frame callback hasn't arrived by then
+    FrameFallback {
+        delay: Duration,
+    },
+    /// Advance the dock of a layer surface after the delay
+    DockTimer {
+        delay: Duration,
+    },
+    /// `None` hides the cursor
+    Cursor(Option<Shape>),
+    /// `None` clears the override
+    CursorOverride(Option<Shape>),
+    /// x, y, width, height in surface coordinates
+    ImeCursorArea(i32, i32, i32, i32),
+    /// No text field has the focus anymore
+    ImeDisabled,
+    /// Interactive move and resize from client-side decorations
+    Move(XdgToplevel),
+    Resize(XdgToplevel, ResizeEdge),
+    /// Compositor window menu at x, y in surface coordinates
+    WindowMenu(XdgToplevel, i32, i32),
+    /// Close the window as if the compositor requested it
+    Close,
+    /// Close the window without asking the container
+    ForceClose,
+    /// Activate the window with a fresh XDG activation token
+    RequestAttention,
+    /// Text typed into the focused widget, see `Application::insert_text`
+    InsertText(String),
+    /// Output is selected once pending enter and leave events are handled
+    Fullscreen(XdgToplevel, OutputSelector),
+    PresentAt(Instant),
+}
+
+pub(crate) struct SurfaceRequest {
+    /// Object id of the wl_surface, unlike the protocol id it's not reused
+    /// by a later surface
+    pub surface: ObjectId,
+    pub kind: SurfaceRequestKind,
+}
+
+/// Handle for requesting a redraw of a surface, can be sent to other threads
+///
+/// The redraw happens on the next frame callback of the surface.
+#[derive(Clone)]
+pub struct RedrawHandle {
+    pub(crate) surface: ObjectId,
+    pub(crate) sender: Sender<SurfaceRequest>,
+}
+
+impl RedrawHandle {
+    pub fn request_redraw(&self) {
+        self.request_redraw_after(Duration::ZERO);
+    }
+
+    pub fn request_redraw_after(&self, delay: Duration) {
+        self.send(SurfaceRequestKind::Redraw { delay });
+    }
+
+    /// Redraw so the frame is shown at the vblank nearest to the target,
+    /// e.g. the time of the next video frame. Rendering is held until then,
+    /// aligned with presentation feedback when the compositor has it.
+    pub fn present_at(&self, target: Instant) {
+        self.send(SurfaceRequestKind::PresentAt(target));
+    }
+
+    /// Ask the compositor to focus the window of the surface, or mark it
+    /// urgent, e.g. from a thread that got a background event
+    pub fn request_attention(&self) {
+        self.send(SurfaceRequestKind::RequestAttention);
+    }
+
+    /// Close the window of the surface without asking, e.g. after the user
+    /// confirmed a close refused by `EguiAppData::close_requested`
+    pub fn close(&self) {
+        self.send(SurfaceRequestKind::ForceClose);
+    }
+
+    /// Insert text into the widget focused on the surface as if typed, e.g.
+    /// from an emoji picker in another surface
+    pub fn insert_text(&self, text: impl Into<String>) {
+        self.send(SurfaceRequestKind::InsertText(text.into()));
+    }
+
+    /// Cursor shown over the surface whatever is under the pointer, e.g. a
+    /// wait cursor during a long operation. `None` goes back to the cursor
+    /// of the content.
+    pub fn set_cursor_override(&self, shape: Option<Shape>) {
+        self.send(SurfaceRequestKind::CursorOverride(shape));
+    }
+
+    pub(crate) fn send(&self, kind: SurfaceRequestKind) {
+        // Fails only if the application is gone, nothing to redraw then
+        let _ = self.sender.send(SurfaceRequest {
+            surface: self.surface.clone(),
+            kind,
+        });
+    }
+}
+
+/// Interactive move, resize and the window menu for windows drawing their
+/// own titlebar, see `Application::window_handle`
+///
+/// Safe to use from `ui()`: the requests go through the application, which
+/// runs them after the frame with the seat and serial of the latest press.
+/// They do nothing if there hasn't been any input yet.
+#[derive(Clone)]
+pub struct WindowHandle {
+    pub(crate) redraw: RedrawHandle,
+    pub(crate) toplevel: XdgToplevel,
+}
+
+impl WindowHandle {
+    /// Move the window with the pointer, call on the press or the start of
+    /// a drag
+    pub fn start_interactive_move(&self) {
+        self.redraw
+            .send(SurfaceRequestKind::Move(self.toplevel.clone()));
+    }
+
+    /// Resize the window from the edge with the pointer, e.g. from a grip
+    pub fn start_interactive_resize(&self, edge: ResizeEdge) {
+        self.redraw
+            .send(SurfaceRequestKind::Resize(self.toplevel.clone(), edge));
+    }
+
+    /// Compositor's window menu at the position in surface coordinates,
+    /// e.g. on a right click on the titlebar
+    pub fn show_window_menu(&self, x: i32, y: i32) {
+        self.redraw
+            .send(SurfaceRequestKind::WindowMenu(self.toplevel.clone(), x, y));
+    }
+}
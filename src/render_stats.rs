@@ -0,0 +1,168 @@
+use crate::DegradationEvent;
+use crate::DegradationStep;
+use std::collections::VecDeque;
+use std::time::Duration;
+use wayland_client::protocol::wl_output::Subpixel;
+
+/// Amount of frames kept for the average and percentile
+const STATS_WINDOW: usize = 120;
+
+/// Amount of latest degradation transitions kept
+const DEGRADATION_EVENTS: usize = 16;
+
+/// Time spent in each phase of a presented frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FramePhases {
+    /// Running the app's `ui`
+    pub ui: Duration,
+    /// Ending the EGUI frame and tessellating
+    pub end_frame: Duration,
+    /// Acquiring the texture and recording the draw
+    pub draw: Duration,
+    /// Submitting and presenting
+    pub present: Duration,
+}
+
+impl FramePhases {
+    pub fn total(&self) -> Duration {
+        self.ui + self.end_frame + self.draw + self.present
+    }
+}
+
+/// Render statistics of a surface, collected when enabled with
+/// `with_render_stats`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RenderStats {
+    pub frames: u64,
+    pub frames_skipped: u64,
+    /// Fully transparent frames following a presented transparent frame,
+    /// neither drawn nor committed
+    pub frames_elided: u64,
+    pub last_frame: Option<FramePhases>,
+    pub avg_frame_time: Option<Duration>,
+    pub p95_frame_time: Option<Duration>,
+    /// Error of the latest texture acquire that failed, the frame is
+    /// skipped and the swapchain reconfigured
+    pub last_present_error: Option<String>,
+    /// Old and new format of the latest change, made when the old one was no
+    /// longer supported, e.g. after a driver update
+    pub last_format_change: Option<(wgpu::TextureFormat, wgpu::TextureFormat)>,
+    /// Glyphs are snapped to pixels, see `TextHinting`
+    pub text_hinted: bool,
+    /// Subpixel layout of the output the surface entered last, EGUI
+    /// antialiases text in grayscale regardless
+    pub subpixel: Option<Subpixel>,
+    /// Clipboard and primary selection reads for pasting
+    pub clipboard_reads: u64,
+    /// Reads given up since the owner didn't answer in time
+    pub clipboard_timeouts: u64,
+    pub last_clipboard_read: Option<Duration>,
+    pub max_clipboard_read: Option<Duration>,
+    /// GPU time of the latest measured frame, `None` without timestamp
+    /// queries
+    pub gpu_frame_time: Option<Duration>,
+    /// Degradation steps of the frame budget active now, in activation order
+    pub degradation: Vec<DegradationStep>,
+    /// Latest degradation transitions, oldest first
+    pub degradation_events: Vec<DegradationEvent>,
+}
+
+#[derive(Default)]
+pub(crate) struct RenderStatsCollector {
+    frames: u64,
+    frames_skipped: u64,
+    frames_elided: u64,
+    last_frame: Option<FramePhases>,
+    frame_times: VecDeque<Duration>,
+    last_present_error: Option<String>,
+    last_format_change: Option<(wgpu::TextureFormat, wgpu::TextureFormat)>,
+    text_hinted: bool,
+    subpixel: Option<Subpixel>,
+    clipboard_reads: u64,
+    clipboard_timeouts: u64,
+    last_clipboard_read: Option<Duration>,
+    max_clipboard_read: Option<Duration>,
+    gpu_frame_time: Option<Duration>,
+    degradation: Vec<DegradationStep>,
+    degradation_events: VecDeque<DegradationEvent>,
+}
+
+impl RenderStatsCollector {
+    pub fn record_frame(&mut self, phases: FramePhases) {
+        if self.frame_times.len() == STATS_WINDOW {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(phases.total());
+        self.last_frame = Some(phases);
+        self.frames += 1;
+    }
+
+    pub fn record_skipped(&mut self) {
+        self.frames_skipped += 1;
+    }
+
+    pub fn record_elided(&mut self) {
+        self.frames_elided += 1;
+    }
+
+    pub fn record_present_error(&mut self, error: String) {
+        self.last_present_error = Some(error);
+        self.frames_skipped += 1;
+    }
+
+    pub fn record_format_change(&mut self, old: wgpu::TextureFormat, new: wgpu::TextureFormat) {
+        self.last_format_change = Some((old, new));
+    }
+
+    pub fn record_text_rendering(&mut self, hinted: bool, subpixel: Option<Subpixel>) {
+        self.text_hinted = hinted;
+        self.subpixel = subpixel;
+    }
+
+    pub fn record_clipboard_read(&mut self, latency: Duration, timed_out: bool) {
+        self.clipboard_reads += 1;
+        if timed_out {
+            self.clipboard_timeouts += 1;
+        }
+        self.last_clipboard_read = Some(latency);
+        self.max_clipboard_read = self.max_clipboard_read.max(Some(latency));
+    }
+
+    pub fn record_gpu_time(&mut self, gpu_time: Option<Duration>) {
+        self.gpu_frame_time = gpu_time;
+    }
+
+    pub fn record_degradation(&mut self, event: DegradationEvent, active: &[DegradationStep]) {
+        if self.degradation_events.len() == DEGRADATION_EVENTS {
+            self.degradation_events.pop_front();
+        }
+        self.degradation_events.push_back(event);
+        self.degradation = active.to_vec();
+    }
+
+    pub fn stats(&self) -> RenderStats {
+        let mut sorted: Vec<Duration> = self.frame_times.iter().copied().collect();
+        sorted.sort();
+        let p95 = ((sorted.len() as f32 * 0.95).ceil() as usize).saturating_sub(1);
+        RenderStats {
+            frames: self.frames,
+            frames_skipped: self.frames_skipped,
+            frames_elided: self.frames_elided,
+            last_frame: self.last_frame,
+            avg_frame_time: (!sorted.is_empty())
+                .then(|| sorted.iter().sum::<Duration>() / sorted.len() as u32),
+            p95_frame_time: sorted.get(p95).copied(),
+            last_present_error: self.last_present_error.clone(),
+            last_format_change: self.last_format_change,
+            text_hinted: self.text_hinted,
+            subpixel: self.subpixel,
+            clipboard_reads: self.clipboard_reads,
+            clipboard_timeouts: self.clipboard_timeouts,
+            last_clipboard_read: self.last_clipboard_read,
+            max_clipboard_read: self.max_clipboard_read,
+            gpu_frame_time: self.gpu_frame_time,
+            degradation: self.degradation.clone(),
+            degradation_events: self.degradation_events.iter().copied().collect(),
+        }
+    }
+}
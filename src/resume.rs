@@ -0,0 +1,113 @@
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+
+/// How often the clocks are sampled
+pub(crate) const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Gaps shorter than this are ordinary scheduling jitter
+const GAP_THRESHOLD: Duration = Duration::from_secs(3);
+
+/// Detects suspend and large clock steps by sampling both clocks
+///
+/// The monotonic clock stops while the system is suspended and the wall
+/// clock doesn't, a long stall of the process shows up as a late sample of
+/// the monotonic clock. Timers run on the monotonic clock, the wall clock is
+/// only ever compared here.
+pub(crate) struct ResumeWatch {
+    last: Option<(Instant, SystemTime)>,
+}
+
+impl ResumeWatch {
+    pub fn new() -> Self {
+        Self { last: None }
+    }
+
+    /// Sample the clocks, the gap if time passed unnoticed since the last
+    /// sample
+    pub fn sample(&mut self, now: Instant, wall: SystemTime) -> Option<Duration> {
+        let (last, last_wall) = self.last.replace((now, wall))?;
+        let elapsed = now.saturating_duration_since(last);
+        // Negative if the wall clock was stepped back, e.g. by NTP, nothing
+        // was missed then
+        let wall_elapsed = wall.duration_since(last_wall).unwrap_or_default();
+        let gap = wall_elapsed
+            .saturating_sub(elapsed)
+            .max(elapsed.saturating_sub(WATCH_INTERVAL));
+        (gap >= GAP_THRESHOLD).then_some(gap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Clocks sampled every `WATCH_INTERVAL` from a fixed start
+    struct Clocks {
+        watch: ResumeWatch,
+        now: Instant,
+        wall: SystemTime,
+    }
+
+    impl Clocks {
+        fn new() -> Self {
+            let mut clocks = Self {
+                watch: ResumeWatch::new(),
+                now: Instant::now(),
+                wall: SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+            };
+            assert_eq!(clocks.tick(Duration::ZERO, Duration::ZERO), None);
+            clocks
+        }
+
+        fn tick(&mut self, monotonic: Duration, wall: Duration) -> Option<Duration> {
+            self.now += monotonic;
+            self.wall += wall;
+            self.watch.sample(self.now, self.wall)
+        }
+
+        fn tick_back(&mut self, monotonic: Duration, wall_back: Duration) -> Option<Duration> {
+            self.now += monotonic;
+            self.wall -= wall_back;
+            self.watch.sample(self.now, self.wall)
+        }
+    }
+
+    #[test]
+    fn regular_samples_have_no_gap() {
+        let mut clocks = Clocks::new();
+        for _ in 0..10 {
+            assert_eq!(clocks.tick(WATCH_INTERVAL, WATCH_INTERVAL), None);
+        }
+        // Scheduling jitter
+        let late = WATCH_INTERVAL + Duration::from_secs(2);
+        assert_eq!(clocks.tick(late, late), None);
+    }
+
+    #[test]
+    fn suspend_is_reported_once() {
+        let mut clocks = Clocks::new();
+        // Monotonic clock stopped for a minute, the wall clock didn't
+        let gap = clocks.tick(WATCH_INTERVAL, WATCH_INTERVAL + Duration::from_secs(60));
+        assert_eq!(gap, Some(Duration::from_secs(60)));
+        assert_eq!(clocks.tick(WATCH_INTERVAL, WATCH_INTERVAL), None);
+    }
+
+    #[test]
+    fn stalled_process_is_reported_once() {
+        let mut clocks = Clocks::new();
+        let stall = Duration::from_secs(10);
+        assert_eq!(clocks.tick(stall, stall), Some(stall - WATCH_INTERVAL));
+        assert_eq!(clocks.tick(WATCH_INTERVAL, WATCH_INTERVAL), None);
+    }
+
+    #[test]
+    fn wall_clock_steps_back_are_not_gaps() {
+        let mut clocks = Clocks::new();
+        assert_eq!(
+            clocks.tick_back(WATCH_INTERVAL, Duration::from_secs(3600)),
+            None
+        );
+        assert_eq!(clocks.tick(WATCH_INTERVAL, WATCH_INTERVAL), None);
+    }
+}
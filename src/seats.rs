@@ -0,0 +1,189 @@
+use crate::keyboard::Compose;
+use crate::text_input::SeatTextInput;
+use smithay_client_toolkit::reexports::calloop::RegistrationToken;
+use smithay_client_toolkit::seat::keyboard::KeyEvent;
+use smithay_client_toolkit::seat::keyboard::RepeatInfo;
+use smithay_client_toolkit::seat::pointer::ThemedPointer;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::time::Instant;
+use wayland_backend::client::ObjectId;
+use wayland_client::Proxy;
+use wayland_client::protocol::wl_keyboard::WlKeyboard;
+use wayland_client::protocol::wl_pointer::WlPointer;
+use wayland_client::protocol::wl_seat::WlSeat;
+
+/// Held key that repeats, kept while repeat is disabled so enabling it
+/// starts repeating the key
+pub(crate) struct KeyRepeat {
+    pub event: KeyEvent,
+    /// Repeating starts after the delay from this
+    pub since: Instant,
+    pub timer: Option<RegistrationToken>,
+}
+
+/// Input devices created for a seat, each released with its capability
+///
+/// Keyboard focus, held keys, key repeat, compose sequences and the text
+/// input are per seat too, a seat leaving a surface doesn't disturb typing
+/// on another.
+#[derive(Default)]
+pub(crate) struct SeatResources {
+    pub keyboard: Option<WlKeyboard>,
+    /// Surface the keyboard of the seat entered last, `None` after it left
+    pub keyboard_focus: Option<ObjectId>,
+    /// Raw keycodes held down, includes keys held at keyboard enter
+    pub pressed_keys: HashSet<u32>,
+    /// Timer of the stored key must be removed before replacing it
+    pub key_repeat: Option<KeyRepeat>,
+    /// Created with the keyboard
+    pub compose: Option<Compose>,
+    /// Created with the keyboard, `None` without the text input protocol
+    pub text_input: Option<SeatTextInput>,
+    pub pointer: Option<WlPointer>,
+    /// Only without the cursor shape protocol, owns `pointer` then
+    pub themed_pointer: Option<ThemedPointer>,
+    /// Latest wl_keyboard.repeat_info, `None` until the compositor sends it
+    pub repeat_info: Option<RepeatInfo>,
+}
+
+impl SeatResources {
+    /// Forget keys that may have been released unnoticed, e.g. during a
+    /// suspend, and their repeat. `true` if any were held. The timer of the
+    /// repeat is returned for removal from the loop.
+    pub fn forget_held_keys(&mut self) -> (bool, Option<RegistrationToken>) {
+        let timer = self
+            .key_repeat
+            .take()
+            .and_then(|key_repeat| key_repeat.timer);
+        let held = !self.pressed_keys.is_empty();
+        self.pressed_keys.clear();
+        (held, timer)
+    }
+
+    /// Focus and key state must be handed back before this, see
+    /// `Application::drop_keyboard_focus`
+    pub fn release_keyboard(&mut self) {
+        self.compose = None;
+        if let Some(text_input) = self.text_input.take() {
+            text_input.destroy();
+        }
+        if let Some(keyboard) = self.keyboard.take()
+            && keyboard.version() >= 3
+        {
+            keyboard.release();
+        }
+    }
+
+    pub fn release_pointer(&mut self) {
+        // Themed pointer destroys its cursor surface when dropped
+        self.themed_pointer = None;
+        if let Some(pointer) = self.pointer.take()
+            && pointer.version() >= 3
+        {
+            pointer.release();
+        }
+    }
+}
+
+/// Resources of every seat, e.g. seat0 and a virtual seat of a VNC server
+#[derive(Default)]
+pub(crate) struct Seats {
+    seats: HashMap<ObjectId, (WlSeat, SeatResources)>,
+}
+
+impl Seats {
+    pub fn resources(&mut self, seat: &WlSeat) -> &mut SeatResources {
+        &mut self
+            .seats
+            .entry(seat.id())
+            .or_insert_with(|| (seat.clone(), SeatResources::default()))
+            .1
+    }
+
+    pub fn get(&self, seat: &WlSeat) -> Option<&SeatResources> {
+        self.seats.get(&seat.id()).map(|(_, resources)| resources)
+    }
+
+    pub fn get_mut(&mut self, seat: &WlSeat) -> Option<&mut SeatResources> {
+        self.seats
+            .get_mut(&seat.id())
+            .map(|(_, resources)| resources)
+    }
+
+    pub fn resources_mut(&mut self) -> impl Iterator<Item = &mut SeatResources> {
+        self.seats.values_mut().map(|(_, resources)| resources)
+    }
+
+    pub fn all(&self) -> Vec<WlSeat> {
+        self.seats.values().map(|(seat, _)| seat.clone()).collect()
+    }
+
+    /// Keyboard of some seat other than `except` is on the surface
+    pub fn keyboard_focused_by_other(&self, surface: &ObjectId, except: &WlSeat) -> bool {
+        self.seats.values().any(|(seat, resources)| {
+            seat != except && resources.keyboard_focus.as_ref() == Some(surface)
+        })
+    }
+
+    /// Release everything of the seat, other seats are not touched
+    pub fn remove(&mut self, seat: &WlSeat) {
+        if let Some((_, mut resources)) = self.seats.remove(&seat.id()) {
+            resources.release_keyboard();
+            resources.release_pointer();
+        }
+    }
+
+    pub fn seat_of_keyboard(&self, keyboard: &WlKeyboard) -> Option<WlSeat> {
+        self.seats
+            .values()
+            .find(|(_, resources)| resources.keyboard.as_ref() == Some(keyboard))
+            .map(|(seat, _)| seat.clone())
+    }
+
+    pub fn seat_of_pointer(&self, pointer: &WlPointer) -> Option<WlSeat> {
+        self.seats
+            .values()
+            .find(|(_, resources)| resources.pointer.as_ref() == Some(pointer))
+            .map(|(seat, _)| seat.clone())
+    }
+
+    pub fn themed_pointer(&self, pointer: &WlPointer) -> Option<&ThemedPointer> {
+        self.seats
+            .values()
+            .find(|(_, resources)| resources.pointer.as_ref() == Some(pointer))
+            .and_then(|(_, resources)| resources.themed_pointer.as_ref())
+    }
+
+    /// Any remaining seat, for input that isn't tied to an event
+    pub fn first(&self) -> Option<WlSeat> {
+        self.seats.values().next().map(|(seat, _)| seat.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smithay_client_toolkit::seat::keyboard::Keysym;
+
+    #[test]
+    fn resume_forgets_held_keys_and_their_repeat_once() {
+        let mut resources = SeatResources::default();
+        resources.pressed_keys.insert(30);
+        resources.key_repeat = Some(KeyRepeat {
+            event: KeyEvent {
+                time: 0,
+                raw_code: 30,
+                keysym: Keysym::a,
+                utf8: Some("a".to_string()),
+            },
+            since: Instant::now(),
+            timer: None,
+        });
+        assert_eq!(resources.forget_held_keys(), (true, None));
+        assert!(resources.pressed_keys.is_empty());
+        assert!(resources.key_repeat.is_none());
+        // Nothing held anymore, the focused surface isn't told again
+        assert_eq!(resources.forget_held_keys(), (false, None));
+    }
+}
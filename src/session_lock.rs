@@ -0,0 +1,50 @@
+use crate::Application;
+use crate::LockSurfaceContainer;
+use smithay_client_toolkit::reexports::calloop::LoopHandle;
+use smithay_client_toolkit::session_lock::SessionLock;
+use wayland_backend::client::ObjectId;
+use wayland_client::protocol::wl_output::WlOutput;
+
+pub(crate) type LockSurfaceFactory = Box<
+    dyn FnMut(&mut Application, &SessionLockHandle, &WlOutput) -> Box<dyn LockSurfaceContainer>,
+>;
+
+/// Lock of the session created with `Application::lock_session`
+///
+/// The session stays locked until `unlock` even if every handle is dropped,
+/// a crashed locker leaves the outputs locked by the compositor.
+#[derive(Clone)]
+pub struct SessionLockHandle {
+    lock: SessionLock,
+    loop_handle: LoopHandle<'static, Application>,
+}
+
+impl SessionLockHandle {
+    pub(crate) fn new(lock: SessionLock, loop_handle: LoopHandle<'static, Application>) -> Self {
+        Self { lock, loop_handle }
+    }
+
+    pub(crate) fn session_lock(&self) -> &SessionLock {
+        &self.lock
+    }
+
+    /// The compositor has hidden the session, sent once every output shows
+    /// a lock surface
+    pub fn is_locked(&self) -> bool {
+        self.lock.is_locked()
+    }
+
+    /// Unlock after the current dispatch, safe to call from `ui()` of a lock
+    /// surface since the surfaces are destroyed only afterwards
+    pub fn unlock(&self) {
+        self.loop_handle.insert_idle(|app| app.unlock_session());
+    }
+}
+
+/// Session lock in progress and the lock surface of each output
+pub(crate) struct ActiveLock {
+    pub handle: SessionLockHandle,
+    /// Taken while it runs, it gets the application
+    pub factory: Option<LockSurfaceFactory>,
+    pub surfaces: Vec<(WlOutput, ObjectId)>,
+}
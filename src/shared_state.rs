@@ -0,0 +1,174 @@
+use crate::RedrawHandle;
+use crate::stall_watch::HeldLock;
+use std::ops::Deref;
+use std::ops::DerefMut;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+
+/// State shared between `ui()` and background threads, clones share it
+///
+/// `ui()` runs on the event loop thread, which also delivers the input and
+/// frame callbacks of every surface. A lock held by another thread stalls
+/// all surfaces until it's released, so threads should hold it only while
+/// changing the state, never across I/O or while waiting on something
+/// `ui()` does. Requesting a redraw only queues a message for the loop, it
+/// never blocks or calls `ui()`. `update` and `redraw` release the lock
+/// before waking the surface, so `ui()` never waits for the thread that
+/// woke it. Debug builds log `ui()` calls blocked for over a second, with
+/// the thread and source location of each lock held at the time.
+pub struct SharedState<T> {
+    state: Arc<Mutex<T>>,
+    redraw: RedrawHandle,
+}
+
+/// Lock of a `SharedState`, see `SharedState::lock`
+pub struct SharedGuard<'a, T> {
+    guard: MutexGuard<'a, T>,
+    _held: HeldLock,
+}
+
+impl<T> Deref for SharedGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T> DerefMut for SharedGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<T> Clone for SharedState<T> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+            redraw: self.redraw.clone(),
+        }
+    }
+}
+
+impl<T> SharedState<T> {
+    pub fn new(state: T, redraw: RedrawHandle) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(state)),
+            redraw,
+        }
+    }
+
+    /// Lock without waking the surface, e.g. for reading in `ui()`
+    ///
+    /// A thread that panicked while holding the lock doesn't poison it, the
+    /// state is whatever the thread left.
+    #[track_caller]
+    pub fn lock(&self) -> SharedGuard<'_, T> {
+        let location = std::panic::Location::caller();
+        let guard = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        SharedGuard {
+            guard,
+            _held: HeldLock::new(location),
+        }
+    }
+
+    /// Release the lock and then redraw the surface, for changes made
+    /// through `lock`
+    pub fn redraw(&self, guard: SharedGuard<'_, T>) {
+        drop(guard);
+        self.redraw.request_redraw();
+    }
+
+    /// Change the state and redraw the surface, the lock is held only for
+    /// the closure
+    #[track_caller]
+    pub fn update<R>(&self, change: impl FnOnce(&mut T) -> R) -> R {
+        let result = change(&mut self.lock());
+        self.redraw.request_redraw();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::redraw::SurfaceRequest;
+    use crate::redraw::SurfaceRequestKind;
+    use smithay_client_toolkit::reexports::calloop::channel;
+    use smithay_client_toolkit::reexports::calloop::channel::Channel;
+    use wayland_backend::client::ObjectId;
+
+    fn shared(state: u32) -> (SharedState<u32>, Channel<SurfaceRequest>) {
+        let (sender, requests) = channel::channel();
+        let redraw = RedrawHandle {
+            surface: ObjectId::null(),
+            sender,
+        };
+        (SharedState::new(state, redraw), requests)
+    }
+
+    fn is_redraw(request: SurfaceRequest) -> bool {
+        matches!(request.kind, SurfaceRequestKind::Redraw { .. })
+    }
+
+    /// A background thread changes the state and wakes the surface, the
+    /// loop locks it as soon as the wake arrives. With the lock held across
+    /// the wake this used to block the loop.
+    #[test]
+    fn wake_arrives_after_the_lock_is_released() {
+        let (shared, requests) = shared(0);
+        let background = shared.clone();
+        let thread = std::thread::spawn(move || background.update(|state| *state += 1));
+        assert!(is_redraw(requests.recv().unwrap()));
+        assert_eq!(*shared.state.try_lock().unwrap(), 1);
+        thread.join().unwrap();
+
+        let background = shared.clone();
+        let thread = std::thread::spawn(move || {
+            let mut guard = background.lock();
+            *guard += 1;
+            background.redraw(guard);
+        });
+        assert!(is_redraw(requests.recv().unwrap()));
+        assert_eq!(*shared.state.try_lock().unwrap(), 2);
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn update_returns_the_result_and_redraws_once() {
+        let (shared, requests) = shared(1);
+        let doubled = shared.update(|state| {
+            *state *= 2;
+            *state
+        });
+        assert_eq!(doubled, 2);
+        assert!(is_redraw(requests.try_recv().unwrap()));
+        assert!(requests.try_recv().is_err());
+    }
+
+    #[test]
+    fn lock_doesnt_redraw() {
+        let (shared, requests) = shared(1);
+        *shared.lock() += 1;
+        assert_eq!(*shared.lock(), 2);
+        assert!(requests.try_recv().is_err());
+    }
+
+    #[test]
+    fn panicking_thread_doesnt_poison_the_state() {
+        let (shared, _requests) = shared(1);
+        let background = shared.clone();
+        let result = std::thread::spawn(move || {
+            let mut guard = background.lock();
+            *guard = 5;
+            panic!("background task failed");
+        })
+        .join();
+        assert!(result.is_err());
+        assert_eq!(*shared.lock(), 5);
+    }
+}
@@ -1,9 +1,11 @@
 ///! Single color buffer example implementations for containers.
 ///!
 ///! Use this as an example to how to start implementing your own containers.
+///! `SingleColorSurface` is the one meant for use, e.g. as a backdrop.
 use crate::Application;
 use crate::BaseTrait;
 use crate::CompositorHandlerContainer;
+use crate::DndHandlerContainer;
 use crate::KeyboardHandlerContainer;
 use crate::LayerSurfaceContainer;
 use crate::PointerHandlerContainer;
@@ -19,12 +21,15 @@ use smithay_client_toolkit::shell::xdg::popup::Popup;
 use smithay_client_toolkit::shell::xdg::popup::PopupConfigure;
 use smithay_client_toolkit::shell::xdg::window::Window;
 use smithay_client_toolkit::shell::xdg::window::WindowConfigure;
+use smithay_client_toolkit::shm::slot::Buffer;
 use smithay_client_toolkit::shm::slot::SlotPool;
 use std::num::NonZero;
 use wayland_client::Proxy;
 use wayland_client::QueueHandle;
 use wayland_client::protocol::wl_shm;
+use wayland_client::protocol::wl_subsurface::WlSubsurface;
 use wayland_client::protocol::wl_surface::WlSurface;
+use wayland_protocols::wp::viewporter::client::wp_viewport::WpViewport;
 
 fn single_color_example_buffer_configure(
     pool: &mut SlotPool,
@@ -63,15 +68,55 @@ fn single_color_example_buffer_configure(
     surface.commit();
 }
 
+/// Pool and queue of an example container, taken from the global
+/// application unless the container was created with `new_with`
+fn example_pool_and_qh<'a>(
+    pool: &'a mut Option<SlotPool>,
+    qh: &mut Option<QueueHandle<Application>>,
+    width: u32,
+    height: u32,
+) -> (&'a mut SlotPool, QueueHandle<Application>) {
+    let qh = qh.get_or_insert_with(|| get_app().qh.clone()).clone();
+    let pool = pool.get_or_insert_with(|| {
+        SlotPool::new(
+            (width * height * 4).try_into().unwrap(),
+            &get_app().shm_state,
+        )
+        .expect("Failed to create SlotPool")
+    });
+    (pool, qh)
+}
+
+/// Pool of the example containers' `new_with`, grows with the surface
+fn example_pool(app: &Application) -> SlotPool {
+    SlotPool::new(256 * 256 * 4, &app.shm_state).expect("Failed to create SlotPool")
+}
+
 pub struct ExampleSingleColorWindow {
     pub window: Window,
     pub color: (u8, u8, u8),
+    /// From the global application when `None`
     pub pool: Option<SlotPool>,
+    pub qh: Option<QueueHandle<Application>>,
+}
+
+impl ExampleSingleColorWindow {
+    /// Create without the global application
+    pub fn new_with(app: &Application, window: Window, color: (u8, u8, u8)) -> Self {
+        Self {
+            window,
+            color,
+            pool: Some(example_pool(app)),
+            qh: Some(app.qh.clone()),
+        }
+    }
 }
 
 impl CompositorHandlerContainer for ExampleSingleColorWindow {}
 impl KeyboardHandlerContainer for ExampleSingleColorWindow {}
 impl PointerHandlerContainer for ExampleSingleColorWindow {}
+
+impl DndHandlerContainer for ExampleSingleColorWindow {}
 impl BaseTrait for ExampleSingleColorWindow {
     fn get_object_id(&self) -> wayland_backend::client::ObjectId {
         self.window.wl_surface().id()
@@ -80,7 +125,6 @@ impl BaseTrait for ExampleSingleColorWindow {
 
 impl WindowContainer for ExampleSingleColorWindow {
     fn configure(&mut self, configure: &WindowConfigure) {
-        let app = get_app();
         let width = configure
             .new_size
             .0
@@ -93,16 +137,13 @@ impl WindowContainer for ExampleSingleColorWindow {
             .get();
 
         // Ensure pool exists
-        let pool = self.pool.get_or_insert_with(|| {
-            SlotPool::new((width * height * 4).try_into().unwrap(), &app.shm_state)
-                .expect("Failed to create SlotPool")
-        });
+        let (pool, qh) = example_pool_and_qh(&mut self.pool, &mut self.qh, width, height);
 
         // Handle window configuration changes here
         single_color_example_buffer_configure(
             pool,
             &self.window.wl_surface().clone(),
-            &app.qh,
+            &qh,
             width,
             height,
             self.color,
@@ -117,12 +158,28 @@ impl WindowContainer for ExampleSingleColorWindow {
 pub struct ExampleSingleColorLayerSurface {
     pub layer_surface: LayerSurface,
     pub color: (u8, u8, u8),
+    /// From the global application when `None`
     pub pool: Option<SlotPool>,
+    pub qh: Option<QueueHandle<Application>>,
+}
+
+impl ExampleSingleColorLayerSurface {
+    /// Create without the global application
+    pub fn new_with(app: &Application, layer_surface: LayerSurface, color: (u8, u8, u8)) -> Self {
+        Self {
+            layer_surface,
+            color,
+            pool: Some(example_pool(app)),
+            qh: Some(app.qh.clone()),
+        }
+    }
 }
 
 impl CompositorHandlerContainer for ExampleSingleColorLayerSurface {}
 impl KeyboardHandlerContainer for ExampleSingleColorLayerSurface {}
 impl PointerHandlerContainer for ExampleSingleColorLayerSurface {}
+
+impl DndHandlerContainer for ExampleSingleColorLayerSurface {}
 impl BaseTrait for ExampleSingleColorLayerSurface {
     fn get_object_id(&self) -> wayland_backend::client::ObjectId {
         self.layer_surface.wl_surface().id()
@@ -131,21 +188,17 @@ impl BaseTrait for ExampleSingleColorLayerSurface {
 
 impl LayerSurfaceContainer for ExampleSingleColorLayerSurface {
     fn configure(&mut self, config: &LayerSurfaceConfigure) {
-        let app = get_app();
         let width = config.new_size.0;
         let height = config.new_size.1;
 
         // Ensure pool exists
-        let pool = self.pool.get_or_insert_with(|| {
-            SlotPool::new((width * height * 4).try_into().unwrap(), &app.shm_state)
-                .expect("Failed to create SlotPool")
-        });
+        let (pool, qh) = example_pool_and_qh(&mut self.pool, &mut self.qh, width, height);
 
         // Handle layer surface configuration changes here
         single_color_example_buffer_configure(
             pool,
             &self.layer_surface.wl_surface().clone(),
-            &app.qh,
+            &qh,
             width,
             height,
             self.color,
@@ -160,12 +213,28 @@ impl LayerSurfaceContainer for ExampleSingleColorLayerSurface {
 pub struct ExampleSingleColorPopup {
     pub popup: Popup,
     pub color: (u8, u8, u8),
+    /// From the global application when `None`
     pub pool: Option<SlotPool>,
+    pub qh: Option<QueueHandle<Application>>,
+}
+
+impl ExampleSingleColorPopup {
+    /// Create without the global application
+    pub fn new_with(app: &Application, popup: Popup, color: (u8, u8, u8)) -> Self {
+        Self {
+            popup,
+            color,
+            pool: Some(example_pool(app)),
+            qh: Some(app.qh.clone()),
+        }
+    }
 }
 
 impl CompositorHandlerContainer for ExampleSingleColorPopup {}
 impl KeyboardHandlerContainer for ExampleSingleColorPopup {}
 impl PointerHandlerContainer for ExampleSingleColorPopup {}
+
+impl DndHandlerContainer for ExampleSingleColorPopup {}
 impl BaseTrait for ExampleSingleColorPopup {
     fn get_object_id(&self) -> wayland_backend::client::ObjectId {
         self.popup.wl_surface().id()
@@ -174,21 +243,17 @@ impl BaseTrait for ExampleSingleColorPopup {
 
 impl PopupContainer for ExampleSingleColorPopup {
     fn configure(&mut self, config: &PopupConfigure) {
-        let app = get_app();
         let width = config.width as u32;
         let height = config.height as u32;
 
         // Ensure pool exists
-        let pool = self.pool.get_or_insert_with(|| {
-            SlotPool::new((width * height * 4).try_into().unwrap(), &app.shm_state)
-                .expect("Failed to create SlotPool")
-        });
+        let (pool, qh) = example_pool_and_qh(&mut self.pool, &mut self.qh, width, height);
 
         // Handle popup configuration changes here
         single_color_example_buffer_configure(
             pool,
             &self.popup.wl_surface().clone(),
-            &app.qh,
+            &qh,
             width,
             height,
             self.color,
@@ -203,12 +268,28 @@ impl PopupContainer for ExampleSingleColorPopup {
 pub struct ExampleSingleColorSubsurface {
     pub wl_surface: WlSurface,
     pub color: (u8, u8, u8),
+    /// From the global application when `None`
     pub pool: Option<SlotPool>,
+    pub qh: Option<QueueHandle<Application>>,
+}
+
+impl ExampleSingleColorSubsurface {
+    /// Create without the global application
+    pub fn new_with(app: &Application, wl_surface: WlSurface, color: (u8, u8, u8)) -> Self {
+        Self {
+            wl_surface,
+            color,
+            pool: Some(example_pool(app)),
+            qh: Some(app.qh.clone()),
+        }
+    }
 }
 
 impl CompositorHandlerContainer for ExampleSingleColorSubsurface {}
 impl KeyboardHandlerContainer for ExampleSingleColorSubsurface {}
 impl PointerHandlerContainer for ExampleSingleColorSubsurface {}
+
+impl DndHandlerContainer for ExampleSingleColorSubsurface {}
 impl BaseTrait for ExampleSingleColorSubsurface {
     fn get_object_id(&self) -> wayland_backend::client::ObjectId {
         self.wl_surface.id()
@@ -217,20 +298,209 @@ impl BaseTrait for ExampleSingleColorSubsurface {
 
 impl SubsurfaceContainer for ExampleSingleColorSubsurface {
     fn configure(&mut self, width: u32, height: u32) {
-        let app = get_app();
-        let pool = self.pool.get_or_insert_with(|| {
-            SlotPool::new((width * height * 4).try_into().unwrap(), &app.shm_state)
-                .expect("Failed to create SlotPool")
-        });
+        let (pool, qh) = example_pool_and_qh(&mut self.pool, &mut self.qh, width, height);
 
         // Handle subsurface configuration changes here
         single_color_example_buffer_configure(
             pool,
             &self.wl_surface.clone(),
-            &app.qh,
+            &qh,
             width,
             height,
             self.color,
         );
     }
 }
+
+/// Role of a `SingleColorSurface`
+pub enum SingleColorRole {
+    Window(Window),
+    LayerSurface(LayerSurface),
+    /// Destroyed with the container, e.g. from `Application::create_subsurface`
+    Subsurface(WlSubsurface, WlSurface),
+}
+
+impl SingleColorRole {
+    fn wl_surface(&self) -> &WlSurface {
+        match self {
+            SingleColorRole::Window(window) => window.wl_surface(),
+            SingleColorRole::LayerSurface(layer_surface) => layer_surface.wl_surface(),
+            SingleColorRole::Subsurface(_, wl_surface) => wl_surface,
+        }
+    }
+}
+
+/// Surface of a single color drawn from shm without WGPU, e.g. a cheap
+/// backdrop behind subsurfaces
+///
+/// With viewporter a single pixel buffer is stretched to the size, resizing
+/// then only sets the destination. Without it the buffer has the full size
+/// and is painted again only when the size or the color changes.
+pub struct SingleColorSurface {
+    role: SingleColorRole,
+    /// Straight RGBA, premultiplied when painted
+    color: [u8; 4],
+    size: (u32, u32),
+    pool: SlotPool,
+    /// Last attached buffer and its size, reused once released
+    buffer: Option<(Buffer, (u32, u32))>,
+    viewport: Option<WpViewport>,
+}
+
+impl SingleColorSurface {
+    pub fn new(role: SingleColorRole, color: [u8; 4], width: u32, height: u32) -> Self {
+        Self::new_with(get_app(), role, color, width, height)
+    }
+
+    /// Create without the global application
+    pub fn new_with(
+        app: &mut Application,
+        role: SingleColorRole,
+        color: [u8; 4],
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let viewport = app
+            .viewporter
+            .as_ref()
+            .map(|viewporter| viewporter.get_viewport(role.wl_surface(), &app.qh, ()));
+        let pool = SlotPool::new(4, &app.shm_state).expect("Failed to create SlotPool");
+        let mut this = Self {
+            role,
+            color,
+            size: (width, height),
+            pool,
+            buffer: None,
+            viewport,
+        };
+        // Subsurfaces have no configure
+        if matches!(this.role, SingleColorRole::Subsurface(..)) {
+            this.present(true);
+        }
+        this
+    }
+
+    pub fn wl_surface(&self) -> &WlSurface {
+        self.role.wl_surface()
+    }
+
+    /// Paint and commit right away, alpha is straight
+    pub fn set_color(&mut self, r: u8, g: u8, b: u8, a: u8) {
+        if self.color == [r, g, b, a] {
+            return;
+        }
+        self.color = [r, g, b, a];
+        self.present(true);
+    }
+
+    pub fn color(&self) -> [u8; 4] {
+        self.color
+    }
+
+    /// Apply the configured size, zero keeps the current size
+    fn configure(&mut self, width: u32, height: u32) {
+        let size = (
+            if width == 0 { self.size.0 } else { width },
+            if height == 0 { self.size.1 } else { height },
+        );
+        self.size = size;
+        self.present(false);
+    }
+
+    /// Commit the current buffer when it still fits, otherwise paint one
+    fn present(&mut self, repaint: bool) {
+        let (width, height) = self.size;
+        if width == 0 || height == 0 {
+            return;
+        }
+        let wl_surface = self.role.wl_surface().clone();
+        if let Some(viewport) = &self.viewport {
+            viewport.set_destination(width as i32, height as i32);
+        }
+        let buffer_size = if self.viewport.is_some() {
+            (1, 1)
+        } else {
+            self.size
+        };
+        let fits = matches!(&self.buffer, Some((_, size)) if *size == buffer_size);
+        if fits && !repaint {
+            wl_surface.commit();
+            return;
+        }
+
+        // The old buffer may still be held by the compositor, it's dropped
+        // only after the new one is attached
+        let old = self.buffer.take();
+        let buffer = match old {
+            Some((buffer, size)) if size == buffer_size && self.pool.canvas(&buffer).is_some() => {
+                buffer
+            }
+            _ => {
+                trace!("[COMMON] Create single color buffer {:?}", buffer_size);
+                self.pool
+                    .create_buffer(
+                        buffer_size.0 as i32,
+                        buffer_size.1 as i32,
+                        buffer_size.0 as i32 * 4,
+                        wl_shm::Format::Argb8888,
+                    )
+                    .expect("create buffer")
+                    .0
+            }
+        };
+        let [r, g, b, a] = self.color;
+        let premultiply = |channel: u8| (channel as u16 * a as u16 / 255) as u8;
+        if let Some(canvas) = self.pool.canvas(&buffer) {
+            for chunk in canvas.chunks_exact_mut(4) {
+                // ARGB little-endian: B, G, R, A
+                chunk.copy_from_slice(&[premultiply(b), premultiply(g), premultiply(r), a]);
+            }
+        }
+        wl_surface.damage_buffer(0, 0, buffer_size.0 as i32, buffer_size.1 as i32);
+        buffer.attach_to(&wl_surface).expect("buffer attach");
+        wl_surface.commit();
+        self.buffer = Some((buffer, buffer_size));
+    }
+}
+
+impl Drop for SingleColorSurface {
+    fn drop(&mut self) {
+        if let Some(viewport) = &self.viewport {
+            viewport.destroy();
+        }
+        if let SingleColorRole::Subsurface(subsurface, _) = &self.role {
+            subsurface.destroy();
+        }
+    }
+}
+
+impl CompositorHandlerContainer for SingleColorSurface {}
+impl KeyboardHandlerContainer for SingleColorSurface {}
+impl PointerHandlerContainer for SingleColorSurface {}
+impl DndHandlerContainer for SingleColorSurface {}
+
+impl BaseTrait for SingleColorSurface {
+    fn get_object_id(&self) -> wayland_backend::client::ObjectId {
+        self.role.wl_surface().id()
+    }
+}
+
+impl WindowContainer for SingleColorSurface {
+    fn configure(&mut self, configure: &WindowConfigure) {
+        let width = configure.new_size.0.map_or(0, |size| size.get());
+        let height = configure.new_size.1.map_or(0, |size| size.get());
+        SingleColorSurface::configure(self, width, height);
+    }
+}
+
+impl LayerSurfaceContainer for SingleColorSurface {
+    fn configure(&mut self, config: &LayerSurfaceConfigure) {
+        SingleColorSurface::configure(self, config.new_size.0, config.new_size.1);
+    }
+}
+
+impl SubsurfaceContainer for SingleColorSurface {
+    fn configure(&mut self, width: u32, height: u32) {
+        SingleColorSurface::configure(self, width, height);
+    }
+}
@@ -0,0 +1,233 @@
+use log::error;
+use log::warn;
+use std::fmt::Write;
+use std::panic::Location;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::Once;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
+
+/// `ui()` calls blocked for longer are reported
+const STALL_THRESHOLD: Duration = Duration::from_secs(1);
+
+/// How often the watchdog thread samples the running call
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Running `ui()` call, `None` between calls
+static CURRENT: Mutex<Option<RunningCall>> = Mutex::new(None);
+
+/// Locks of `SharedState` held right now, named in the report
+static HOLDERS: Mutex<Vec<Holder>> = Mutex::new(Vec::new());
+
+static NEXT_HOLDER: AtomicU64 = AtomicU64::new(0);
+
+static WATCHDOG: Once = Once::new();
+
+struct RunningCall {
+    surface: u32,
+    started: Instant,
+    reported: bool,
+}
+
+impl RunningCall {
+    fn new(surface: u32, started: Instant) -> Self {
+        Self {
+            surface,
+            started,
+            reported: false,
+        }
+    }
+
+    /// `true` once when the call has run past the threshold
+    fn check(&mut self, now: Instant) -> bool {
+        if self.reported || now.saturating_duration_since(self.started) < STALL_THRESHOLD {
+            return false;
+        }
+        self.reported = true;
+        true
+    }
+}
+
+struct Holder {
+    id: u64,
+    thread: String,
+    location: &'static Location<'static>,
+    since: Instant,
+}
+
+/// Marks a running `ui()` call until dropped, debug builds only
+///
+/// A call that doesn't return within a second is logged by a watchdog
+/// thread, usually `ui()` waits for a lock held by a background thread.
+/// Stacks of other threads can't be captured, the report names the surface,
+/// how long it has been blocked, and the thread and source location of
+/// every `SharedState` lock held at the time.
+pub(crate) struct UiCall(());
+
+impl UiCall {
+    pub fn start(surface: u32) -> Self {
+        if cfg!(debug_assertions) {
+            WATCHDOG.call_once(spawn_watchdog);
+            *current() = Some(RunningCall::new(surface, Instant::now()));
+        }
+        Self(())
+    }
+}
+
+impl Drop for UiCall {
+    fn drop(&mut self) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+        if let Some(call) = current().take()
+            && call.reported
+        {
+            warn!(
+                "[MAIN] ui() of surface {} returned after {:.1?}",
+                call.surface,
+                call.started.elapsed()
+            );
+        }
+    }
+}
+
+/// `SharedState` lock being held until dropped, debug builds only
+pub(crate) struct HeldLock(Option<u64>);
+
+impl HeldLock {
+    pub fn new(location: &'static Location<'static>) -> Self {
+        if !cfg!(debug_assertions) {
+            return Self(None);
+        }
+        let id = NEXT_HOLDER.fetch_add(1, Ordering::Relaxed);
+        let thread = std::thread::current();
+        holders().push(Holder {
+            id,
+            thread: thread.name().unwrap_or("unnamed").to_string(),
+            location,
+            since: Instant::now(),
+        });
+        Self(Some(id))
+    }
+}
+
+impl Drop for HeldLock {
+    fn drop(&mut self) {
+        if let Some(id) = self.0 {
+            holders().retain(|holder| holder.id != id);
+        }
+    }
+}
+
+fn current() -> MutexGuard<'static, Option<RunningCall>> {
+    CURRENT
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn holders() -> MutexGuard<'static, Vec<Holder>> {
+    HOLDERS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Report of a blocked call, with the locks held at `now`
+fn report(call: &RunningCall, holders: &[Holder], now: Instant) -> String {
+    let mut report = format!(
+        "ui() of surface {} blocked for {:.1?}, all surfaces are stalled.",
+        call.surface,
+        now.saturating_duration_since(call.started)
+    );
+    if holders.is_empty() {
+        report.push_str(" No SharedState lock is held.");
+    }
+    for holder in holders {
+        let _ = write!(
+            report,
+            " SharedState locked by thread '{}' at {} for {:.1?}.",
+            holder.thread,
+            holder.location,
+            now.saturating_duration_since(holder.since)
+        );
+    }
+    report
+}
+
+fn spawn_watchdog() {
+    let spawned = std::thread::Builder::new()
+        .name("wayapp stall watch".into())
+        .spawn(|| {
+            loop {
+                std::thread::sleep(SAMPLE_INTERVAL);
+                let now = Instant::now();
+                let mut current = current();
+                let Some(call) = current.as_mut() else {
+                    continue;
+                };
+                if call.check(now) {
+                    error!("[MAIN] {}", report(call, &holders(), now));
+                }
+            }
+        });
+    if let Err(err) = spawned {
+        warn!("[MAIN] Failed to spawn the stall watch: {}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn call_is_reported_once_past_the_threshold() {
+        let start = Instant::now();
+        let mut call = RunningCall::new(7, start);
+        assert!(!call.check(start + STALL_THRESHOLD / 2));
+        assert!(call.check(start + STALL_THRESHOLD));
+        assert!(!call.check(start + STALL_THRESHOLD * 5));
+    }
+
+    #[test]
+    fn next_call_starts_unreported() {
+        let start = Instant::now();
+        let mut call = RunningCall::new(7, start);
+        assert!(call.check(start + STALL_THRESHOLD));
+        let later = start + STALL_THRESHOLD * 2;
+        let mut call = RunningCall::new(7, later);
+        assert!(!call.check(later));
+        assert!(call.check(later + STALL_THRESHOLD));
+    }
+
+    #[test]
+    fn report_names_the_lock_holders() {
+        let start = Instant::now();
+        let call = RunningCall::new(7, start);
+        let location = Location::caller();
+        let holders = [Holder {
+            id: 0,
+            thread: "fetcher".to_string(),
+            location,
+            since: start,
+        }];
+        let report = report(&call, &holders, start + Duration::from_secs(2));
+        assert!(report.contains("surface 7"));
+        assert!(report.contains("thread 'fetcher'"));
+        assert!(report.contains(&location.to_string()));
+        assert!(super::report(&call, &[], start).contains("No SharedState lock"));
+    }
+
+    #[test]
+    fn held_locks_are_registered_until_dropped() {
+        let held = HeldLock::new(Location::caller());
+        // Release builds don't register
+        let Some(id) = held.0 else {
+            return;
+        };
+        assert!(holders().iter().any(|holder| holder.id == id));
+        drop(held);
+        assert!(!holders().iter().any(|holder| holder.id == id));
+    }
+}
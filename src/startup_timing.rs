@@ -0,0 +1,74 @@
+//! Startup phase timings, logged with `WAYAPP_STARTUP_TIMING=1`.
+//!
+//! Each phase is logged once with the time since the connection was
+//! requested and since the previous phase, so slow phases and regressions
+//! of the time to the first frame are visible.
+use log::info;
+use std::sync::Mutex;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Phase {
+    Connected,
+    RegistryReady,
+    GlobalsBound,
+    InstanceReady,
+    AdapterReady,
+    DeviceCreated,
+    FontsLoaded,
+    FirstFrame,
+}
+
+struct Timeline {
+    start: Instant,
+    previous: Instant,
+    logged: Vec<Phase>,
+}
+
+fn enabled() -> bool {
+    static ENABLED: OnceLock<bool> = OnceLock::new();
+    *ENABLED.get_or_init(|| std::env::var("WAYAPP_STARTUP_TIMING").is_ok_and(|value| value == "1"))
+}
+
+fn timeline() -> &'static Mutex<Option<Timeline>> {
+    static TIMELINE: Mutex<Option<Timeline>> = Mutex::new(None);
+    &TIMELINE
+}
+
+/// Start the timeline, phases marked before this are not logged
+pub(crate) fn start() {
+    if !enabled() {
+        return;
+    }
+    let now = Instant::now();
+    let mut timeline = timeline().lock().unwrap_or_else(|err| err.into_inner());
+    timeline.get_or_insert(Timeline {
+        start: now,
+        previous: now,
+        logged: Vec::new(),
+    });
+}
+
+/// Log the phase if it's the first time it's reached
+pub(crate) fn mark(phase: Phase) {
+    if !enabled() {
+        return;
+    }
+    let now = Instant::now();
+    let mut timeline = timeline().lock().unwrap_or_else(|err| err.into_inner());
+    let Some(timeline) = timeline.as_mut() else {
+        return;
+    };
+    if timeline.logged.contains(&phase) {
+        return;
+    }
+    timeline.logged.push(phase);
+    info!(
+        "[MAIN] Startup {:?} at {:.1} ms (+{:.1} ms)",
+        phase,
+        now.duration_since(timeline.start).as_secs_f64() * 1000.0,
+        now.duration_since(timeline.previous).as_secs_f64() * 1000.0
+    );
+    timeline.previous = now;
+}
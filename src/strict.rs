@@ -0,0 +1,153 @@
+//! Development checks for the order of surface operations.
+//!
+//! Debug builds only, enabled with `WAYAPP_STRICT=1` which panics on a
+//! violation or `WAYAPP_STRICT=lenient` which logs it with a backtrace and
+//! continues. Each surface tracks its phase, operations assert the phase
+//! admits them.
+use log::warn;
+use std::backtrace::Backtrace;
+use std::fmt;
+use std::panic::Location;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StrictMode {
+    Off,
+    Panic,
+    Lenient,
+}
+
+fn mode() -> StrictMode {
+    static MODE: OnceLock<StrictMode> = OnceLock::new();
+    *MODE.get_or_init(|| {
+        if !cfg!(debug_assertions) {
+            return StrictMode::Off;
+        }
+        match std::env::var("WAYAPP_STRICT").as_deref() {
+            Ok("lenient") => StrictMode::Lenient,
+            Ok("0") | Ok("") | Err(_) => StrictMode::Off,
+            Ok(_) => StrictMode::Panic,
+        }
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SurfaceRole {
+    Toplevel,
+    LayerSurface,
+    Popup,
+    /// Has no configure, the parent's state decides what is shown
+    Subsurface,
+    LockSurface,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Created,
+    Configured,
+    Mapped,
+    /// Null buffer committed, waiting for the configure of the next mapping
+    Unmapped,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rule {
+    /// Buffers of xdg and layer surfaces are only allowed after the first
+    /// configure has been acked
+    PresentBeforeConfigure,
+    /// After unmapping the surface must wait for a new configure
+    PresentWhileUnmapped,
+    /// Only one frame callback is kept in flight
+    DuplicateFrameCallback,
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rule::PresentBeforeConfigure => {
+                write!(f, "buffer presented before the first configure")
+            }
+            Rule::PresentWhileUnmapped => {
+                write!(
+                    f,
+                    "buffer presented after unmapping without a new configure"
+                )
+            }
+            Rule::DuplicateFrameCallback => {
+                write!(f, "frame callback requested while one is pending")
+            }
+        }
+    }
+}
+
+/// Phase of one surface
+pub(crate) struct StrictSurface {
+    role: SurfaceRole,
+    phase: Phase,
+    frame_callback_pending: bool,
+}
+
+impl StrictSurface {
+    pub fn new(role: SurfaceRole) -> Self {
+        Self {
+            role,
+            phase: Phase::Created,
+            frame_callback_pending: false,
+        }
+    }
+
+    pub fn configured(&mut self) {
+        self.phase = Phase::Configured;
+    }
+
+    pub fn unmapped(&mut self) {
+        self.phase = Phase::Unmapped;
+        self.frame_callback_pending = false;
+    }
+
+    #[track_caller]
+    pub fn present(&mut self) {
+        match self.phase {
+            Phase::Created if self.role != SurfaceRole::Subsurface => {
+                self.violation(Rule::PresentBeforeConfigure)
+            }
+            Phase::Unmapped => self.violation(Rule::PresentWhileUnmapped),
+            _ => {}
+        }
+        self.phase = Phase::Mapped;
+    }
+
+    #[track_caller]
+    pub fn request_frame(&mut self) {
+        if self.frame_callback_pending {
+            self.violation(Rule::DuplicateFrameCallback);
+        }
+        self.frame_callback_pending = true;
+    }
+
+    pub fn frame_done(&mut self) {
+        self.frame_callback_pending = false;
+    }
+
+    #[track_caller]
+    fn violation(&self, rule: Rule) {
+        let location = Location::caller();
+        match mode() {
+            StrictMode::Off => {}
+            StrictMode::Panic => {
+                panic!(
+                    "[STRICT] {:?} {:?}: {} at {}",
+                    self.role, self.phase, rule, location
+                )
+            }
+            StrictMode::Lenient => warn!(
+                "[STRICT] {:?} {:?}: {} at {}\n{}",
+                self.role,
+                self.phase,
+                rule,
+                location,
+                Backtrace::force_capture()
+            ),
+        }
+    }
+}
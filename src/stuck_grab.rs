@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+/// Pointer events are expected this soon after a popup with the pointer is
+/// destroyed
+pub(crate) const STUCK_GRAB_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Desktop the workaround is decided for, `XDG_CURRENT_DESKTOP`
+pub(crate) fn current_desktop() -> String {
+    std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default()
+}
+
+/// Whether the desktop is one of the allowlisted compositors, it may name
+/// several separated by colons
+pub(crate) fn has_quirk(desktop: &str, compositors: &[String]) -> bool {
+    desktop.split(':').any(|name| {
+        compositors
+            .iter()
+            .any(|compositor| compositor.eq_ignore_ascii_case(name))
+    })
+}
+
+/// Whether the grab is assumed stuck `elapsed` after the popup was
+/// destroyed, `frames` pointer frames having arrived since
+pub(crate) fn is_stuck(
+    desktop: &str,
+    compositors: &[String],
+    frames: u64,
+    elapsed: Duration,
+) -> bool {
+    frames == 0 && elapsed >= STUCK_GRAB_TIMEOUT && has_quirk(desktop, compositors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compositors() -> Vec<String> {
+        vec!["sway".into(), "river".into(), "wlroots".into()]
+    }
+
+    #[test]
+    fn drought_after_dismissal_is_stuck() {
+        assert!(is_stuck("sway", &compositors(), 0, STUCK_GRAB_TIMEOUT));
+    }
+
+    #[test]
+    fn pointer_frame_after_dismissal_is_not_stuck() {
+        assert!(!is_stuck("sway", &compositors(), 1, STUCK_GRAB_TIMEOUT * 2));
+    }
+
+    #[test]
+    fn not_stuck_before_the_timeout() {
+        assert!(!is_stuck("sway", &compositors(), 0, STUCK_GRAB_TIMEOUT / 2));
+    }
+
+    #[test]
+    fn only_allowlisted_compositors() {
+        assert!(!is_stuck("GNOME", &compositors(), 0, STUCK_GRAB_TIMEOUT));
+        assert!(!is_stuck("", &compositors(), 0, STUCK_GRAB_TIMEOUT));
+        assert!(!is_stuck("sway", &[], 0, STUCK_GRAB_TIMEOUT));
+        // Extended by the application
+        let extended = ["Hyprland".to_string()];
+        assert!(is_stuck("Hyprland", &extended, 0, STUCK_GRAB_TIMEOUT));
+    }
+
+    #[test]
+    fn desktop_names_are_split_and_case_insensitive() {
+        assert!(has_quirk("Custom:wlroots", &compositors()));
+        assert!(has_quirk("SWAY", &compositors()));
+        assert!(!has_quirk("swayfx", &compositors()));
+    }
+}
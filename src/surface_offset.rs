@@ -0,0 +1,55 @@
+use crate::ProtocolFeature;
+use std::fmt;
+use wayland_client::Proxy;
+use wayland_client::protocol::wl_surface::WlSurface;
+
+/// Returned when offsetting a surface whose role doesn't allow it, or when
+/// the compositor's wl_surface is older than version 5
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OffsetNotAllowed;
+
+impl fmt::Display for OffsetNotAllowed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Surface role or version doesn't allow a buffer offset")
+    }
+}
+
+impl std::error::Error for OffsetNotAllowed {}
+
+/// Offset of the next buffer relative to the current one, consumed by the
+/// next commit
+///
+/// Used by surfaces that move their content relative to the surface origin.
+/// The buffers are attached by WGPU, so the offset needs `wl_surface.offset`
+/// and can't fall back to the offset of `wl_surface.attach`.
+#[derive(Debug, Default)]
+pub struct SurfaceOffset {
+    pending: Option<(i32, i32)>,
+}
+
+impl SurfaceOffset {
+    /// Fails without `wl_surface.offset`, i.e. before wl_surface version 5
+    pub fn set_next(
+        &mut self,
+        wl_surface: &WlSurface,
+        dx: i32,
+        dy: i32,
+    ) -> Result<(), OffsetNotAllowed> {
+        if !ProtocolFeature::SurfaceOffset.is_supported(wl_surface.version()) {
+            return Err(OffsetNotAllowed);
+        }
+        self.pending = Some((dx, dy));
+        Ok(())
+    }
+
+    pub fn pending(&self) -> Option<(i32, i32)> {
+        self.pending
+    }
+
+    /// Send the pending offset, part of the commit presenting the next buffer
+    pub fn apply(&mut self, wl_surface: &WlSurface) {
+        if let Some((dx, dy)) = self.pending.take() {
+            wl_surface.offset(dx, dy);
+        }
+    }
+}
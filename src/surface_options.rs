@@ -0,0 +1,66 @@
+/// WGPU swapchain options of a surface
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurfaceOptions {
+    /// Falls back to the first supported of Mailbox, FifoRelaxed and Fifo
+    pub present_mode: wgpu::PresentMode,
+    pub desired_latency: u32,
+    /// `None` chooses from the background, premultiplied for translucent ones
+    pub alpha_mode: Option<wgpu::CompositeAlphaMode>,
+    /// Used if the surface supports it, otherwise the first supported format
+    pub preferred_format: Option<wgpu::TextureFormat>,
+}
+
+impl Default for SurfaceOptions {
+    fn default() -> Self {
+        Self {
+            present_mode: wgpu::PresentMode::Mailbox,
+            desired_latency: 2,
+            alpha_mode: None,
+            preferred_format: None,
+        }
+    }
+}
+
+impl SurfaceOptions {
+    /// Battery friendly options for surfaces that rarely change, e.g. bars
+    pub fn fifo() -> Self {
+        Self {
+            present_mode: wgpu::PresentMode::Fifo,
+            ..Default::default()
+        }
+    }
+
+    pub fn select_present_mode(&self, supported: &[wgpu::PresentMode]) -> wgpu::PresentMode {
+        // Auto modes are resolved by WGPU itself
+        if matches!(
+            self.present_mode,
+            wgpu::PresentMode::AutoVsync | wgpu::PresentMode::AutoNoVsync
+        ) || supported.contains(&self.present_mode)
+        {
+            return self.present_mode;
+        }
+        [
+            wgpu::PresentMode::Mailbox,
+            wgpu::PresentMode::FifoRelaxed,
+            wgpu::PresentMode::Fifo,
+        ]
+        .into_iter()
+        .find(|mode| supported.contains(mode))
+        .unwrap_or(wgpu::PresentMode::Fifo)
+    }
+
+    pub fn select_format(&self, supported: &[wgpu::TextureFormat]) -> wgpu::TextureFormat {
+        self.preferred_format
+            .filter(|format| supported.contains(format))
+            .or_else(|| supported.first().copied())
+            .unwrap_or(wgpu::TextureFormat::Bgra8Unorm)
+    }
+
+    /// Requested alpha mode if supported
+    pub fn select_alpha_mode(
+        &self,
+        supported: &[wgpu::CompositeAlphaMode],
+    ) -> Option<wgpu::CompositeAlphaMode> {
+        self.alpha_mode.filter(|mode| supported.contains(mode))
+    }
+}
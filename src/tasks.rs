@@ -0,0 +1,65 @@
+use crate::RedrawHandle;
+use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+
+/// Background work for a surface, results are taken in `ui()` and the
+/// surface is redrawn whenever one completes
+///
+/// Each task runs on its own thread, futures are driven with `pollster` so
+/// they can't rely on a tokio reactor. Take the results at the start of
+/// `ui()`, they are then applied before the input of that frame reaches the
+/// widgets, and in the order the tasks completed.
+pub struct Tasks<T> {
+    sender: mpsc::Sender<T>,
+    receiver: mpsc::Receiver<T>,
+    redraw: RedrawHandle,
+    running: Arc<AtomicUsize>,
+}
+
+impl<T: Send + 'static> Tasks<T> {
+    pub fn new(redraw: RedrawHandle) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            sender,
+            receiver,
+            redraw,
+            running: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Run the future to completion on a background thread
+    pub fn spawn(&self, future: impl Future<Output = T> + Send + 'static) {
+        self.spawn_blocking(move || pollster::block_on(future));
+    }
+
+    /// Run the closure on a background thread, e.g. a file read
+    pub fn spawn_blocking(&self, task: impl FnOnce() -> T + Send + 'static) {
+        let sender = self.sender.clone();
+        let redraw = self.redraw.clone();
+        let running = self.running.clone();
+        running.fetch_add(1, Ordering::Relaxed);
+        std::thread::Builder::new()
+            .name("wayapp task".into())
+            .spawn(move || {
+                // Fails only if the surface is gone
+                let sent = sender.send(task()).is_ok();
+                running.fetch_sub(1, Ordering::Relaxed);
+                if sent {
+                    redraw.request_redraw();
+                }
+            })
+            .expect("Failed to spawn task thread");
+    }
+
+    /// Completed results, oldest first
+    pub fn drain(&self) -> impl Iterator<Item = T> + '_ {
+        self.receiver.try_iter()
+    }
+
+    /// Tasks not completed yet, e.g. for showing a spinner
+    pub fn running(&self) -> usize {
+        self.running.load(Ordering::Relaxed)
+    }
+}
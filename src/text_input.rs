@@ -0,0 +1,209 @@
+use crate::Application;
+use log::trace;
+use wayland_backend::client::ObjectId;
+use wayland_client::Connection;
+use wayland_client::Dispatch;
+use wayland_client::Proxy;
+use wayland_client::QueueHandle;
+use wayland_client::globals::GlobalList;
+use wayland_client::protocol::wl_seat::WlSeat;
+use wayland_protocols::wp::text_input::zv3::client::zwp_text_input_manager_v3::ZwpTextInputManagerV3;
+use wayland_protocols::wp::text_input::zv3::client::zwp_text_input_v3::ChangeCause;
+use wayland_protocols::wp::text_input::zv3::client::zwp_text_input_v3::ContentHint;
+use wayland_protocols::wp::text_input::zv3::client::zwp_text_input_v3::ContentPurpose;
+use wayland_protocols::wp::text_input::zv3::client::zwp_text_input_v3::Event;
+use wayland_protocols::wp::text_input::zv3::client::zwp_text_input_v3::ZwpTextInputV3;
+
+/// zwp_text_input_v3 manager, each seat has its own text input
+#[derive(Default)]
+pub(crate) struct TextInputState {
+    manager: Option<ZwpTextInputManagerV3>,
+}
+
+impl TextInputState {
+    pub fn bind(globals: &GlobalList, qh: &QueueHandle<Application>) -> Self {
+        let manager = globals
+            .bind::<ZwpTextInputManagerV3, _, _>(qh, 1..=1, ())
+            .inspect_err(|_| trace!("[MAIN] Text input manager not available"))
+            .ok();
+        Self { manager }
+    }
+
+    /// Text input of the seat, created with its keyboard
+    pub fn create_for_seat(
+        &self,
+        seat: &WlSeat,
+        qh: &QueueHandle<Application>,
+    ) -> Option<SeatTextInput> {
+        let manager = self.manager.as_ref()?;
+        Some(SeatTextInput {
+            text_input: manager.get_text_input(seat, qh, seat.clone()),
+            surface: None,
+            enabled: false,
+            cursor_rect: None,
+            pending_preedit: None,
+            pending_commit: None,
+            pending_delete: None,
+        })
+    }
+}
+
+/// Input method state of a seat, events are double-buffered and applied on
+/// done
+pub(crate) struct SeatTextInput {
+    text_input: ZwpTextInputV3,
+    /// Surface the text input entered, follows keyboard focus
+    surface: Option<ObjectId>,
+    /// Enabled while a text field of the surface has the focus, otherwise
+    /// keys go to the surface as they are
+    enabled: bool,
+    cursor_rect: Option<(i32, i32, i32, i32)>,
+    pending_preedit: Option<(String, i32, i32)>,
+    pending_commit: Option<String>,
+    pending_delete: Option<(u32, u32)>,
+}
+
+impl SeatTextInput {
+    /// A text field of the surface has the focus, the input method is
+    /// enabled and places its candidate window next to the cursor area in
+    /// surface coordinates
+    pub fn focus_text_field(&mut self, surface_id: &ObjectId, x: i32, y: i32, w: i32, h: i32) {
+        if self.surface.as_ref() != Some(surface_id) {
+            return;
+        }
+        if !self.enabled {
+            self.enabled = true;
+            self.cursor_rect = None;
+            self.text_input.enable();
+            self.text_input
+                .set_content_type(ContentHint::None, ContentPurpose::Normal);
+            self.text_input.set_text_change_cause(ChangeCause::Other);
+        } else if self.cursor_rect == Some((x, y, w, h)) {
+            return;
+        }
+        self.cursor_rect = Some((x, y, w, h));
+        self.text_input.set_cursor_rectangle(x, y, w, h);
+        self.text_input.commit();
+    }
+
+    /// No text field of the surface has the focus, `true` if the input
+    /// method was disabled
+    pub fn unfocus_text_field(&mut self, surface_id: &ObjectId) -> bool {
+        if self.surface.as_ref() != Some(surface_id) || !self.enabled {
+            return false;
+        }
+        self.disable();
+        true
+    }
+
+    fn disable(&mut self) {
+        if self.enabled {
+            self.enabled = false;
+            self.cursor_rect = None;
+            self.text_input.disable();
+            self.text_input.commit();
+        }
+    }
+
+    /// Surface the text input was enabled for, it's not anymore
+    pub fn take_surface(&mut self) -> Option<ObjectId> {
+        self.surface.take()
+    }
+
+    pub fn destroy(self) {
+        self.text_input.destroy();
+    }
+}
+
+impl Dispatch<ZwpTextInputManagerV3, ()> for Application {
+    fn event(
+        _: &mut Self,
+        _: &ZwpTextInputManagerV3,
+        _: <ZwpTextInputManagerV3 as Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwpTextInputV3, WlSeat> for Application {
+    fn event(
+        app: &mut Self,
+        _: &ZwpTextInputV3,
+        event: Event,
+        seat: &WlSeat,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        trace!("[MAIN] Text input event: {:?}", event);
+        let Some(state) = app
+            .seats
+            .get_mut(seat)
+            .and_then(|resources| resources.text_input.as_mut())
+        else {
+            return;
+        };
+        match event {
+            // Enabled once the surface reports a focused text field
+            Event::Enter { surface } => {
+                state.surface = Some(surface.id());
+                app.with_keyboard_container(&surface.id(), |container| {
+                    container.text_input_focus(true)
+                });
+            }
+            Event::Leave { .. } => {
+                state.disable();
+                // Preedit is not applied anywhere after leaving, clear it
+                if let Some(surface_id) = state.surface.take() {
+                    app.with_keyboard_container(&surface_id, |container| {
+                        container.preedit(None, None);
+                        container.text_input_focus(false);
+                    });
+                }
+            }
+            Event::PreeditString {
+                text,
+                cursor_begin,
+                cursor_end,
+            } => {
+                state.pending_preedit = Some((text.unwrap_or_default(), cursor_begin, cursor_end));
+            }
+            Event::CommitString { text } => {
+                state.pending_commit = text;
+            }
+            Event::DeleteSurroundingText {
+                before_length,
+                after_length,
+            } => {
+                state.pending_delete = Some((before_length, after_length));
+            }
+            Event::Done { .. } => {
+                let Some(surface_id) = state.surface.clone() else {
+                    return;
+                };
+                let preedit = state.pending_preedit.take();
+                let commit = state.pending_commit.take();
+                let delete = state.pending_delete.take();
+                // Order given by the protocol: delete, commit, then preedit
+                app.with_keyboard_container(&surface_id, |container| {
+                    if let Some((before, after)) = delete {
+                        container.delete_surrounding_text(before, after);
+                    }
+                    if let Some(text) = &commit {
+                        container.commit_text(text);
+                    }
+                    match &preedit {
+                        Some((text, begin, end)) if !text.is_empty() => {
+                            // Negative cursor means it's hidden
+                            let cursor = (*begin >= 0 && *end >= 0).then_some((*begin, *end));
+                            container.preedit(Some(text), cursor);
+                        }
+                        _ => container.preedit(None, None),
+                    }
+                });
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use crate::Application;
+use log::trace;
+use wayland_client::Connection;
+use wayland_client::Dispatch;
+use wayland_client::QueueHandle;
+use wayland_client::globals::GlobalList;
+use wayland_protocols::wp::viewporter::client::wp_viewport;
+use wayland_protocols::wp::viewporter::client::wp_viewport::WpViewport;
+use wayland_protocols::wp::viewporter::client::wp_viewporter;
+use wayland_protocols::wp::viewporter::client::wp_viewporter::WpViewporter;
+
+/// `None` without wp_viewporter, buffers are then shown at their own size
+pub(crate) fn bind(globals: &GlobalList, qh: &QueueHandle<Application>) -> Option<WpViewporter> {
+    globals
+        .bind::<WpViewporter, _, _>(qh, 1..=1, ())
+        .inspect_err(|_| trace!("[MAIN] Viewporter not available"))
+        .ok()
+}
+
+impl Dispatch<WpViewporter, ()> for Application {
+    fn event(
+        _: &mut Self,
+        _: &WpViewporter,
+        _: wp_viewporter::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<WpViewport, ()> for Application {
+    fn event(
+        _: &mut Self,
+        _: &WpViewport,
+        _: wp_viewport::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}